@@ -17,3 +17,13 @@ pub async fn test_api(mut req: Request<()>) -> Result {
     let User { name, age } = req.body_json().await?;
     Ok(format!("Hello, {}!  {} years old", name, age).into())
 }
+
+#[summer_boot::route("/greeting", method = "GET|POST")]
+pub async fn greeting(req: Request<()>) -> Result {
+    Ok(format!("Hello from {}", req.method()).into())
+}
+
+#[summer_boot::get("/user/:id")]
+pub async fn user(id: u64) -> Result {
+    Ok(format!("User {}", id).into())
+}
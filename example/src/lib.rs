@@ -1,2 +1,3 @@
-mod read_yml;
-mod log;
\ No newline at end of file
+pub mod log;
+pub mod read_yml;
+pub mod tide_compat;
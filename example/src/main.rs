@@ -2,6 +2,7 @@ use serde::Deserialize;
 use summer_boot::{Request, Result};
 
 mod http_method;
+mod nested;
 
 #[derive(Debug, Deserialize)]
 struct User {
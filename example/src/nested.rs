@@ -0,0 +1,44 @@
+//! `auto_scan` 同样会扫描文件中 `mod` 块下的接口
+
+pub mod api {
+    pub mod v1 {
+        use summer_boot::{Request, Result};
+
+        #[summer_boot::get("/api/v1/ping")]
+        pub async fn ping(_req: Request<()>) -> Result {
+            Ok("pong".into())
+        }
+
+        // 未开启 `experimental` feature 时函数不存在，`auto_scan` 也不会注册它
+        #[cfg(feature = "experimental")]
+        #[summer_boot::get("/api/v1/experimental")]
+        pub async fn experimental(_req: Request<()>) -> Result {
+            Ok("experimental".into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use summer_boot::http_types::{self, Method, Url};
+    use summer_boot::{Request, Result};
+
+    // 测试模块中的接口不会被 `auto_scan` 注册，否则正常构建时找不到该函数
+    #[summer_boot::get("/api/test-only")]
+    pub async fn test_only(_req: Request<()>) -> Result {
+        Ok("test".into())
+    }
+
+    #[async_std::test]
+    async fn nested_handler() {
+        let mut app = summer_boot::new();
+        app.at("/api/v1/ping").get(super::api::v1::ping);
+        app.at("/api/test-only").get(test_only);
+        let url = Url::parse("http://localhost/api/v1/ping").unwrap();
+        let mut res: http_types::Response = app
+            .respond(http_types::Request::new(Method::Get, url))
+            .await
+            .unwrap();
+        assert_eq!(res.body_string().await.unwrap(), "pong");
+    }
+}
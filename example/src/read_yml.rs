@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 use serde_json::{from_str as json_from_str, to_string_pretty};
 use serde_yaml::from_str as yaml_from_str;
 use std::fs::read_to_string;
-
+use summer_boot_autoconfigure::{apply_env_overrides, PROFILES_ACTIVE_ENV};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct GlobalConfig {
@@ -43,18 +43,18 @@ pub fn load_env_conf() -> Option<EnvConfig> {
             &path
         )
     }));
-    return match schema {
+    match schema {
         Ok(json) => {
             let data = to_string_pretty(&json).expect("resources/app.yml file data error！");
             let p: EnvConfig =
-                json_from_str(&*data).expect("Failed to transfer JSON data to EnvConfig object！");
-            return Some(p);
+                json_from_str(&data).expect("Failed to transfer JSON data to EnvConfig object！");
+            Some(p)
         }
         Err(err) => {
             println!("{}", err);
             None
         }
-    };
+    }
 }
 
 /*
@@ -69,7 +69,7 @@ pub fn load_global_config(action: String) -> Option<GlobalConfig> {
             &path
         )
     }));
-    return match schema {
+    match schema {
         Ok(json) => {
             let data = to_string_pretty(&json).unwrap_or_else(|_| {
                 panic!(
@@ -77,23 +77,28 @@ pub fn load_global_config(action: String) -> Option<GlobalConfig> {
                     path
                 )
             });
-            let p = json_from_str(&*data)
+            let p = json_from_str(&data)
                 .expect("Failed to transfer JSON data to BriefProConfig object！");
-            return Some(p);
+            Some(p)
         }
         Err(err) => {
             println!("{}", err);
             None
         }
-    };
+    }
 }
 
 /*
 先加载环境配置 在根据当前加载的环境 去加载相应的信息
+环境变量 SUMMER_PROFILES_ACTIVE 优先于 application.yml 中的 profiles.active
  */
 pub fn load_conf() -> Option<GlobalConfig> {
     if let Some(init) = load_env_conf() {
-        return load_global_config(init.profiles.active);
+        let active = std::env::var(PROFILES_ACTIVE_ENV)
+            .ok()
+            .filter(|profile| !profile.is_empty())
+            .unwrap_or(init.profiles.active);
+        return load_global_config(active).map(apply_env_overrides);
     }
     None
 }
@@ -106,9 +111,9 @@ mod tests {
     fn test_load_env_conf_mysql() {
         let pro = load_conf();
         println!("{:?}", pro);
-        pro.as_ref().map(|a| {
+        if let Some(a) = pro.as_ref() {
             println!("mysqlConfig:{}", serde_json::to_string(&a.mysql).unwrap());
-        });
+        }
     }
 
     #[test]
@@ -133,17 +138,17 @@ mod tests {
     fn test_load_global_config() {
         let pro = load_global_config("dev".to_string());
         println!("{:?}", pro);
-        pro.as_ref().map(|a| {
+        if let Some(a) = pro.as_ref() {
             println!("mysqlConfig:{}", serde_json::to_string(&a.mysql).unwrap());
-        });
+        }
     }
 
     #[test]
     fn test_load_conf() {
         let pro = load_conf();
         println!("{:?}", pro);
-        pro.as_ref().map(|a| {
+        if let Some(a) = pro.as_ref() {
             println!("mysqlConfig:{}", serde_json::to_string(&a.mysql).unwrap());
-        });
+        }
     }
 }
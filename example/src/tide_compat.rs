@@ -0,0 +1,97 @@
+//! 从 tide 迁移的示例
+//!
+//! 处理函数和中间件保持 tide 原有的写法，只有路由注册改为 summer boot。
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use summer_boot::compat::tide::{TideEndpoint, TideMiddleware};
+use summer_boot::Server;
+
+#[derive(Clone, Default)]
+pub struct State {
+    visits: Arc<AtomicUsize>,
+}
+
+async fn hello(_req: tide::Request<State>) -> tide::Result {
+    Ok("Hello, tide!".into())
+}
+
+async fn greet(req: tide::Request<State>) -> tide::Result {
+    let name = req.param("name")?;
+    Ok(format!("Hello, {}!", name).into())
+}
+
+async fn visits(req: tide::Request<State>) -> tide::Result {
+    let visits = req.state().visits.load(Ordering::SeqCst);
+    Ok(tide::Response::builder(200)
+        .body(tide::convert::json!({ "visits": visits }))
+        .build())
+}
+
+/// 统计访问次数并添加响应头
+struct VisitCounter;
+
+#[tide::utils::async_trait]
+impl tide::Middleware<State> for VisitCounter {
+    async fn handle(&self, req: tide::Request<State>, next: tide::Next<'_, State>) -> tide::Result {
+        req.state().visits.fetch_add(1, Ordering::SeqCst);
+        let mut res = next.run(req).await;
+        res.insert_header("X-Powered-By", "tide");
+        Ok(res)
+    }
+}
+
+pub fn app() -> std::io::Result<Server<State>> {
+    let mut app = summer_boot::with_state(State::default());
+    app.with(TideMiddleware::new(VisitCounter));
+    app.at("/").get(TideEndpoint::new("/", hello));
+    app.at("/hello/:name")
+        .get(TideEndpoint::new("/hello/:name", greet));
+    app.at("/visits").get(TideEndpoint::new("/visits", visits));
+    app.at("/resources/*").serve_dir("src/resources")?;
+    Ok(app)
+}
+
+#[cfg(test)]
+mod test {
+    use summer_boot::http_types::{Method, Request, Response, StatusCode, Url};
+
+    fn get(path: &str) -> Request {
+        Request::new(
+            Method::Get,
+            Url::parse("http://localhost").unwrap().join(path).unwrap(),
+        )
+    }
+
+    #[async_std::test]
+    async fn ported_routes() {
+        let app = super::app().unwrap();
+
+        let mut res: Response = app.respond(get("/")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::Ok);
+        assert_eq!(res["X-Powered-By"], "tide");
+        assert_eq!(res.body_string().await.unwrap(), "Hello, tide!");
+
+        let mut res: Response = app.respond(get("/hello/summer")).await.unwrap();
+        assert_eq!(res.body_string().await.unwrap(), "Hello, summer!");
+
+        let mut res: Response = app.respond(get("/visits")).await.unwrap();
+        assert_eq!(res.body_string().await.unwrap(), r#"{"visits":3}"#);
+    }
+
+    #[async_std::test]
+    async fn ported_serve_dir() {
+        let app = super::app().unwrap();
+
+        let res: Response = app
+            .respond(get("/resources/application.yml"))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::Ok);
+        assert_eq!(res["X-Powered-By"], "tide");
+
+        let res: Response = app.respond(get("/resources/missing.yml")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NotFound);
+    }
+}
@@ -0,0 +1,94 @@
+//! 启动 `auto_scan` 生成的 `main`，检查扫描注册的路由和注入的路径参数
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// 测试结束时停止示例服务
+struct Example(Child);
+
+impl Drop for Example {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+impl Example {
+    /// 启动示例服务，返回它实际监听的地址
+    ///
+    /// `application-test.yml` 中的 `server.port` 为 0，由系统分配空闲端口，
+    /// 端口从启动日志中读取，避免连到占用固定端口的其他进程。
+    fn start() -> (Self, SocketAddr) {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_example"))
+            .current_dir(env!("CARGO_MANIFEST_DIR"))
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let example = Example(child);
+
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break };
+                if let Some((_, addr)) = line.split_once("Server listening on http://") {
+                    let addr = addr.split(|c: char| c.is_whitespace() || c == '"').next();
+                    let port = addr.and_then(|addr| addr.rsplit(':').next());
+                    let _ = tx.send(port.unwrap_or_default().to_string());
+                }
+            }
+        });
+        let port: u16 = rx
+            .recv_timeout(Duration::from_secs(10))
+            .expect("example did not start listening")
+            .parse()
+            .unwrap();
+        (example, SocketAddr::from(([127, 0, 0, 1], port)))
+    }
+}
+
+fn get(addr: SocketAddr, path: &str) -> String {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(
+        stream,
+        "GET {} HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n",
+        path
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+#[test]
+fn registers_handlers_in_nested_modules() {
+    let (_example, addr) = Example::start();
+
+    let response = get(addr, "/api/v1/ping");
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert!(response.ends_with("pong"));
+    // `#[cfg]` 排除的接口不会注册，开启 `experimental` feature 时才有
+    let experimental = get(addr, "/api/v1/experimental");
+    if cfg!(feature = "experimental") {
+        assert!(experimental.starts_with("HTTP/1.1 200"), "{}", experimental);
+    } else {
+        assert!(experimental.starts_with("HTTP/1.1 404"), "{}", experimental);
+    }
+    // 测试模块中的接口不会注册
+    assert!(get(addr, "/api/test-only").starts_with("HTTP/1.1 404"));
+}
+
+#[test]
+fn rejects_unparsable_path_params() {
+    let (_example, addr) = Example::start();
+
+    let response = get(addr, "/user/7");
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert!(response.ends_with("User 7"));
+    // `id: u64` 无法从 `abc` 解析，返回 400 而不是进入处理函数
+    let response = get(addr, "/user/abc");
+    assert!(response.starts_with("HTTP/1.1 400"), "{}", response);
+}
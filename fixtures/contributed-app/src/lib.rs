@@ -0,0 +1,87 @@
+//! 挂载 `contributed-lib` 贡献的路由，不需要知道它提供了哪些路由
+
+use contributed_lib as _;
+use summer_boot::contributed::{MountOptions, RouteConflict};
+use summer_boot::Server;
+
+/// 创建服务器，`contributed-lib` 的路由挂载在 `prefix` 下
+pub fn app(prefix: &str) -> Result<Server<()>, RouteConflict> {
+    let mut app = summer_boot::new();
+    app.at("/health").get(|_| async { Ok("ok") });
+    app.mount_contributed_with(MountOptions::new().prefix("contributed-lib", prefix))?;
+    Ok(app)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use http_types::{Method, Request, Response, StatusCode, Url};
+
+    fn request(method: Method, path: &str) -> Request {
+        Request::new(
+            method,
+            Url::parse("http://localhost").unwrap().join(path).unwrap(),
+        )
+    }
+
+    #[async_std::test]
+    async fn serves_contributed_routes() {
+        let app = app("/").unwrap();
+        let mut res: Response = app
+            .respond(request(Method::Get, "/lib/feature"))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::Ok);
+        assert_eq!(res.body_string().await.unwrap(), "contributed feature");
+
+        let mut req = request(Method::Post, "/lib/echo");
+        req.set_body("hello");
+        let mut res: Response = app.respond(req).await.unwrap();
+        assert_eq!(res.body_string().await.unwrap(), "hello");
+
+        let res: Response = app.respond(request(Method::Get, "/health")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::Ok);
+    }
+
+    #[async_std::test]
+    async fn applies_prefix_in_order() {
+        let app = app("/plugins").unwrap();
+        let res: Response = app
+            .respond(request(Method::Get, "/plugins/lib/feature"))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::Ok);
+        let res: Response = app
+            .respond(request(Method::Get, "/lib/feature"))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::NotFound);
+
+        let routes: Vec<_> = summer_boot::contributed::contributed_routes()
+            .iter()
+            .map(|route| (route.method().to_string(), route.path(), route.handler()))
+            .collect();
+        assert_eq!(
+            routes,
+            [
+                ("POST".to_string(), "/lib/echo", "contributed_lib::echo"),
+                (
+                    "GET".to_string(),
+                    "/lib/feature",
+                    "contributed_lib::feature"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_conflicts() {
+        let mut app = summer_boot::new();
+        app.at("/lib/feature").get(|_| async { Ok("taken") });
+        let err = app.mount_contributed().unwrap_err();
+        assert_eq!(err.path(), "/lib/feature");
+        assert_eq!(err.handler(), "contributed_lib::feature");
+        // 冲突时不注册任何路由
+        assert_eq!(app.routes_dot().matches("/lib/echo").count(), 0);
+    }
+}
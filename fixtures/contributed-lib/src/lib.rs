@@ -0,0 +1,13 @@
+//! 通过 `#[summer_boot::contribute]` 贡献路由的库
+
+use summer_boot::{Request, Result};
+
+#[summer_boot::contribute(get, "/lib/feature")]
+async fn feature(_req: Request<()>) -> Result {
+    Ok("contributed feature".into())
+}
+
+#[summer_boot::contribute(post, "/lib/echo")]
+async fn echo(mut req: Request<()>) -> Result {
+    Ok(req.body_string().await?.into())
+}
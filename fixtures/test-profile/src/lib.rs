@@ -0,0 +1,34 @@
+//! `profiles.active` 为 `dev`，同时提供 `test` 环境配置的项目
+
+use summer_boot::Server;
+
+/// 返回当前请求使用的 `server.context_path`
+pub fn app() -> Server<()> {
+    let mut app = summer_boot::new();
+    app.at("/context-path")
+        .get(|req: summer_boot::Request<()>| async move {
+            let config = req.ext::<std::sync::Arc<summer_boot::config::GlobalConfig>>();
+            Ok(config
+                .and_then(|config| config.server.as_ref())
+                .map(|server| server.context_path.clone())
+                .unwrap_or_default())
+        });
+    app
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use summer_boot::testing::TestServer;
+
+    #[async_std::test]
+    async fn test_server_loads_test_profile() {
+        let server = TestServer::builder().build(app());
+
+        let config = server.config().unwrap();
+        assert_eq!(config.server.as_ref().unwrap().port, 8082);
+
+        let mut res = server.get("/context-path").await.unwrap();
+        assert_eq!(res.body_string().await.unwrap(), "/test");
+    }
+}
@@ -0,0 +1,34 @@
+//! 环境的选择顺序：显式指定的环境 > `SUMMER_PROFILES_ACTIVE` > `profiles.active`
+//!
+//! 修改了进程的环境变量，所有断言放在同一个测试中按顺序执行。
+
+use summer_boot_autoconfigure::{load_conf, load_conf_with_profile, PROFILES_ACTIVE_ENV};
+
+fn context_path(config: Option<summer_boot_autoconfigure::GlobalConfig>) -> String {
+    config.unwrap().server.unwrap().context_path
+}
+
+#[test]
+fn profile_precedence() {
+    std::env::remove_var(PROFILES_ACTIVE_ENV);
+    assert_eq!(context_path(load_conf().unwrap()), "/dev");
+
+    // 请求的环境不存在时回退到 profiles.active
+    assert_eq!(
+        context_path(load_conf_with_profile("staging").unwrap()),
+        "/dev"
+    );
+
+    std::env::set_var(PROFILES_ACTIVE_ENV, "test");
+    assert_eq!(context_path(load_conf().unwrap()), "/test");
+
+    std::env::set_var(PROFILES_ACTIVE_ENV, "staging");
+    assert_eq!(context_path(load_conf().unwrap()), "/dev");
+
+    // 显式指定的环境优先于环境变量
+    std::env::set_var(PROFILES_ACTIVE_ENV, "dev");
+    assert_eq!(
+        context_path(load_conf_with_profile("test").unwrap()),
+        "/test"
+    );
+}
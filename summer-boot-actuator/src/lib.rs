@@ -1 +1,6 @@
-mod configuration_properties;
\ No newline at end of file
+//! summer boot actuator
+//!
+//! 实现位于 `summer_boot::actuator`，开启 `summer-boot` 的 `actuator` feature 即可使用，
+//! 本 crate 保留原有的导入路径。
+pub use summer_boot::actuator::*;
+pub use summer_boot::build_info;
@@ -0,0 +1,231 @@
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Number, Value};
+use std::env;
+
+/// 覆盖配置项的环境变量前缀，例如 `SUMMER_SERVER_PORT` 覆盖 `server.port`
+pub const ENV_OVERRIDE_PREFIX: &str = "SUMMER_";
+
+///
+/// 用 `SUMMER_` 开头的环境变量覆盖配置文件中的值
+///
+/// 变量名去掉前缀后按 `_` 对应到嵌套的配置项，字段名本身包含 `_` 时同样可以匹配，
+/// 例如 `SUMMER_SERVER_CONTEXT_PATH` 覆盖 `server.context_path`。
+/// 值按配置项原来的类型转换，转换失败时打印警告并保留原值。
+/// 每个变量单独检查，不合法的变量只会被忽略，不影响其他变量。
+/// 配置文件中没有的段（例如未配置 `mysql`）不会被创建。
+///
+pub fn apply_env_overrides<T>(config: T) -> T
+where
+    T: Serialize + DeserializeOwned,
+{
+    let mut value = match serde_json::to_value(&config) {
+        Ok(value) => value,
+        Err(_) => return config,
+    };
+    let vars = env::vars().filter_map(|(key, value)| {
+        key.strip_prefix(ENV_OVERRIDE_PREFIX)
+            .map(|name| (key.clone(), name.to_string(), value))
+    });
+    if !override_with::<T>(&mut value, vars) {
+        return config;
+    }
+    match serde_json::from_value(value) {
+        Ok(config) => config,
+        Err(err) => {
+            println!(
+                "warning: ignoring environment overrides, invalid configuration: {}",
+                err
+            );
+            config
+        }
+    }
+}
+
+/// 逐个应用覆盖，只保留应用后仍能解析为 `T` 的变量，返回是否修改了配置
+fn override_with<T: DeserializeOwned>(
+    value: &mut Value,
+    vars: impl Iterator<Item = (String, String, String)>,
+) -> bool {
+    let mut changed = false;
+    for (key, name, raw) in vars {
+        let Some(target) = find(value, &name) else {
+            continue;
+        };
+        let kind = type_name(target);
+        let applied = coerce(target, &raw).into_iter().find_map(|candidate| {
+            let mut next = value.clone();
+            *find(&mut next, &name)? = candidate;
+            serde_json::from_value::<T>(next.clone())
+                .is_ok()
+                .then_some(next)
+        });
+        match applied {
+            Some(next) => {
+                *value = next;
+                changed = true;
+            }
+            None => println!(
+                "warning: environment variable {} is not a valid {}, ignored",
+                key, kind
+            ),
+        }
+    }
+    changed
+}
+
+/// 根据去掉前缀的变量名找到对应的配置项
+fn find<'a>(value: &'a mut Value, name: &str) -> Option<&'a mut Value> {
+    let Value::Object(map) = value else {
+        return None;
+    };
+    let key = map.keys().find(|key| {
+        let key = env_name(key);
+        name == key || (name.starts_with(&key) && name.as_bytes().get(key.len()) == Some(&b'_'))
+    })?;
+    let key = key.clone();
+    let rest = &name[env_name(&key).len()..];
+    let child = map.get_mut(&key)?;
+    if rest.is_empty() {
+        // 只覆盖叶子节点
+        (!child.is_object() && !child.is_array()).then_some(child)
+    } else {
+        find(child, &rest[1..])
+    }
+}
+
+fn env_name(key: &str) -> String {
+    key.to_ascii_uppercase().replace('-', "_")
+}
+
+/// 按原值的类型转换环境变量的值，返回按顺序尝试的候选值
+fn coerce(current: &Value, raw: &str) -> Vec<Value> {
+    let string = Value::String(raw.to_string());
+    match current {
+        Value::String(_) => vec![string],
+        Value::Bool(_) => raw.parse().map(Value::Bool).into_iter().collect(),
+        Value::Number(_) => parse_number(raw).map(Value::Number).into_iter().collect(),
+        // 未设置的可选项不知道类型，依次尝试布尔值、数字和原始字符串
+        Value::Null => raw
+            .parse()
+            .ok()
+            .map(Value::Bool)
+            .into_iter()
+            .chain(parse_number(raw).map(Value::Number))
+            .chain(Some(string))
+            .collect(),
+        Value::Array(_) | Value::Object(_) => Vec::new(),
+    }
+}
+
+fn parse_number(raw: &str) -> Option<Number> {
+    if let Ok(n) = raw.parse::<u64>() {
+        Some(n.into())
+    } else if let Ok(n) = raw.parse::<i64>() {
+        Some(n.into())
+    } else {
+        raw.parse::<f64>().ok().and_then(Number::from_f64)
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Bool(_) => "bool",
+        Value::Number(_) => "number",
+        _ => "value",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn vars(pairs: &[(&str, &str)]) -> impl Iterator<Item = (String, String, String)> {
+        pairs
+            .iter()
+            .map(|(key, value)| {
+                (
+                    key.to_string(),
+                    key.strip_prefix(ENV_OVERRIDE_PREFIX).unwrap().to_string(),
+                    value.to_string(),
+                )
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn overrides_nested_keys_with_coercion() {
+        let mut config = json!({
+            "mysql": null,
+            "server": { "port": 8080, "context_path": "/", "charset": null },
+        });
+        let changed = override_with::<Value>(
+            &mut config,
+            vars(&[
+                ("SUMMER_SERVER_PORT", "9090"),
+                ("SUMMER_SERVER_CONTEXT_PATH", "/api"),
+                ("SUMMER_SERVER_CHARSET", "utf-8"),
+                ("SUMMER_MYSQL_PASSWORD", "secret"),
+                ("SUMMER_PROFILES_ACTIVE", "test"),
+            ]),
+        );
+        assert!(changed);
+        assert_eq!(
+            config,
+            json!({
+                "mysql": null,
+                "server": { "port": 9090, "context_path": "/api", "charset": "utf-8" },
+            })
+        );
+    }
+
+    #[derive(Debug, serde::Deserialize)]
+    #[allow(dead_code)]
+    struct Server {
+        port: u32,
+        charset: Option<String>,
+        keep_alive_timeout_seconds: Option<u64>,
+    }
+
+    #[test]
+    fn unset_options_keep_the_target_type() {
+        let mut config = json!({
+            "port": 8080,
+            "charset": null,
+            "keep_alive_timeout_seconds": null,
+        });
+        let changed = override_with::<Server>(
+            &mut config,
+            vars(&[
+                ("SUMMER_CHARSET", "1252"),
+                ("SUMMER_KEEP_ALIVE_TIMEOUT_SECONDS", "true"),
+                ("SUMMER_PORT", "9090"),
+            ]),
+        );
+        // 数字形式的字符串仍按字符串保存，不合法的变量不影响其他变量
+        assert!(changed);
+        assert_eq!(
+            config,
+            json!({
+                "port": 9090,
+                "charset": "1252",
+                "keep_alive_timeout_seconds": null,
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_values_are_ignored() {
+        let mut config = json!({ "server": { "port": 8080, "context_path": "/" } });
+        let changed = override_with::<Value>(
+            &mut config,
+            vars(&[("SUMMER_SERVER_PORT", "eighty"), ("SUMMER_SERVER", "x")]),
+        );
+        assert!(!changed);
+        assert_eq!(
+            config,
+            json!({ "server": { "port": 8080, "context_path": "/" } })
+        );
+    }
+}
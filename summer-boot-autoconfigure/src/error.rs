@@ -0,0 +1,72 @@
+use std::{error::Error, fmt, io};
+
+///
+/// 加载配置文件时的错误，配置文件不存在不属于错误
+///
+#[derive(Debug)]
+pub enum ConfigError {
+    /// 配置文件存在但无法读取
+    Io { path: String, source: io::Error },
+    /// 配置文件格式错误，或者内容与配置结构不匹配
+    Parse { path: String, message: String },
+    /// 配置项的值不合法，`field` 为配置项的完整路径，例如 `server.port`
+    Invalid { field: String, message: String },
+    /// 校验配置时发现的所有错误
+    Validation(Vec<ConfigError>),
+}
+
+impl ConfigError {
+    /// 出错的配置文件，校验错误没有对应的文件
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            ConfigError::Io { path, .. } | ConfigError::Parse { path, .. } => Some(path),
+            ConfigError::Invalid { .. } | ConfigError::Validation(_) => None,
+        }
+    }
+
+    pub(crate) fn invalid(field: &str, message: impl fmt::Display) -> Self {
+        ConfigError::Invalid {
+            field: field.to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    pub(crate) fn parse(path: &str, err: impl fmt::Display) -> Self {
+        ConfigError::Parse {
+            path: path.to_string(),
+            message: err.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io { path, source } => {
+                write!(f, "failed to read configuration file {}: {}", path, source)
+            }
+            ConfigError::Parse { path, message } => {
+                write!(f, "invalid configuration file {}: {}", path, message)
+            }
+            ConfigError::Invalid { field, message } => write!(f, "{}: {}", field, message),
+            ConfigError::Validation(errors) => {
+                write!(f, "invalid configuration:")?;
+                for error in errors {
+                    write!(f, "\n  - {}", error)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConfigError::Io { source, .. } => Some(source),
+            ConfigError::Parse { .. }
+            | ConfigError::Invalid { .. }
+            | ConfigError::Validation(_) => None,
+        }
+    }
+}
@@ -1,3 +1,391 @@
+mod env_override;
+mod error;
+#[cfg(feature = "mysql")]
+mod mysql_pool;
+mod read_toml;
 mod read_yml;
+mod schema;
+mod validate;
 
+pub use env_override::*;
+pub use error::ConfigError;
+#[cfg(feature = "mysql")]
+pub use mysql_pool::*;
+pub use read_toml::*;
 pub use read_yml::*;
+pub use schema::{config_sections, generate_template, ConfigKey, ConfigSchema, ConfigSection};
+pub use validate::ConfigValidator;
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::{env, fs, io};
+
+/// 覆盖 `profiles.active` 的环境变量
+pub const PROFILES_ACTIVE_ENV: &str = "SUMMER_PROFILES_ACTIVE";
+
+/// 配置文件格式的优先顺序，逗号分隔的扩展名，例如 `toml,yml`
+pub const CONFIG_FORMATS_ENV: &str = "SUMMER_CONFIG_FORMATS";
+
+/// 配置文件格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Yaml,
+    Toml,
+}
+
+impl ConfigFormat {
+    /// 默认的优先顺序，先 YAML 后 TOML
+    pub const DEFAULT_ORDER: [ConfigFormat; 2] = [ConfigFormat::Yaml, ConfigFormat::Toml];
+
+    /// 配置文件扩展名
+    pub fn extension(self) -> &'static str {
+        match self {
+            ConfigFormat::Yaml => "yml",
+            ConfigFormat::Toml => "toml",
+        }
+    }
+
+    /// 根据扩展名识别格式，支持 `yml`、`yaml` 和 `toml`
+    pub fn from_extension(extension: &str) -> Option<ConfigFormat> {
+        match extension.trim().to_ascii_lowercase().as_str() {
+            "yml" | "yaml" => Some(ConfigFormat::Yaml),
+            "toml" => Some(ConfigFormat::Toml),
+            _ => None,
+        }
+    }
+
+    ///
+    /// 按 [`ConfigFormat::order`] 的顺序选择第一个存在的 `application` 配置文件
+    ///
+    pub fn detect() -> Option<ConfigFormat> {
+        ConfigFormat::detect_in(&ConfigFormat::order())
+    }
+
+    ///
+    /// 按 `order` 的顺序选择第一个存在的 `application` 配置文件，
+    /// 存在多个时打印警告
+    ///
+    pub fn detect_in(order: &[ConfigFormat]) -> Option<ConfigFormat> {
+        select_format(order, |format| {
+            fs::metadata(config_path("application", format)).is_ok()
+        })
+    }
+
+    ///
+    /// 配置文件格式的优先顺序
+    ///
+    /// 设置了 [`CONFIG_FORMATS_ENV`] 时使用其中的顺序，无法识别的扩展名打印警告并忽略，
+    /// 否则使用 [`ConfigFormat::DEFAULT_ORDER`]。
+    ///
+    pub fn order() -> Vec<ConfigFormat> {
+        parse_order(env::var(CONFIG_FORMATS_ENV).ok().as_deref())
+    }
+}
+
+/// 解析逗号分隔的扩展名列表，没有可用的格式时使用默认顺序
+fn parse_order(formats: Option<&str>) -> Vec<ConfigFormat> {
+    let mut order = Vec::new();
+    for extension in formats.unwrap_or_default().split(',') {
+        if extension.trim().is_empty() {
+            continue;
+        }
+        match ConfigFormat::from_extension(extension) {
+            Some(format) if !order.contains(&format) => order.push(format),
+            Some(_) => {}
+            None => println!(
+                "warning: unknown configuration format {} in {}",
+                extension.trim(),
+                CONFIG_FORMATS_ENV
+            ),
+        }
+    }
+    if order.is_empty() {
+        ConfigFormat::DEFAULT_ORDER.to_vec()
+    } else {
+        order
+    }
+}
+
+fn select_format(
+    order: &[ConfigFormat],
+    exists: impl Fn(ConfigFormat) -> bool,
+) -> Option<ConfigFormat> {
+    let found: Vec<ConfigFormat> = order.iter().copied().filter(|f| exists(*f)).collect();
+    let selected = *found.first()?;
+    for ignored in &found[1..] {
+        println!(
+            "warning: application.{} and application.{} both exist, application.{} is used",
+            selected.extension(),
+            ignored.extension(),
+            selected.extension()
+        );
+    }
+    Some(selected)
+}
+
+///
+/// 获取resources下配置文件的路径，`name` 不带扩展名
+///
+pub(crate) fn config_path(name: &str, format: ConfigFormat) -> String {
+    let types = check_project_workspace();
+
+    if types.eq("workspace") {
+        let package_name = get_package_name();
+        format!(
+            "{}/src/resources/{}.{}",
+            package_name,
+            name,
+            format.extension()
+        )
+    } else if types.eq("project") {
+        format!("src/resources/{}.{}", name, format.extension())
+    } else {
+        String::new()
+    }
+}
+
+///
+/// 确定生效的环境，`requested` 对应的配置文件存在时优先使用，否则使用 `configured`
+///
+/// 多个环境用逗号分隔，其中任意一个配置文件存在即可。
+///
+pub(crate) fn active_profile(
+    configured: String,
+    requested: Option<String>,
+    format: ConfigFormat,
+) -> String {
+    select_profile(configured, requested, |profiles| {
+        profile_names(profiles).any(|profile| {
+            fs::metadata(config_path(&format!("application-{}", profile), format)).is_ok()
+        })
+    })
+}
+
+/// 拆分逗号分隔的环境列表
+fn profile_names(profiles: &str) -> impl Iterator<Item = &str> {
+    profiles
+        .split(',')
+        .map(str::trim)
+        .filter(|profile| !profile.is_empty())
+}
+
+///
+/// 读取配置文件，文件不存在时返回 `None`
+///
+pub(crate) fn read_config(path: &str) -> Result<Option<String>, ConfigError> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(Some(content)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(source) => Err(ConfigError::Io {
+            path: path.to_string(),
+            source,
+        }),
+    }
+}
+
+/// 按格式把配置文件内容解析为通用的值
+fn parse_config(content: &str, path: &str, format: ConfigFormat) -> Result<Value, ConfigError> {
+    match format {
+        ConfigFormat::Yaml => {
+            serde_yaml::from_str(content).map_err(|e| ConfigError::parse(path, e))
+        }
+        ConfigFormat::Toml => toml::from_str(content).map_err(|e| ConfigError::parse(path, e)),
+    }
+}
+
+///
+/// 加载单个配置文件，文件不存在时返回 `Ok(None)`
+///
+pub(crate) fn load_config<T: DeserializeOwned>(
+    name: &str,
+    format: ConfigFormat,
+) -> Result<Option<T>, ConfigError> {
+    let path = config_path(name, format);
+    let Some(content) = read_config(&path)? else {
+        return Ok(None);
+    };
+    let value = parse_config(&content, &path, format)?;
+    serde_json::from_value(value)
+        .map(Some)
+        .map_err(|e| ConfigError::parse(&path, e))
+}
+
+///
+/// 按顺序加载 `profiles` 中每个环境的配置文件，深度合并后反序列化，后面的环境覆盖前面的
+///
+/// 不存在的配置文件打印警告并跳过，所有文件都不存在时返回 `Ok(None)`。
+///
+pub(crate) fn load_profiles<T: DeserializeOwned>(
+    profiles: &str,
+    format: ConfigFormat,
+) -> Result<Option<T>, ConfigError> {
+    let mut paths = Vec::new();
+    let mut docs = Vec::new();
+    for profile in profile_names(profiles) {
+        let path = config_path(&format!("application-{}", profile), format);
+        match read_config(&path)? {
+            Some(content) => docs.push(parse_config(&content, &path, format)?),
+            None => println!(
+                "warning: configuration file {} does not exist, profile {} is skipped",
+                path, profile
+            ),
+        }
+        paths.push(path);
+    }
+
+    let Some(merged) = merge_profiles(docs) else {
+        return Ok(None);
+    };
+    serde_json::from_value(merged)
+        .map(Some)
+        .map_err(|e| ConfigError::parse(&paths.join(", "), e))
+}
+
+/// 依次把每个配置合并到前面的结果上，没有配置时返回 `None`
+fn merge_profiles(docs: impl IntoIterator<Item = Value>) -> Option<Value> {
+    docs.into_iter().reduce(|mut base, overlay| {
+        merge(&mut base, overlay);
+        base
+    })
+}
+
+/// 对象按键递归合并，其他值直接替换
+fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+fn select_profile(
+    configured: String,
+    requested: Option<String>,
+    exists: impl Fn(&str) -> bool,
+) -> String {
+    match requested {
+        Some(profile) if !profile.is_empty() && profile != configured => {
+            if exists(&profile) {
+                profile
+            } else {
+                println!(
+                    "warning: profile {} is not configured, falling back to {}",
+                    profile, configured
+                );
+                configured
+            }
+        }
+        _ => configured,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn requested_profile_wins_when_configured() {
+        let exists = |profile: &str| profile == "test";
+        assert_eq!(
+            select_profile("dev".to_string(), Some("test".to_string()), exists),
+            "test"
+        );
+        assert_eq!(
+            select_profile("dev".to_string(), Some("staging".to_string()), exists),
+            "dev"
+        );
+        assert_eq!(select_profile("dev".to_string(), None, exists), "dev");
+        assert_eq!(
+            select_profile("dev".to_string(), Some(String::new()), exists),
+            "dev"
+        );
+    }
+
+    #[test]
+    fn missing_files_are_not_errors() {
+        assert!(matches!(read_config("src/resources/missing.yml"), Ok(None)));
+        let config: Result<Option<GlobalConfig>, _> =
+            load_profiles("missing,also-missing", ConfigFormat::Yaml);
+        assert!(matches!(config, Ok(None)));
+
+        let err =
+            parse_config("server: [port", "application-dev.yml", ConfigFormat::Yaml).unwrap_err();
+        assert!(matches!(err, ConfigError::Parse { .. }));
+        assert_eq!(err.path(), Some("application-dev.yml"));
+        assert!(parse_config("[server", "application.toml", ConfigFormat::Toml).is_err());
+    }
+
+    #[test]
+    fn picks_first_existing_format_in_order() {
+        assert_eq!(parse_order(None), ConfigFormat::DEFAULT_ORDER);
+        assert_eq!(
+            parse_order(Some(" TOML, yaml,yml,ini")),
+            [ConfigFormat::Toml, ConfigFormat::Yaml]
+        );
+        assert_eq!(parse_order(Some("ini")), ConfigFormat::DEFAULT_ORDER);
+
+        let only_toml = |format| format == ConfigFormat::Toml;
+        assert_eq!(
+            select_format(&ConfigFormat::DEFAULT_ORDER, only_toml),
+            Some(ConfigFormat::Toml)
+        );
+        let both = |_| true;
+        assert_eq!(
+            select_format(&ConfigFormat::DEFAULT_ORDER, both),
+            Some(ConfigFormat::Yaml)
+        );
+        assert_eq!(
+            select_format(&[ConfigFormat::Toml, ConfigFormat::Yaml], both),
+            Some(ConfigFormat::Toml)
+        );
+        assert_eq!(select_format(&[ConfigFormat::Yaml], only_toml), None);
+    }
+
+    #[test]
+    fn missing_keys_use_defaults() {
+        let config: GlobalConfig =
+            serde_yaml::from_str("mysql:\n  host: db-1\nserver:\n  port: 9090\n").unwrap();
+        let mysql = config.mysql.unwrap();
+        assert_eq!(mysql.host, "db-1");
+        assert_eq!((mysql.port, mysql.db.as_str()), (3306, "summer"));
+        // 账号没有默认值，由校验报告缺失
+        assert_eq!((mysql.user, mysql.password), (None, None));
+        let server = config.server.unwrap();
+        assert_eq!((server.port, server.context_path.as_str()), (9090, "/"));
+    }
+
+    #[test]
+    fn later_profiles_override_earlier_ones() {
+        let merged = merge_profiles([
+            serde_json::json!({
+                "server": { "port": 8080, "context_path": "/" },
+                "mysql": { "host": "db", "port": 3306 },
+            }),
+            serde_json::json!({
+                "server": { "port": 9090, "charset": "utf-8" },
+                "metrics": { "enabled": true },
+            }),
+        ]);
+        assert_eq!(
+            merged,
+            Some(serde_json::json!({
+                "server": { "port": 9090, "context_path": "/", "charset": "utf-8" },
+                "mysql": { "host": "db", "port": 3306 },
+                "metrics": { "enabled": true },
+            }))
+        );
+        assert_eq!(merge_profiles([]), None);
+        assert_eq!(
+            profile_names(" prod, ,metrics ").collect::<Vec<_>>(),
+            ["prod", "metrics"]
+        );
+    }
+}
@@ -0,0 +1,96 @@
+use crate::Mysql;
+use mysql_async::{Error, IoError, OptsBuilder, Pool, PoolConstraints, PoolOpts};
+use std::io;
+use std::time::Duration;
+
+///
+/// 根据 `mysql` 配置创建连接池，需要开启 `mysql` feature
+///
+/// 连接池保持 `pool_min_idle` 个空闲连接，最多打开 `pool_max_open` 个连接。
+/// 创建后会获取一个连接验证配置，`timeout_seconds` 内无法连接时返回错误。
+/// 超时使用 `async-std` 的计时器，不依赖调用方的运行时；`mysql_async` 的连接仍然需要在
+/// tokio 运行时中调用。返回的 `Pool` 可以直接通过 `summer_boot::with_state(pool)`
+/// 作为 `Server` 的状态，在处理函数中使用 `req.state().get_conn()` 获取连接。
+///
+/// ```no_run
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// let config = summer_boot_autoconfigure::load_conf()?.expect("missing configuration");
+/// let pool = summer_boot_autoconfigure::init_mysql_pool(&config.mysql.unwrap()).await?;
+/// let conn = pool.get_conn().await?;
+/// # Ok(()) }
+/// ```
+///
+pub async fn init_mysql_pool(cfg: &Mysql) -> Result<Pool, Error> {
+    let constraints = PoolConstraints::new(cfg.pool_min_idle as usize, cfg.pool_max_open as usize)
+        .ok_or_else(|| {
+            invalid_input(format!(
+                "pool_min_idle {} is greater than pool_max_open {}",
+                cfg.pool_min_idle, cfg.pool_max_open
+            ))
+        })?;
+    let port = u16::try_from(cfg.port)
+        .map_err(|_| invalid_input(format!("invalid mysql port {}", cfg.port)))?;
+
+    let opts = OptsBuilder::default()
+        .ip_or_hostname(cfg.host.as_str())
+        .tcp_port(port)
+        .user(cfg.user.as_deref())
+        .pass(cfg.password.as_deref())
+        .db_name(Some(cfg.db.as_str()))
+        .pool_opts(PoolOpts::default().with_constraints(constraints));
+    let pool = Pool::new(opts);
+
+    let timeout = Duration::from_secs(cfg.timeout_seconds);
+    match async_std::future::timeout(timeout, pool.get_conn()).await {
+        Ok(Ok(conn)) => {
+            drop(conn);
+            Ok(pool)
+        }
+        Ok(Err(err)) => {
+            let _ = pool.disconnect().await;
+            Err(err)
+        }
+        Err(_) => {
+            let _ = pool.disconnect().await;
+            Err(Error::Io(IoError::Io(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("connecting to mysql timed out after {:?}", timeout),
+            ))))
+        }
+    }
+}
+
+fn invalid_input(message: String) -> Error {
+    Error::Io(IoError::Io(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        message,
+    )))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn config() -> Mysql {
+        Mysql {
+            host: "127.0.0.1".to_string(),
+            port: 1,
+            user: Some("root".to_string()),
+            password: Some("secret".to_string()),
+            db: "summer".to_string(),
+            pool_min_idle: 1,
+            pool_max_open: 4,
+            timeout_seconds: 5,
+        }
+    }
+
+    #[tokio::test]
+    async fn returns_errors_instead_of_panicking() {
+        let mut cfg = config();
+        cfg.pool_min_idle = 8;
+        assert!(init_mysql_pool(&cfg).await.is_err());
+
+        // 没有服务监听的端口
+        assert!(init_mysql_pool(&config()).await.is_err());
+    }
+}
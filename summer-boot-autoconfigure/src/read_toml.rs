@@ -0,0 +1,53 @@
+use crate::{load_config, load_profiles, ConfigError, ConfigFormat, EnvConfig, GlobalConfig};
+
+///
+/// 加载 `application.toml` 环境配置，文件不存在时返回 `Ok(None)`
+///
+pub fn load_env_conf_toml() -> Result<Option<EnvConfig>, ConfigError> {
+    load_config("application", ConfigFormat::Toml)
+}
+
+///
+/// 根据环境加载 `application-{action}.toml` 全局配置
+///
+/// action  dev 开始环境 test 测试环境 prod 生产环境，多个环境用逗号分隔，合并规则与
+/// [`load_global_config`](crate::load_global_config) 相同
+///
+pub fn load_global_config_toml(action: String) -> Result<Option<GlobalConfig>, ConfigError> {
+    load_profiles(&action, ConfigFormat::Toml)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn deserializes_global_config() {
+        let config: GlobalConfig = toml::from_str(
+            r#"
+            [server]
+            port = 8080
+            context_path = "/api"
+
+            [mysql]
+            host = "localhost"
+            port = 3306
+            user = "root"
+            password = "secret"
+            db = "summer"
+            pool_min_idle = 8
+            pool_max_open = 32
+            timeout_seconds = 15
+            "#,
+        )
+        .unwrap();
+        let server = config.server.unwrap();
+        assert_eq!(server.port, 8080);
+        assert_eq!(server.context_path, "/api");
+        assert_eq!(server.charset, None);
+        assert_eq!(config.mysql.unwrap().db, "summer");
+
+        let env: EnvConfig = toml::from_str("[profiles]\nactive = \"dev\"").unwrap();
+        assert_eq!(env.profiles.active, "dev");
+    }
+}
@@ -1,11 +1,9 @@
-use schemars::schema::RootSchema;
-use serde::{Deserialize, Serialize};
-use serde_json::{from_str as json_from_str, to_string_pretty};
-use serde_yaml::from_str as yaml_from_str;
-use std::{
-    fs::{self, read_to_string},
-    io::Read
+use crate::{
+    active_profile, apply_env_overrides, load_config, load_env_conf_toml, load_global_config_toml,
+    load_profiles, ConfigError, ConfigFormat, ConfigValidator, PROFILES_ACTIVE_ENV,
 };
+use serde::{Deserialize, Serialize};
+use std::fs;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct GlobalConfig {
@@ -13,29 +11,85 @@ pub struct GlobalConfig {
     pub server: Option<Server>,
 }
 
+/// 缺省的配置项使用 `Default` 中的值，与 [`generate_template`](crate::generate_template) 一致
+///
+/// `user` 和 `password` 没有默认值，缺少时配置校验失败，不会以默认账号连接数据库。
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Mysql {
     pub host: String,
     pub port: u32,
-    pub user: String,
-    pub password: String,
+    /// 用户名，必填
+    pub user: Option<String>,
+    /// 密码，必填，无密码的账号写空字符串
+    pub password: Option<String>,
     pub db: String,
     pub pool_min_idle: u64,
     pub pool_max_open: u64,
     pub timeout_seconds: u64,
 }
 
+impl Default for Mysql {
+    fn default() -> Self {
+        Mysql {
+            host: "127.0.0.1".to_string(),
+            port: 3306,
+            user: None,
+            password: None,
+            db: "summer".to_string(),
+            pool_min_idle: 1,
+            pool_max_open: 10,
+            timeout_seconds: 30,
+        }
+    }
+}
+
+/// 缺省的配置项使用 `Default` 中的值，与 [`generate_template`](crate::generate_template) 一致
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Server {
     pub port: u32,
     pub context_path: String,
+    /// text/* 响应的默认字符集
+    pub charset: Option<String>,
+    /// 受信任的反向代理，CIDR 或单个地址
+    pub trusted_proxies: Vec<String>,
+    /// keep-alive 连接两个请求之间的最长空闲时间，单位秒，缺省不限制
+    pub keep_alive_timeout_seconds: Option<u64>,
+    /// 每个连接最多处理的请求数，缺省不限制
+    pub max_requests_per_connection: Option<usize>,
+    /// 开发模式，错误响应中包含 panic 等内部信息，生产环境不要开启
+    pub debug_mode: bool,
+}
+
+impl Default for Server {
+    fn default() -> Self {
+        Server {
+            port: 8080,
+            context_path: "/".to_string(),
+            charset: None,
+            trusted_proxies: Vec::new(),
+            keep_alive_timeout_seconds: None,
+            max_requests_per_connection: None,
+            debug_mode: false,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize)]
+#[serde(default)]
 pub struct Profiles {
     pub active: String,
 }
 
+impl Default for Profiles {
+    fn default() -> Self {
+        Profiles {
+            active: "dev".to_string(),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct EnvConfig {
     pub profiles: Profiles,
@@ -62,17 +116,17 @@ struct Name {
 ///
 /// 判断是workspace还是project
 ///
-fn check_project_workspace() -> String {
+pub(crate) fn check_project_workspace() -> String {
     let mut types: String = String::new();
 
-    // 找到需要扫描的路径
-    let mut cargo_toml = fs::File::open("Cargo.toml").expect("2222");
-    let mut content = String::new();
-    cargo_toml.read_to_string(&mut content).expect("3333");
+    // 找到需要扫描的路径，没有 Cargo.toml 时无法定位配置文件
+    let Ok(content) = fs::read_to_string("Cargo.toml") else {
+        return types;
+    };
 
     // 根据包类型分别处理
     if let Ok(conf_work_space) = toml::from_str::<ConfWorkSpace>(&content) {
-        if let Some(_) = conf_work_space.workspace {
+        if conf_work_space.workspace.is_some() {
             types.push_str("workspace");
         } else {
             types.push_str("project");
@@ -84,10 +138,10 @@ fn check_project_workspace() -> String {
 ///
 /// 获取toml package_name
 ///
-fn get_package_name() -> String {
-    let mut cargo_toml = fs::File::open("Cargo.toml").expect("4444");
-    let mut content = String::new();
-    cargo_toml.read_to_string(&mut content).expect("5555");
+pub(crate) fn get_package_name() -> String {
+    let Ok(content) = fs::read_to_string("Cargo.toml") else {
+        return String::from("_");
+    };
 
     let mut projects = Vec::<String>::new();
 
@@ -96,15 +150,16 @@ fn get_package_name() -> String {
             if let Some(members) = workspace.members {
                 for member in members {
                     projects.push(format!("{}/src/resources/application.yml", member));
+                    projects.push(format!("{}/src/resources/application.toml", member));
                     for project in &projects {
                         let check = fs::metadata(project).is_ok();
-                        if check == true {
+                        if check {
                             return member;
                         }
                     }
                 }
             }
-        } else if projects.len() == 0 {
+        } else if projects.is_empty() {
             if let Some(package) = conf_work_space.package {
                 return package.name;
             }
@@ -117,36 +172,10 @@ fn get_package_name() -> String {
 ///
 /// 加载环境配置
 ///
-pub fn load_env_conf() -> Option<EnvConfig> {
-    let mut path = String::new();
-    let types = check_project_workspace();
-
-    if types.eq("workspace") {
-        let package_name = get_package_name();
-        path = format!("{}/src/resources/application.yml", package_name);
-    } else if types.eq("project") {
-        path = format!("src/resources/application.yml");
-    }
-
-    let schema = yaml_from_str::<RootSchema>(&read_to_string(&path).unwrap_or_else(|_| {
-        panic!(
-            "Error loading configuration file {}, please check the configuration!",
-            &path
-        )
-    }));
-    return match schema {
-        Ok(json) => {
-            let data =
-                to_string_pretty(&json).expect("resources/application.yml file data error！");
-            let p: EnvConfig =
-                json_from_str(&*data).expect("Failed to transfer JSON data to EnvConfig object！");
-            return Some(p);
-        }
-        Err(err) => {
-            println!("{}", err);
-            None
-        }
-    };
+/// `application.yml` 不存在时返回 `Ok(None)`，格式错误时返回 `Err`。
+///
+pub fn load_env_conf() -> Result<Option<EnvConfig>, ConfigError> {
+    load_config("application", ConfigFormat::Yaml)
 }
 
 ///
@@ -154,48 +183,77 @@ pub fn load_env_conf() -> Option<EnvConfig> {
 ///
 /// action  dev 开始环境 test 测试环境 prod 生产环境
 ///
-pub fn load_global_config(action: String) -> Option<GlobalConfig> {
-    let mut path = String::new();
-    let types = check_project_workspace();
+/// 可以用逗号分隔多个环境，例如 `prod,metrics`，依次加载 `application-{action}.yml`
+/// 并深度合并，后面的环境覆盖前面的。不存在的配置文件会被跳过，都不存在时返回 `Ok(None)`。
+///
+pub fn load_global_config(action: String) -> Result<Option<GlobalConfig>, ConfigError> {
+    load_profiles(&action, ConfigFormat::Yaml)
+}
 
-    if types.eq("workspace") {
-        let package_name = get_package_name();
-        path = format!("{}/src/resources/application-{}.yml", package_name, &action);
-    } else if types.eq("project") {
-        path = format!("src/resources/application-{}.yml", &action);
-    }
+///
+/// 先加载环境配置 在根据当前加载的环境 去加载相应的信息
+///
+/// 按 [`ConfigFormat::order`] 的优先顺序选择第一个存在的 `application.yml` 或
+/// `application.toml`，两种格式的配置结构相同。生效的环境按以下顺序确定：
+///
+/// 1. 环境变量 [`PROFILES_ACTIVE_ENV`](crate::PROFILES_ACTIVE_ENV)（`SUMMER_PROFILES_ACTIVE`），
+///    对应的 `application-{profile}` 文件不存在时打印警告并忽略
+/// 2. `application.yml` 或 `application.toml` 中的 `profiles.active`
+///
+/// 加载环境的配置文件之后再应用 [`apply_env_overrides`] 的环境变量覆盖，
+/// 最后用 [`ConfigValidator`] 校验。所有校验错误都会打印到标准错误，
+/// 然后以 [`ConfigError::Validation`] 返回，避免每次只能发现一个错误。
+/// 没有配置文件时返回 `Ok(None)`，配置文件无法读取或格式错误时返回 `Err`。
+///
+pub fn load_conf() -> Result<Option<GlobalConfig>, ConfigError> {
+    load_conf_in(&ConfigFormat::order())
+}
 
-    let schema = yaml_from_str::<RootSchema>(&read_to_string(&path).unwrap_or_else(|_| {
-        panic!(
-            "Error loading configuration file {}, please check the configuration!",
-            &path
-        )
-    }));
-    return match schema {
-        Ok(json) => {
-            let data = to_string_pretty(&json).unwrap_or_else(|_| {
-                panic!(
-                    "{} file data error！, please check the configuration!",
-                    path
-                )
-            });
-            let p = json_from_str(&*data)
-                .expect("Failed to transfer JSON data to BriefProConfig object！");
-            return Some(p);
-        }
-        Err(err) => {
-            println!("{}", err);
-            None
-        }
-    };
+///
+/// 同 [`load_conf`]，按 `order` 的顺序选择配置文件格式
+///
+pub fn load_conf_in(order: &[ConfigFormat]) -> Result<Option<GlobalConfig>, ConfigError> {
+    load_conf_for(order, std::env::var(PROFILES_ACTIVE_ENV).ok())
 }
 
 ///
-/// 先加载环境配置 在根据当前加载的环境 去加载相应的信息
+/// 同 [`load_conf`]，使用 `profile` 代替环境变量 [`PROFILES_ACTIVE_ENV`](crate::PROFILES_ACTIVE_ENV)
 ///
-pub fn load_conf() -> Option<GlobalConfig> {
-    if let Some(init) = load_env_conf() {
-        return load_global_config(init.profiles.active);
+/// `profile` 对应的配置文件不存在时同样回退到 `profiles.active`。
+/// 测试中使用它加载 `test` 环境，不需要修改进程的环境变量。
+///
+pub fn load_conf_with_profile(profile: &str) -> Result<Option<GlobalConfig>, ConfigError> {
+    load_conf_for(&ConfigFormat::order(), Some(profile.to_string()))
+}
+
+fn load_conf_for(
+    order: &[ConfigFormat],
+    requested: Option<String>,
+) -> Result<Option<GlobalConfig>, ConfigError> {
+    let Some(format) = ConfigFormat::detect_in(order) else {
+        return Ok(None);
+    };
+    let init = match format {
+        ConfigFormat::Toml => load_env_conf_toml()?,
+        ConfigFormat::Yaml => load_env_conf()?,
+    };
+    let Some(init) = init else {
+        return Ok(None);
+    };
+    let action = active_profile(init.profiles.active, requested, format);
+    let config = match format {
+        ConfigFormat::Toml => load_global_config_toml(action)?,
+        ConfigFormat::Yaml => load_global_config(action)?,
+    };
+    let Some(config) = config.map(apply_env_overrides) else {
+        return Ok(None);
+    };
+    let errors = config.validate();
+    if !errors.is_empty() {
+        for error in &errors {
+            eprintln!("error: invalid configuration {}", error);
+        }
+        return Err(ConfigError::Validation(errors));
     }
-    None
+    Ok(Some(config))
 }
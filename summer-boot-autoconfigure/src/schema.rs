@@ -0,0 +1,285 @@
+use crate::{ConfigError, ConfigValidator, Mysql, Profiles, Server};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+///
+/// 一个配置项的说明
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConfigKey {
+    /// 配置项名称，与结构体字段名相同
+    pub name: &'static str,
+    /// 取值类型，例如 `integer`、`string`
+    pub ty: &'static str,
+    /// 说明，生成模板时作为注释
+    pub doc: &'static str,
+}
+
+///
+/// 一段配置的结构，默认值来自对应结构体的 `Default`，缺省配置项时使用相同的值
+///
+/// 取值的校验使用结构体的 [`ConfigValidator`] 实现。
+///
+pub trait ConfigSchema: Serialize + DeserializeOwned + Default + ConfigValidator {
+    /// 段名称，例如 `server`
+    const NAME: &'static str;
+    /// 段说明
+    const DOC: &'static str;
+    /// 段中的所有配置项，顺序即模板中的顺序
+    const KEYS: &'static [ConfigKey];
+}
+
+///
+/// 注册表中的一段配置
+///
+#[derive(Debug, Clone)]
+pub struct ConfigSection {
+    pub name: &'static str,
+    pub doc: &'static str,
+    pub keys: &'static [ConfigKey],
+    /// 每个配置项的默认值，没有默认值的配置项为 `null`
+    pub defaults: Value,
+    validate: fn(&Value) -> Vec<ConfigError>,
+}
+
+impl ConfigSection {
+    fn of<T: ConfigSchema>() -> Self {
+        ConfigSection {
+            name: T::NAME,
+            doc: T::DOC,
+            keys: T::KEYS,
+            defaults: serde_json::to_value(T::default()).unwrap_or(Value::Null),
+            validate: validate_section::<T>,
+        }
+    }
+
+    ///
+    /// 校验这一段配置的取值，`value` 无法转换为对应的结构体时返回一个错误
+    ///
+    pub fn validate(&self, value: &Value) -> Vec<ConfigError> {
+        (self.validate)(value)
+    }
+}
+
+fn validate_section<T: ConfigSchema>(value: &Value) -> Vec<ConfigError> {
+    match T::deserialize(value) {
+        Ok(section) => section.validate(),
+        Err(err) => vec![ConfigError::invalid(T::NAME, err.to_string())],
+    }
+}
+
+impl ConfigSchema for Profiles {
+    const NAME: &'static str = "profiles";
+    const DOC: &'static str = "切换配置文件，只在 application.yml 中生效";
+    const KEYS: &'static [ConfigKey] = &[ConfigKey {
+        name: "active",
+        ty: "string",
+        doc: "生效的环境，加载 application-{active}.yml，多个环境用逗号分隔",
+    }];
+}
+
+impl ConfigSchema for Server {
+    const NAME: &'static str = "server";
+    const DOC: &'static str = "Web 服务器";
+    const KEYS: &'static [ConfigKey] = &[
+        ConfigKey {
+            name: "port",
+            ty: "integer",
+            doc: "监听端口，0 - 65535，0 表示由系统分配空闲端口",
+        },
+        ConfigKey {
+            name: "context_path",
+            ty: "string",
+            doc: "所有路由的前缀，必须以 / 开头",
+        },
+        ConfigKey {
+            name: "charset",
+            ty: "string",
+            doc: "text/* 响应的默认字符集，例如 utf-8",
+        },
+        ConfigKey {
+            name: "trusted_proxies",
+            ty: "list",
+            doc: "受信任的反向代理，CIDR 或单个地址，例如 [10.0.0.0/8, \"::1\"]",
+        },
+        ConfigKey {
+            name: "keep_alive_timeout_seconds",
+            ty: "integer",
+            doc: "keep-alive 连接两个请求之间的最长空闲时间，单位秒，缺省不限制",
+        },
+        ConfigKey {
+            name: "max_requests_per_connection",
+            ty: "integer",
+            doc: "每个连接最多处理的请求数，之后关闭连接，缺省不限制",
+        },
+        ConfigKey {
+            name: "debug_mode",
+            ty: "bool",
+            doc: "开发模式，错误响应中包含 panic 消息等内部信息，生产环境不要开启",
+        },
+    ];
+}
+
+impl ConfigSchema for Mysql {
+    const NAME: &'static str = "mysql";
+    const DOC: &'static str = "mysql 配置，不使用数据库时删除此段";
+    const KEYS: &'static [ConfigKey] = &[
+        ConfigKey {
+            name: "host",
+            ty: "string",
+            doc: "主机名或 IP 地址",
+        },
+        ConfigKey {
+            name: "port",
+            ty: "integer",
+            doc: "端口，1 - 65535",
+        },
+        ConfigKey {
+            name: "user",
+            ty: "string",
+            doc: "用户名，必填",
+        },
+        ConfigKey {
+            name: "password",
+            ty: "string",
+            doc: "密码，必填，无密码的账号写空字符串",
+        },
+        ConfigKey {
+            name: "db",
+            ty: "string",
+            doc: "数据库名称",
+        },
+        ConfigKey {
+            name: "pool_min_idle",
+            ty: "integer",
+            doc: "最小连接数，不能大于 pool_max_open",
+        },
+        ConfigKey {
+            name: "pool_max_open",
+            ty: "integer",
+            doc: "最大连接数",
+        },
+        ConfigKey {
+            name: "timeout_seconds",
+            ty: "integer",
+            doc: "连接超时时间，单位秒",
+        },
+    ];
+}
+
+///
+/// 所有配置段，按模板中的顺序排列
+///
+pub fn config_sections() -> Vec<ConfigSection> {
+    vec![
+        ConfigSection::of::<Profiles>(),
+        ConfigSection::of::<Server>(),
+        ConfigSection::of::<Mysql>(),
+    ]
+}
+
+///
+/// 根据 [`config_sections`] 生成带注释的 YAML 配置模板
+///
+/// 模板中的值即缺省时使用的默认值，没有默认值的配置项被注释掉。
+/// `profiles` 段放在 `application.yml` 中，其他段放在 `application-{profile}.yml` 中。
+///
+pub fn generate_template() -> String {
+    let mut template = String::from(
+        "# summer-boot 配置模板\n\
+         # profiles 段放在 application.yml 中，其他段放在 application-{profile}.yml 中\n",
+    );
+    for section in config_sections() {
+        template.push_str(&format!("\n# {}\n{}:\n", section.doc, section.name));
+        for key in section.keys {
+            template.push_str(&format!("  # {} ({})\n", key.doc, key.ty));
+            match section.defaults.get(key.name) {
+                Some(Value::Null) | None => template.push_str(&format!("  # {}:\n", key.name)),
+                Some(value) => template.push_str(&format!("  {}: {}\n", key.name, scalar(value))),
+            }
+        }
+    }
+    template
+}
+
+/// 单个值的 YAML 表示
+fn scalar(value: &Value) -> String {
+    serde_yaml::to_string(value)
+        .map(|yaml| yaml.trim_end().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{ConfigValidator, EnvConfig, GlobalConfig};
+
+    #[test]
+    fn registry_matches_config_structs() {
+        for section in config_sections() {
+            let mut names: Vec<&str> = section.keys.iter().map(|key| key.name).collect();
+            let mut fields: Vec<&str> = section
+                .defaults
+                .as_object()
+                .unwrap()
+                .keys()
+                .map(String::as_str)
+                .collect();
+            names.sort_unstable();
+            fields.sort_unstable();
+            assert_eq!(names, fields, "keys of section {}", section.name);
+        }
+    }
+
+    #[test]
+    fn template_is_valid_and_uses_defaults() {
+        let template = generate_template();
+        let config: GlobalConfig = serde_yaml::from_str(&template).unwrap();
+        // 只有没有默认值的数据库账号需要填写
+        let missing: Vec<String> = config
+            .validate()
+            .into_iter()
+            .map(|err| match err {
+                ConfigError::Invalid { field, .. } => field,
+                err => panic!("unexpected error {}", err),
+            })
+            .collect();
+        assert_eq!(missing, ["mysql.user", "mysql.password"]);
+        let env: EnvConfig = serde_yaml::from_str(&template).unwrap();
+        assert_eq!(env.profiles.active, "dev");
+
+        // 模板中的值与缺省配置项时的值相同
+        let template: Value = serde_yaml::from_str(&template).unwrap();
+        let absent: GlobalConfig = serde_yaml::from_str("server: {}\nmysql: {}").unwrap();
+        let absent = serde_json::to_value(absent).unwrap();
+        for section in config_sections() {
+            for key in section.keys {
+                let default = &section.defaults[key.name];
+                assert_eq!(&template[section.name][key.name], default, "{}", key.name);
+                if section.name != "profiles" {
+                    assert_eq!(&absent[section.name][key.name], default, "{}", key.name);
+                }
+            }
+        }
+        let absent: EnvConfig = serde_yaml::from_str("profiles: {}").unwrap();
+        assert_eq!(absent.profiles.active, "dev");
+    }
+
+    #[test]
+    fn sections_validate_their_values() {
+        let server = config_sections()
+            .into_iter()
+            .find(|section| section.name == "server")
+            .unwrap();
+        assert!(server.validate(&server.defaults).is_empty());
+        let errors = server.validate(&serde_json::json!({ "port": 70000 }));
+        assert_eq!(
+            errors.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            ["server.port: must be between 0 and 65535, got 70000"]
+        );
+        let errors = server.validate(&serde_json::json!({ "port": "http" }));
+        assert!(errors[0].to_string().starts_with("server: invalid type"));
+    }
+}
@@ -0,0 +1,240 @@
+use crate::{config_sections, ConfigError, GlobalConfig, Mysql, Profiles, Server};
+
+use std::net::IpAddr;
+
+///
+/// 校验配置项的取值，返回发现的所有错误
+///
+pub trait ConfigValidator {
+    fn validate(&self) -> Vec<ConfigError>;
+}
+
+/// 按 [`config_sections`] 的顺序校验存在的每一段配置
+impl ConfigValidator for GlobalConfig {
+    fn validate(&self) -> Vec<ConfigError> {
+        let config = serde_json::to_value(self).expect("config is serializable");
+        config_sections()
+            .iter()
+            .filter_map(|section| match config.get(section.name) {
+                None | Some(serde_json::Value::Null) => None,
+                Some(value) => Some(section.validate(value)),
+            })
+            .flatten()
+            .collect()
+    }
+}
+
+impl ConfigValidator for Profiles {
+    fn validate(&self) -> Vec<ConfigError> {
+        Vec::new()
+    }
+}
+
+impl ConfigValidator for Server {
+    fn validate(&self) -> Vec<ConfigError> {
+        let mut errors = Vec::new();
+        // 0 表示由系统分配空闲端口，实际地址在启动日志中输出
+        check_port("server.port", self.port, 0, &mut errors);
+        if !self.context_path.starts_with('/') {
+            errors.push(ConfigError::invalid(
+                "server.context_path",
+                format!("must start with '/', got {:?}", self.context_path),
+            ));
+        }
+        for proxy in &self.trusted_proxies {
+            if !is_valid_cidr(proxy) {
+                errors.push(ConfigError::invalid(
+                    "server.trusted_proxies",
+                    format!("{:?} is not a valid CIDR or IP address", proxy),
+                ));
+            }
+        }
+        if self.keep_alive_timeout_seconds == Some(0) {
+            errors.push(ConfigError::invalid(
+                "server.keep_alive_timeout_seconds",
+                "must be greater than 0",
+            ));
+        }
+        if self.max_requests_per_connection == Some(0) {
+            errors.push(ConfigError::invalid(
+                "server.max_requests_per_connection",
+                "must be greater than 0",
+            ));
+        }
+        errors
+    }
+}
+
+impl ConfigValidator for Mysql {
+    fn validate(&self) -> Vec<ConfigError> {
+        let mut errors = Vec::new();
+        if !is_valid_host(&self.host) {
+            errors.push(ConfigError::invalid(
+                "mysql.host",
+                format!("{:?} is not a valid hostname or IP address", self.host),
+            ));
+        }
+        check_port("mysql.port", self.port, 1, &mut errors);
+        // 不以默认账号连接数据库
+        for (field, value) in [
+            ("mysql.user", &self.user),
+            ("mysql.password", &self.password),
+        ] {
+            if value.is_none() {
+                errors.push(ConfigError::invalid(field, "is required"));
+            }
+        }
+        if self.pool_min_idle > self.pool_max_open {
+            errors.push(ConfigError::invalid(
+                "mysql.pool_min_idle",
+                format!(
+                    "must not exceed mysql.pool_max_open ({} > {})",
+                    self.pool_min_idle, self.pool_max_open
+                ),
+            ));
+        }
+        errors
+    }
+}
+
+fn check_port(field: &str, port: u32, min: u32, errors: &mut Vec<ConfigError>) {
+    if !(min..=65535).contains(&port) {
+        errors.push(ConfigError::invalid(
+            field,
+            format!("must be between {} and 65535, got {}", min, port),
+        ));
+    }
+}
+
+/// 单个 IP 地址或 `地址/前缀长度`
+fn is_valid_cidr(cidr: &str) -> bool {
+    let (addr, prefix) = match cidr.trim().split_once('/') {
+        Some((addr, prefix)) => (addr, Some(prefix)),
+        None => (cidr.trim(), None),
+    };
+    let max = match addr.parse::<IpAddr>() {
+        Ok(IpAddr::V4(_)) => 32,
+        Ok(IpAddr::V6(_)) => 128,
+        Err(_) => return false,
+    };
+    match prefix {
+        Some(prefix) => prefix.parse::<u8>().is_ok_and(|prefix| prefix <= max),
+        None => true,
+    }
+}
+
+/// IP 地址或符合 RFC 1123 的主机名
+fn is_valid_host(host: &str) -> bool {
+    if host.parse::<IpAddr>().is_ok() {
+        return true;
+    }
+    let host = host.strip_suffix('.').unwrap_or(host);
+    !host.is_empty()
+        && host.len() <= 253
+        && host.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn mysql(host: &str) -> Mysql {
+        Mysql {
+            host: host.to_string(),
+            port: 3306,
+            user: Some("root".to_string()),
+            password: Some(String::new()),
+            db: "summer".to_string(),
+            pool_min_idle: 1,
+            pool_max_open: 10,
+            timeout_seconds: 30,
+        }
+    }
+
+    #[test]
+    fn valid_config_has_no_errors() {
+        let config = GlobalConfig {
+            mysql: Some(mysql("db-1.internal")),
+            server: Some(Server {
+                port: 8080,
+                context_path: "/".to_string(),
+                charset: None,
+                trusted_proxies: vec!["10.0.0.0/8".to_string(), "::1".to_string()],
+                keep_alive_timeout_seconds: Some(75),
+                max_requests_per_connection: Some(1000),
+                debug_mode: false,
+            }),
+        };
+        assert!(config.validate().is_empty());
+        assert!(mysql("127.0.0.1").validate().is_empty());
+        assert!(mysql("::1").validate().is_empty());
+        assert!(mysql("localhost").validate().is_empty());
+    }
+
+    #[test]
+    fn collects_every_error() {
+        let mut db = mysql("bad_host!");
+        db.port = 0;
+        db.pool_min_idle = 20;
+        db.password = None;
+        let config = GlobalConfig {
+            mysql: Some(db),
+            server: Some(Server {
+                port: 70000,
+                context_path: "api".to_string(),
+                charset: None,
+                trusted_proxies: vec!["10.0.0.0/33".to_string()],
+                keep_alive_timeout_seconds: Some(0),
+                max_requests_per_connection: Some(0),
+                debug_mode: false,
+            }),
+        };
+        let fields: Vec<String> = config
+            .validate()
+            .into_iter()
+            .map(|err| match err {
+                ConfigError::Invalid { field, .. } => field,
+                err => panic!("unexpected error {}", err),
+            })
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                "server.port",
+                "server.context_path",
+                "server.trusted_proxies",
+                "server.keep_alive_timeout_seconds",
+                "server.max_requests_per_connection",
+                "mysql.host",
+                "mysql.port",
+                "mysql.password",
+                "mysql.pool_min_idle",
+            ]
+        );
+
+        let err = ConfigError::Validation(
+            Server {
+                port: 70000,
+                context_path: "/".to_string(),
+                charset: None,
+                trusted_proxies: Vec::new(),
+                keep_alive_timeout_seconds: None,
+                max_requests_per_connection: None,
+                debug_mode: false,
+            }
+            .validate(),
+        );
+        assert_eq!(
+            err.to_string(),
+            "invalid configuration:\n  - server.port: must be between 0 and 65535, got 70000"
+        );
+        assert!(!is_valid_host("-db.example"));
+        assert!(!is_valid_host(&"a".repeat(64)));
+    }
+}
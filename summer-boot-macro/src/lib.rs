@@ -6,21 +6,30 @@
 //! # auto_scan
 //! 提供了基础的`auto_scan`功能用于发现并自动注册路由。
 //!
+//! # component
+//! 标注组件，由`auto_scan`在注册路由之前创建并注册。
+//!
+//! # contribute
+//! 在库 crate 中贡献路由，由`Server::mount_contributed`在链接后统一注册。
+//!
 //! # post、get、delete、put、patch、head、options、connect、trace
 //! 提供了简单的路由宏标注。
 //!
+//! # route
+//! 同一个函数注册多个请求方法的路由宏标注。
+//!
 //!
 
 use proc_macro::TokenStream;
 use proc_macro2::{Ident, Span};
 use quote::{quote, ToTokens};
 use serde::Deserialize;
-use serde_json::Value;
-use std::io::Read;
 use std::fs;
+use std::io::Read;
+use std::path::Path;
 use syn::{
-    parse_file, parse_macro_input, parse_quote, punctuated::Punctuated, AttributeArgs, Item,
-    ItemFn, Lit, Meta, NestedMeta, Pat, Stmt, Token,
+    parse_file, parse_macro_input, parse_quote, punctuated::Punctuated, AttributeArgs, FnArg, Item,
+    ItemFn, ItemStruct, Lit, Meta, NestedMeta, Pat, Stmt, Token, Type,
 };
 
 /// 用于匹配项目根目录下的 `Cargo.toml` 文件。
@@ -47,6 +56,10 @@ struct Name {
 }
 
 /// 用于标记 summer_boot web 的入口点
+///
+/// 可以通过 `worker_threads = N` 设置运行时的工作线程数，
+/// 需要更多配置时手动使用 `summer_boot::rt::SummerRuntimeBuilder`。
+/// 命令行参数包含 `--generate-config` 时打印配置模板并退出。
 /// # Examples
 /// ```
 /// #[summer_boot::main]
@@ -54,8 +67,36 @@ struct Name {
 ///     async { println!("Hello world"); }.await
 /// }
 /// ```
+/// ```
+/// #[summer_boot::main(worker_threads = 2)]
+/// async fn main() {
+///     async { println!("Hello world"); }.await
+/// }
+/// ```
 #[proc_macro_attribute]
-pub fn main(_: TokenStream, item: TokenStream) -> TokenStream {
+pub fn main(args: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args as AttributeArgs);
+    let mut worker_threads = None;
+    for arg in args {
+        match arg {
+            NestedMeta::Meta(Meta::NameValue(meta)) if meta.path.is_ident("worker_threads") => {
+                match meta.lit {
+                    Lit::Int(n) => worker_threads = Some(n),
+                    lit => {
+                        return syn::Error::new_spanned(lit, "worker_threads 必须是整数")
+                            .to_compile_error()
+                            .into();
+                    }
+                }
+            }
+            arg => {
+                return syn::Error::new_spanned(arg, "仅支持 worker_threads = N")
+                    .to_compile_error()
+                    .into();
+            }
+        }
+    }
+
     let mut input = parse_macro_input!(item as ItemFn);
     let attrs = &input.attrs;
     let vis = &input.vis;
@@ -70,10 +111,21 @@ pub fn main(_: TokenStream, item: TokenStream) -> TokenStream {
     }
     sig.asyncness = None;
 
+    let runtime = match worker_threads {
+        Some(n) => quote! {
+            summer_boot::rt::SummerRuntime::builder()
+                .worker_threads(#n)
+                .build()
+                .expect("创建运行时失败")
+        },
+        None => quote! { summer_boot::rt::SummerRuntime::new() },
+    };
+
     (quote! {
         #(#attrs)*
         #vis #sig {
-            summer_boot::rt::SummerRuntime::new()
+            summer_boot::config::generate_config_if_requested();
+            #runtime
             .block_on(async move { #body });
         }
     })
@@ -84,11 +136,14 @@ pub fn main(_: TokenStream, item: TokenStream) -> TokenStream {
 /// 函数，然后在此处进行装配活动。也可以手动增加过滤路径或过滤文件。
 /// 如果增加过滤路径，需要在末尾添加 `/`，如果增加过滤文件，需要在末尾添加 `.rs`。
 ///
+/// 不同函数注册了相同的请求方法和路径时编译失败，错误中包含两个函数的路径；
+/// 带有不同 `#[cfg(...)]` 条件的函数不做检查。
+///
 /// 注意：如果需要在此处添加运行时，必须在当前宏的后面配置，否则无法完成装配
 /// # Examples
-/// ```rust
-/// // #[summer_boot::auto_scan]
-/// // #[summer_boot::auto_scan("summer-boot-tests/src/lib.rs")]
+/// ```rust,ignore
+/// #[summer_boot::auto_scan]
+/// #[summer_boot::auto_scan("summer-boot-tests/src/lib.rs")]
 /// fn main() {
 ///     summer_boot::run();
 /// }
@@ -97,13 +152,25 @@ pub fn main(_: TokenStream, item: TokenStream) -> TokenStream {
 pub fn auto_scan(args: TokenStream, input: TokenStream) -> TokenStream {
     let mut project = Vec::<String>::new();
     let mut filter_paths = Vec::<String>::new();
+
+    // 解析宏信息
+    let args = parse_macro_input!(args as AttributeArgs);
+    for arg in args {
+        if let NestedMeta::Lit(Lit::Str(project)) = arg {
+            filter_paths.push(project.value());
+        }
+    }
+
+    // 解析函数体
+    let mut input = parse_macro_input!(input as ItemFn);
+
     // 找到需要扫描的路径
-    let mut cargo_toml = fs::File::open("Cargo.toml").expect("Cargo Toml文件找不到");
     let mut content = String::new();
-
-    cargo_toml
-        .read_to_string(&mut content)
-        .expect("Cargo内容为空");
+    if let Err(err) =
+        fs::File::open("Cargo.toml").and_then(|mut file| file.read_to_string(&mut content))
+    {
+        return scan_failed(scan_error(Path::new("Cargo.toml"), err), &input);
+    }
 
     // 根据包类型分别处理
     if let Ok(conf_work_space) = toml::from_str::<ConfWorkSpace>(&content) {
@@ -113,57 +180,140 @@ pub fn auto_scan(args: TokenStream, input: TokenStream) -> TokenStream {
                     project.push(format!("{}/{}", member, "src"));
                 }
             }
-        } else if project.len() == 0 {
-            if let Some(_) = conf_work_space.package {
-                project.push("src".to_string());
-            }
+        } else if project.is_empty() && conf_work_space.package.is_some() {
+            project.push("src".to_string());
         }
     }
 
-    // 解析宏信息
-    let args = parse_macro_input!(args as AttributeArgs);
-    for arg in args {
-        if let NestedMeta::Lit(Lit::Str(project)) = arg {
-            filter_paths.push(project.value());
-        }
-    }
-
-    // 解析函数体
-    let mut input = parse_macro_input!(input as ItemFn);
-
     // 查找主函数的位置和是否存在变量名
     // 未找到则直接退出宏处理
     // 变量名不存在则默认添加app
     // 如果存在则会返回出来，供后续使用
-    if let Some((master_index, master_name)) = scan_master_fn(&mut input) {
+    if let Some((mut master_index, master_name)) = scan_master_fn(&mut input) {
         // 解析yaml文件
         let mut listener_addr = String::from("0.0.0.0:");
         let mut app_context_path = String::from("");
-        let config = summer_boot_autoconfigure::load_conf();
+        let mut charset = None;
+        let mut trusted_proxies = Vec::new();
+        let mut keep_alive_timeout = None;
+        let mut max_requests = None;
+        let config = match summer_boot_autoconfigure::load_conf() {
+            Ok(config) => config,
+            Err(err) => {
+                return scan_failed(syn::Error::new(Span::call_site(), err), &input);
+            }
+        };
         if let Some(config) = config {
-            let read_server = serde_json::to_string(&config.server).expect("读取服务配置文件失败");
-            let v: Value = serde_json::from_str(&read_server).expect("读取服务配置文件失败");
+            let v = match serde_json::to_value(&config.server) {
+                Ok(v) => v,
+                Err(err) => {
+                    let message = format!("读取服务配置文件失败：{}", err);
+                    return scan_failed(syn::Error::new(Span::call_site(), message), &input);
+                }
+            };
             let port = v["port"].to_string();
             let context_path = v["context_path"].to_string();
             listener_addr.push_str(&port);
             app_context_path.push_str(&context_path);
+            charset = v["charset"].as_str().map(str::to_string);
+            if let Some(server) = &config.server {
+                trusted_proxies = server.trusted_proxies.clone();
+                keep_alive_timeout = server.keep_alive_timeout_seconds;
+                max_requests = server.max_requests_per_connection;
+            }
+        }
+
+        // 配置受信任的反向代理
+        if !trusted_proxies.is_empty() {
+            master_index += 1;
+            input.block.stmts.insert(
+                master_index as usize,
+                parse_quote! {
+                    #master_name.trusted_proxies(
+                        summer_boot::utils::trusted_proxy::TrustedProxies::parse([#(#trusted_proxies),*])
+                            .expect("server.trusted_proxies 配置错误"),
+                    );
+                },
+            );
+        }
+
+        // 配置默认字符集
+        if let Some(charset) = charset {
+            master_index += 1;
+            input.block.stmts.insert(
+                master_index as usize,
+                parse_quote! {
+                    #master_name.with(summer_boot::utils::content_type::DefaultCharset::new(#charset));
+                },
+            );
+        }
+
+        // 先注册组件，保证路由中可以获取
+        for path in &project {
+            let components = match scan_components(path, &filter_paths) {
+                Ok(components) => components,
+                Err(err) => return scan_failed(err, &input),
+            };
+            for component in components {
+                master_index += 1;
+                input.block.stmts.insert(
+                    master_index as usize,
+                    parse_quote! {
+                        <#component as summer_boot::Component>::register();
+                    },
+                );
+            }
         }
 
-        // 开始扫描
+        // 开始扫描，所有文件中的路由一起检查重复
+        let mut routes = Vec::new();
         for path in project {
-            scan_method(
+            if let Err(err) = scan_method(
                 &path,
                 &filter_paths,
                 &mut input,
                 &app_context_path,
                 (master_index, &master_name),
-            );
+                &mut routes,
+            ) {
+                return scan_failed(err, &input);
+            }
         }
 
-        // 配置listen
-        input.block.stmts.push(parse_quote! {
-            #master_name.listen(#listener_addr).await.expect("配置listen失败");
-        });
+        // 开启 actuator 时，在所有路由之后注册 actuator endpoints
+        if cfg!(feature = "actuator") {
+            input.block.stmts.push(parse_quote! {
+                ::summer_boot::actuator::mount(&mut #master_name, ::summer_boot::build_info!());
+            });
+        }
+
+        // 配置listen，设置了连接选项时通过 `TcpListener` 传入
+        if keep_alive_timeout.is_none() && max_requests.is_none() {
+            input.block.stmts.push(parse_quote! {
+                #master_name.listen(#listener_addr).await.expect("配置listen失败");
+            });
+        } else {
+            let mut server_options = quote! { summer_boot::http::ServerOptions::default() };
+            if let Some(seconds) = keep_alive_timeout {
+                server_options = quote! {
+                    #server_options.idle_timeout(std::time::Duration::from_secs(#seconds))
+                };
+            }
+            if let Some(max) = max_requests {
+                server_options = quote! { #server_options.max_requests_per_connection(#max) };
+            }
+            input.block.stmts.push(parse_quote! {
+                #master_name
+                    .listen(
+                        summer_boot::TcpListener::from_addrs(vec![#listener_addr
+                            .parse()
+                            .expect("配置listen失败")])
+                        .with_server_options(#server_options),
+                    )
+                    .await
+                    .expect("配置listen失败");
+            });
+        }
     }
 
     // 构建新的函数结构，增加函数行
@@ -178,9 +328,9 @@ fn scan_master_fn(input: &mut ItemFn) -> Option<(i32, Ident)> {
     let mut master_index: i32 = -1;
     let mut master_name = Ident::new("app", Span::call_site());
 
-    for (index, stmt) in (&mut input.block.stmts).iter_mut().enumerate() {
+    for (index, stmt) in input.block.stmts.iter_mut().enumerate() {
         let master = stmt.to_token_stream().to_string();
-        if let Some(_) = master.find("summer_boot :: run()") {
+        if master.contains("summer_boot :: run()") {
             master_index = index as i32;
         }
     }
@@ -211,106 +361,428 @@ fn scan_master_fn(input: &mut ItemFn) -> Option<(i32, Ident)> {
     }
 }
 
+/// 标注组件，组件需要实现 `Default`
+///
+/// `auto_scan` 会在注册路由之前创建组件并注册到全局的 `ComponentRegistry`，
+/// 之后通过 `summer_boot::inject::<T>()` 获取。
+/// # Examples
+/// ```rust,ignore
+/// #[summer_boot::component]
+/// #[derive(Default)]
+/// struct UserService;
+///
+/// let service = summer_boot::inject::<UserService>();
+/// ```
+#[proc_macro_attribute]
+pub fn component(_: TokenStream, input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as ItemStruct);
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    (quote! {
+        #input
+
+        impl #impl_generics summer_boot::Component for #name #ty_generics #where_clause {}
+    })
+    .into()
+}
+
+/// 在库 crate 中贡献路由，由二进制 crate 调用 `Server::mount_contributed` 统一注册
+///
+/// 需要开启 summer-boot 的 `distributed-routes` 特性，参数为 HTTP 方法和路径。
+///
+/// # Examples
+/// ```ignore
+/// #[summer_boot::contribute(get, "/lib/feature")]
+/// async fn feature(_req: summer_boot::Request<()>) -> summer_boot::Result {
+///     Ok("feature".into())
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn contribute(args: TokenStream, input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args as AttributeArgs);
+    let input = parse_macro_input!(input as ItemFn);
+
+    let (method, path) = match args.as_slice() {
+        [NestedMeta::Meta(Meta::Path(method)), NestedMeta::Lit(Lit::Str(path))] => (method, path),
+        _ => {
+            return syn::Error::new(Span::call_site(), "用法：#[contribute(get, \"/path\")]")
+                .to_compile_error()
+                .into();
+        }
+    };
+    let variant = match method.get_ident().map(|ident| ident.to_string()).as_deref() {
+        Some("get") => "Get",
+        Some("head") => "Head",
+        Some("put") => "Put",
+        Some("post") => "Post",
+        Some("delete") => "Delete",
+        Some("patch") => "Patch",
+        Some("trace") => "Trace",
+        Some("options") => "Options",
+        Some("connect") => "Connect",
+        _ => {
+            return syn::Error::new_spanned(method, "不支持的 HTTP 方法")
+                .to_compile_error()
+                .into();
+        }
+    };
+    if input.sig.asyncness.is_none() {
+        return syn::Error::new_spanned(input.sig.fn_token, "仅支持 async fn")
+            .to_compile_error()
+            .into();
+    }
+    let variant = Ident::new(variant, Span::call_site());
+    let name = &input.sig.ident;
+
+    (quote! {
+        #input
+
+        const _: () = {
+            #[::summer_boot::contributed::linkme::distributed_slice(
+                ::summer_boot::contributed::CONTRIBUTED_ROUTES
+            )]
+            #[linkme(crate = ::summer_boot::contributed::linkme)]
+            static ROUTE: ::summer_boot::contributed::ContributedRoute =
+                ::summer_boot::contributed::ContributedRoute::new(
+                    env!("CARGO_PKG_NAME"),
+                    ::summer_boot::http_types::Method::#variant,
+                    #path,
+                    concat!(module_path!(), "::", stringify!(#name)),
+                    |route| {
+                        route.method(::summer_boot::http_types::Method::#variant, #name);
+                    },
+                );
+        };
+    })
+    .into()
+}
+
+// 扫描失败时报告错误并原样保留函数，避免再报告找不到 `main`
+fn scan_failed(err: syn::Error, input: &ItemFn) -> TokenStream {
+    let err = err.to_compile_error();
+    quote!(#err #input).into()
+}
+
+// 扫描出错时在 `#[auto_scan]` 属性处报告，附带出错的文件路径
+fn scan_error(path: &Path, message: impl std::fmt::Display) -> syn::Error {
+    syn::Error::new(
+        Span::call_site(),
+        format!("auto_scan 扫描 {} 失败：{}", path.display(), message),
+    )
+}
+
+// 读取并解析扫描到的文件
+fn read_file(path: &Path) -> syn::Result<syn::File> {
+    let content = fs::read_to_string(path).map_err(|err| scan_error(path, err))?;
+    parse_file(&content).map_err(|err| scan_error(path, err))
+}
+
+// 扫描 `#[component]` 标注的结构体，返回结构体的全路径
+fn scan_components(
+    path: &str,
+    filter_paths: &[String],
+) -> syn::Result<Vec<proc_macro2::TokenStream>> {
+    let mut components = Vec::new();
+    if filter_paths.iter().any(|p| path.contains(p)) {
+        return Ok(components);
+    }
+    if let Ok(entries) = fs::read_dir(path) {
+        for entry in entries.flatten() {
+            let file_path = entry.path();
+            if !file_path.is_file() || file_path.extension().map_or(true, |e| e != "rs") {
+                continue;
+            }
+            let ast = read_file(&file_path)?;
+            let mut result = Ok(());
+            walk_items(&ast.items, &mut Vec::new(), &mut |mods, item| {
+                if result.is_err() {
+                    return;
+                }
+                if let Item::Struct(item) = item {
+                    let is_component = item.attrs.iter().any(|attr| {
+                        matches!(
+                            attr.path.to_token_stream().to_string().as_str(),
+                            "component"
+                                | "summer_boot :: component"
+                                | "summer_boot_macro :: component"
+                        )
+                    });
+                    if is_component {
+                        result = config_function_path(&file_path, mods, &item.ident.to_string())
+                            .map(|path| components.push(path));
+                    }
+                }
+            });
+            result?;
+        }
+    }
+    Ok(components)
+}
+
+/// 是否带有只在测试时成立的 `#[cfg]`，例如 `#[cfg(test)]`、`#[cfg(all(test, unix))]`
+fn is_cfg_test(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        attr.path.is_ident("cfg")
+            && matches!(
+                attr.parse_meta(),
+                Ok(Meta::List(list)) if list.nested.len() == 1 && requires_test(&list.nested[0])
+            )
+    })
+}
+
+// cfg 条件成立是否需要 `test`：`test` 本身、包含这样一项的 `all(..)`、每一项都是这样的 `any(..)`
+fn requires_test(predicate: &NestedMeta) -> bool {
+    match predicate {
+        NestedMeta::Meta(Meta::Path(path)) => path.is_ident("test"),
+        NestedMeta::Meta(Meta::List(list)) if list.path.is_ident("all") => {
+            list.nested.iter().any(requires_test)
+        }
+        NestedMeta::Meta(Meta::List(list)) if list.path.is_ident("any") => {
+            !list.nested.is_empty() && list.nested.iter().all(requires_test)
+        }
+        _ => false,
+    }
+}
+
+// 深度优先遍历文件中的所有项，包括 `mod` 块中嵌套的项
+// `mods` 为当前项所在的模块路径
+fn walk_items<'a>(
+    items: &'a [Item],
+    mods: &mut Vec<String>,
+    visit: &mut impl FnMut(&[String], &'a Item),
+) {
+    for item in items {
+        if let Item::Mod(item_mod) = item {
+            // `mod foo;` 形式的声明没有内容，对应的文件会单独扫描；
+            // 只在测试时编译的模块（`#[cfg(test)]` 等），注册其中的接口会导致正常构建失败
+            if is_cfg_test(&item_mod.attrs) {
+                continue;
+            }
+            if let Some((_, content)) = &item_mod.content {
+                mods.push(item_mod.ident.to_string());
+                walk_items(content, mods, visit);
+                mods.pop();
+            }
+        } else {
+            visit(mods, item);
+        }
+    }
+}
+
+/// `auto_scan` 扫描到的路由，用于检查重复注册
+struct ScannedRoute {
+    method: String,
+    url: String,
+    handler: String,
+    cfgs: String,
+}
+
+// 路由的形状，参数名不同的路径匹配同样的请求，例如 `/users/:id` 和 `/users/:name`
+fn route_shape(url: &str) -> Vec<&str> {
+    url.split('/')
+        .map(|segment| match segment.chars().next() {
+            Some(':') => ":",
+            Some('*') => "*",
+            _ => segment,
+        })
+        .collect()
+}
+
+// 记录扫描到的路由，同一个函数重复注册同一个路由时返回 `false`，不再生成注册语句；
+// 不同函数注册相同的方法和路径时返回错误。`cfg` 条件不同的函数可能不会同时编译，不视为冲突
+fn check_route(routes: &mut Vec<ScannedRoute>, route: ScannedRoute) -> Result<bool, String> {
+    let existing = routes.iter().find(|existing| {
+        existing.method == route.method
+            && existing.cfgs == route.cfgs
+            && route_shape(&existing.url) == route_shape(&route.url)
+    });
+    match existing {
+        Some(existing) if existing.handler == route.handler => Ok(false),
+        Some(existing) => Err(format!(
+            "路由冲突：{} {} 同时由 {} 和 {} 注册",
+            route.method.to_ascii_uppercase(),
+            route.url,
+            existing.handler,
+            route.handler
+        )),
+        None => {
+            routes.push(route);
+            Ok(true)
+        }
+    }
+}
+
 // 判断是否是目录，如果是路径则需要循环递归处理，
 // 如果是文件则直接处理
 // 处理过程中会将函数调用函数拼接，然后插入到指定的位置 下标+1 的位置
 fn scan_method(
     path: &str,
-    filter_paths: &Vec<String>,
+    filter_paths: &[String],
     input_token_stream: &mut ItemFn,
     context_path: &str,
     (mut master_index, master_name): (i32, &Ident),
-) {
+    routes: &mut Vec<ScannedRoute>,
+) -> syn::Result<()> {
     if let Ok(entries) = fs::read_dir(path) {
-        for entry in entries {
-            if let Ok(entry) = entry {
+        for entry in entries.flatten() {
+            {
                 let file_path = entry.path();
                 if file_path.is_file() {
                     if let Some(extension) = file_path.extension() {
                         if extension == "rs" {
                             if filter_paths.iter().any(|p| path.contains(p)) {
-                                return;
+                                return Ok(());
                             }
-                            // 如果是文件，则处理内部细节
-                            let content = fs::read_to_string(entry.path()).expect("处理内部细节");
-                            // 解析文件
-                            let ast = parse_file(&content).expect("解析文件失败");
-                            let items = ast.items;
-                            for item in items {
+                            // 如果是文件，则读取并解析
+                            let ast = read_file(&file_path)?;
+                            let mut result = Ok(());
+                            walk_items(&ast.items, &mut Vec::new(), &mut |mods, item| {
+                                if result.is_err() {
+                                    return;
+                                }
                                 if let Item::Fn(item) = item {
+                                    // 函数上的 `#[cfg(...)]` 原样加到注册语句上，
+                                    // 条件不满足时函数不存在，注册语句也不能生成
+                                    let cfgs: Vec<&syn::Attribute> = item
+                                        .attrs
+                                        .iter()
+                                        .filter(|attr| attr.path.is_ident("cfg"))
+                                        .collect();
                                     // 处理函数中的函数名，指定宏信息
-                                    for attr in item.attrs {
-                                        // 遍历所有宏信息
-                                        if let Meta::List(meta) =
-                                            attr.parse_meta().expect("所有所有宏信息")
-                                        {
-                                            // 判断宏是否为指定的宏
-                                            let attr_path = meta.path.to_token_stream().to_string();
-
-                                            let method = config_req_type(&attr_path);
-                                            if method.is_none() {
-                                                continue;
+                                    for attr in &item.attrs {
+                                        // 判断宏是否为指定的宏，其他属性不需要解析
+                                        let attr_path = attr.path.to_token_stream().to_string();
+                                        let fn_name: &String = &item.sig.ident.to_string();
+                                        let mut methods = match config_req_type(attr) {
+                                            Ok(methods) if methods.is_empty() => continue,
+                                            Ok(methods) => methods,
+                                            Err(err) => {
+                                                result = Err(scan_error(
+                                                    &file_path,
+                                                    format!(
+                                                        "{} 的 #[{}]：{}",
+                                                        fn_name, attr_path, err
+                                                    ),
+                                                ));
+                                                return;
                                             }
-                                            let method =
-                                                method.expect("是否为指定的宏").to_token_stream();
-
-                                            // 获取函数全路径名
-                                            let fn_name: &String = &item.sig.ident.to_string();
-                                            let fn_path_token_stream = config_function_path(
-                                                &file_path.to_str().unwrap_or("文件为空"),
-                                                fn_name,
-                                            );
+                                        };
+
+                                        let url = match route_url(attr) {
+                                            Ok(url) => url,
+                                            Err(err) => {
+                                                result = Err(scan_error(
+                                                    &file_path,
+                                                    format!(
+                                                        "{} 的 #[{}]：{}",
+                                                        fn_name, attr_path, err
+                                                    ),
+                                                ));
+                                                return;
+                                            }
+                                        };
 
-                                            // 如果是 summer_boot 的宏信息，则处理
-                                            let attr_url = meta
-                                                .nested
-                                                .into_iter()
-                                                .next()
-                                                .expect("summer_boot 的宏信息");
-                                            if let NestedMeta::Lit(Lit::Str(url)) = attr_url {
-                                                let url = url.value();
-                                                let url = format!("{}{}", context_path, url)
-                                                    .replace("\"", "")
-                                                    .replace("//", "/");
-
-                                                if input_token_stream.block.stmts.len() < 1 {
-                                                    // 如果注入的方法中没有任何代码，则不操作
-                                                    break;
-                                                } else {
-                                                    // 添加，注意下标加 1
-                                                    master_index += 1;
-                                                    input_token_stream.block.stmts.insert(
-                                                    master_index as usize,
-                                                    parse_quote! {
-                                                        #master_name.at(#url).#method(#fn_path_token_stream);
-                                                    },
-                                                );
+                                        // 获取函数全路径名
+                                        let fn_path_token_stream =
+                                            match config_function_path(&file_path, mods, fn_name) {
+                                                Ok(fn_path) => fn_path,
+                                                Err(err) => {
+                                                    result = Err(err);
+                                                    return;
+                                                }
+                                            };
+
+                                        let url = format!("{}{}", context_path, url)
+                                            .replace("\"", "")
+                                            .replace("//", "/");
+
+                                        let cfgs_key = quote!(#(#cfgs)*).to_string();
+                                        let handler =
+                                            fn_path_token_stream.to_string().replace(' ', "");
+                                        let mut conflict = None;
+                                        methods.retain(|method| {
+                                            let route = ScannedRoute {
+                                                method: method.to_string(),
+                                                url: url.clone(),
+                                                handler: handler.clone(),
+                                                cfgs: cfgs_key.clone(),
+                                            };
+                                            match check_route(routes, route) {
+                                                Ok(new) => new,
+                                                Err(err) => {
+                                                    conflict.get_or_insert(err);
+                                                    false
                                                 }
                                             }
+                                        });
+                                        if let Some(err) = conflict {
+                                            result = Err(scan_error(&file_path, err));
+                                            return;
+                                        }
+
+                                        if methods.is_empty() {
+                                            // 全部是重复注册
+                                            continue;
+                                        } else if input_token_stream.block.stmts.is_empty() {
+                                            // 如果注入的方法中没有任何代码，则不操作
+                                            break;
+                                        } else {
+                                            // 添加，注意下标加 1
+                                            master_index += 1;
+                                            input_token_stream.block.stmts.insert(
+                                                master_index as usize,
+                                                parse_quote! {
+                                                    #(#cfgs)*
+                                                    #master_name.at(#url)#(.#methods(#fn_path_token_stream))*;
+                                                },
+                                            );
                                         }
                                     }
                                 }
-                            }
+                            });
+                            result?;
                         }
                     }
                 }
             }
         }
     }
+    Ok(())
+}
+
+// 取出路由宏的路径参数，例如 `#[get("/hello")]` 中的 `/hello`
+fn route_url(attr: &syn::Attribute) -> syn::Result<String> {
+    let meta = attr.parse_meta()?;
+    let Meta::List(meta) = meta else {
+        return Err(syn::Error::new_spanned(meta, "缺少路由路径"));
+    };
+    match meta.nested.first() {
+        Some(NestedMeta::Lit(Lit::Str(url))) => Ok(url.value()),
+        Some(other) => Err(syn::Error::new_spanned(other, "路由路径必须是字符串")),
+        None => Err(syn::Error::new_spanned(meta, "缺少路由路径")),
+    }
 }
 
 // 配置函数全路径
-// 根据相对项目的绝对路径找到函数调用的全路径链
-// 注意：目前无法完成文件中mod下的函数调用，无法找到
-fn config_function_path(path: &str, fu_name: &str) -> proc_macro2::TokenStream {
+// 根据相对项目的绝对路径找到函数调用的全路径链，
+// 文件中 `mod` 块下的函数需要追加模块路径 `mods`
+fn config_function_path(
+    file_path: &Path,
+    mods: &[String],
+    fu_name: &str,
+) -> syn::Result<proc_macro2::TokenStream> {
     let mut fn_path_idents = Punctuated::<Ident, Token![::]>::new();
     fn_path_idents.push(Ident::new("crate", Span::call_site()));
 
     // 配置函数路径
-    let names: Vec<&str> = path
-        [path.find("src").expect("转换src") + 4..path.rfind(".rs").expect("转换rs后缀")]
-        .split("/")
-        .collect();
+    let path = file_path
+        .to_str()
+        .ok_or_else(|| scan_error(file_path, "文件路径不是有效的 UTF-8"))?;
+    let (Some(start), Some(end)) = (path.find("src"), path.rfind(".rs")) else {
+        return Err(scan_error(file_path, "文件不在 src 目录下"));
+    };
+    let names: Vec<&str> = path[start + 4..end].split("/").collect();
 
     let len = names.len();
     for (index, name) in names.into_iter().enumerate() {
@@ -325,64 +797,166 @@ fn config_function_path(path: &str, fu_name: &str) -> proc_macro2::TokenStream {
         }
         if !name.is_empty() {
             // 配置文件包名
-            fn_path_idents.push(Ident::new(name, Span::call_site()));
+            fn_path_idents.push(path_ident(file_path, name)?);
         }
     }
+    // 配置文件中的模块
+    for name in mods {
+        fn_path_idents.push(path_ident(file_path, name)?);
+    }
     // 配置函数名称
-    fn_path_idents.push(Ident::new(fu_name, Span::call_site()));
+    fn_path_idents.push(path_ident(file_path, fu_name)?);
 
-    fn_path_idents.to_token_stream()
+    Ok(fn_path_idents.to_token_stream())
+}
+
+// 路径中的一段，文件名不是合法标识符（例如 `my-handlers.rs`）时报告错误而不是 panic
+fn path_ident(file_path: &Path, name: &str) -> syn::Result<Ident> {
+    syn::parse_str::<Ident>(name)
+        .map_err(|_| scan_error(file_path, format!("{:?} 不是合法的模块名", name)))
+}
+
+/// 路由宏支持的请求方法
+const METHODS: [&str; 9] = [
+    "get", "head", "put", "post", "delete", "options", "connect", "patch", "trace",
+];
+
+// 去掉 `summer_boot_macro ::` 或 `summer_boot ::` 前缀后的宏名称
+fn macro_name(attr_path: &str) -> &str {
+    attr_path
+        .strip_prefix("summer_boot_macro :: ")
+        .or_else(|| attr_path.strip_prefix("summer_boot :: "))
+        .unwrap_or(attr_path)
 }
 
 // 配置请求类型
-fn config_req_type(attr_path: &str) -> Option<Ident> {
-    if attr_path == "summer_boot_macro :: get"
-        || attr_path == "summer_boot :: get"
-        || attr_path == "get"
-        || attr_path == "summer_boot_macro :: head"
-        || attr_path == "summer_boot :: head"
-        || attr_path == "head"
-        || attr_path == "summer_boot_macro :: put"
-        || attr_path == "summer_boot :: put"
-        || attr_path == "put"
-        || attr_path == "summer_boot_macro :: post"
-        || attr_path == "summer_boot :: post"
-        || attr_path == "post"
-        || attr_path == "summer_boot_macro :: delete"
-        || attr_path == "summer_boot :: delete"
-        || attr_path == "delete"
-        || attr_path == "summer_boot_macro :: head"
-        || attr_path == "summer_boot :: head"
-        || attr_path == "head"
-        || attr_path == "summer_boot_macro :: options"
-        || attr_path == "summer_boot :: options"
-        || attr_path == "options"
-        || attr_path == "summer_boot_macro :: connect"
-        || attr_path == "summer_boot :: connect"
-        || attr_path == "connect"
-        || attr_path == "summer_boot_macro :: patch"
-        || attr_path == "summer_boot :: patch"
-        || attr_path == "patch"
-        || attr_path == "summer_boot_macro :: trace"
-        || attr_path == "summer_boot :: trace"
-        || attr_path == "trace"
-    {
-        if attr_path.starts_with("summer_boot_macro ::") {
-            return Some(Ident::new(
-                &attr_path["summer_boot_macro :: ".len()..],
-                Span::call_site(),
-            ));
-        } else if attr_path.starts_with("summer_boot ::") {
-            return Some(Ident::new(
-                &attr_path["summer_boot :: ".len()..],
-                Span::call_site(),
+// `#[get]` 等返回对应的方法，`#[route]` 返回 `method` 参数中列出的方法，
+// 其他属性返回空列表
+fn config_req_type(attr: &syn::Attribute) -> syn::Result<Vec<Ident>> {
+    let attr_path = attr.path.to_token_stream().to_string();
+    let name = macro_name(&attr_path);
+    if METHODS.contains(&name) {
+        return Ok(vec![Ident::new(name, Span::call_site())]);
+    }
+    if name != "route" {
+        return Ok(Vec::new());
+    }
+    let Meta::List(meta) = attr.parse_meta()? else {
+        return Err(syn::Error::new_spanned(
+            attr,
+            "用法：#[route(\"/path\", method = \"GET|POST\")]",
+        ));
+    };
+    route_methods(&meta.nested)
+}
+
+// 解析 `#[route]` 的 `method = "GET|POST"` 参数，方法名不区分大小写
+fn route_methods(args: &Punctuated<NestedMeta, Token![,]>) -> syn::Result<Vec<Ident>> {
+    let method = args.iter().find_map(|arg| match arg {
+        NestedMeta::Meta(Meta::NameValue(meta)) if meta.path.is_ident("method") => Some(&meta.lit),
+        _ => None,
+    });
+    let Some(Lit::Str(method)) = method else {
+        return Err(syn::Error::new_spanned(
+            args,
+            "缺少 method 参数，例如 method = \"GET|POST\"",
+        ));
+    };
+    let mut methods = Vec::new();
+    for name in method.value().split('|') {
+        let name = name.trim().to_ascii_lowercase();
+        if !METHODS.contains(&name.as_str()) {
+            return Err(syn::Error::new_spanned(
+                method,
+                format!("不支持的 HTTP 方法 `{}`", name),
             ));
-        } else {
-            return Some(Ident::new(attr_path, Span::call_site()));
         }
-    } else {
-        return None;
+        if !methods.iter().any(|m: &Ident| m == &name) {
+            methods.push(Ident::new(&name, Span::call_site()));
+        }
     }
+    Ok(methods)
+}
+
+// 路径中的命名参数，例如 `/users/:id` 中的 `id`
+fn path_params(url: &str) -> Vec<&str> {
+    url.split('/')
+        .filter_map(|segment| segment.strip_prefix(':'))
+        .collect()
+}
+
+// 参数类型是否为 `Request<State>`
+fn is_request(ty: &Type) -> bool {
+    match ty {
+        Type::Path(ty) => ty
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Request"),
+        _ => false,
+    }
+}
+
+// 注入路径参数
+// 与路径参数同名的函数参数从签名中移除，改为在函数体开头通过 `param_as` 取出，
+// 转换失败时返回 `400 Bad Request`。没有这样的参数时函数保持不变。
+// 移除后函数只接收 `Request`：原签名中没有 `Request` 参数时使用泛型的 `Request<State>`
+fn inject_path_params(url: &str, input: &mut ItemFn) -> syn::Result<()> {
+    let names = path_params(url);
+    let mut request = None;
+    let mut params = Vec::new();
+    let mut others = Vec::new();
+    for arg in &input.sig.inputs {
+        let FnArg::Typed(arg) = arg else {
+            continue;
+        };
+        match &*arg.pat {
+            _ if is_request(&arg.ty) => request = Some(arg),
+            Pat::Ident(pat) if names.iter().any(|name| pat.ident == name) => {
+                params.push((pat, &arg.ty))
+            }
+            _ => others.push(arg),
+        }
+    }
+    if params.is_empty() {
+        return Ok(());
+    }
+    if let Some(arg) = others.first() {
+        return Err(syn::Error::new_spanned(
+            &arg.pat,
+            format!("参数不在路由路径 `{}` 中", url),
+        ));
+    }
+
+    let req = Ident::new("req", Span::mixed_site());
+    let lets = params.iter().map(|(pat, ty)| {
+        let name = pat.ident.to_string();
+        let mutability = &pat.mutability;
+        let ident = &pat.ident;
+        quote!(let #mutability #ident: #ty = #req.param_as(#name)?;)
+    });
+    let bind_request = request.map(|arg| {
+        let pat = &arg.pat;
+        quote!(let #pat = #req;)
+    });
+    let request_ty: Type = match request {
+        Some(arg) => (*arg.ty).clone(),
+        None => {
+            input.sig.generics.params.push(parse_quote!(
+                State: ::std::clone::Clone + ::std::marker::Send + ::std::marker::Sync + 'static
+            ));
+            parse_quote!(summer_boot::Request<State>)
+        }
+    };
+    let body = &input.block;
+    let block = parse_quote!({
+        #(#lets)*
+        #bind_request
+        #body
+    });
+    *input.block = block;
+    input.sig.inputs = parse_quote!(#req: #request_ty);
+    Ok(())
 }
 
 macro_rules! doc_comment {
@@ -421,30 +995,237 @@ async fn example(mut req: Request<()>) -> Result {
     Ok(format!("Hello World").into())
 }
 ```
+
+# 路径参数
+与路径参数同名的函数参数由宏从请求中取出并转换为参数类型，转换失败时返回`400 Bad Request`。
+需要读取请求时可以同时保留`Request`参数。
+
+```rust
+# use summer_boot::{Request, Result};
+#[summer_boot_macro::"#, stringify!($method), r#"("/users/:id")]
+async fn user(id: u64) -> Result {
+    Ok(format!("user {}", id).into())
+}
+
+#[summer_boot_macro::"#, stringify!($method), r#"("/users/:id/name")]
+async fn user_name(req: Request<()>, id: u64) -> Result {
+    Ok(format!("{} user {}", req.method(), id).into())
+}
+
+let mut app = summer_boot::new();
+app.at("/users/:id")."#, stringify!($method), r#"(user);
+app.at("/users/:id/name")."#, stringify!($method), r#"(user_name);
+```
 "#);
             #[proc_macro_attribute]
-            pub fn $method(_args: TokenStream, input: TokenStream) -> TokenStream {
-
+            pub fn $method(args: TokenStream, input: TokenStream) -> TokenStream {
+                let args = parse_macro_input!(args as AttributeArgs);
                 let mut input = parse_macro_input!(input as ItemFn);
-                let attrs = &input.attrs;
-                let vis = &input.vis;
-                let sig = &mut input.sig;
-                let body = &input.block;
-                let _name = &sig.ident;
-                if sig.asyncness.is_none() {
-                    return syn::Error::new_spanned(sig.fn_token, "仅支持 async fn")
+                if input.sig.asyncness.is_none() {
+                    return syn::Error::new_spanned(input.sig.fn_token, "仅支持 async fn")
                         .to_compile_error()
                         .into();
                 }
+                if let Some(NestedMeta::Lit(Lit::Str(url))) = args.first() {
+                    if let Err(err) = inject_path_params(&url.value(), &mut input) {
+                        return err.to_compile_error().into();
+                    }
+                }
 
-                (quote! {
-                    #(#attrs)*
-                    #vis #sig
-                        #body
-                }).into()
+                input.into_token_stream().into()
             }
         })+
     };
 }
 
 method_macro!(get, head, put, post, delete, patch, trace, options, connect,);
+
+/// 同一个函数注册多个请求方法的路由
+///
+/// 第一个参数为路径，`method` 为以 `|` 分隔的请求方法，不区分大小写。
+/// 与 `get`、`post` 等宏一样由 `auto_scan` 读取并注册，同样支持注入路径参数。
+///
+/// # Examples
+/// ```rust
+/// # use summer_boot::{Request, Result};
+/// #[summer_boot_macro::route("/users", method = "GET|POST")]
+/// async fn users(req: Request<()>) -> Result {
+///     Ok(format!("{} /users", req.method()).into())
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn route(args: TokenStream, input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args with Punctuated::<NestedMeta, Token![,]>::parse_terminated);
+    let mut input = parse_macro_input!(input as ItemFn);
+
+    let Some(NestedMeta::Lit(Lit::Str(url))) = args.first() else {
+        return syn::Error::new(
+            Span::call_site(),
+            "用法：#[route(\"/path\", method = \"GET|POST\")]",
+        )
+        .to_compile_error()
+        .into();
+    };
+    if let Err(err) = route_methods(&args) {
+        return err.to_compile_error().into();
+    }
+    if input.sig.asyncness.is_none() {
+        return syn::Error::new_spanned(input.sig.fn_token, "仅支持 async fn")
+            .to_compile_error()
+            .into();
+    }
+    if let Err(err) = inject_path_params(&url.value(), &mut input) {
+        return err.to_compile_error().into();
+    }
+
+    input.into_token_stream().into()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn detects_test_only_cfgs() {
+        let cases: [(syn::Attribute, bool); 7] = [
+            (parse_quote!(#[cfg(test)]), true),
+            (parse_quote!(#[cfg(all(test, unix))]), true),
+            (parse_quote!(#[cfg(all(unix, any(test, test)))]), true),
+            (parse_quote!(#[cfg(any(test, unix))]), false),
+            (parse_quote!(#[cfg(not(test))]), false),
+            (parse_quote!(#[cfg(feature = "test")]), false),
+            (parse_quote!(#[allow(test)]), false),
+        ];
+        for (attr, expected) in cases {
+            let text = attr.to_token_stream().to_string();
+            assert_eq!(is_cfg_test(&[attr]), expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn walks_nested_modules() {
+        let file: syn::File = parse_quote! {
+            fn index() {}
+            mod foo {
+                mod bar {
+                    fn handler() {}
+                }
+                fn list() {}
+            }
+            mod declared;
+            #[cfg(test)]
+            mod test {
+                fn test_only() {}
+            }
+        };
+        let mut found = Vec::new();
+        walk_items(&file.items, &mut Vec::new(), &mut |mods, item| {
+            if let Item::Fn(item) = item {
+                found.push((mods.join("::"), item.sig.ident.to_string()));
+            }
+        });
+        let found: Vec<(&str, &str)> = found
+            .iter()
+            .map(|(mods, name)| (mods.as_str(), name.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [("", "index"), ("foo::bar", "handler"), ("foo", "list")]
+        );
+
+        let mods = ["foo".to_string(), "bar".to_string()];
+        let path = config_function_path(Path::new("src/handlers.rs"), &mods, "handler").unwrap();
+        assert_eq!(
+            path.to_string().replace(' ', ""),
+            "crate::handlers::foo::bar::handler"
+        );
+    }
+
+    #[test]
+    fn scans_components_in_inline_modules() {
+        let src = std::env::temp_dir().join(format!("summer-boot-scan-{}/src", std::process::id()));
+        fs::create_dir_all(&src).unwrap();
+        fs::write(
+            src.join("services.rs"),
+            "#[component] struct Top;\n\
+             mod users { mod store { #[summer_boot::component] pub struct UserStore; } }\n\
+             #[cfg(test)] mod test { #[component] struct Mock; }\n",
+        )
+        .unwrap();
+        let components: Vec<String> = scan_components(src.to_str().unwrap(), &[])
+            .unwrap()
+            .iter()
+            .map(|path| path.to_string().replace(' ', ""))
+            .collect();
+        assert_eq!(
+            components,
+            [
+                "crate::services::Top",
+                "crate::services::users::store::UserStore"
+            ]
+        );
+
+        // 文件名不是合法的模块名时报告编译错误，不 panic
+        fs::write(src.join("my-services.rs"), "#[component] struct Bad;").unwrap();
+        let err = scan_components(src.to_str().unwrap(), &[]).unwrap_err();
+        assert!(err.to_string().contains("my-services"), "{}", err);
+        fs::remove_dir_all(src.parent().unwrap()).unwrap();
+    }
+
+    fn scanned(method: &str, url: &str, handler: &str) -> ScannedRoute {
+        ScannedRoute {
+            method: method.to_string(),
+            url: url.to_string(),
+            handler: handler.to_string(),
+            cfgs: String::new(),
+        }
+    }
+
+    #[test]
+    fn route_shape_ignores_param_names() {
+        assert_eq!(route_shape("/users/:id"), route_shape("/users/:name"));
+        assert_eq!(route_shape("/files/*path"), route_shape("/files/*"));
+        assert_ne!(route_shape("/users/:id"), route_shape("/users/me"));
+        assert_ne!(route_shape("/users/:id"), route_shape("/users/:id/posts"));
+    }
+
+    #[test]
+    fn detects_route_conflicts() {
+        let mut routes = Vec::new();
+        assert_eq!(
+            check_route(&mut routes, scanned("get", "/users/:id", "crate::user")),
+            Ok(true)
+        );
+
+        // 同一个函数重复注册，不再生成注册语句
+        assert_eq!(
+            check_route(&mut routes, scanned("get", "/users/:id", "crate::user")),
+            Ok(false)
+        );
+
+        // 不同函数注册相同的路由，或参数名不同但形状相同的路由
+        let err =
+            check_route(&mut routes, scanned("get", "/users/:id", "crate::other")).unwrap_err();
+        assert!(err.contains("GET /users/:id"), "{}", err);
+        assert!(
+            err.contains("crate::user") && err.contains("crate::other"),
+            "{}",
+            err
+        );
+        assert!(check_route(&mut routes, scanned("get", "/users/:name", "crate::other")).is_err());
+
+        // 方法、路径形状或 cfg 条件不同时不冲突
+        assert_eq!(
+            check_route(&mut routes, scanned("post", "/users/:id", "crate::other")),
+            Ok(true)
+        );
+        assert_eq!(
+            check_route(&mut routes, scanned("get", "/users/me", "crate::me")),
+            Ok(true)
+        );
+        let mut gated = scanned("get", "/users/:id", "crate::gated");
+        gated.cfgs = "# [cfg (feature = \"beta\")]".to_string();
+        assert_eq!(check_route(&mut routes, gated), Ok(true));
+        assert_eq!(routes.len(), 4);
+    }
+}
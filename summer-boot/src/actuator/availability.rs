@@ -0,0 +1,148 @@
+//!
+//! Liveness and readiness endpoints
+//!
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+
+use crate::{Endpoint, Request, Response, Result};
+use async_trait::async_trait;
+
+use super::health::Status;
+
+pub const LIVENESS_PATH: &str = "/actuator/liveness";
+pub const READINESS_PATH: &str = "/actuator/readiness";
+
+/// 应用的存活和就绪状态，用于 Kubernetes 探针
+#[derive(Debug, Clone)]
+pub struct Availability {
+    live: Arc<AtomicBool>,
+    ready: Arc<AtomicBool>,
+}
+
+impl Default for Availability {
+    fn default() -> Self {
+        Availability {
+            live: Arc::new(AtomicBool::new(true)),
+            ready: Arc::new(AtomicBool::new(true)),
+        }
+    }
+}
+
+impl Availability {
+    pub fn new() -> Self {
+        Availability::default()
+    }
+
+    /// 全局状态，自动注册的探针 endpoint 使用该状态
+    pub fn global() -> &'static Availability {
+        static GLOBAL: OnceLock<Availability> = OnceLock::new();
+        GLOBAL.get_or_init(Availability::new)
+    }
+
+    pub fn is_live(&self) -> bool {
+        self.live.load(Ordering::SeqCst)
+    }
+
+    pub fn set_live(&self, live: bool) {
+        self.live.store(live, Ordering::SeqCst);
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::SeqCst)
+    }
+
+    pub fn set_ready(&self, ready: bool) {
+        self.ready.store(ready, Ordering::SeqCst);
+    }
+}
+
+fn probe(status: Status) -> Result {
+    let mut res = Response::new(status.status_code());
+    res.body_json(&serde_json::json!({ "status": status }))?;
+    Ok(res)
+}
+
+/// `/actuator/liveness` endpoint，应用损坏时返回 503
+#[derive(Debug, Clone, Default)]
+pub struct LivenessEndpoint {
+    availability: Availability,
+}
+
+impl LivenessEndpoint {
+    pub fn new(availability: Availability) -> Self {
+        LivenessEndpoint { availability }
+    }
+}
+
+#[async_trait]
+impl<State: Clone + Send + Sync + 'static> Endpoint<State> for LivenessEndpoint {
+    async fn call(&self, _req: Request<State>) -> Result {
+        if self.availability.is_live() {
+            probe(Status::Up)
+        } else {
+            probe(Status::Down)
+        }
+    }
+}
+
+/// `/actuator/readiness` endpoint，不接收流量时返回 503
+#[derive(Debug, Clone, Default)]
+pub struct ReadinessEndpoint {
+    availability: Availability,
+}
+
+impl ReadinessEndpoint {
+    pub fn new(availability: Availability) -> Self {
+        ReadinessEndpoint { availability }
+    }
+}
+
+#[async_trait]
+impl<State: Clone + Send + Sync + 'static> Endpoint<State> for ReadinessEndpoint {
+    async fn call(&self, _req: Request<State>) -> Result {
+        if self.availability.is_ready() {
+            probe(Status::Up)
+        } else {
+            probe(Status::OutOfService)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http_types::{Method, Request, Response, Url};
+    use crate::StatusCode;
+
+    async fn get(app: &crate::Server<()>, path: &str) -> StatusCode {
+        let url = Url::parse("http://localhost").unwrap().join(path).unwrap();
+        let res: Response = app.respond(Request::new(Method::Get, url)).await.unwrap();
+        res.status()
+    }
+
+    #[async_std::test]
+    async fn probes_follow_availability() {
+        let availability = Availability::new();
+        let mut app = crate::new();
+        app.at(LIVENESS_PATH)
+            .get(LivenessEndpoint::new(availability.clone()));
+        app.at(READINESS_PATH)
+            .get(ReadinessEndpoint::new(availability.clone()));
+
+        assert_eq!(get(&app, LIVENESS_PATH).await, StatusCode::Ok);
+        assert_eq!(get(&app, READINESS_PATH).await, StatusCode::Ok);
+
+        availability.set_ready(false);
+        assert_eq!(
+            get(&app, READINESS_PATH).await,
+            StatusCode::ServiceUnavailable
+        );
+        assert_eq!(get(&app, LIVENESS_PATH).await, StatusCode::Ok);
+
+        availability.set_live(false);
+        assert_eq!(
+            get(&app, LIVENESS_PATH).await,
+            StatusCode::ServiceUnavailable
+        );
+    }
+}
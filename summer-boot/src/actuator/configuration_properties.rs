@@ -0,0 +1,137 @@
+//!
+//! Configuration properties
+//!
+use serde_json::Value;
+
+/// 默认需要脱敏的键
+pub const DEFAULT_KEYS_SANITIZE: &[&str] = &["password", "secret", "key", "token"];
+
+/// 脱敏后的值
+pub const SANITIZED_VALUE: &str = "******";
+
+/// 配置输出相关的属性
+///
+/// 键以 `keys_sanitize` 或 `additional_keys_sanitize` 中的任一项结尾时会被脱敏，
+/// 不区分大小写。只匹配完整的单词：单词之间使用 `.`、`_` 或 `-` 分隔，
+/// 所以 `key` 匹配 `api_key`，不匹配 `monkey`。嵌套的键使用 `.` 连接，例如 `mysql.password`。
+#[derive(Debug, Clone)]
+pub struct ConfigurationProperties {
+    pub keys_sanitize: Vec<String>,
+    pub additional_keys_sanitize: Vec<String>,
+}
+
+impl Default for ConfigurationProperties {
+    fn default() -> Self {
+        ConfigurationProperties {
+            keys_sanitize: DEFAULT_KEYS_SANITIZE
+                .iter()
+                .map(|k| k.to_string())
+                .collect(),
+            additional_keys_sanitize: Vec::new(),
+        }
+    }
+}
+
+impl ConfigurationProperties {
+    pub fn new() -> ConfigurationProperties {
+        ConfigurationProperties::default()
+    }
+
+    /// 键是否需要脱敏
+    pub fn should_sanitize(&self, key: &str) -> bool {
+        let key = key.to_lowercase();
+        self.keys_sanitize
+            .iter()
+            .chain(&self.additional_keys_sanitize)
+            .any(|pattern| !pattern.is_empty() && ends_with_words(&key, &pattern.to_lowercase()))
+    }
+
+    /// 将 `value` 中需要脱敏的值替换为 [`SANITIZED_VALUE`]
+    pub fn sanitize(&self, value: &mut Value) {
+        self.sanitize_at("", value);
+    }
+
+    fn sanitize_at(&self, prefix: &str, value: &mut Value) {
+        if let Value::Object(map) = value {
+            for (key, value) in map.iter_mut() {
+                let path = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                if self.should_sanitize(&path) && !value.is_null() {
+                    *value = Value::String(SANITIZED_VALUE.to_string());
+                } else {
+                    self.sanitize_at(&path, value);
+                }
+            }
+        }
+    }
+
+    pub fn get_keys_sanitize(&self) -> &Vec<String> {
+        &self.keys_sanitize
+    }
+
+    pub fn set_keys_sanitize(&mut self, keys_sanitize: Vec<String>) {
+        self.keys_sanitize = keys_sanitize;
+    }
+
+    pub fn get_additional_keys_sanitize(&self) -> &Vec<String> {
+        &self.additional_keys_sanitize
+    }
+
+    pub fn set_additional_keys_sanitize(&mut self, additional_keys_sanitize: Vec<String>) {
+        self.additional_keys_sanitize = additional_keys_sanitize;
+    }
+}
+
+/// `key` 是否以完整的单词 `pattern` 结尾
+fn ends_with_words(key: &str, pattern: &str) -> bool {
+    match key.strip_suffix(pattern) {
+        Some("") => true,
+        Some(rest) => rest.ends_with(['.', '_', '-']),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn sanitizes_nested_keys_case_insensitively() {
+        let mut properties = ConfigurationProperties::new();
+        properties.set_additional_keys_sanitize(vec!["Mysql.User".to_string()]);
+
+        let mut value = json!({
+            "mysql": { "host": "localhost", "user": "root", "PASSWORD": "secret" },
+            "server": { "port": 8080, "api_token": "abc" },
+        });
+        properties.sanitize(&mut value);
+        assert_eq!(
+            value,
+            json!({
+                "mysql": { "host": "localhost", "user": "******", "PASSWORD": "******" },
+                "server": { "port": 8080, "api_token": "******" },
+            })
+        );
+    }
+
+    #[test]
+    fn matches_whole_words_only() {
+        let properties = ConfigurationProperties::new();
+        for key in [
+            "key",
+            "api_key",
+            "api-key",
+            "mysql.password",
+            "server.API_TOKEN",
+        ] {
+            assert!(properties.should_sanitize(key), "{}", key);
+        }
+        for key in ["monkey", "hotkey", "tokens", "mysql.passwords"] {
+            assert!(!properties.should_sanitize(key), "{}", key);
+        }
+    }
+}
@@ -0,0 +1,95 @@
+//!
+//! Env endpoint
+//!
+use std::sync::Arc;
+
+use crate::{Endpoint, Request, Response, Result};
+use async_trait::async_trait;
+use serde_json::{Map, Value};
+
+use super::configuration_properties::ConfigurationProperties;
+use super::load_config;
+
+pub const ENV_PATH: &str = "/actuator/env";
+
+/// 输出脱敏后的配置
+///
+/// ```
+/// use summer_boot::actuator::configuration_properties::ConfigurationProperties;
+/// use summer_boot::actuator::env::{EnvEndpoint, ENV_PATH};
+///
+/// let mut properties = ConfigurationProperties::new();
+/// properties.set_additional_keys_sanitize(vec!["mysql.host".to_string()]);
+///
+/// let mut app = summer_boot::new();
+/// app.at(ENV_PATH).get(EnvEndpoint::new(properties));
+/// ```
+#[derive(Debug, Clone)]
+pub struct EnvEndpoint {
+    config: Arc<Value>,
+}
+
+impl EnvEndpoint {
+    /// 加载配置并按 `properties` 脱敏
+    pub fn new(properties: ConfigurationProperties) -> Self {
+        let config = load_config()
+            .and_then(|config| serde_json::to_value(config).ok())
+            .unwrap_or_else(|| Value::Object(Map::new()));
+        Self::from_value(config, &properties)
+    }
+
+    fn from_value(mut config: Value, properties: &ConfigurationProperties) -> Self {
+        properties.sanitize(&mut config);
+        EnvEndpoint {
+            config: Arc::new(config),
+        }
+    }
+}
+
+#[async_trait]
+impl<State> Endpoint<State> for EnvEndpoint
+where
+    State: Clone + Send + Sync + 'static,
+{
+    async fn call(&self, _req: Request<State>) -> Result {
+        Ok(Response::from(self.config.as_ref().clone()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http_types::{Method, Request, Response, Url};
+    use serde_json::json;
+    use summer_boot_autoconfigure::{GlobalConfig, Mysql};
+
+    #[async_std::test]
+    async fn redacts_mysql_password() {
+        let config = GlobalConfig {
+            mysql: Some(Mysql {
+                host: "localhost".to_string(),
+                port: 3306,
+                user: Some("root".to_string()),
+                password: Some("hunter2".to_string()),
+                db: "summer".to_string(),
+                pool_min_idle: 8,
+                pool_max_open: 32,
+                timeout_seconds: 15,
+            }),
+            server: None,
+        };
+        let endpoint = EnvEndpoint::from_value(
+            serde_json::to_value(config).unwrap(),
+            &ConfigurationProperties::new(),
+        );
+
+        let mut app = crate::new();
+        app.at(ENV_PATH).get(endpoint);
+        let url = Url::parse("http://localhost/actuator/env").unwrap();
+        let mut res: Response = app.respond(Request::new(Method::Get, url)).await.unwrap();
+        let body: Value = res.body_json().await.unwrap();
+        assert_eq!(body["mysql"]["password"], json!("******"));
+        assert_eq!(body["mysql"]["user"], json!("root"));
+        assert_eq!(body["server"], Value::Null);
+    }
+}
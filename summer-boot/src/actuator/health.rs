@@ -0,0 +1,275 @@
+//!
+//! Health endpoint
+//!
+use std::collections::BTreeMap;
+use std::sync::{Arc, OnceLock, RwLock};
+
+use crate::{Endpoint, Request, Response, Result, StatusCode};
+use async_trait::async_trait;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+pub const HEALTH_PATH: &str = "/actuator/health";
+
+/// 健康状态，聚合时按 `DOWN` > `OUT_OF_SERVICE` > `UP` > `UNKNOWN` 的顺序取最严重的状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Status {
+    Up,
+    Down,
+    OutOfService,
+    Unknown,
+}
+
+impl Status {
+    fn severity(self) -> u8 {
+        match self {
+            Status::Down => 3,
+            Status::OutOfService => 2,
+            Status::Up => 1,
+            Status::Unknown => 0,
+        }
+    }
+
+    /// 对应的HTTP状态码，`DOWN` 和 `OUT_OF_SERVICE` 返回 503
+    pub fn status_code(self) -> StatusCode {
+        match self {
+            Status::Down | Status::OutOfService => StatusCode::ServiceUnavailable,
+            Status::Up | Status::Unknown => StatusCode::Ok,
+        }
+    }
+}
+
+/// 单个组件的健康信息
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Health {
+    pub status: Status,
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    pub details: Map<String, Value>,
+}
+
+impl Health {
+    pub fn new(status: Status) -> Self {
+        Health {
+            status,
+            details: Map::new(),
+        }
+    }
+
+    pub fn up() -> Self {
+        Health::new(Status::Up)
+    }
+
+    pub fn down() -> Self {
+        Health::new(Status::Down)
+    }
+
+    pub fn with_detail(mut self, key: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.details.insert(key.into(), value.into());
+        self
+    }
+}
+
+/// 健康检查指标，例如数据库连接、磁盘空间或自定义检查
+#[async_trait]
+pub trait HealthIndicator: Send + Sync + 'static {
+    /// 组件名称，作为 `components` 中的键
+    fn name(&self) -> &str;
+
+    /// 检查组件的健康状态
+    async fn health(&self) -> Health;
+}
+
+/// 已注册的健康检查指标
+#[derive(Clone, Default)]
+pub struct HealthRegistry {
+    indicators: Arc<RwLock<Vec<Arc<dyn HealthIndicator>>>>,
+}
+
+impl HealthRegistry {
+    pub fn new() -> Self {
+        HealthRegistry::default()
+    }
+
+    /// 全局注册表，自动注册的 `/actuator/health` 使用该注册表
+    pub fn global() -> &'static HealthRegistry {
+        static GLOBAL: OnceLock<HealthRegistry> = OnceLock::new();
+        GLOBAL.get_or_init(HealthRegistry::new)
+    }
+
+    pub fn register(&self, indicator: impl HealthIndicator) {
+        self.indicators
+            .write()
+            .expect("健康检查注册表锁异常")
+            .push(Arc::new(indicator));
+    }
+
+    /// 依次检查所有指标并聚合结果
+    pub async fn check(&self) -> CompositeHealth {
+        let indicators = self
+            .indicators
+            .read()
+            .expect("健康检查注册表锁异常")
+            .clone();
+
+        let mut status = Status::Up;
+        let mut components = BTreeMap::new();
+        for indicator in indicators {
+            let health = indicator.health().await;
+            if health.status.severity() > status.severity() {
+                status = health.status;
+            }
+            components.insert(indicator.name().to_string(), health);
+        }
+        CompositeHealth { status, components }
+    }
+}
+
+/// 聚合后的健康信息
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CompositeHealth {
+    pub status: Status,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub components: BTreeMap<String, Health>,
+}
+
+/// `/actuator/health` endpoint
+#[derive(Clone, Default)]
+pub struct HealthEndpoint {
+    registry: HealthRegistry,
+}
+
+impl HealthEndpoint {
+    pub fn new(registry: HealthRegistry) -> Self {
+        HealthEndpoint { registry }
+    }
+}
+
+#[async_trait]
+impl<State: Clone + Send + Sync + 'static> Endpoint<State> for HealthEndpoint {
+    async fn call(&self, _req: Request<State>) -> Result {
+        let health = self.registry.check().await;
+        let mut res = Response::new(health.status.status_code());
+        res.body_json(&health)?;
+        Ok(res)
+    }
+}
+
+/// 磁盘空间检查，可用空间低于阈值时为 `DOWN`
+#[derive(Debug, Clone)]
+pub struct DiskSpaceHealthIndicator {
+    path: std::path::PathBuf,
+    threshold: u64,
+}
+
+impl DiskSpaceHealthIndicator {
+    /// `threshold` 为最小可用字节数
+    pub fn new(path: impl Into<std::path::PathBuf>, threshold: u64) -> Self {
+        DiskSpaceHealthIndicator {
+            path: path.into(),
+            threshold,
+        }
+    }
+}
+
+impl Default for DiskSpaceHealthIndicator {
+    fn default() -> Self {
+        DiskSpaceHealthIndicator::new(".", 10 * 1024 * 1024)
+    }
+}
+
+#[async_trait]
+impl HealthIndicator for DiskSpaceHealthIndicator {
+    fn name(&self) -> &str {
+        "diskSpace"
+    }
+
+    async fn health(&self) -> Health {
+        let health = match disk_space(&self.path) {
+            Some((total, free)) if free >= self.threshold => Health::up()
+                .with_detail("total", total)
+                .with_detail("free", free),
+            Some((total, free)) => Health::down()
+                .with_detail("total", total)
+                .with_detail("free", free),
+            None => Health::new(Status::Unknown),
+        };
+        health
+            .with_detail("threshold", self.threshold)
+            .with_detail("path", self.path.display().to_string())
+    }
+}
+
+/// 返回 (总空间, 可用空间)
+#[cfg(unix)]
+fn disk_space(path: &std::path::Path) -> Option<(u64, u64)> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: path 是有效的 C 字符串，stat 由 statvfs 在成功时完整写入
+    let stat = unsafe {
+        if libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) != 0 {
+            return None;
+        }
+        stat.assume_init()
+    };
+    let block = stat.f_frsize as u64;
+    Some((stat.f_blocks as u64 * block, stat.f_bavail as u64 * block))
+}
+
+#[cfg(not(unix))]
+fn disk_space(_path: &std::path::Path) -> Option<(u64, u64)> {
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http_types::{Method, Request, Response, Url};
+
+    struct Fixed(&'static str, Status);
+
+    #[async_trait]
+    impl HealthIndicator for Fixed {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        async fn health(&self) -> Health {
+            Health::new(self.1).with_detail("checked", true)
+        }
+    }
+
+    async fn get(registry: HealthRegistry) -> (StatusCode, Value) {
+        let mut app = crate::new();
+        app.at(HEALTH_PATH).get(HealthEndpoint::new(registry));
+        let url = Url::parse("http://localhost/actuator/health").unwrap();
+        let mut res: Response = app.respond(Request::new(Method::Get, url)).await.unwrap();
+        (res.status(), res.body_json().await.unwrap())
+    }
+
+    #[async_std::test]
+    async fn aggregates_indicators() {
+        let registry = HealthRegistry::new();
+        let (status, body) = get(registry.clone()).await;
+        assert_eq!(status, StatusCode::Ok);
+        assert_eq!(body, serde_json::json!({ "status": "UP" }));
+
+        registry.register(Fixed("db", Status::Up));
+        registry.register(DiskSpaceHealthIndicator::new(".", 0));
+        let (status, body) = get(registry.clone()).await;
+        assert_eq!(status, StatusCode::Ok);
+        assert_eq!(body["components"]["db"]["status"], "UP");
+        assert_eq!(body["components"]["db"]["details"]["checked"], true);
+        assert_eq!(body["components"]["diskSpace"]["details"]["threshold"], 0);
+
+        registry.register(Fixed("cache", Status::OutOfService));
+        registry.register(Fixed("queue", Status::Down));
+        let (status, body) = get(registry).await;
+        assert_eq!(status, StatusCode::ServiceUnavailable);
+        assert_eq!(body["status"], "DOWN");
+        assert_eq!(body["components"]["cache"]["status"], "OUT_OF_SERVICE");
+    }
+}
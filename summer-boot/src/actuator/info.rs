@@ -0,0 +1,161 @@
+//!
+//! Info endpoint
+//!
+use std::sync::Arc;
+
+use crate::{Endpoint, Request, Response, Result};
+use async_trait::async_trait;
+use serde_json::{json, Map, Value};
+
+use super::load_config;
+
+pub const INFO_PATH: &str = "/actuator/info";
+
+/// 构建信息，通常在应用 crate 中通过 [`build_info!`](crate::build_info) 生成
+#[derive(Debug, Clone, Copy)]
+pub struct BuildInfo {
+    pub name: &'static str,
+    pub version: &'static str,
+    /// 编译时 `GIT_COMMIT` 环境变量的值
+    pub commit: Option<&'static str>,
+}
+
+/// 在调用处读取当前 crate 的名称、版本和编译时的 `GIT_COMMIT` 环境变量
+///
+/// ```
+/// let build = summer_boot::build_info!();
+/// assert_eq!(build.name, "summer-boot");
+/// ```
+#[macro_export]
+macro_rules! build_info {
+    () => {
+        $crate::actuator::info::BuildInfo {
+            name: env!("CARGO_PKG_NAME"),
+            version: env!("CARGO_PKG_VERSION"),
+            commit: option_env!("GIT_COMMIT"),
+        }
+    };
+}
+
+/// [`InfoEndpoint`] 的构建器
+///
+/// ```
+/// use summer_boot::actuator::info::{InfoBuilder, INFO_PATH};
+///
+/// let mut app = summer_boot::new();
+/// app.at(INFO_PATH).get(
+///     InfoBuilder::new()
+///         .build_info(summer_boot::build_info!())
+///         .with("team", "payments")
+///         .build(),
+/// );
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct InfoBuilder {
+    info: Map<String, Value>,
+}
+
+impl InfoBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 添加加载的 `GlobalConfig` 中的 `server` 配置，没有配置文件时不添加
+    ///
+    /// 数据库等配置可能包含密码，不会输出。
+    pub fn config(mut self) -> Self {
+        if let Some(server) = load_config().and_then(|config| config.server) {
+            if let Ok(server) = serde_json::to_value(server) {
+                self.info.insert("server".to_string(), server);
+            }
+        }
+        self
+    }
+
+    /// 以 `build` 为键添加构建信息
+    pub fn build_info(mut self, build: BuildInfo) -> Self {
+        let mut value = json!({
+            "name": build.name,
+            "version": build.version,
+        });
+        if let Some(commit) = build.commit {
+            value["commit"] = json!(commit);
+        }
+        self.info.insert("build".to_string(), value);
+        self
+    }
+
+    /// 添加自定义的键值，相同的键会被覆盖
+    pub fn with(mut self, key: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.info.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn build(self) -> InfoEndpoint {
+        InfoEndpoint {
+            info: Arc::new(Value::Object(self.info)),
+        }
+    }
+}
+
+/// 返回应用信息的 endpoint，没有任何信息时返回 `{}`
+#[derive(Debug, Clone)]
+pub struct InfoEndpoint {
+    info: Arc<Value>,
+}
+
+#[async_trait]
+impl<State> Endpoint<State> for InfoEndpoint
+where
+    State: Clone + Send + Sync + 'static,
+{
+    async fn call(&self, _req: Request<State>) -> Result {
+        Ok(Response::from(self.info.as_ref().clone()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http_types::{Method, Request as HttpRequest, Url};
+
+    async fn get_info(endpoint: InfoEndpoint) -> Value {
+        let mut app = crate::new();
+        app.at(INFO_PATH).get(endpoint);
+        let req = HttpRequest::new(
+            Method::Get,
+            Url::parse("http://localhost/actuator/info").unwrap(),
+        );
+        let mut res: crate::http_types::Response = app.respond(req).await.unwrap();
+        res.body_json().await.unwrap()
+    }
+
+    #[async_std::test]
+    async fn empty_when_nothing_configured() {
+        let info = get_info(InfoBuilder::new().config().build()).await;
+        assert_eq!(info, json!({}));
+    }
+
+    #[async_std::test]
+    async fn build_info_and_custom_details() {
+        let build = BuildInfo {
+            name: "demo",
+            version: "1.0.0",
+            commit: Some("abc123"),
+        };
+        let endpoint = InfoBuilder::new()
+            .build_info(build)
+            .with("team", "payments")
+            .with("replicas", 3)
+            .build();
+        let info = get_info(endpoint).await;
+        assert_eq!(
+            info,
+            json!({
+                "build": { "name": "demo", "version": "1.0.0", "commit": "abc123" },
+                "team": "payments",
+                "replicas": 3,
+            })
+        );
+    }
+}
@@ -0,0 +1,290 @@
+//!
+//! Metrics endpoints
+//!
+
+use crate::http_types::mime::Mime;
+use crate::log::{AfterSendOutcome, RequestObservation, RequestObserver};
+use crate::utils::retry_after::RetryAfterCause;
+use crate::{Endpoint, Request, Response, Result, StatusCode};
+use async_trait::async_trait;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry, TextEncoder,
+};
+
+use super::process::ProcessCollector;
+
+pub const METRICS_PATH: &str = "/actuator/metrics";
+pub const PROMETHEUS_PATH: &str = "/actuator/prometheus";
+
+/// 没有匹配到路由的请求使用的标签，避免按原始路径产生过多的时间序列
+const UNMATCHED_ROUTE: &str = "UNMATCHED";
+
+/// HTTP 服务指标
+///
+/// 请求数、耗时和状态码按请求方法和路由模板打标签。
+/// 通过 [`Server::observe`](crate::Server::observe) 注册在服务上后由 `LoggingSystem` 和监听器更新，
+/// [`mount`](super::mount) 会为每个服务创建并注册一个实例。
+/// 同时包含 [`ProcessCollector`] 提供的进程内存、文件描述符、线程数和 CPU 时间。
+#[derive(Clone)]
+pub struct Metrics {
+    registry: Registry,
+    requests: IntCounterVec,
+    duration: HistogramVec,
+    responses: IntCounterVec,
+    active_connections: IntGauge,
+    after_send: IntCounterVec,
+    retry_after: HistogramVec,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new();
+        let requests = IntCounterVec::new(
+            Opts::new("http_server_requests_total", "HTTP 请求总数"),
+            &["method", "route"],
+        )
+        .unwrap();
+        let duration = HistogramVec::new(
+            HistogramOpts::new("http_server_request_duration_seconds", "HTTP 请求耗时"),
+            &["method", "route"],
+        )
+        .unwrap();
+        let responses = IntCounterVec::new(
+            Opts::new("http_server_responses_total", "按状态码统计的 HTTP 响应数"),
+            &["method", "route", "status"],
+        )
+        .unwrap();
+        let active_connections =
+            IntGauge::new("http_server_active_connections", "当前活跃的连接数").unwrap();
+        let after_send = IntCounterVec::new(
+            Opts::new("http_server_after_send_total", "响应发送后任务数"),
+            &["outcome"],
+        )
+        .unwrap();
+        let retry_after = HistogramVec::new(
+            HistogramOpts::new(
+                "http_server_retry_after_seconds",
+                "响应中 Retry-After 告诉客户端的重试时间",
+            )
+            .buckets(vec![1.0, 2.0, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0]),
+            &["cause"],
+        )
+        .unwrap();
+
+        registry.register(Box::new(requests.clone())).unwrap();
+        registry.register(Box::new(duration.clone())).unwrap();
+        registry.register(Box::new(responses.clone())).unwrap();
+        registry
+            .register(Box::new(active_connections.clone()))
+            .unwrap();
+        registry.register(Box::new(after_send.clone())).unwrap();
+        registry.register(Box::new(retry_after.clone())).unwrap();
+        registry
+            .register(Box::new(ProcessCollector::new()))
+            .unwrap();
+
+        Metrics {
+            registry,
+            requests,
+            duration,
+            responses,
+            active_connections,
+            after_send,
+            retry_after,
+        }
+    }
+
+    /// 底层的 Prometheus 注册表，可以注册自定义指标
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    /// 以 Prometheus 文本格式导出
+    pub fn render(&self) -> String {
+        let mut buf = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buf)
+            .expect("Prometheus 文本编码失败");
+        String::from_utf8(buf).expect("Prometheus 文本格式不是 UTF-8")
+    }
+}
+
+impl RequestObserver for Metrics {
+    fn on_request(&self, observation: &RequestObservation<'_>) {
+        let method = observation.method.to_string();
+        let route = observation.route.unwrap_or(UNMATCHED_ROUTE);
+        let status = (observation.status as u16).to_string();
+
+        self.requests.with_label_values(&[&method, route]).inc();
+        self.duration
+            .with_label_values(&[&method, route])
+            .observe(observation.elapsed.as_secs_f64());
+        self.responses
+            .with_label_values(&[&method, route, &status])
+            .inc();
+        if let Some(retry) = observation.retry_after {
+            let cause = match retry.cause() {
+                RetryAfterCause::RateLimit => "rate_limit",
+                RetryAfterCause::LoadShed => "load_shed",
+                RetryAfterCause::Shutdown => "shutdown",
+            };
+            self.retry_after
+                .with_label_values(&[cause])
+                .observe(retry.delay().as_secs_f64());
+        }
+    }
+
+    fn on_connection_open(&self) {
+        self.active_connections.inc();
+    }
+
+    fn on_connection_close(&self) {
+        self.active_connections.dec();
+    }
+
+    fn on_after_send(&self, outcome: AfterSendOutcome) {
+        let outcome = match outcome {
+            AfterSendOutcome::Queued => "queued",
+            AfterSendOutcome::Completed => "completed",
+            AfterSendOutcome::Failed => "failed",
+        };
+        self.after_send.with_label_values(&[outcome]).inc();
+    }
+}
+
+/// `/actuator/metrics` endpoint，返回所有指标名称
+#[derive(Clone)]
+pub struct MetricsEndpoint {
+    metrics: Metrics,
+}
+
+impl MetricsEndpoint {
+    pub fn new(metrics: Metrics) -> Self {
+        MetricsEndpoint { metrics }
+    }
+}
+
+#[async_trait]
+impl<State: Clone + Send + Sync + 'static> Endpoint<State> for MetricsEndpoint {
+    async fn call(&self, _req: Request<State>) -> Result {
+        let names: Vec<String> = self
+            .metrics
+            .registry
+            .gather()
+            .iter()
+            .map(|family| family.name().to_string())
+            .collect();
+        let mut res = Response::new(StatusCode::Ok);
+        res.body_json(&serde_json::json!({ "names": names }))?;
+        Ok(res)
+    }
+}
+
+/// `/actuator/prometheus` endpoint，返回 Prometheus 文本格式
+#[derive(Clone)]
+pub struct PrometheusEndpoint {
+    metrics: Metrics,
+}
+
+impl PrometheusEndpoint {
+    pub fn new(metrics: Metrics) -> Self {
+        PrometheusEndpoint { metrics }
+    }
+}
+
+#[async_trait]
+impl<State: Clone + Send + Sync + 'static> Endpoint<State> for PrometheusEndpoint {
+    async fn call(&self, _req: Request<State>) -> Result {
+        let mime: Mime = TextEncoder::new().format_type().parse()?;
+        let mut res = Response::new(StatusCode::Ok);
+        res.set_body(self.metrics.render());
+        res.set_content_type(mime);
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http_types::{Method, Request, Response, Url};
+    use crate::utils::retry_after::RetryAfter;
+    use std::time::Duration;
+
+    async fn get(app: &crate::Server<()>, path: &str) -> Response {
+        let url = Url::parse("http://localhost").unwrap().join(path).unwrap();
+        app.respond(Request::new(Method::Get, url)).await.unwrap()
+    }
+
+    #[async_std::test]
+    async fn records_requests_by_route() {
+        // 观察者只属于这个服务，测试结束时随服务一起释放
+        let metrics = Metrics::new();
+        let mut app = crate::new();
+        app.observe(metrics.clone());
+        app.at("/metrics-test/:id")
+            .get(|_| async { Ok("ok") })
+            .post(|_| async { Ok(StatusCode::Created) });
+        app.at(METRICS_PATH)
+            .get(MetricsEndpoint::new(metrics.clone()));
+        app.at(PROMETHEUS_PATH)
+            .get(PrometheusEndpoint::new(metrics.clone()));
+
+        get(&app, "/metrics-test/1").await;
+        get(&app, "/metrics-test/2").await;
+        get(&app, "/metrics-test-missing").await;
+
+        let mut res = get(&app, PROMETHEUS_PATH).await;
+        assert_eq!(res.status(), StatusCode::Ok);
+        assert_eq!(res.content_type().unwrap().essence(), "text/plain");
+        let body = res.body_string().await.unwrap();
+        assert!(body
+            .contains(r#"http_server_requests_total{method="GET",route="/metrics-test/:id"} 2"#));
+        assert!(body.contains(
+            r#"http_server_responses_total{method="GET",route="/metrics-test/:id",status="200"} 2"#
+        ));
+        assert!(body.contains(
+            r#"http_server_request_duration_seconds_count{method="GET",route="/metrics-test/:id"} 2"#
+        ));
+        assert!(body.contains(r#"route="UNMATCHED",status="404""#));
+        assert!(body.contains("http_server_active_connections 0"));
+
+        let mut res = get(&app, METRICS_PATH).await;
+        let body: serde_json::Value = res.body_json().await.unwrap();
+        assert!(body["names"]
+            .as_array()
+            .unwrap()
+            .contains(&"http_server_requests_total".into()));
+    }
+
+    #[async_std::test]
+    async fn records_retry_after_by_cause() {
+        let metrics = Metrics::new();
+        let mut app = crate::new();
+        app.observe(metrics.clone());
+        app.at("/draining").get(|_| async {
+            let mut res = crate::Response::new(StatusCode::ServiceUnavailable);
+            RetryAfter::shutdown(Duration::from_secs(30)).apply(&mut res);
+            Ok(res)
+        });
+
+        let res = get(&app, "/draining").await;
+        assert_eq!(res.header("Retry-After").unwrap(), "30");
+        get(&app, "/draining").await;
+
+        let body = metrics.render();
+        assert!(body.contains(r#"http_server_retry_after_seconds_count{cause="shutdown"} 2"#));
+        assert!(body.contains(r#"http_server_retry_after_seconds_sum{cause="shutdown"} 60"#));
+        assert!(
+            body.contains(r#"http_server_retry_after_seconds_bucket{cause="shutdown",le="10"} 0"#)
+        );
+        assert!(
+            body.contains(r#"http_server_retry_after_seconds_bucket{cause="shutdown",le="30"} 2"#)
+        );
+    }
+}
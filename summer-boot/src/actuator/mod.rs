@@ -0,0 +1,177 @@
+//! actuator endpoints
+//!
+//! 开启 `actuator` feature 后可用，提供健康检查、探针、指标、应用信息和路由列表。
+
+pub mod availability;
+pub mod configuration_properties;
+pub mod env;
+pub mod health;
+pub mod info;
+pub mod metrics;
+pub mod process;
+pub mod routes;
+
+use crate::{log, Endpoint, Server};
+
+use summer_boot_autoconfigure::{load_conf, GlobalConfig};
+
+use availability::{Availability, LivenessEndpoint, ReadinessEndpoint};
+use configuration_properties::ConfigurationProperties;
+use env::EnvEndpoint;
+use health::{HealthEndpoint, HealthRegistry};
+use info::{BuildInfo, InfoBuilder};
+use metrics::{Metrics, MetricsEndpoint, PrometheusEndpoint};
+
+/// 注册所有 actuator endpoints
+///
+/// 开启 `summer-boot` 的 `actuator` feature 后，`auto_scan` 会在注册完路由后自动调用，
+/// 并传入在应用 crate 中展开的 [`build_info!`](crate::build_info)。
+/// 健康检查和探针使用全局的 [`HealthRegistry`] 和 [`Availability`]，
+/// 指标使用为这个服务新建并通过 [`Server::observe`] 注册的 [`Metrics`]，
+/// 需要注册自定义指标时使用 [`mount_with_metrics`]。
+///
+/// 输出配置的 [`env_endpoint`] 不会自动注册，即使脱敏也可能暴露内部地址等信息，
+/// 需要时显式挂载到 [`env::ENV_PATH`]。
+///
+/// ```
+/// let mut app = summer_boot::new();
+/// summer_boot::actuator::mount(&mut app, summer_boot::build_info!());
+/// ```
+pub fn mount<State>(app: &mut Server<State>, build: BuildInfo)
+where
+    State: Clone + Send + Sync + 'static,
+{
+    mount_with_metrics(app, build, Metrics::new());
+}
+
+/// 与 [`mount`] 相同，使用传入的 [`Metrics`]
+///
+/// ```
+/// use summer_boot::actuator::metrics::Metrics;
+///
+/// let metrics = Metrics::new();
+/// // 在 metrics.registry() 中注册自定义指标
+/// let mut app = summer_boot::new();
+/// summer_boot::actuator::mount_with_metrics(&mut app, summer_boot::build_info!(), metrics);
+/// ```
+pub fn mount_with_metrics<State>(app: &mut Server<State>, build: BuildInfo, metrics: Metrics)
+where
+    State: Clone + Send + Sync + 'static,
+{
+    app.observe(metrics.clone());
+    app.at(health::HEALTH_PATH).get(health_endpoint());
+    app.at(info::INFO_PATH).get(info_endpoint(build));
+    app.at(availability::LIVENESS_PATH)
+        .get(LivenessEndpoint::new(Availability::global().clone()));
+    app.at(availability::READINESS_PATH)
+        .get(ReadinessEndpoint::new(Availability::global().clone()));
+    app.at(metrics::METRICS_PATH)
+        .get(MetricsEndpoint::new(metrics.clone()));
+    app.at(metrics::PROMETHEUS_PATH)
+        .get(PrometheusEndpoint::new(metrics));
+    routes::routes_dot(app);
+    routes::mappings(app);
+}
+
+/// 使用全局 [`HealthRegistry`] 的健康检查 endpoint
+///
+/// 任一指标为 `DOWN` 时整体状态为 `DOWN`，并返回 `503`。
+///
+/// ```
+/// use summer_boot::actuator::health::{Health, HealthIndicator, HealthRegistry};
+///
+/// struct Db;
+///
+/// #[async_trait::async_trait]
+/// impl HealthIndicator for Db {
+///     fn name(&self) -> &str {
+///         "db"
+///     }
+///
+///     async fn health(&self) -> Health {
+///         Health::up()
+///     }
+/// }
+///
+/// HealthRegistry::global().register(Db);
+/// let mut app = summer_boot::new();
+/// app.at("/actuator/health")
+///     .get(summer_boot::actuator::health_endpoint());
+/// ```
+pub fn health_endpoint<State>() -> impl Endpoint<State>
+where
+    State: Clone + Send + Sync + 'static,
+{
+    HealthEndpoint::new(HealthRegistry::global().clone())
+}
+
+/// 应用信息 endpoint，包含配置文件中的 `server` 配置和 `build` 构建信息
+///
+/// `build` 需要在应用 crate 中通过 [`build_info!`](crate::build_info) 生成，
+/// 才能得到应用自身的名称和版本。需要自定义键值时使用 [`InfoBuilder`]。
+pub fn info_endpoint<State>(build: BuildInfo) -> impl Endpoint<State>
+where
+    State: Clone + Send + Sync + 'static,
+{
+    InfoBuilder::new().config().build_info(build).build()
+}
+
+/// 输出加载的 `GlobalConfig` 的 endpoint，敏感的键按默认的 [`ConfigurationProperties`] 脱敏
+///
+/// 没有配置文件时返回 `{}`。需要额外脱敏的键时使用 [`EnvEndpoint::new`]。
+/// [`mount`] 不会注册这个 endpoint。
+///
+/// ```
+/// let mut app = summer_boot::new();
+/// app.at(summer_boot::actuator::env::ENV_PATH)
+///     .get(summer_boot::actuator::env_endpoint());
+/// ```
+pub fn env_endpoint<State>() -> impl Endpoint<State>
+where
+    State: Clone + Send + Sync + 'static,
+{
+    EnvEndpoint::new(ConfigurationProperties::new())
+}
+
+/// 加载配置文件，没有配置文件时返回 `None`
+pub(crate) fn load_config() -> Option<GlobalConfig> {
+    load_conf().unwrap_or_else(|err| {
+        log::warn!("无法加载配置文件", { error: err.to_string() });
+        None
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http_types::{Method, Request, Response, Url};
+
+    #[async_std::test]
+    async fn info_reports_caller_build() {
+        let mut app = crate::new();
+        mount(
+            &mut app,
+            BuildInfo {
+                name: "demo-app",
+                version: "2.1.0",
+                commit: None,
+            },
+        );
+        let url = Url::parse("http://localhost/actuator/info").unwrap();
+        let mut res: Response = app.respond(Request::new(Method::Get, url)).await.unwrap();
+        let info: serde_json::Value = res.body_json().await.unwrap();
+        assert_eq!(
+            info["build"],
+            serde_json::json!({ "name": "demo-app", "version": "2.1.0" })
+        );
+    }
+
+    #[async_std::test]
+    async fn env_is_not_mounted_by_default() {
+        let mut app = crate::new();
+        mount(&mut app, crate::build_info!());
+        let url = Url::parse("http://localhost/actuator/env").unwrap();
+        let res: Response = app.respond(Request::new(Method::Get, url)).await.unwrap();
+        assert_eq!(res.status(), crate::StatusCode::NotFound);
+    }
+}
@@ -0,0 +1,349 @@
+//!
+//! Process metrics
+//!
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use prometheus::core::{Collector, Desc};
+use prometheus::proto::MetricFamily;
+use prometheus::{Counter, IntGauge};
+use serde::Serialize;
+
+use super::health::{Health, HealthIndicator, Status};
+
+/// 进程的资源使用情况，当前平台不支持的项为 `None`
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ProcessStats {
+    /// 常驻内存（RSS），字节
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resident_memory_bytes: Option<u64>,
+    /// 虚拟内存，字节
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub virtual_memory_bytes: Option<u64>,
+    /// 打开的文件描述符数
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub open_fds: Option<u64>,
+    /// 线程数
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub threads: Option<u64>,
+    /// 用户态和内核态 CPU 时间之和，秒
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpu_seconds: Option<f64>,
+}
+
+impl ProcessStats {
+    /// 读取当前进程的资源使用情况
+    ///
+    /// Linux 上读取 `/proc/self`，其他平台返回全为 `None` 的结果。
+    pub fn read() -> Self {
+        platform::read()
+    }
+}
+
+/// 解析 `/proc/<pid>/stat` 的内容
+///
+/// 进程名可能包含空格和括号，从最后一个 `)` 之后按空格切分。
+/// `page_size` 为内存页的字节数，`ticks_per_second` 为 `utime`、`stime` 的时钟频率。
+fn parse_stat(content: &str, page_size: u64, ticks_per_second: u64) -> ProcessStats {
+    let Some((_, fields)) = content.rsplit_once(')') else {
+        return ProcessStats::default();
+    };
+    // 切分后的第一个字段是 stat 中的第 3 个字段 `state`
+    let fields: Vec<&str> = fields.split_whitespace().collect();
+    let field = |n: usize| fields.get(n - 3).and_then(|v| v.parse::<u64>().ok());
+
+    let cpu_seconds = match (field(14), field(15)) {
+        (Some(utime), Some(stime)) if ticks_per_second > 0 => {
+            Some((utime + stime) as f64 / ticks_per_second as f64)
+        }
+        _ => None,
+    };
+    ProcessStats {
+        resident_memory_bytes: field(24).map(|pages| pages * page_size),
+        virtual_memory_bytes: field(23),
+        open_fds: None,
+        threads: field(20),
+        cpu_seconds,
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::{parse_stat, ProcessStats};
+
+    pub(super) fn read() -> ProcessStats {
+        let Ok(content) = std::fs::read_to_string("/proc/self/stat") else {
+            return ProcessStats::default();
+        };
+        // SAFETY: sysconf 只读取系统配置
+        let (page_size, ticks) = unsafe {
+            (
+                libc::sysconf(libc::_SC_PAGESIZE),
+                libc::sysconf(libc::_SC_CLK_TCK),
+            )
+        };
+        let mut stats = parse_stat(&content, page_size.max(0) as u64, ticks.max(0) as u64);
+        stats.open_fds = std::fs::read_dir("/proc/self/fd")
+            .ok()
+            .map(|entries| entries.count() as u64);
+        stats
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod platform {
+    use super::ProcessStats;
+
+    pub(super) fn read() -> ProcessStats {
+        ProcessStats::default()
+    }
+}
+
+struct Cache {
+    stats: ProcessStats,
+    read_at: Option<Instant>,
+}
+
+/// 进程资源的 Prometheus 指标
+///
+/// 在抓取时读取 [`ProcessStats`]，两次读取的间隔小于 `min_interval` 时使用上一次的结果，
+/// 避免频繁抓取时反复读取 `/proc`。当前平台不支持的指标不会输出。
+#[derive(Clone)]
+pub struct ProcessCollector {
+    min_interval: Duration,
+    read: fn() -> ProcessStats,
+    cache: Arc<Mutex<Cache>>,
+    resident_memory: IntGauge,
+    virtual_memory: IntGauge,
+    open_fds: IntGauge,
+    threads: IntGauge,
+    cpu_seconds: Counter,
+}
+
+impl Default for ProcessCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProcessCollector {
+    /// 默认的最小读取间隔为 1 秒
+    pub fn new() -> Self {
+        Self::with_reader(Duration::from_secs(1), ProcessStats::read)
+    }
+
+    /// 设置两次读取的最小间隔
+    #[must_use]
+    pub fn min_interval(mut self, min_interval: Duration) -> Self {
+        self.min_interval = min_interval;
+        self
+    }
+
+    fn with_reader(min_interval: Duration, read: fn() -> ProcessStats) -> Self {
+        ProcessCollector {
+            min_interval,
+            read,
+            cache: Arc::new(Mutex::new(Cache {
+                stats: ProcessStats::default(),
+                read_at: None,
+            })),
+            resident_memory: IntGauge::new("process_resident_memory_bytes", "常驻内存字节数")
+                .unwrap(),
+            virtual_memory: IntGauge::new("process_virtual_memory_bytes", "虚拟内存字节数")
+                .unwrap(),
+            open_fds: IntGauge::new("process_open_fds", "打开的文件描述符数").unwrap(),
+            threads: IntGauge::new("process_threads", "线程数").unwrap(),
+            cpu_seconds: Counter::new("process_cpu_seconds_total", "用户态和内核态 CPU 时间")
+                .unwrap(),
+        }
+    }
+
+    /// 当前的资源使用情况，距上次读取不足最小间隔时返回缓存的结果
+    pub fn stats(&self) -> ProcessStats {
+        let mut cache = self.cache.lock().expect("进程指标缓存锁异常");
+        self.refresh(&mut cache);
+        cache.stats.clone()
+    }
+
+    fn refresh(&self, cache: &mut Cache) {
+        if cache
+            .read_at
+            .is_some_and(|read_at| read_at.elapsed() < self.min_interval)
+        {
+            return;
+        }
+        cache.stats = (self.read)();
+        cache.read_at = Some(Instant::now());
+    }
+}
+
+impl Collector for ProcessCollector {
+    fn desc(&self) -> Vec<&Desc> {
+        let mut desc = Vec::new();
+        desc.extend(self.resident_memory.desc());
+        desc.extend(self.virtual_memory.desc());
+        desc.extend(self.open_fds.desc());
+        desc.extend(self.threads.desc());
+        desc.extend(self.cpu_seconds.desc());
+        desc
+    }
+
+    fn collect(&self) -> Vec<MetricFamily> {
+        // 持有锁直到输出完成，并发抓取时不会交错地更新指标
+        let mut cache = self.cache.lock().expect("进程指标缓存锁异常");
+        self.refresh(&mut cache);
+        let stats = &cache.stats;
+
+        let mut families = Vec::new();
+        let gauges: [(&IntGauge, Option<u64>); 4] = [
+            (&self.resident_memory, stats.resident_memory_bytes),
+            (&self.virtual_memory, stats.virtual_memory_bytes),
+            (&self.open_fds, stats.open_fds),
+            (&self.threads, stats.threads),
+        ];
+        for (gauge, value) in gauges {
+            if let Some(value) = value {
+                gauge.set(value as i64);
+                families.extend(gauge.collect());
+            }
+        }
+        if let Some(seconds) = stats.cpu_seconds {
+            self.cpu_seconds.reset();
+            self.cpu_seconds.inc_by(seconds);
+            families.extend(self.cpu_seconds.collect());
+        }
+        families
+    }
+}
+
+/// 内存检查，常驻内存超过阈值时为 `DOWN`
+///
+/// 详情中包含进程资源使用情况的摘要。当前平台无法读取常驻内存时为 `UNKNOWN`。
+#[derive(Clone)]
+pub struct MemoryHealthIndicator {
+    collector: ProcessCollector,
+    threshold: u64,
+}
+
+impl MemoryHealthIndicator {
+    /// `threshold` 为最大常驻内存字节数
+    pub fn new(threshold: u64) -> Self {
+        MemoryHealthIndicator {
+            collector: ProcessCollector::new(),
+            threshold,
+        }
+    }
+}
+
+#[async_trait]
+impl HealthIndicator for MemoryHealthIndicator {
+    fn name(&self) -> &str {
+        "memory"
+    }
+
+    async fn health(&self) -> Health {
+        let stats = self.collector.stats();
+        let status = match stats.resident_memory_bytes {
+            Some(rss) if rss <= self.threshold => Status::Up,
+            Some(_) => Status::Down,
+            None => Status::Unknown,
+        };
+        Health::new(status)
+            .with_detail("threshold", self.threshold)
+            .with_detail("process", serde_json::to_value(&stats).unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const STAT: &str = "4242 (my (odd) app) S 1 4242 4242 0 -1 4194560 1875 0 0 0 \
+        250 150 0 0 20 0 7 0 123456 104857600 2560 18446744073709551615 \
+        1 1 0 0 0 0 0 4096 17003 0 0 0 17 3 0 0 0 0 0";
+
+    #[test]
+    fn parses_proc_stat() {
+        let stats = parse_stat(STAT, 4096, 100);
+        assert_eq!(stats.resident_memory_bytes, Some(2560 * 4096));
+        assert_eq!(stats.virtual_memory_bytes, Some(104_857_600));
+        assert_eq!(stats.threads, Some(7));
+        assert_eq!(stats.cpu_seconds, Some(4.0));
+        assert_eq!(stats.open_fds, None);
+
+        assert_eq!(parse_stat("garbage", 4096, 100), ProcessStats::default());
+        let truncated = parse_stat("1 (app) S 1 2 3", 4096, 100);
+        assert_eq!(truncated.threads, None);
+        assert_eq!(truncated.cpu_seconds, None);
+    }
+
+    fn fixture() -> ProcessStats {
+        ProcessStats {
+            open_fds: Some(12),
+            ..parse_stat(STAT, 4096, 100)
+        }
+    }
+
+    static READS: AtomicUsize = AtomicUsize::new(0);
+
+    fn counted_fixture() -> ProcessStats {
+        READS.fetch_add(1, Ordering::SeqCst);
+        fixture()
+    }
+
+    #[test]
+    fn caches_between_scrapes() {
+        let collector = ProcessCollector::with_reader(Duration::from_secs(60), counted_fixture);
+        let registry = prometheus::Registry::new();
+        registry.register(Box::new(collector.clone())).unwrap();
+
+        let names: Vec<String> = registry
+            .gather()
+            .iter()
+            .map(|family| family.name().to_string())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "process_cpu_seconds_total",
+                "process_open_fds",
+                "process_resident_memory_bytes",
+                "process_threads",
+                "process_virtual_memory_bytes",
+            ]
+        );
+        registry.gather();
+        assert_eq!(collector.stats().threads, Some(7));
+        assert_eq!(READS.load(Ordering::SeqCst), 1);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn reads_own_process() {
+        let stats = ProcessStats::read();
+        assert!(stats.resident_memory_bytes.unwrap() > 0);
+        assert!(stats.threads.unwrap() >= 1);
+        assert!(stats.open_fds.unwrap() >= 3);
+    }
+
+    #[test]
+    fn skips_unsupported_metrics() {
+        let collector = ProcessCollector::with_reader(Duration::ZERO, ProcessStats::default);
+        assert!(collector.collect().is_empty());
+    }
+
+    #[async_std::test]
+    async fn memory_threshold() {
+        let indicator = |threshold| MemoryHealthIndicator {
+            collector: ProcessCollector::with_reader(Duration::ZERO, fixture),
+            threshold,
+        };
+        let health = indicator(100 * 1024 * 1024).health().await;
+        assert_eq!(health.status, Status::Up);
+        assert_eq!(health.details["process"]["threads"], 7);
+        assert_eq!(health.details["process"]["open_fds"], 12);
+        assert_eq!(indicator(1024).health().await.status, Status::Down);
+    }
+}
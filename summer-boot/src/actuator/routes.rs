@@ -0,0 +1,102 @@
+//!
+//! Routes endpoint
+//!
+use std::sync::Arc;
+
+use crate::{Request, Response, Server, StatusCode};
+use serde_json::{json, Value};
+
+pub const ROUTES_DOT_PATH: &str = "/actuator/routes.dot";
+pub const MAPPINGS_PATH: &str = "/actuator/mappings";
+
+/// 在 `/actuator/routes.dot` 输出路由树的 Graphviz DOT 描述
+///
+/// 路由树在调用时生成，需要在注册完其他路由之后调用。
+pub fn routes_dot<State>(app: &mut Server<State>)
+where
+    State: Clone + Send + Sync + 'static,
+{
+    let dot = Arc::new(app.routes_dot());
+    app.at(ROUTES_DOT_PATH).get(move |_: Request<State>| {
+        let dot = dot.clone();
+        async move {
+            Ok(Response::builder(StatusCode::Ok)
+                .content_type("text/vnd.graphviz")
+                .body(dot.as_str())
+                .build())
+        }
+    });
+}
+
+/// 在 `/actuator/mappings` 以 JSON 输出所有已注册的路由
+///
+/// 每个路由包含 `method`、`path`、`name`、`endpoint` 和 `middleware`，
+/// 匹配所有方法的路由 `method` 为 `null`。路由列表在调用时生成，需要在注册完其他路由之后调用。
+pub fn mappings<State>(app: &mut Server<State>)
+where
+    State: Clone + Send + Sync + 'static,
+{
+    let routes: Vec<Value> = app
+        .list_routes()
+        .into_iter()
+        .map(|route| {
+            json!({
+                "method": route.method.map(|method| method.to_string()),
+                "path": route.path,
+                "name": route.name,
+                "endpoint": route.endpoint,
+                "middleware": route.middleware,
+            })
+        })
+        .collect();
+    let body = Arc::new(json!({ "routes": routes }));
+    app.at(MAPPINGS_PATH).get(move |_: Request<State>| {
+        let body = body.clone();
+        async move {
+            let mut res = Response::new(StatusCode::Ok);
+            res.body_json(&*body)?;
+            Ok(res)
+        }
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use crate::http_types::{Method, Request, Response, Url};
+
+    #[async_std::test]
+    async fn serves_routes_dot() {
+        let mut app = crate::new();
+        app.at("/users/:id").get(|_| async { Ok("user") });
+        super::routes_dot(&mut app);
+
+        let url = Url::parse("http://localhost/actuator/routes.dot").unwrap();
+        let mut res: Response = app.respond(Request::new(Method::Get, url)).await.unwrap();
+        assert_eq!(res.content_type().unwrap().essence(), "text/vnd.graphviz");
+
+        let body = res.body_string().await.unwrap();
+        assert!(body.starts_with("digraph routes {"));
+        assert!(body.contains(":id"));
+        assert!(!body.contains("routes.dot"));
+    }
+
+    #[async_std::test]
+    async fn serves_mappings() {
+        let mut app = crate::new();
+        app.at("/users/:id").get(|_| async { Ok("user") });
+        app.at("/users").post(|_| async { Ok("created") });
+        super::mappings(&mut app);
+
+        let url = Url::parse("http://localhost/actuator/mappings").unwrap();
+        let mut res: Response = app.respond(Request::new(Method::Get, url)).await.unwrap();
+        assert_eq!(res.content_type().unwrap().essence(), "application/json");
+
+        let body: serde_json::Value = res.body_json().await.unwrap();
+        let routes = body["routes"].as_array().unwrap();
+        assert_eq!(routes.len(), 2);
+        assert_eq!(routes[0]["method"], "GET");
+        assert_eq!(routes[0]["path"], "/users/:id");
+        assert_eq!(routes[1]["method"], "POST");
+        assert_eq!(routes[1]["path"], "/users");
+    }
+}
@@ -0,0 +1,5 @@
+//! 与其他框架的兼容层
+
+#[cfg(feature = "tide")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tide")))]
+pub mod tide;
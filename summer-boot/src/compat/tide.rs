@@ -0,0 +1,200 @@
+//! tide 应用迁移适配
+//!
+//! summer boot 与 tide 底层都基于 `http-types`，请求和响应之间的转换
+//! 只需要移动内部的 `http_types::Request` / `http_types::Response`，扩展数据会一并保留。
+//!
+//! - [`TideEndpoint`] 将 `tide::Endpoint` 挂载到 summer boot 路由上，原有处理函数无需修改。
+//! - [`TideMiddleware`] 将 `tide::Middleware` 注册到 summer boot 的中间件链中。
+//!
+//! # 不支持的功能
+//!
+//! - `TideMiddleware` 内部无法读取路由参数，`req.param` 会返回错误。
+//! - `TideMiddleware` 中多次调用 `next.run` 会得到 `500` 响应。
+//! - tide 的 `Route::serve_dir`、`Route::nest`、`Server::listen` 等路由与监听功能，
+//!   需要改用 summer boot 自身的对应方法。
+//! - `TideEndpoint` 的状态类型必须与 summer boot `Server` 的状态类型一致。
+
+use crate::{Endpoint, Error, Middleware, Next, Request, Result, StatusCode};
+
+use async_channel::{Receiver, Sender};
+use async_trait::async_trait;
+use futures_util::future;
+
+use std::sync::{Arc, OnceLock};
+
+/// 将 `tide::Endpoint` 包装为 summer boot 的 [`Endpoint`]
+///
+/// `path` 需要与注册的路由路径一致，tide 处理函数才能通过 `req.param` 读取路由参数。
+/// 内部的 tide 服务在第一次请求时用服务状态创建，之后的请求复用同一个实例。
+///
+/// ```ignore
+/// app.at("/hello/:name").get(TideEndpoint::new("/hello/:name", greet));
+/// ```
+pub struct TideEndpoint<State> {
+    path: String,
+    endpoint: Arc<dyn ::tide::Endpoint<State>>,
+    app: OnceLock<::tide::Server<State>>,
+}
+
+impl<State> TideEndpoint<State>
+where
+    State: Clone + Send + Sync + 'static,
+{
+    /// 创建一个 `TideEndpoint` 新的实例。
+    pub fn new(path: impl Into<String>, endpoint: impl ::tide::Endpoint<State>) -> Self {
+        Self {
+            path: path.into(),
+            endpoint: Arc::new(endpoint),
+            app: OnceLock::new(),
+        }
+    }
+
+    fn app(&self, state: &State) -> &::tide::Server<State> {
+        self.app.get_or_init(|| {
+            let mut app = ::tide::with_state(state.clone());
+            app.at(&self.path)
+                .all(SharedEndpoint(self.endpoint.clone()));
+            app
+        })
+    }
+}
+
+#[async_trait]
+impl<State> Endpoint<State> for TideEndpoint<State>
+where
+    State: Clone + Send + Sync + 'static,
+{
+    async fn call(&self, req: Request<State>) -> Result {
+        let app = self.app(req.state());
+        let res: http_types::Response = app.respond(http_types::Request::from(req)).await?;
+        Ok(res.into())
+    }
+}
+
+/// 将 `tide::Middleware` 包装为 summer boot 的 [`Middleware`]
+///
+/// 与 [`TideEndpoint`] 一样，内部的 tide 服务只在第一次请求时创建一次。
+pub struct TideMiddleware<State> {
+    middleware: Arc<dyn ::tide::Middleware<State>>,
+    app: OnceLock<::tide::Server<State>>,
+}
+
+impl<State> TideMiddleware<State>
+where
+    State: Clone + Send + Sync + 'static,
+{
+    /// 创建一个 `TideMiddleware` 新的实例。
+    pub fn new(middleware: impl ::tide::Middleware<State>) -> Self {
+        Self {
+            middleware: Arc::new(middleware),
+            app: OnceLock::new(),
+        }
+    }
+
+    fn app(&self, state: &State) -> &::tide::Server<State> {
+        self.app.get_or_init(|| {
+            let mut app = ::tide::with_state(state.clone());
+            app.with(SharedMiddleware(self.middleware.clone()));
+            app.at("/").all(forward);
+            app.at("*").all(forward);
+            app
+        })
+    }
+}
+
+/// tide 中间件调用 `next.run` 时，通过通道将请求交还给 summer boot 的中间件链
+struct Forward {
+    request: Sender<http_types::Request>,
+    response: Receiver<http_types::Response>,
+}
+
+#[async_trait]
+impl<State> Middleware<State> for TideMiddleware<State>
+where
+    State: Clone + Send + Sync + 'static,
+{
+    async fn handle(&self, mut req: Request<State>, next: Next<'_, State>) -> Result {
+        let state = req.state().clone();
+        let route_params = std::mem::take(&mut req.route_params);
+        let (request_tx, request_rx) = async_channel::bounded(1);
+        let (response_tx, response_rx) = async_channel::bounded(1);
+
+        let mut req: http_types::Request = req.into();
+        req.ext_mut().insert(Forward {
+            request: request_tx,
+            response: response_rx,
+        });
+
+        let tide = self.app(&state).respond::<_, http_types::Response>(req);
+        let summer = async move {
+            // tide 中间件没有调用 next.run 时，请求被丢弃，通道随之关闭
+            if let Ok(req) = request_rx.recv().await {
+                let req = Request::new(state, req, route_params);
+                let res: http_types::Response = next.run(req).await.into();
+                let _ = response_tx.send(res).await;
+            }
+        };
+
+        let (res, ()) = future::join(tide, summer).await;
+        Ok(res?.into())
+    }
+
+    fn name(&self) -> &str {
+        self.middleware.name()
+    }
+}
+
+async fn forward<State>(req: ::tide::Request<State>) -> ::tide::Result
+where
+    State: Clone + Send + Sync + 'static,
+{
+    let mut req: http_types::Request = req.into();
+    let forward = req.ext_mut().remove::<Forward>().ok_or_else(|| {
+        Error::from_str(
+            StatusCode::InternalServerError,
+            "tide 中间件只能调用一次 next.run",
+        )
+    })?;
+
+    let closed = || {
+        Error::from_str(
+            StatusCode::InternalServerError,
+            "summer boot 中间件链已关闭",
+        )
+    };
+    forward.request.send(req).await.map_err(|_| closed())?;
+    let res = forward.response.recv().await.map_err(|_| closed())?;
+    Ok(res.into())
+}
+
+struct SharedEndpoint<State>(Arc<dyn ::tide::Endpoint<State>>);
+
+#[async_trait]
+impl<State> ::tide::Endpoint<State> for SharedEndpoint<State>
+where
+    State: Clone + Send + Sync + 'static,
+{
+    async fn call(&self, req: ::tide::Request<State>) -> ::tide::Result {
+        self.0.call(req).await
+    }
+}
+
+struct SharedMiddleware<State>(Arc<dyn ::tide::Middleware<State>>);
+
+#[async_trait]
+impl<State> ::tide::Middleware<State> for SharedMiddleware<State>
+where
+    State: Clone + Send + Sync + 'static,
+{
+    async fn handle(
+        &self,
+        req: ::tide::Request<State>,
+        next: ::tide::Next<'_, State>,
+    ) -> ::tide::Result {
+        self.0.handle(req, next).await
+    }
+
+    fn name(&self) -> &str {
+        self.0.name()
+    }
+}
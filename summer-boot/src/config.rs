@@ -0,0 +1,23 @@
+//! 配置文件的结构和模板
+//!
+//! 运行 `#[summer_boot::main]` 生成的程序时加上 `--generate-config`，
+//! 会打印 [`generate_template`] 生成的配置模板并退出。
+
+pub use summer_boot_autoconfigure::{
+    config_sections, generate_template, ConfigKey, ConfigSchema, ConfigSection, GlobalConfig,
+};
+
+/// 打印配置模板的命令行参数
+pub const GENERATE_CONFIG_FLAG: &str = "--generate-config";
+
+/// 命令行参数包含 [`GENERATE_CONFIG_FLAG`] 时打印配置模板并退出进程
+#[doc(hidden)]
+pub fn generate_config_if_requested() {
+    if std::env::args()
+        .skip(1)
+        .any(|arg| arg == GENERATE_CONFIG_FLAG)
+    {
+        print!("{}", generate_template());
+        std::process::exit(0);
+    }
+}
@@ -0,0 +1,105 @@
+//! 组件注册表
+//!
+//! 使用 `#[summer_boot::component]` 标注的结构体由 `auto_scan` 在注册路由之前
+//! 通过 `Default::default()` 创建并注册，之后可以在任何位置通过 [`inject`] 获取。
+
+use dashmap::DashMap;
+
+use std::any::{type_name, Any, TypeId};
+use std::sync::{Arc, OnceLock};
+
+/// 可以注册到 [`ComponentRegistry`] 的组件
+///
+/// 一般由 `#[summer_boot::component]` 宏实现。
+pub trait Component: Default + Send + Sync + 'static {
+    /// 创建默认实例并注册到全局注册表
+    fn register() {
+        ComponentRegistry::global().register(Self::default());
+    }
+}
+
+/// 按类型保存组件实例
+#[derive(Debug, Default)]
+pub struct ComponentRegistry {
+    components: DashMap<TypeId, Arc<dyn Any + Send + Sync>>,
+}
+
+impl ComponentRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 全局注册表
+    pub fn global() -> &'static ComponentRegistry {
+        static GLOBAL: OnceLock<ComponentRegistry> = OnceLock::new();
+        GLOBAL.get_or_init(ComponentRegistry::new)
+    }
+
+    /// 注册组件，同一类型重复注册时替换之前的实例
+    pub fn register<T: Send + Sync + 'static>(&self, component: T) {
+        self.components
+            .insert(TypeId::of::<T>(), Arc::new(component));
+    }
+
+    /// 获取组件，未注册时返回 `None`
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<Arc<T>> {
+        let component = self.components.get(&TypeId::of::<T>())?.value().clone();
+        component.downcast::<T>().ok()
+    }
+}
+
+/// 从全局注册表获取组件
+///
+/// # Panics
+///
+/// 组件未注册时 panic。
+///
+/// # Examples
+///
+/// ```
+/// #[derive(Default)]
+/// struct UserService;
+///
+/// summer_boot::ComponentRegistry::global().register(UserService);
+/// let service = summer_boot::inject::<UserService>();
+/// ```
+pub fn inject<T: Send + Sync + 'static>() -> Arc<T> {
+    ComponentRegistry::global()
+        .get::<T>()
+        .unwrap_or_else(|| panic!("组件 `{}` 未注册", type_name::<T>()))
+}
+
+#[cfg(all(test, feature = "macros"))]
+mod test {
+    use super::*;
+    use crate as summer_boot;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[summer_boot::component]
+    #[derive(Default)]
+    struct UserService {
+        calls: AtomicUsize,
+    }
+
+    impl UserService {
+        fn find(&self) -> &'static str {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            "James"
+        }
+    }
+
+    #[test]
+    fn inject_from_multiple_threads() {
+        <UserService as Component>::register();
+
+        let handles: Vec<_> = (0..8)
+            .map(|_| std::thread::spawn(|| summer_boot::inject::<UserService>().find()))
+            .collect();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), "James");
+        }
+        assert_eq!(inject::<UserService>().calls.load(Ordering::SeqCst), 8);
+        assert!(ComponentRegistry::new().get::<UserService>().is_none());
+    }
+}
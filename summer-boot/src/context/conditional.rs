@@ -0,0 +1,106 @@
+//! 静态文件的条件请求处理。
+//!
+//! 根据文件元数据或内容生成 `ETag` 和 `Last-Modified`，
+//! 并处理 `If-None-Match` / `If-Modified-Since` 请求头。
+
+use crate::http1::date::{fmt_http_date, parse_http_date};
+use crate::{Request, Response, StatusCode};
+
+use http_types::headers::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use xxhash_rust::xxh3::xxh3_64;
+
+use std::fs::Metadata;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// 文件的缓存校验信息
+#[derive(Debug, Clone)]
+pub(crate) struct Validators {
+    etag: String,
+    last_modified: Option<SystemTime>,
+}
+
+impl Validators {
+    /// 通过文件元数据计算校验信息，ETag 为 inode + mtime 的 xxHash 十六进制值。
+    pub(crate) fn from_metadata(metadata: &Metadata) -> Self {
+        let modified = metadata.modified().ok();
+        let nanos = modified
+            .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+
+        let mut buf = [0u8; 24];
+        buf[..8].copy_from_slice(&inode(metadata).to_le_bytes());
+        buf[8..].copy_from_slice(&nanos.to_le_bytes());
+
+        Self {
+            etag: format!("\"{:016x}\"", xxh3_64(&buf)),
+            // HTTP 日期只精确到秒
+            last_modified: modified.map(truncate_to_secs),
+        }
+    }
+
+    /// 通过文件内容计算校验信息，用于编译进二进制的文件，ETag 为内容的 xxHash 十六进制值。
+    pub(crate) fn from_contents(contents: &[u8], modified: Option<SystemTime>) -> Self {
+        Self {
+            etag: format!("\"{:016x}\"", xxh3_64(contents)),
+            last_modified: modified.map(truncate_to_secs),
+        }
+    }
+
+    /// 强校验的 ETag，包含双引号
+    pub(crate) fn etag(&self) -> &str {
+        &self.etag
+    }
+
+    /// 判断客户端缓存是否仍然有效。
+    ///
+    /// 存在 `If-None-Match` 时忽略 `If-Modified-Since`。
+    pub(crate) fn is_not_modified<State>(&self, req: &Request<State>) -> bool {
+        if let Some(values) = req.header(IF_NONE_MATCH) {
+            return values
+                .iter()
+                .flat_map(|value| value.as_str().split(','))
+                .map(str::trim)
+                .any(|tag| tag == "*" || tag.trim_start_matches("W/") == self.etag);
+        }
+
+        match (self.last_modified, req.header(IF_MODIFIED_SINCE)) {
+            (Some(modified), Some(since)) => parse_http_date(since.last().as_str())
+                .map(|since| modified <= since)
+                .unwrap_or(false),
+            _ => false,
+        }
+    }
+
+    /// 为响应添加 `ETag` 和 `Last-Modified`。
+    pub(crate) fn apply(&self, res: &mut Response) {
+        res.insert_header(ETAG, self.etag.as_str());
+        if let Some(modified) = self.last_modified {
+            res.insert_header(LAST_MODIFIED, fmt_http_date(modified));
+        }
+    }
+
+    /// 生成空body的 `304 Not Modified` 响应。
+    pub(crate) fn not_modified(&self) -> Response {
+        let mut res = Response::new(StatusCode::NotModified);
+        self.apply(&mut res);
+        res
+    }
+}
+
+#[cfg(unix)]
+fn inode(metadata: &Metadata) -> u64 {
+    std::os::unix::fs::MetadataExt::ino(metadata)
+}
+
+#[cfg(not(unix))]
+fn inode(_: &Metadata) -> u64 {
+    0
+}
+
+fn truncate_to_secs(time: SystemTime) -> SystemTime {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => UNIX_EPOCH + Duration::from_secs(d.as_secs()),
+        Err(_) => time,
+    }
+}
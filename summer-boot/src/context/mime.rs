@@ -0,0 +1,48 @@
+//! 静态文件的 MIME 类型覆盖。
+
+use crate::Response;
+
+use http_types::mime::{self, Mime};
+
+use std::collections::HashMap;
+use std::path::Path;
+
+/// 扩展名 -> MIME 类型的覆盖表
+///
+/// 默认包含 `Body::from_file` 容易识别错误的扩展名。
+#[derive(Debug, Clone)]
+pub(crate) struct MimeOverrides {
+    overrides: HashMap<String, Mime>,
+}
+
+impl Default for MimeOverrides {
+    fn default() -> Self {
+        let mut overrides = Self {
+            overrides: HashMap::new(),
+        };
+        let javascript = "text/javascript".parse().expect("合法的 MIME 类型");
+        overrides.insert("mjs", javascript);
+        overrides.insert("wasm", mime::WASM);
+        overrides
+    }
+}
+
+impl MimeOverrides {
+    /// 注册扩展名对应的 MIME 类型，扩展名不区分大小写
+    pub(crate) fn insert(&mut self, extension: &str, mime: Mime) {
+        let extension = extension.trim_start_matches('.').to_ascii_lowercase();
+        self.overrides.insert(extension, mime);
+    }
+
+    /// 按文件扩展名设置响应的 `Content-Type`
+    pub(crate) fn apply(&self, path: impl AsRef<Path>, res: &mut Response) {
+        let mime = path
+            .as_ref()
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(|ext| self.overrides.get(&ext.to_ascii_lowercase()));
+        if let Some(mime) = mime {
+            res.set_content_type(mime.clone());
+        }
+    }
+}
@@ -1,2 +1,7 @@
+pub mod component;
+mod conditional;
+mod mime;
+mod range;
 pub mod serve_dir;
+pub mod serve_embedded;
 pub mod serve_file;
@@ -0,0 +1,147 @@
+//! 静态文件的 `Range` 请求处理。
+//!
+//! 只支持单个 `bytes=` 范围，多个范围按普通请求返回完整内容。
+
+use super::conditional::Validators;
+use super::mime::MimeOverrides;
+use crate::{Body, Request, Response, StatusCode};
+
+use async_std::fs::File;
+use async_std::io::{self, prelude::*, BufReader, SeekFrom};
+use async_std::path::Path;
+use http_types::headers::{ACCEPT_RANGES, CONTENT_RANGE, IF_RANGE};
+
+/// 请求的字节范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ByteRange {
+    /// 没有 `Range` 或无法识别，返回完整内容
+    Full,
+    /// `[start, end)`
+    Partial(u64, u64),
+    /// 范围超出内容长度
+    Unsatisfiable,
+}
+
+impl ByteRange {
+    /// 解析请求的范围，`If-Range` 与当前 ETag 不一致时返回完整内容
+    pub(crate) fn requested<State>(
+        req: &Request<State>,
+        len: u64,
+        validators: &Validators,
+    ) -> Self {
+        let fresh = req
+            .header(IF_RANGE)
+            .map_or(true, |tag| tag.as_str() == validators.etag());
+        match req.header("Range").filter(|_| fresh) {
+            Some(range) => parse(range.as_str(), len),
+            None => ByteRange::Full,
+        }
+    }
+
+    /// `416 Range Not Satisfiable` 响应
+    pub(crate) fn not_satisfiable(len: u64) -> Response {
+        let mut res = Response::new(StatusCode::RequestedRangeNotSatisfiable);
+        res.insert_header(CONTENT_RANGE, format!("bytes */{}", len));
+        res.insert_header(ACCEPT_RANGES, "bytes");
+        res
+    }
+
+    /// 设置 `206 Partial Content` 响应的状态和 `Content-Range`
+    pub(crate) fn partial(res: &mut Response, start: u64, end: u64, len: u64) {
+        res.set_status(StatusCode::PartialContent);
+        res.insert_header(
+            CONTENT_RANGE,
+            format!("bytes {}-{}/{}", start, end - 1, len),
+        );
+    }
+}
+
+/// 解析 `bytes=start-end`、`bytes=start-` 和 `bytes=-suffix`
+fn parse(value: &str, len: u64) -> ByteRange {
+    let Some(spec) = value.trim().strip_prefix("bytes=") else {
+        return ByteRange::Full;
+    };
+    if spec.contains(',') {
+        return ByteRange::Full;
+    }
+    let Some((start, end)) = spec.trim().split_once('-') else {
+        return ByteRange::Full;
+    };
+    match (start.trim(), end.trim()) {
+        ("", "") => ByteRange::Full,
+        ("", suffix) => match suffix.parse::<u64>() {
+            Ok(0) => ByteRange::Unsatisfiable,
+            Ok(_) if len == 0 => ByteRange::Unsatisfiable,
+            Ok(suffix) => ByteRange::Partial(len.saturating_sub(suffix), len),
+            Err(_) => ByteRange::Full,
+        },
+        (start, end) => {
+            let Ok(start) = start.parse::<u64>() else {
+                return ByteRange::Full;
+            };
+            let end = match end {
+                "" => len,
+                end => match end.parse::<u64>() {
+                    Ok(end) if end >= start => end.saturating_add(1).min(len),
+                    _ => return ByteRange::Full,
+                },
+            };
+            if start >= len {
+                ByteRange::Unsatisfiable
+            } else {
+                ByteRange::Partial(start, end)
+            }
+        }
+    }
+}
+
+/// 生成文件的响应，按 `Range` 只读取请求的部分
+///
+/// MIME 类型与 `Body::from_file` 相同，再按 `mime_overrides` 覆盖。
+pub(crate) async fn file_response<State>(
+    req: &Request<State>,
+    path: &Path,
+    len: u64,
+    validators: &Validators,
+    mime_overrides: &MimeOverrides,
+) -> io::Result<Response> {
+    let body = Body::from_file(path).await?;
+    let mut res = Response::new(StatusCode::Ok);
+    match ByteRange::requested(req, len, validators) {
+        ByteRange::Full => res.set_body(body),
+        ByteRange::Unsatisfiable => return Ok(ByteRange::not_satisfiable(len)),
+        ByteRange::Partial(start, end) => {
+            let mime = body.mime().clone();
+            let mut file = File::open(path).await?;
+            file.seek(SeekFrom::Start(start)).await?;
+            let part = end - start;
+            let mut body = Body::from_reader(BufReader::new(file.take(part)), Some(part as usize));
+            body.set_mime(mime);
+            res.set_body(body);
+            ByteRange::partial(&mut res, start, end, len);
+        }
+    }
+    mime_overrides.apply(path, &mut res);
+    res.insert_header(ACCEPT_RANGES, "bytes");
+    validators.apply(&mut res);
+    Ok(res)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_single_ranges() {
+        assert_eq!(parse("bytes=0-4", 10), ByteRange::Partial(0, 5));
+        assert_eq!(parse("bytes=5-", 10), ByteRange::Partial(5, 10));
+        assert_eq!(parse("bytes=-3", 10), ByteRange::Partial(7, 10));
+        assert_eq!(parse("bytes=-30", 10), ByteRange::Partial(0, 10));
+        assert_eq!(parse("bytes=8-100", 10), ByteRange::Partial(8, 10));
+        assert_eq!(parse("bytes=10-", 10), ByteRange::Unsatisfiable);
+        assert_eq!(parse("bytes=-0", 10), ByteRange::Unsatisfiable);
+        assert_eq!(parse("bytes=0-1,4-5", 10), ByteRange::Full);
+        assert_eq!(parse("bytes=5-2", 10), ByteRange::Full);
+        assert_eq!(parse("items=0-1", 10), ByteRange::Full);
+    }
+}
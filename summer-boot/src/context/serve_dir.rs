@@ -1,23 +1,186 @@
+use super::conditional::Validators;
+use super::mime::MimeOverrides;
+use super::range;
+use crate::gateway::router::decode_path;
 use crate::log;
-use crate::{Body, Endpoint, Request, Response, Result, StatusCode};
+use crate::{Endpoint, Request, Response, Result, StatusCode};
 
-use async_std::path::PathBuf as AsyncPathBuf;
+use async_std::fs;
+use async_std::path::{Path as AsyncPath, PathBuf as AsyncPathBuf};
+use async_std::stream::StreamExt;
+use http_types::mime::{self, Mime};
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
 
 use std::path::{Path, PathBuf};
 use std::{ffi::OsStr, io};
 
-pub(crate) struct ServeDir {
+/// 目录列表中链接需要编码的字符
+const PATH_SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// 静态目录服务的选项
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServeDirOptions {
+    /// 请求目录时，如果目录下存在 `index.html` 则返回该文件
+    pub index: bool,
+    /// 请求目录时，如果没有可用的 `index.html` 则返回目录列表
+    pub listing: bool,
+}
+
+impl Default for ServeDirOptions {
+    fn default() -> Self {
+        Self {
+            index: true,
+            listing: false,
+        }
+    }
+}
+
+/// 静态目录服务
+///
+/// 一般通过 [`Route::serve_dir`](crate::Route::serve_dir) 使用，
+/// 需要自定义 MIME 类型时可以直接构建后注册到路由。
+#[derive(Debug)]
+pub struct ServeDir {
     prefix: String,
     dir: PathBuf,
+    mime_overrides: MimeOverrides,
+    options: ServeDirOptions,
 }
 
 impl ServeDir {
     /// 创建一个 `ServeDir` 新的实例。
-    pub(crate) fn new(prefix: String, dir: PathBuf) -> Self {
-        Self { prefix, dir }
+    pub fn new(prefix: String, dir: PathBuf) -> Self {
+        // 根目录需要是规范路径，才能和解析符号链接后的路径比较
+        let dir = dir.canonicalize().unwrap_or(dir);
+        Self {
+            prefix,
+            dir,
+            mime_overrides: MimeOverrides::default(),
+            options: ServeDirOptions::default(),
+        }
+    }
+
+    /// 指定扩展名对应的 MIME 类型，例如 `mime_override("wasm", mime::WASM)`。
+    pub fn mime_override(mut self, extension: &str, mime: Mime) -> Self {
+        self.mime_overrides.insert(extension, mime);
+        self
+    }
+
+    /// 设置目录索引和目录列表选项。
+    pub fn options(mut self, options: ServeDirOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// 解析符号链接，无法提供服务时返回对应的错误响应
+    ///
+    /// 路径不存在返回 `404 Not Found`，解析后不在根目录下返回 `403 Forbidden`。
+    async fn resolve(
+        &self,
+        path: &AsyncPath,
+    ) -> Result<std::result::Result<AsyncPathBuf, Response>> {
+        match path.canonicalize().await {
+            Ok(resolved) if resolved.starts_with(&self.dir) => Ok(Ok(resolved)),
+            Ok(resolved) => {
+                log::warn!("没有权限尝试读取: {:?}", resolved);
+                Ok(Err(Response::new(StatusCode::Forbidden)))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                log::warn!("文件未找到: {:?}", path);
+                Ok(Err(Response::new(StatusCode::NotFound)))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn serve_file<State>(&self, req: &Request<State>, file_path: &AsyncPath) -> Result {
+        let metadata = match fs::metadata(file_path).await {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                log::warn!("文件未找到: {:?}", file_path);
+                return Ok(Response::new(StatusCode::NotFound));
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let validators = Validators::from_metadata(&metadata);
+
+        if validators.is_not_modified(req) {
+            return Ok(validators.not_modified());
+        }
+
+        let res = range::file_response(
+            req,
+            file_path,
+            metadata.len(),
+            &validators,
+            &self.mime_overrides,
+        )
+        .await;
+        match res {
+            Ok(res) => Ok(res),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                log::warn!("文件未找到: {:?}", file_path);
+                Ok(Response::new(StatusCode::NotFound))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// 生成目录列表的 HTML 页面
+    async fn listing<State>(&self, req: &Request<State>, dir: &AsyncPath) -> Result {
+        let mut entries = Vec::new();
+        let mut read_dir = fs::read_dir(dir).await?;
+        while let Some(entry) = read_dir.next().await {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let is_dir = entry.file_type().await.map(|t| t.is_dir()).unwrap_or(false);
+            entries.push((name, is_dir));
+        }
+        entries.sort();
+
+        let base = req.url().path().trim_end_matches('/');
+        let title = escape_html(&format!("{}/", base));
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>Index of {title}</title></head>\n<body>\n<h1>Index of {title}</h1>\n<ul>\n",
+        );
+        for (name, is_dir) in entries {
+            let slash = if is_dir { "/" } else { "" };
+            html.push_str(&format!(
+                "<li><a href=\"{}/{}{}\">{}{}</a></li>\n",
+                base,
+                utf8_percent_encode(&name, PATH_SEGMENT),
+                slash,
+                escape_html(&name),
+                slash,
+            ));
+        }
+        html.push_str("</ul>\n</body>\n</html>\n");
+
+        Ok(Response::builder(StatusCode::Ok)
+            .body(html)
+            .content_type(mime::HTML)
+            .build())
     }
 }
 
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
 #[async_trait::async_trait]
 impl<State> Endpoint<State> for ServeDir
 where
@@ -26,17 +189,24 @@ where
     async fn call(&self, req: Request<State>) -> Result {
         let path = req.url().path();
         let path = path
-            .strip_prefix(&self.prefix.trim_end_matches('*'))
+            .strip_prefix(self.prefix.trim_end_matches('*'))
             .unwrap();
         let path = path.trim_start_matches('/');
+        let path = match decode_path(path) {
+            Some(path) => path,
+            None => {
+                log::warn!("非法的请求路径: {:?}", req.url().path());
+                return Ok(Response::new(StatusCode::BadRequest));
+            }
+        };
         let mut file_path = self.dir.clone();
-        for p in Path::new(path) {
+        for p in Path::new(&path) {
             if p == OsStr::new(".") {
                 continue;
             } else if p == OsStr::new("..") {
                 file_path.pop();
             } else {
-                file_path.push(&p);
+                file_path.push(p);
             }
         }
 
@@ -45,16 +215,183 @@ where
         let file_path = AsyncPathBuf::from(file_path);
         if !file_path.starts_with(&self.dir) {
             log::warn!("没有权限尝试读取: {:?}", file_path);
-            Ok(Response::new(StatusCode::Forbidden))
-        } else {
-            match Body::from_file(&file_path).await {
-                Ok(body) => Ok(Response::builder(StatusCode::Ok).body(body).build()),
-                Err(e) if e.kind() == io::ErrorKind::NotFound => {
-                    log::warn!("文件未找到: {:?}", &file_path);
-                    Ok(Response::new(StatusCode::NotFound))
-                }
-                Err(e) => Err(e.into()),
+            return Ok(Response::new(StatusCode::Forbidden));
+        }
+
+        let file_path = match self.resolve(&file_path).await? {
+            Ok(file_path) => file_path,
+            Err(res) => return Ok(res),
+        };
+
+        if !file_path.is_dir().await {
+            return self.serve_file(&req, &file_path).await;
+        }
+
+        if self.options.index {
+            let index = file_path.join("index.html");
+            if index.is_file().await {
+                return match self.resolve(&index).await? {
+                    Ok(index) => self.serve_file(&req, &index).await,
+                    Err(res) => Ok(res),
+                };
             }
         }
+
+        if self.options.listing {
+            return self.listing(&req, &file_path).await;
+        }
+
+        log::warn!("目录没有可用的索引: {:?}", &file_path);
+        Ok(Response::new(StatusCode::NotFound))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate as summer_boot;
+    use http_types::headers::{ETAG, IF_NONE_MATCH};
+    use http_types::{Method, Request, Response, StatusCode, Url};
+
+    #[async_std::test]
+    async fn if_none_match_returns_not_modified() {
+        let dir = std::env::temp_dir().join("summer-boot-serve-dir-etag");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("index.txt"), "hello summer").unwrap();
+
+        let mut app = summer_boot::new();
+        app.at("/static/*").serve_dir(&dir).unwrap();
+
+        let url = Url::parse("http://localhost/static/index.txt").unwrap();
+        let res: Response = app
+            .respond(Request::new(Method::Get, url.clone()))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::Ok);
+        let etag = res.header(ETAG).unwrap().as_str().to_string();
+
+        let mut req = Request::new(Method::Get, url);
+        req.insert_header(IF_NONE_MATCH, format!("W/{}", etag));
+        let res: Response = app.respond(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NotModified);
+    }
+
+    fn listing_app(dir: &std::path::Path, listing: bool) -> summer_boot::Server<()> {
+        let mut app = summer_boot::new();
+        app.at("/static/*")
+            .serve_dir_with(
+                dir,
+                summer_boot::ServeDirOptions {
+                    index: true,
+                    listing,
+                },
+            )
+            .unwrap();
+        app
+    }
+
+    async fn get(app: &summer_boot::Server<()>, path: &str) -> Response {
+        let url = Url::parse("http://localhost").unwrap().join(path).unwrap();
+        app.respond(Request::new(Method::Get, url)).await.unwrap()
+    }
+
+    #[async_std::test]
+    async fn directory_index_fallback() {
+        let dir = std::env::temp_dir().join("summer-boot-serve-dir-index");
+        std::fs::create_dir_all(dir.join("docs")).unwrap();
+        std::fs::create_dir_all(dir.join("empty")).unwrap();
+        std::fs::write(dir.join("docs/index.html"), "<h1>docs</h1>").unwrap();
+
+        let app = listing_app(&dir, false);
+        let mut res = get(&app, "/static/docs/").await;
+        assert_eq!(res.status(), StatusCode::Ok);
+        assert_eq!(res.body_string().await.unwrap(), "<h1>docs</h1>");
+
+        let res = get(&app, "/static/empty/").await;
+        assert_eq!(res.status(), StatusCode::NotFound);
+    }
+
+    #[async_std::test]
+    async fn directory_listing() {
+        let dir = std::env::temp_dir().join("summer-boot-serve-dir-listing");
+        std::fs::create_dir_all(dir.join("sub dir")).unwrap();
+        std::fs::write(dir.join("a&b <1>.txt"), "a").unwrap();
+
+        let app = listing_app(&dir, true);
+        let mut res = get(&app, "/static/").await;
+        assert_eq!(res.status(), StatusCode::Ok);
+        assert_eq!(res.content_type().unwrap().essence(), "text/html");
+        let body = res.body_string().await.unwrap();
+        assert!(body.contains(r#"<a href="/static/a&b%20%3C1%3E.txt">a&amp;b &lt;1&gt;.txt</a>"#));
+        assert!(body.contains(r#"<a href="/static/sub%20dir/">sub dir/</a>"#));
+
+        let res = get(&app, "/static/sub%20dir/").await;
+        assert_eq!(res.status(), StatusCode::Ok);
+    }
+
+    #[async_std::test]
+    async fn range_requests() {
+        let dir = std::env::temp_dir().join("summer-boot-serve-dir-range");
+        std::fs::create_dir_all(&dir).unwrap();
+        let contents: Vec<u8> = (0..=255).collect();
+        std::fs::write(dir.join("bytes.bin"), &contents).unwrap();
+
+        let app = listing_app(&dir, false);
+        let url = Url::parse("http://localhost/static/bytes.bin").unwrap();
+        let mut req = Request::new(Method::Get, url.clone());
+        req.insert_header("Range", "bytes=250-");
+        let mut res: Response = app.respond(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::PartialContent);
+        assert_eq!(res.header("Content-Range").unwrap(), "bytes 250-255/256");
+        assert_eq!(res.body_bytes().await.unwrap(), &contents[250..]);
+
+        let mut req = Request::new(Method::Get, url);
+        req.insert_header("Range", "bytes=256-");
+        let res: Response = app.respond(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::RequestedRangeNotSatisfiable);
+    }
+
+    #[async_std::test]
+    async fn percent_encoded_paths() {
+        let dir = std::env::temp_dir().join("summer-boot-serve-dir-encoded");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("my report.pdf"), "report").unwrap();
+        std::fs::write(dir.join("报告.txt"), "utf8").unwrap();
+        std::fs::write(dir.join("a+b.txt"), "plus").unwrap();
+
+        let app = listing_app(&dir, false);
+        let mut res = get(&app, "/static/my%20report.pdf").await;
+        assert_eq!(res.body_string().await.unwrap(), "report");
+        let mut res = get(&app, "/static/%E6%8A%A5%E5%91%8A.txt").await;
+        assert_eq!(res.body_string().await.unwrap(), "utf8");
+        let mut res = get(&app, "/static/a+b.txt").await;
+        assert_eq!(res.body_string().await.unwrap(), "plus");
+
+        let res = get(&app, "/static/..%2F..%2Fetc%2Fpasswd").await;
+        assert_eq!(res.status(), StatusCode::BadRequest);
+        let res = get(&app, "/static/a%00.txt").await;
+        assert_eq!(res.status(), StatusCode::BadRequest);
+    }
+
+    #[cfg(unix)]
+    #[async_std::test]
+    async fn symlink_escaping_root_is_forbidden() {
+        let base = std::env::temp_dir().join("summer-boot-serve-dir-symlink");
+        let root = base.join("root");
+        let outside = base.join("outside");
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        std::fs::write(outside.join("secret.txt"), "secret").unwrap();
+        let link = root.join("linked");
+        if std::fs::symlink_metadata(&link).is_err() {
+            std::os::unix::fs::symlink(&outside, &link).unwrap();
+        }
+
+        let app = listing_app(&root, true);
+        let res = get(&app, "/static/linked/secret.txt").await;
+        assert_eq!(res.status(), StatusCode::Forbidden);
+        let res = get(&app, "/static/linked/").await;
+        assert_eq!(res.status(), StatusCode::Forbidden);
+        let res = get(&app, "/static/../outside/secret.txt").await;
+        assert_ne!(res.status(), StatusCode::Ok);
     }
 }
@@ -0,0 +1,265 @@
+//! 提供编译进二进制的静态文件。
+//!
+//! [`EmbeddedDir`] 和 [`EmbeddedAsset`] 只描述按路径查找文件内容，
+//! 可以为 `include_dir`、`rust-embed` 等任意嵌入方式实现。
+
+use super::conditional::Validators;
+use super::mime::MimeOverrides;
+use super::range::ByteRange;
+use crate::gateway::router::decode_path;
+use crate::log;
+use crate::{Body, Endpoint, Request, Response, Result, StatusCode};
+
+use async_std::io::Cursor;
+use http_types::headers::ACCEPT_RANGES;
+use http_types::mime::{self, Mime};
+
+use std::path::Path;
+use std::time::SystemTime;
+
+/// 编译进二进制的单个文件
+pub trait EmbeddedAsset: Send + Sync {
+    /// 文件内容
+    fn contents(&self) -> &'static [u8];
+
+    /// 修改时间，用于 `Last-Modified`，默认没有
+    fn modified(&self) -> Option<SystemTime> {
+        None
+    }
+}
+
+/// 编译进二进制的目录
+pub trait EmbeddedDir: Send + Sync {
+    /// 按相对路径查找文件，路径使用 `/` 分隔且不以 `/` 开头，例如 `css/app.css`
+    fn get(&self, path: &str) -> Option<&dyn EmbeddedAsset>;
+}
+
+impl EmbeddedAsset for &'static [u8] {
+    fn contents(&self) -> &'static [u8] {
+        self
+    }
+}
+
+impl EmbeddedDir for &'static [(&'static str, &'static [u8])] {
+    fn get(&self, path: &str) -> Option<&dyn EmbeddedAsset> {
+        find(self, path)
+    }
+}
+
+impl<const N: usize> EmbeddedDir for [(&'static str, &'static [u8]); N] {
+    fn get(&self, path: &str) -> Option<&dyn EmbeddedAsset> {
+        find(self, path)
+    }
+}
+
+fn find<'a>(
+    entries: &'a [(&'static str, &'static [u8])],
+    path: &str,
+) -> Option<&'a dyn EmbeddedAsset> {
+    entries
+        .iter()
+        .find(|(name, _)| name.trim_start_matches('/') == path)
+        .map(|(_, contents)| contents as &dyn EmbeddedAsset)
+}
+
+/// 嵌入目录服务
+///
+/// 一般通过 [`Route::serve_embedded`](crate::Route::serve_embedded) 使用，
+/// 需要自定义 MIME 类型时可以直接构建后注册到路由。
+pub struct ServeEmbedded {
+    prefix: String,
+    dir: &'static dyn EmbeddedDir,
+    mime_overrides: MimeOverrides,
+}
+
+impl std::fmt::Debug for ServeEmbedded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ServeEmbedded")
+            .field("prefix", &self.prefix)
+            .field("mime_overrides", &self.mime_overrides)
+            .finish()
+    }
+}
+
+impl ServeEmbedded {
+    /// 创建一个 `ServeEmbedded` 新的实例。
+    pub fn new(prefix: String, dir: &'static dyn EmbeddedDir) -> Self {
+        Self {
+            prefix,
+            dir,
+            mime_overrides: MimeOverrides::default(),
+        }
+    }
+
+    /// 指定扩展名对应的 MIME 类型，例如 `mime_override("wasm", mime::WASM)`。
+    pub fn mime_override(mut self, extension: &str, mime: Mime) -> Self {
+        self.mime_overrides.insert(extension, mime);
+        self
+    }
+
+    /// 查找文件，目录返回其中的 `index.html`
+    fn lookup(&self, path: &str) -> Option<(String, &dyn EmbeddedAsset)> {
+        let index = if path.is_empty() || path.ends_with('/') {
+            format!("{}index.html", path)
+        } else if let Some(asset) = self.dir.get(path) {
+            return Some((path.to_string(), asset));
+        } else {
+            format!("{}/index.html", path)
+        };
+        self.dir.get(&index).map(|asset| (index, asset))
+    }
+}
+
+/// 去掉 `.` 并处理 `..`，不会超出根目录
+fn normalize(path: &str) -> String {
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+    let mut normalized = segments.join("/");
+    if path.ends_with('/') && !normalized.is_empty() {
+        normalized.push('/');
+    }
+    normalized
+}
+
+#[async_trait::async_trait]
+impl<State> Endpoint<State> for ServeEmbedded
+where
+    State: Clone + Send + Sync + 'static,
+{
+    async fn call(&self, req: Request<State>) -> Result {
+        let path = req.url().path();
+        let path = path
+            .strip_prefix(self.prefix.trim_end_matches('*'))
+            .unwrap_or_default();
+        let path = match decode_path(path.trim_start_matches('/')) {
+            Some(path) => normalize(&path),
+            None => {
+                log::warn!("非法的请求路径: {:?}", req.url().path());
+                return Ok(Response::new(StatusCode::BadRequest));
+            }
+        };
+
+        let Some((path, asset)) = self.lookup(&path) else {
+            log::warn!("文件未找到: {:?}", path);
+            return Ok(Response::new(StatusCode::NotFound));
+        };
+
+        let contents = asset.contents();
+        let validators = Validators::from_contents(contents, asset.modified());
+        if validators.is_not_modified(&req) {
+            return Ok(validators.not_modified());
+        }
+
+        // 与 `Body::from_file` 相同，先根据内容识别，再根据扩展名识别
+        let mime = Mime::sniff(contents)
+            .ok()
+            .or_else(|| {
+                Path::new(&path)
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .and_then(Mime::from_extension)
+            })
+            .unwrap_or(mime::BYTE_STREAM);
+
+        let len = contents.len() as u64;
+        let mut res = Response::new(StatusCode::Ok);
+        match ByteRange::requested(&req, len, &validators) {
+            ByteRange::Full => res.set_body(Body::from_reader(
+                Cursor::new(contents),
+                Some(contents.len()),
+            )),
+            ByteRange::Unsatisfiable => return Ok(ByteRange::not_satisfiable(len)),
+            ByteRange::Partial(start, end) => {
+                let part = &contents[start as usize..end as usize];
+                res.set_body(Body::from_reader(Cursor::new(part), Some(part.len())));
+                ByteRange::partial(&mut res, start, end, len);
+            }
+        }
+        res.set_content_type(mime);
+        self.mime_overrides.apply(&path, &mut res);
+        res.insert_header(ACCEPT_RANGES, "bytes");
+        validators.apply(&mut res);
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate as summer_boot;
+    use http_types::headers::{CONTENT_RANGE, ETAG, IF_NONE_MATCH};
+    use http_types::{Method, Response, Url};
+
+    static ASSETS: [(&str, &[u8]); 3] = [
+        ("index.html", b"<!DOCTYPE html><h1>summer</h1>"),
+        ("js/app.mjs", b"export default 1;"),
+        ("docs/index.html", b"<!DOCTYPE html><h1>docs</h1>"),
+    ];
+
+    fn app() -> summer_boot::Server<()> {
+        let mut app = summer_boot::new();
+        app.at("/assets/*").serve_embedded(&ASSETS);
+        app
+    }
+
+    async fn get(app: &summer_boot::Server<()>, path: &str, headers: &[(&str, &str)]) -> Response {
+        let url = Url::parse("http://localhost").unwrap().join(path).unwrap();
+        let mut req = http_types::Request::new(Method::Get, url);
+        for (name, value) in headers {
+            req.insert_header(*name, *value);
+        }
+        app.respond(req).await.unwrap()
+    }
+
+    #[async_std::test]
+    async fn serves_files_and_indexes() {
+        let app = app();
+        let mut res = get(&app, "/assets/js/app.mjs", &[]).await;
+        assert_eq!(res.status(), StatusCode::Ok);
+        assert_eq!(res.content_type().unwrap().essence(), "text/javascript");
+        assert_eq!(res.body_string().await.unwrap(), "export default 1;");
+
+        let mut res = get(&app, "/assets/docs/", &[]).await;
+        assert_eq!(res.content_type().unwrap().essence(), "text/html");
+        assert_eq!(
+            res.body_string().await.unwrap(),
+            "<!DOCTYPE html><h1>docs</h1>"
+        );
+        let res = get(&app, "/assets/docs/../js/./app.mjs", &[]).await;
+        assert_eq!(res.status(), StatusCode::Ok);
+        let res = get(&app, "/assets/missing.txt", &[]).await;
+        assert_eq!(res.status(), StatusCode::NotFound);
+
+        let etag = get(&app, "/assets/", &[]).await;
+        let etag = etag.header(ETAG).unwrap().as_str();
+        let res = get(&app, "/assets/", &[(IF_NONE_MATCH.as_str(), etag)]).await;
+        assert_eq!(res.status(), StatusCode::NotModified);
+    }
+
+    #[async_std::test]
+    async fn serves_byte_ranges() {
+        let app = app();
+        let mut res = get(&app, "/assets/js/app.mjs", &[("Range", "bytes=7-13")]).await;
+        assert_eq!(res.status(), StatusCode::PartialContent);
+        assert_eq!(res.header(CONTENT_RANGE).unwrap(), "bytes 7-13/17");
+        assert_eq!(res.body_string().await.unwrap(), "default");
+
+        let mut res = get(&app, "/assets/js/app.mjs", &[("Range", "bytes=-2")]).await;
+        assert_eq!(res.body_string().await.unwrap(), "1;");
+        let res = get(&app, "/assets/js/app.mjs", &[("Range", "bytes=17-")]).await;
+        assert_eq!(res.status(), StatusCode::RequestedRangeNotSatisfiable);
+        assert_eq!(res.header(CONTENT_RANGE).unwrap(), "bytes */17");
+
+        let stale = [("Range", "bytes=0-1"), ("If-Range", "\"stale\"")];
+        let res = get(&app, "/assets/js/app.mjs", &stale).await;
+        assert_eq!(res.status(), StatusCode::Ok);
+    }
+}
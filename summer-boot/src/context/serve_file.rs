@@ -1,30 +1,72 @@
+use super::conditional::Validators;
+use super::mime::MimeOverrides;
+use super::range;
 use crate::log;
-use crate::{Body, Endpoint, Request, Response, Result, StatusCode};
+use crate::{Endpoint, Request, Response, Result, StatusCode};
 use std::io;
 use std::path::Path;
 
+use async_std::fs;
 use async_std::path::PathBuf as AsyncPathBuf;
 use async_trait::async_trait;
+use http_types::mime::Mime;
 
-pub(crate) struct ServeFile {
+/// 静态文件服务
+///
+/// 一般通过 [`Route::serve_file`](crate::Route::serve_file) 使用，
+/// 需要自定义 MIME 类型时可以直接构建后注册到路由。
+#[derive(Debug)]
+pub struct ServeFile {
     path: AsyncPathBuf,
+    mime_overrides: MimeOverrides,
 }
 
 impl ServeFile {
     /// 创建一个 `ServeFile` 新的实例。
-    pub(crate) fn init(path: impl AsRef<Path>) -> io::Result<Self> {
+    pub fn init(path: impl AsRef<Path>) -> io::Result<Self> {
         let file = path.as_ref().to_owned().canonicalize()?;
         Ok(Self {
             path: AsyncPathBuf::from(file),
+            mime_overrides: MimeOverrides::default(),
         })
     }
+
+    /// 指定扩展名对应的 MIME 类型，例如 `mime_override("wasm", mime::WASM)`。
+    ///
+    /// 字符串形式的类型先用 `"text/javascript".parse()?` 解析，无效的类型在解析时报错。
+    pub fn mime_override(mut self, extension: &str, mime: Mime) -> Self {
+        self.mime_overrides.insert(extension, mime);
+        self
+    }
 }
 
 #[async_trait]
 impl<State: Clone + Send + Sync + 'static> Endpoint<State> for ServeFile {
-    async fn call(&self, _: Request<State>) -> Result {
-        match Body::from_file(&self.path).await {
-            Ok(body) => Ok(Response::builder(StatusCode::Ok).body(body).build()),
+    async fn call(&self, req: Request<State>) -> Result {
+        let metadata = match fs::metadata(&self.path).await {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                log::warn!("文件未找到: {:?}", &self.path);
+                return Ok(Response::new(StatusCode::NotFound));
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let validators = Validators::from_metadata(&metadata);
+
+        if validators.is_not_modified(&req) {
+            return Ok(validators.not_modified());
+        }
+
+        let res = range::file_response(
+            &req,
+            &self.path,
+            metadata.len(),
+            &validators,
+            &self.mime_overrides,
+        )
+        .await;
+        match res {
+            Ok(res) => Ok(res),
             Err(e) if e.kind() == io::ErrorKind::NotFound => {
                 log::warn!("文件未找到: {:?}", &self.path);
                 Ok(Response::new(StatusCode::NotFound))
@@ -33,3 +75,117 @@ impl<State: Clone + Send + Sync + 'static> Endpoint<State> for ServeFile {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate as summer_boot;
+    use http_types::headers::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+    use http_types::{Method, Request, Response, StatusCode, Url};
+
+    fn server(name: &str) -> summer_boot::Server<()> {
+        let path = std::env::temp_dir().join(format!("summer-boot-serve-file-{}.txt", name));
+        std::fs::write(&path, "hello summer").unwrap();
+        let mut app = summer_boot::new();
+        app.at("/file").serve_file(&path).unwrap();
+        app
+    }
+
+    fn request() -> Request {
+        Request::new(Method::Get, Url::parse("http://localhost/file").unwrap())
+    }
+
+    #[async_std::test]
+    async fn sets_validators() {
+        let app = server("validators");
+        let res: Response = app.respond(request()).await.unwrap();
+        assert_eq!(res.status(), StatusCode::Ok);
+        assert!(res.header(ETAG).is_some());
+        assert!(res.header(LAST_MODIFIED).is_some());
+    }
+
+    #[async_std::test]
+    async fn if_none_match_returns_not_modified() {
+        let app = server("if-none-match");
+        let res: Response = app.respond(request()).await.unwrap();
+        let etag = res.header(ETAG).unwrap().as_str().to_string();
+
+        let mut req = request();
+        req.insert_header(IF_NONE_MATCH, etag.as_str());
+        let mut res: Response = app.respond(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NotModified);
+        assert_eq!(res.header(ETAG).unwrap().as_str(), etag);
+        assert!(res.body_string().await.unwrap().is_empty());
+
+        let mut req = request();
+        req.insert_header(IF_NONE_MATCH, "\"other\"");
+        let res: Response = app.respond(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::Ok);
+    }
+
+    #[async_std::test]
+    async fn if_modified_since_compares_mtime() {
+        let app = server("if-modified-since");
+        let res: Response = app.respond(request()).await.unwrap();
+        let last_modified = res.header(LAST_MODIFIED).unwrap().as_str().to_string();
+
+        let mut req = request();
+        req.insert_header(IF_MODIFIED_SINCE, last_modified.as_str());
+        let res: Response = app.respond(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NotModified);
+
+        let mut req = request();
+        req.insert_header(IF_MODIFIED_SINCE, "Thu, 01 Jan 1970 00:00:00 GMT");
+        let res: Response = app.respond(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::Ok);
+    }
+
+    #[async_std::test]
+    async fn range_requests() {
+        let app = server("range");
+        let mut req = request();
+        req.insert_header("Range", "bytes=6-11");
+        let mut res: Response = app.respond(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::PartialContent);
+        assert_eq!(res.header("Content-Range").unwrap(), "bytes 6-11/12");
+        assert_eq!(res.header("Accept-Ranges").unwrap(), "bytes");
+        assert_eq!(res.len(), Some(6));
+        assert_eq!(res.body_bytes().await.unwrap(), b"summer");
+
+        let mut req = request();
+        req.insert_header("Range", "bytes=-7");
+        let mut res: Response = app.respond(req).await.unwrap();
+        assert_eq!(res.body_bytes().await.unwrap(), b" summer");
+
+        let mut req = request();
+        req.insert_header("Range", "bytes=12-20");
+        let mut res: Response = app.respond(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::RequestedRangeNotSatisfiable);
+        assert_eq!(res.header("Content-Range").unwrap(), "bytes */12");
+        assert!(res.body_bytes().await.unwrap().is_empty());
+    }
+
+    #[async_std::test]
+    async fn mime_override_sets_content_type() {
+        let dir = std::env::temp_dir();
+        let script = dir.join("summer-boot-serve-file-module.mjs");
+        std::fs::write(&script, "export default 1;").unwrap();
+        let data = dir.join("summer-boot-serve-file-data.bin");
+        std::fs::write(&data, "{}").unwrap();
+
+        let mut app = summer_boot::new();
+        app.at("/module.mjs").serve_file(&script).unwrap();
+        app.at("/data.bin").get(
+            summer_boot::ServeFile::init(&data)
+                .unwrap()
+                .mime_override("bin", http_types::mime::JSON),
+        );
+
+        let url = Url::parse("http://localhost/module.mjs").unwrap();
+        let res: Response = app.respond(Request::new(Method::Get, url)).await.unwrap();
+        assert_eq!(res.content_type().unwrap().essence(), "text/javascript");
+
+        let url = Url::parse("http://localhost/data.bin").unwrap();
+        let res: Response = app.respond(Request::new(Method::Get, url)).await.unwrap();
+        assert_eq!(res.content_type().unwrap().essence(), "application/json");
+    }
+}
@@ -0,0 +1,370 @@
+//! 由其他 crate 贡献的路由
+//!
+//! 库 crate 用 [`contribute`](crate::contribute) 标注处理函数，
+//! 链接时所有标注的路由被收集到 [`CONTRIBUTED_ROUTES`] 中，
+//! 二进制 crate 调用 [`Server::mount_contributed`] 一次性注册，不需要知道每个库提供了哪些路由。
+//! 适用于 `auto_scan` 扫描不到的布局，例如 workspace 之外的 path 依赖。
+//!
+//! ```ignore
+//! // 库 crate `billing`
+//! #[summer_boot::contribute(get, "/invoices/:id")]
+//! async fn invoice(req: summer_boot::Request<()>) -> summer_boot::Result {
+//!     Ok(req.param("id")?.into())
+//! }
+//!
+//! // 二进制 crate
+//! use billing as _;
+//!
+//! let mut app = summer_boot::new();
+//! app.mount_contributed_with(MountOptions::new().prefix("billing", "/billing"))?;
+//! ```
+//!
+//! # 限制
+//!
+//! - 收集依赖链接器，只对静态链接进最终产物的 crate 生效。
+//!   通过 `dylib`、`cdylib` 或 `dlopen` 动态加载的库中的路由不会出现在列表中。
+//! - 没有被二进制 crate 引用的库可能被链接器整体丢弃，
+//!   二进制 crate 需要至少引用一次每个贡献路由的库，例如 `use billing as _;`。
+//! - 处理函数只能使用 `Request<()>`，带状态的服务器不能挂载贡献的路由。
+//! - 只支持 Linux、macOS、Windows 等 [`linkme`] 支持的平台。
+//!
+//! [`Server::mount_contributed`]: crate::Server::mount_contributed
+
+use crate::{Route, RouteInfo};
+
+use http_types::Method;
+
+use std::collections::HashMap;
+use std::fmt;
+
+#[doc(hidden)]
+pub use linkme;
+
+/// 链接时收集的所有贡献路由，顺序不确定，请使用 [`contributed_routes`]
+#[linkme::distributed_slice]
+pub static CONTRIBUTED_ROUTES: [ContributedRoute];
+
+/// 一个贡献的路由，由 [`contribute`](crate::contribute) 宏生成
+pub struct ContributedRoute {
+    crate_name: &'static str,
+    method: Method,
+    path: &'static str,
+    handler: &'static str,
+    register: fn(&mut Route<'_, ()>),
+}
+
+impl ContributedRoute {
+    #[doc(hidden)]
+    pub const fn new(
+        crate_name: &'static str,
+        method: Method,
+        path: &'static str,
+        handler: &'static str,
+        register: fn(&mut Route<'_, ()>),
+    ) -> Self {
+        Self {
+            crate_name,
+            method,
+            path,
+            handler,
+            register,
+        }
+    }
+
+    /// 贡献路由的 crate 名称，即 `CARGO_PKG_NAME`
+    pub fn crate_name(&self) -> &'static str {
+        self.crate_name
+    }
+
+    /// HTTP 方法
+    pub fn method(&self) -> Method {
+        self.method
+    }
+
+    /// 路由路径，不含前缀
+    pub fn path(&self) -> &'static str {
+        self.path
+    }
+
+    /// 处理函数的完整路径
+    pub fn handler(&self) -> &'static str {
+        self.handler
+    }
+
+    /// 在 `route` 上注册处理函数
+    pub(crate) fn register(&self, route: &mut Route<'_, ()>) {
+        (self.register)(route)
+    }
+}
+
+impl fmt::Debug for ContributedRoute {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ContributedRoute")
+            .field("crate_name", &self.crate_name)
+            .field("method", &self.method)
+            .field("path", &self.path)
+            .field("handler", &self.handler)
+            .finish()
+    }
+}
+
+/// 按 crate 名称、路径、方法和处理函数排序的贡献路由，与链接顺序无关
+pub fn contributed_routes() -> Vec<&'static ContributedRoute> {
+    sorted(&CONTRIBUTED_ROUTES)
+}
+
+fn sorted(routes: &[ContributedRoute]) -> Vec<&ContributedRoute> {
+    let mut routes: Vec<_> = routes.iter().collect();
+    routes.sort_by(|a, b| {
+        (a.crate_name, a.path, a.method.as_ref(), a.handler).cmp(&(
+            b.crate_name,
+            b.path,
+            b.method.as_ref(),
+            b.handler,
+        ))
+    });
+    routes
+}
+
+/// 挂载贡献路由的选项
+#[derive(Debug, Clone, Default)]
+pub struct MountOptions {
+    prefixes: HashMap<String, String>,
+}
+
+impl MountOptions {
+    /// 不添加任何前缀
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 为 `crate_name` 贡献的所有路由添加路径前缀
+    #[must_use]
+    pub fn prefix(mut self, crate_name: impl Into<String>, prefix: impl Into<String>) -> Self {
+        self.prefixes.insert(crate_name.into(), prefix.into());
+        self
+    }
+
+    /// 加上前缀后的完整路径，与 [`Route::at`] 的拼接规则相同
+    fn full_path(&self, route: &ContributedRoute) -> String {
+        let Some(prefix) = self.prefixes.get(route.crate_name) else {
+            return route.path.to_string();
+        };
+        let mut path = prefix.clone();
+        if !path.ends_with('/') && !route.path.starts_with('/') {
+            path.push('/');
+        }
+        if route.path != "/" {
+            path.push_str(route.path);
+        }
+        path
+    }
+}
+
+/// 贡献的路由与已注册的路由或其他贡献的路由冲突
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteConflict {
+    method: Method,
+    path: String,
+    handler: String,
+    existing: String,
+}
+
+impl RouteConflict {
+    /// 冲突的 HTTP 方法
+    pub fn method(&self) -> Method {
+        self.method
+    }
+
+    /// 冲突的完整路径
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// 贡献路由的处理函数
+    pub fn handler(&self) -> &str {
+        &self.handler
+    }
+
+    /// 已经占用该路由的 endpoint 或处理函数
+    pub fn existing(&self) -> &str {
+        &self.existing
+    }
+}
+
+impl fmt::Display for RouteConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "contributed route {} {} ({}) conflicts with {}",
+            self.method, self.path, self.handler, self.existing
+        )
+    }
+}
+
+impl std::error::Error for RouteConflict {}
+
+/// 计算要注册的路由及完整路径，有冲突时返回第一个冲突
+///
+/// `registered` 为展开嵌套服务后的路由。参数名不同的路径匹配同样的请求，视为冲突；
+/// 注册到所有方法的路由与任何方法冲突。`global_prefix` 为非严格模式下的全局前缀，
+/// 带有该前缀的请求会先去掉前缀再匹配，因此 `/prefix/users` 与 `/users` 也视为冲突。
+pub(crate) fn plan<'r>(
+    routes: &[&'r ContributedRoute],
+    registered: &[RouteInfo],
+    options: &MountOptions,
+    global_prefix: Option<&str>,
+) -> Result<Vec<(String, &'r ContributedRoute)>, RouteConflict> {
+    let mut taken: Vec<(Option<Method>, Vec<String>, String)> = registered
+        .iter()
+        .map(|info| {
+            (
+                info.method,
+                shapes(&info.path, global_prefix),
+                info.endpoint.clone(),
+            )
+        })
+        .collect();
+
+    let mut planned = Vec::with_capacity(routes.len());
+    for route in routes {
+        let path = options.full_path(route);
+        let shapes = shapes(&path, global_prefix);
+        let existing = taken.iter().find(|(method, taken_shapes, _)| {
+            method.map_or(true, |method| method == route.method)
+                && taken_shapes.iter().any(|shape| shapes.contains(shape))
+        });
+        if let Some((_, _, existing)) = existing {
+            return Err(RouteConflict {
+                method: route.method,
+                path,
+                handler: route.handler.to_string(),
+                existing: existing.clone(),
+            });
+        }
+        taken.push((Some(route.method), shapes, route.handler.to_string()));
+        planned.push((path, *route));
+    }
+    Ok(planned)
+}
+
+/// 路径匹配的请求形状，去掉首尾的 `/` 并忽略参数名，例如 `users/:id/` 为 `/users/:`
+///
+/// 路径带有 `global_prefix` 时同时返回去掉前缀后的形状
+fn shapes(path: &str, global_prefix: Option<&str>) -> Vec<String> {
+    let full = shape(path);
+    let mut shapes = vec![full.clone()];
+    if let Some(prefix) = global_prefix.map(shape) {
+        match full.strip_prefix(&prefix) {
+            Some("") => shapes.push("/".to_string()),
+            Some(rest) if rest.starts_with('/') => shapes.push(rest.to_string()),
+            _ => {}
+        }
+    }
+    shapes
+}
+
+fn shape(path: &str) -> String {
+    let segments: Vec<&str> = path
+        .trim_matches('/')
+        .split('/')
+        .map(|segment| match segment.chars().next() {
+            Some(':') => ":",
+            Some('*') => "*",
+            _ => segment,
+        })
+        .collect();
+    format!("/{}", segments.join("/"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn noop(_: &mut Route<'_, ()>) {}
+
+    const ROUTES: [ContributedRoute; 3] = [
+        ContributedRoute::new("orders", Method::Post, "/orders", "orders::create", noop),
+        ContributedRoute::new("billing", Method::Get, "invoices", "billing::list", noop),
+        ContributedRoute::new("orders", Method::Get, "/orders", "orders::list", noop),
+    ];
+
+    #[test]
+    fn plans_in_order_with_prefixes() {
+        let options = MountOptions::new().prefix("billing", "/billing");
+        let planned = plan(&sorted(&ROUTES), &[], &options, None).unwrap();
+        let planned: Vec<_> = planned
+            .iter()
+            .map(|(path, route)| (path.as_str(), route.handler()))
+            .collect();
+        assert_eq!(
+            planned,
+            [
+                ("/billing/invoices", "billing::list"),
+                ("/orders", "orders::list"),
+                ("/orders", "orders::create"),
+            ]
+        );
+    }
+
+    #[test]
+    fn detects_conflicts() {
+        let mut app = crate::new();
+        app.at("/orders/").get(|_| async { Ok("") });
+        let err = plan(
+            &sorted(&ROUTES),
+            &app.list_routes(),
+            &MountOptions::new(),
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(err.method(), Method::Get);
+        assert_eq!(err.handler(), "orders::list");
+
+        let twice = [&ROUTES[1], &ROUTES[1]];
+        let err = plan(&twice, &[], &MountOptions::new(), None).unwrap_err();
+        assert_eq!(err.existing(), "billing::list");
+        assert_eq!(
+            err.to_string(),
+            "contributed route GET invoices (billing::list) conflicts with billing::list"
+        );
+    }
+
+    #[test]
+    fn detects_conflicts_after_prefixing() {
+        const ROUTE: [ContributedRoute; 1] = [ContributedRoute::new(
+            "users",
+            Method::Get,
+            "/users/:id",
+            "users::show",
+            noop,
+        )];
+        let routes = sorted(&ROUTE);
+
+        // 嵌套服务中的路由，参数名不同
+        let mut app = crate::new();
+        app.at("/admin").nest({
+            let mut admin = crate::new();
+            admin.at("/users/:name").get(|_| async { Ok("") });
+            admin
+        });
+        let options = MountOptions::new().prefix("users", "/admin");
+        let err = plan(&routes, &app.list_routes(), &options, None).unwrap_err();
+        assert_eq!(err.path(), "/admin/users/:id");
+
+        // 注册到所有方法的路由
+        let mut app = crate::new();
+        app.at("/users/:id").all(|_| async { Ok("") });
+        assert!(plan(&routes, &app.list_routes(), &MountOptions::new(), None).is_err());
+
+        // 非严格模式下 `/api/users/1` 会去掉全局前缀后匹配 `/users/:id`
+        let mut app = crate::new();
+        app.at("/users/:id").get(|_| async { Ok("") });
+        let options = MountOptions::new().prefix("users", "/api");
+        let registered = app.list_routes();
+        assert!(plan(&routes, &registered, &options, None).is_ok());
+        let err = plan(&routes, &registered, &options, Some("/api")).unwrap_err();
+        assert_eq!(err.path(), "/api/users/:id");
+    }
+}
@@ -0,0 +1,175 @@
+use crate::http_types::cookies::{Cookie, CookieJar, Delta};
+use crate::http_types::headers;
+use crate::utils::response::CookieEvent;
+use crate::{Middleware, Next, Request};
+
+use async_trait::async_trait;
+
+use std::sync::{Arc, RwLock};
+
+/// 解析请求中的 Cookie，并将响应中的 Cookie 修改转换为 `Set-Cookie` 响应头
+///
+/// 同名的 Cookie 以最后一次修改为准，删除 Cookie 时发送 `Max-Age=0`。
+///
+/// # Examples
+///
+/// ```
+/// use summer_boot::http_types::cookies::Cookie;
+/// use summer_boot::{Request, Response, StatusCode};
+///
+/// let mut app = summer_boot::new();
+/// app.at("/get").get(|req: Request<()>| async move {
+///     Ok(req.cookie("name").map(|c| c.value().to_string()).unwrap_or_default())
+/// });
+/// app.at("/set").get(|_| async {
+///     let mut res = Response::new(StatusCode::Ok);
+///     res.insert_cookie(Cookie::new("name", "value"));
+///     Ok(res)
+/// });
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CookiesMiddleware {
+    _priv: (),
+}
+
+impl CookiesMiddleware {
+    /// 创建一个新的实例
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl<State: Clone + Send + Sync + 'static> Middleware<State> for CookiesMiddleware {
+    async fn handle(&self, mut req: Request<State>, next: Next<'_, State>) -> crate::Result {
+        let jar = match req.ext::<CookieData>() {
+            Some(data) => data.content.clone(),
+            None => {
+                let data = CookieData::from_request(&req);
+                let content = data.content.clone();
+                req.set_ext(data);
+                content
+            }
+        };
+
+        let mut res = next.run(req).await;
+        if res.cookie_events.is_empty() {
+            return Ok(res);
+        }
+
+        let jar = &mut *jar.write().unwrap_or_else(|e| e.into_inner());
+        for event in res.cookie_events.drain(..) {
+            match event {
+                CookieEvent::Added(cookie) => jar.add(cookie),
+                CookieEvent::Removed(cookie) => jar.remove(cookie),
+            }
+        }
+        for cookie in jar.delta() {
+            res.append_header(headers::SET_COOKIE, cookie.to_string());
+        }
+        Ok(res)
+    }
+}
+
+/// 请求中的 Cookie，保存在请求扩展中
+#[derive(Debug, Default, Clone)]
+pub(crate) struct CookieData {
+    pub(crate) content: Arc<RwLock<LazyJar>>,
+}
+
+impl CookieData {
+    pub(crate) fn from_request<State>(req: &Request<State>) -> Self {
+        let jar = req.header(headers::COOKIE).map(|values| {
+            let mut jar = CookieJar::new();
+            for value in values {
+                for pair in value.as_str().split(';') {
+                    if let Ok(cookie) = Cookie::parse_encoded(pair.trim().to_string()) {
+                        jar.add_original(cookie);
+                    }
+                }
+            }
+            jar
+        });
+        CookieData {
+            content: Arc::new(RwLock::new(LazyJar(jar))),
+        }
+    }
+}
+
+/// 使用时才创建的 `CookieJar`
+#[derive(Debug, Default, Clone)]
+pub(crate) struct LazyJar(Option<CookieJar>);
+
+impl LazyJar {
+    fn add(&mut self, cookie: Cookie<'static>) {
+        self.jar().add(cookie)
+    }
+
+    fn remove(&mut self, cookie: Cookie<'static>) {
+        self.jar().remove(cookie)
+    }
+
+    fn delta(&mut self) -> Delta<'_> {
+        self.jar().delta()
+    }
+
+    pub(crate) fn get(&self, name: &str) -> Option<&Cookie<'static>> {
+        self.0.as_ref().and_then(|jar| jar.get(name))
+    }
+
+    fn jar(&mut self) -> &mut CookieJar {
+        self.0.get_or_insert_with(CookieJar::new)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate as summer_boot;
+    use crate::http_types::cookies::Cookie;
+    use crate::http_types::headers::{COOKIE, SET_COOKIE};
+    use crate::{Request, Response, StatusCode};
+    use http_types::{Method, Url};
+
+    async fn get(app: &summer_boot::Server<()>, path: &str, cookie: Option<&str>) -> Response {
+        let url = Url::parse("http://localhost").unwrap().join(path).unwrap();
+        let mut req = http_types::Request::new(Method::Get, url);
+        if let Some(cookie) = cookie {
+            req.insert_header(COOKIE, cookie);
+        }
+        let res: http_types::Response = app.respond(req).await.unwrap();
+        res.into()
+    }
+
+    #[async_std::test]
+    async fn set_read_and_delete() {
+        let mut app = summer_boot::new();
+        app.at("/login").get(|_| async {
+            let mut res = Response::new(StatusCode::Ok);
+            res.insert_cookie(Cookie::new("session", "first"));
+            res.insert_cookie(Cookie::new("session", "abc"));
+            Ok(res)
+        });
+        app.at("/whoami").get(|req: Request<()>| async move {
+            Ok(req.cookie("session").unwrap().value().to_string())
+        });
+        app.at("/logout").get(|_| async {
+            let mut res = Response::new(StatusCode::Ok);
+            res.remove_cookie(Cookie::named("session"));
+            Ok(res)
+        });
+
+        let res = get(&app, "/login", None).await;
+        let set_cookie: Vec<_> = res[SET_COOKIE].iter().map(|v| v.as_str()).collect();
+        assert_eq!(set_cookie, ["session=abc"]);
+
+        let mut res = get(&app, "/whoami", Some("session=abc; theme=dark")).await;
+        assert_eq!(res.take_body().into_string().await.unwrap(), "abc");
+        assert!(res.header(SET_COOKIE).is_none());
+
+        let res = get(&app, "/logout", Some("session=abc")).await;
+        let removal = res[SET_COOKIE].as_str();
+        assert!(removal.starts_with("session=;"), "{}", removal);
+        assert!(removal.contains("Max-Age=0"), "{}", removal);
+    }
+}
@@ -0,0 +1,11 @@
+//! Cookie 支持
+//!
+//! 开启 `cookies` feature 后，[`CookiesMiddleware`] 默认注册在每个 `Server` 上，
+//! 通过 [`Request::cookie`](crate::Request::cookie) 读取请求中的 Cookie，
+//! 通过 [`Response::insert_cookie`](crate::Response::insert_cookie) 和
+//! [`Response::remove_cookie`](crate::Response::remove_cookie) 修改 Cookie。
+
+mod middleware;
+
+pub(crate) use middleware::CookieData;
+pub use middleware::CookiesMiddleware;
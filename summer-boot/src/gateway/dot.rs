@@ -0,0 +1,193 @@
+//! 将路由树导出为 Graphviz DOT 格式
+//!
+//! 路由路径按 `/` 拆分后合并为前缀树，每个节点对应一个路径段。
+//! 指向节点的边标注该节点上注册的HTTP方法和endpoint名称，
+//! 嵌套的 `Server` 渲染为 `cluster` 子图。输出顺序是确定的。
+
+use super::router::RouteInfo;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+/// 路由前缀树节点
+#[derive(Default)]
+struct Node<'a> {
+    children: BTreeMap<&'a str, Node<'a>>,
+    /// endpoint 名称 -> HTTP 方法
+    endpoints: BTreeMap<&'a str, BTreeSet<String>>,
+    middleware: BTreeSet<&'a str>,
+    nested: Vec<&'a [RouteInfo]>,
+}
+
+impl<'a> Node<'a> {
+    fn build(routes: &'a [RouteInfo]) -> Self {
+        let mut root = Node::default();
+        for route in routes {
+            let node = route
+                .path
+                .split('/')
+                .filter(|segment| !segment.is_empty())
+                .fold(&mut root, |node, segment| {
+                    node.children.entry(segment).or_default()
+                });
+            let method = route
+                .method
+                .as_ref()
+                .map_or_else(|| "ALL".to_string(), |method| method.to_string());
+            node.endpoints
+                .entry(route.endpoint.as_str())
+                .or_default()
+                .insert(method);
+            node.middleware
+                .extend(route.middleware.iter().map(String::as_str));
+            if !route.nested.is_empty() {
+                node.nested.push(&route.nested);
+            }
+        }
+        root
+    }
+
+    /// 指向该节点的边的标签
+    fn endpoint_label(&self) -> String {
+        self.endpoints
+            .iter()
+            .map(|(endpoint, methods)| {
+                let methods: Vec<&str> = methods.iter().map(String::as_str).collect();
+                format!("{}: {}", methods.join(", "), endpoint)
+            })
+            .collect::<Vec<_>>()
+            .join("\\n")
+    }
+}
+
+struct Renderer {
+    out: String,
+    next_node: usize,
+    next_cluster: usize,
+}
+
+impl Renderer {
+    fn node_id(&mut self) -> String {
+        let id = format!("n{}", self.next_node);
+        self.next_node += 1;
+        id
+    }
+
+    fn line(&mut self, depth: usize, line: &str) {
+        let _ = writeln!(self.out, "{}{}", "    ".repeat(depth), line);
+    }
+
+    /// 输出节点及其子树，返回节点ID
+    fn node(&mut self, depth: usize, segment: &str, node: &Node<'_>, root_label: bool) -> String {
+        let id = self.node_id();
+
+        let mut label = escape(segment);
+        if root_label && !node.endpoints.is_empty() {
+            label.push_str("\\n");
+            label.push_str(&escape(&node.endpoint_label()));
+        }
+        if !node.middleware.is_empty() {
+            let middleware: Vec<&str> = node.middleware.iter().copied().collect();
+            label.push_str("\\nmiddleware: ");
+            label.push_str(&escape(&middleware.join(", ")));
+        }
+
+        let style = if segment.starts_with('*') {
+            ", shape=diamond"
+        } else if segment.starts_with(':') {
+            ", style=dashed"
+        } else {
+            ""
+        };
+        self.line(depth, &format!("{} [label=\"{}\"{}];", id, label, style));
+
+        for (segment, child) in &node.children {
+            let child_id = self.node(depth, segment, child, false);
+            let label = child.endpoint_label();
+            if label.is_empty() {
+                self.line(depth, &format!("{} -> {};", id, child_id));
+            } else {
+                self.line(
+                    depth,
+                    &format!("{} -> {} [label=\"{}\"];", id, child_id, escape(&label)),
+                );
+            }
+        }
+
+        for routes in &node.nested {
+            let cluster = self.next_cluster;
+            self.next_cluster += 1;
+            self.line(depth, &format!("subgraph cluster_{} {{", cluster));
+            self.line(depth + 1, "label=\"nested server\";");
+            let nested = Node::build(routes);
+            let nested_id = self.node(depth + 1, "/", &nested, true);
+            self.line(depth, "}");
+            self.line(depth, &format!("{} -> {} [style=dotted];", id, nested_id));
+        }
+
+        id
+    }
+}
+
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// 渲染路由树，`middleware` 为服务级别的中间件，标注在根节点上
+pub(crate) fn render(routes: &[RouteInfo], middleware: &[String]) -> String {
+    let mut root = Node::build(routes);
+    root.middleware
+        .extend(middleware.iter().map(String::as_str));
+
+    let mut renderer = Renderer {
+        out: String::new(),
+        next_node: 0,
+        next_cluster: 0,
+    };
+    renderer.line(0, "digraph routes {");
+    renderer.line(1, "rankdir=LR;");
+    renderer.line(1, "node [shape=box];");
+    renderer.node(1, "/", &root, true);
+    renderer.line(0, "}");
+    renderer.out
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Request, Result};
+
+    async fn index(_: Request<()>) -> Result {
+        Ok("index".into())
+    }
+
+    async fn user(_: Request<()>) -> Result {
+        Ok("user".into())
+    }
+
+    async fn files(_: Request<()>) -> Result {
+        Ok("files".into())
+    }
+
+    async fn health(_: Request<()>) -> Result {
+        Ok("ok".into())
+    }
+
+    /// 与 fixture 中的路由相同，`cookies` feature 默认注册的中间件不写入 fixture
+    #[test]
+    fn fixture_routes() {
+        let mut admin = crate::new();
+        admin.at("/health").get(health);
+
+        let mut app = crate::new();
+        app.at("/").get(index);
+        app.at("/users/:id").get(user).put(user);
+        app.at("/users/:id/files/*").get(files);
+        app.at("/admin").nest(admin);
+
+        let dot = app
+            .routes_dot()
+            .replace("summer_boot::cookies::middleware::CookiesMiddleware, ", "");
+        let expected = include_str!("../../tests/fixtures/routes.dot");
+        assert_eq!(dot, expected);
+    }
+}
@@ -0,0 +1,192 @@
+//! 按请求方法分发的 endpoint
+
+use crate::http_types::headers::ALLOW;
+use crate::http_types::Method;
+use crate::server::endpoint::DynEndpoint;
+use crate::{Endpoint, Request, Response, StatusCode};
+
+use async_trait::async_trait;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// 创建按请求方法分发的 endpoint
+///
+/// 与 [`Route`](crate::Route) 上的方法注册不同，结果是一个独立的 [`Endpoint`]，
+/// 可以在任何需要 endpoint 的地方使用，例如动态组装的路由或嵌套服务。
+/// 没有对应方法时返回 `405 Method Not Allowed` 并带上 `Allow` 响应头，
+/// `HEAD` 请求没有单独注册时使用 `GET` 的 endpoint。
+///
+/// # Examples
+///
+/// ```
+/// use summer_boot::{method_router, Request};
+///
+/// let users = method_router()
+///     .get(|_req: Request<()>| async { Ok("list users") })
+///     .post(|_req: Request<()>| async { Ok("create user") });
+///
+/// let mut app = summer_boot::new();
+/// app.at("/users").all(users);
+/// ```
+#[must_use]
+pub fn method_router<State>() -> MethodRouter<State>
+where
+    State: Clone + Send + Sync + 'static,
+{
+    MethodRouter {
+        endpoints: Arc::new(HashMap::new()),
+    }
+}
+
+/// 按请求方法分发的 endpoint，通过 [`method_router`] 创建
+pub struct MethodRouter<State> {
+    endpoints: Arc<HashMap<Method, Box<DynEndpoint<State>>>>,
+}
+
+impl<State> MethodRouter<State>
+where
+    State: Clone + Send + Sync + 'static,
+{
+    /// 为 `method` 注册 endpoint，重复注册时替换之前的 endpoint
+    ///
+    /// # Panics
+    ///
+    /// 克隆之后不能再注册
+    pub fn method(mut self, method: Method, ep: impl Endpoint<State>) -> Self {
+        Arc::get_mut(&mut self.endpoints)
+            .expect("克隆后的 MethodRouter 无法注册 endpoint")
+            .insert(method, Box::new(ep));
+        self
+    }
+
+    pub fn get(self, ep: impl Endpoint<State>) -> Self {
+        self.method(Method::Get, ep)
+    }
+
+    pub fn head(self, ep: impl Endpoint<State>) -> Self {
+        self.method(Method::Head, ep)
+    }
+
+    pub fn put(self, ep: impl Endpoint<State>) -> Self {
+        self.method(Method::Put, ep)
+    }
+
+    pub fn post(self, ep: impl Endpoint<State>) -> Self {
+        self.method(Method::Post, ep)
+    }
+
+    pub fn delete(self, ep: impl Endpoint<State>) -> Self {
+        self.method(Method::Delete, ep)
+    }
+
+    pub fn options(self, ep: impl Endpoint<State>) -> Self {
+        self.method(Method::Options, ep)
+    }
+
+    pub fn connect(self, ep: impl Endpoint<State>) -> Self {
+        self.method(Method::Connect, ep)
+    }
+
+    pub fn patch(self, ep: impl Endpoint<State>) -> Self {
+        self.method(Method::Patch, ep)
+    }
+
+    pub fn trace(self, ep: impl Endpoint<State>) -> Self {
+        self.method(Method::Trace, ep)
+    }
+
+    /// `Allow` 响应头的值，注册了 `GET` 时包含 `HEAD`
+    fn allow(&self) -> String {
+        let mut methods: Vec<String> = self.endpoints.keys().map(|m| m.to_string()).collect();
+        if self.endpoints.contains_key(&Method::Get) && !self.endpoints.contains_key(&Method::Head)
+        {
+            methods.push(Method::Head.to_string());
+        }
+        methods.sort();
+        methods.join(", ")
+    }
+}
+
+impl<State> Clone for MethodRouter<State> {
+    fn clone(&self) -> Self {
+        Self {
+            endpoints: self.endpoints.clone(),
+        }
+    }
+}
+
+impl<State> fmt::Debug for MethodRouter<State> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut methods: Vec<String> = self.endpoints.keys().map(|m| m.to_string()).collect();
+        methods.sort();
+        f.debug_struct("MethodRouter")
+            .field("methods", &methods)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl<State> Endpoint<State> for MethodRouter<State>
+where
+    State: Clone + Send + Sync + 'static,
+{
+    async fn call(&self, req: Request<State>) -> crate::Result {
+        let method = req.method();
+        let endpoint = self.endpoints.get(&method).or_else(|| {
+            (method == Method::Head)
+                .then(|| self.endpoints.get(&Method::Get))
+                .flatten()
+        });
+        match endpoint {
+            Some(endpoint) => endpoint.call(req).await,
+            None => {
+                let mut res = Response::new(StatusCode::MethodNotAllowed);
+                res.insert_header(ALLOW, self.allow());
+                Ok(res)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate as summer_boot;
+    use http_types::{Request, Url};
+
+    #[async_std::test]
+    async fn dispatches_by_method() {
+        let items = method_router()
+            .get(|_| async { Ok("get") })
+            .post(|_| async { Ok("post") });
+        let mut api = summer_boot::new();
+        api.at("/items").all(items.clone());
+        let mut app = summer_boot::new();
+        app.at("/items").all(items);
+        app.at("/api").nest(api);
+
+        for path in ["/items", "/api/items"] {
+            let url = Url::parse("http://localhost").unwrap().join(path).unwrap();
+            let mut res: http_types::Response = app
+                .respond(Request::new(Method::Post, url.clone()))
+                .await
+                .unwrap();
+            assert_eq!(res.body_string().await.unwrap(), "post");
+
+            let res: http_types::Response = app
+                .respond(Request::new(Method::Head, url.clone()))
+                .await
+                .unwrap();
+            assert_eq!(res.status(), StatusCode::Ok);
+
+            let res: http_types::Response = app
+                .respond(Request::new(Method::Delete, url))
+                .await
+                .unwrap();
+            assert_eq!(res.status(), StatusCode::MethodNotAllowed);
+            assert_eq!(res[ALLOW], "GET, HEAD, POST");
+        }
+    }
+}
@@ -1,2 +1,4 @@
+pub(crate) mod dot;
+pub mod method_router;
 pub mod route;
 pub mod router;
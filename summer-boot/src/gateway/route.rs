@@ -9,12 +9,13 @@ use std::io;
 use std::path::Path;
 use std::sync::Arc;
 
-use context::serve_dir::ServeDir;
+use context::serve_dir::{ServeDir, ServeDirOptions};
+use context::serve_embedded::{EmbeddedDir, ServeEmbedded};
 use context::serve_file::ServeFile;
 use server::endpoint::{Endpoint, MiddlewareEndpoint};
 use utils::middleware::Middleware;
 
-use gateway::router::Router;
+use gateway::router::{RouteEntry, RouteInfo, Router};
 
 /// A handle to route
 ///
@@ -27,6 +28,8 @@ pub struct Route<'a, State> {
     router: &'a mut Router<State>,
     path: String,
     middleware: Vec<Arc<dyn Middleware<State>>>,
+    /// 路由名称
+    name: Option<String>,
     /// 是否将当前路由的路径作为前缀
     /// [`strip_prefix`].
     ///
@@ -40,6 +43,7 @@ impl<'a, State: Clone + Send + Sync + 'static> Route<'a, State> {
             router,
             path,
             middleware: Vec::new(),
+            name: None,
             prefix: false,
         }
     }
@@ -60,6 +64,7 @@ impl<'a, State: Clone + Send + Sync + 'static> Route<'a, State> {
             router: self.router,
             path: p,
             middleware: self.middleware.clone(),
+            name: self.name.clone(),
             prefix: false,
         }
     }
@@ -70,6 +75,36 @@ impl<'a, State: Clone + Send + Sync + 'static> Route<'a, State> {
         &self.path
     }
 
+    /// 设置路由名称，可以通过 [`Request::route_name`](crate::Request::route_name) 获取
+    ///
+    /// ```
+    /// # let mut app = summer_boot::new();
+    /// app.at("/users/:id").name("get_user").get(|_| async { Ok("user") });
+    /// ```
+    pub fn name(&mut self, name: impl Into<String>) -> &mut Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// 放宽该路由的请求目标长度上限，用于查询字符串中带长签名的下载链接等
+    ///
+    /// 长度检查在解析请求头之前进行，此时还没有路由，因此按路由路径中第一个参数或通配符之前的
+    /// 前缀豁免：`/downloads/*` 豁免所有以 `/downloads/` 开头的请求目标。
+    /// 豁免表在侦听时生成，其他请求仍使用
+    /// [`ServerOptions::max_target_length`](crate::http::ServerOptions::max_target_length) 的全局上限。
+    /// 只对最外层服务上注册的路由生效。
+    ///
+    /// ```
+    /// # let mut app = summer_boot::new();
+    /// app.at("/downloads/*")
+    ///     .max_target_length(16 * 1024)
+    ///     .get(|_| async { Ok("file") });
+    /// ```
+    pub fn max_target_length(&mut self, len: usize) -> &mut Self {
+        self.router.exempt_target(literal_prefix(&self.path), len);
+        self
+    }
+
     /// 将当前路径视为前缀，并从请求中去除前缀。
     /// 这个方法标记为不稳定 unstable，后面需要summer boot 宏增强。
     /// 给endpoints提供前缀已经删除的路径。
@@ -134,9 +169,10 @@ impl<'a, State: Clone + Send + Sync + 'static> Route<'a, State> {
         InnerState: Clone + Send + Sync + 'static,
     {
         let prefix = self.prefix;
+        let nested = service.nested_routes();
 
         self.prefix = true;
-        self.all(service);
+        self.register(None, service, nested);
         self.prefix = prefix;
 
         self
@@ -166,13 +202,45 @@ impl<'a, State: Clone + Send + Sync + 'static> Route<'a, State> {
     /// }
     /// ```
     pub fn serve_dir(&mut self, dir: impl AsRef<Path>) -> io::Result<()> {
+        self.serve_dir_with(dir, ServeDirOptions::default())
+    }
+
+    /// 使用指定选项的静态目录服务。
+    ///
+    /// 可以开启目录索引 `index.html` 和目录列表，其余行为与 [`serve_dir`](Self::serve_dir) 相同。
+    pub fn serve_dir_with(
+        &mut self,
+        dir: impl AsRef<Path>,
+        options: ServeDirOptions,
+    ) -> io::Result<()> {
         // 验证路径是否存在，如果不存在，则返回错误。
         let dir = dir.as_ref().to_owned().canonicalize()?;
         let prefix = self.path().to_string();
-        self.get(ServeDir::new(prefix, dir));
+        self.get(ServeDir::new(prefix, dir).options(options));
         Ok(())
     }
 
+    /// 提供编译进二进制的静态目录，路由需要以 `*` 结尾
+    ///
+    /// 与 [`serve_dir`](Self::serve_dir) 一样检测 MIME 类型、返回 `ETag` 并处理条件请求，
+    /// 另外支持单个 `Range` 请求。目录为空路径或以 `/` 结尾时返回其中的 `index.html`。
+    ///
+    /// ```
+    /// use summer_boot::EmbeddedDir;
+    ///
+    /// static ASSETS: &[(&str, &[u8])] = &[
+    ///     ("index.html", b"<h1>summer</h1>"),
+    ///     ("app.js", b"console.log('summer')"),
+    /// ];
+    ///
+    /// let mut app = summer_boot::new();
+    /// app.at("/assets/*").serve_embedded(&ASSETS);
+    /// ```
+    pub fn serve_embedded(&mut self, dir: &'static dyn EmbeddedDir) -> &mut Self {
+        let prefix = self.path().to_string();
+        self.get(ServeEmbedded::new(prefix, dir))
+    }
+
     /// 提供静态文件。
     ///
     /// 每一个文件都将从磁盘io流传输，并确定了mime类型
@@ -183,45 +251,64 @@ impl<'a, State: Clone + Send + Sync + 'static> Route<'a, State> {
     }
 
     /// 给定HTTP方法添加endpoint
-    pub fn method(&mut self, method: http_types::Method, ep: impl Endpoint<State>) -> &mut Self {
-        if self.prefix {
-            let ep = StripPrefixEndpoint::new(ep);
-            let wildcard = self.at("*");
-            wildcard.router.add(
-                &wildcard.path,
-                method,
-                MiddlewareEndpoint::wrap_with_middleware(ep, &wildcard.middleware),
-            );
-        } else {
-            self.router.add(
-                &self.path,
-                method,
-                MiddlewareEndpoint::wrap_with_middleware(ep, &self.middleware),
-            );
-        }
-        self
+    pub fn method<E: Endpoint<State>>(&mut self, method: http_types::Method, ep: E) -> &mut Self {
+        self.register(Some(method), ep, Vec::new())
     }
 
     /// 为所有HTTP方法添加一个endpoin，作为回调。
     ///
     /// 尝试使用特定HTTP方法的路由。
-    pub fn all(&mut self, ep: impl Endpoint<State>) -> &mut Self {
+    pub fn all<E: Endpoint<State>>(&mut self, ep: E) -> &mut Self {
+        self.register(None, ep, Vec::new())
+    }
+
+    /// 注册endpoint并记录路由信息，`method` 为 `None` 时匹配所有HTTP方法
+    fn register<E: Endpoint<State>>(
+        &mut self,
+        method: Option<http_types::Method>,
+        ep: E,
+        nested: Vec<RouteInfo>,
+    ) -> &mut Self {
+        let endpoint = std::any::type_name::<E>().to_string();
         if self.prefix {
             let ep = StripPrefixEndpoint::new(ep);
-            let wildcard = self.at("*");
-            wildcard.router.add_all(
-                &wildcard.path,
-                MiddlewareEndpoint::wrap_with_middleware(ep, &wildcard.middleware),
-            );
+            let mut wildcard = self.at("*");
+            wildcard.add(method, ep, endpoint, nested);
         } else {
-            self.router.add_all(
-                &self.path,
-                MiddlewareEndpoint::wrap_with_middleware(ep, &self.middleware),
-            );
+            self.add(method, ep, endpoint, nested);
         }
         self
     }
 
+    fn add<E: Endpoint<State>>(
+        &mut self,
+        method: Option<http_types::Method>,
+        ep: E,
+        endpoint: String,
+        nested: Vec<RouteInfo>,
+    ) {
+        self.router.describe(RouteInfo {
+            path: self.path.clone(),
+            name: self.name.clone(),
+            method,
+            endpoint,
+            middleware: self
+                .middleware
+                .iter()
+                .map(|m| m.name().to_string())
+                .collect(),
+            nested,
+        });
+        let ep = RouteEntry::new(
+            MiddlewareEndpoint::wrap_with_middleware(ep, &self.middleware),
+            self.name.clone(),
+        );
+        match method {
+            Some(method) => self.router.add(&self.path, method, ep),
+            None => self.router.add_all(&self.path, ep),
+        }
+    }
+
     /// 为 `GET` 请求添加endpoint
     pub fn get(&mut self, ep: impl Endpoint<State>) -> &mut Self {
         self.method(http_types::Method::Get, ep);
@@ -322,3 +409,19 @@ where
             .await
     }
 }
+
+/// 路由路径中第一个参数或通配符之前的部分，保留末尾的 `/`
+fn literal_prefix(path: &str) -> String {
+    let mut prefix = String::from("/");
+    for segment in path.split('/').filter(|segment| !segment.is_empty()) {
+        if segment.starts_with(':') || segment.starts_with('*') {
+            return prefix;
+        }
+        prefix.push_str(segment);
+        prefix.push('/');
+    }
+    if prefix.len() > 1 && !path.ends_with('/') {
+        prefix.pop();
+    }
+    prefix
+}
@@ -1,7 +1,11 @@
+use crate::http_types::headers::ALLOW;
+use crate::http_types::Method;
 use crate::server;
-use crate::{Request, Response, StatusCode};
+use crate::{Endpoint, Request, Response, StatusCode};
 
-use routefinder::{Captures, Router as MethodRouter};
+use async_trait::async_trait;
+use percent_encoding::percent_decode_str;
+use routefinder::{Capture, Captures, Router as MethodRouter};
 use std::collections::HashMap;
 
 use server::endpoint::DynEndpoint;
@@ -12,8 +16,50 @@ use server::endpoint::DynEndpoint;
 /// 通过该方法，可以提高效率
 #[allow(missing_debug_implementations)]
 pub(crate) struct Router<State> {
-    method_map: HashMap<http_types::Method, MethodRouter<Box<DynEndpoint<State>>>>,
-    all_method_router: MethodRouter<Box<DynEndpoint<State>>>,
+    method_map: HashMap<http_types::Method, MethodRouter<RouteEntry<State>>>,
+    all_method_router: MethodRouter<RouteEntry<State>>,
+    routes: Vec<RouteInfo>,
+    not_found: Option<Box<DynEndpoint<State>>>,
+    method_not_allowed: MethodNotAllowed<State>,
+    /// 按路径前缀放宽的请求目标长度上限
+    target_exemptions: Vec<(String, usize)>,
+}
+
+/// 路由中保存的endpoint及其名称
+pub(crate) struct RouteEntry<State> {
+    endpoint: Box<DynEndpoint<State>>,
+    name: Option<String>,
+}
+
+impl<State> RouteEntry<State> {
+    pub(crate) fn new(endpoint: Box<DynEndpoint<State>>, name: Option<String>) -> Self {
+        RouteEntry { endpoint, name }
+    }
+}
+
+impl<State> std::fmt::Debug for RouteEntry<State> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RouteEntry")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+/// 已注册路由的描述信息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteInfo {
+    /// 路由路径
+    pub path: String,
+    /// 路由名称，通过 [`Route::name`](crate::Route::name) 设置
+    pub name: Option<String>,
+    /// HTTP 方法，`None` 表示所有方法
+    pub method: Option<http_types::Method>,
+    /// endpoint 的类型名称
+    pub endpoint: String,
+    /// 作用于该路由的中间件名称，按执行顺序排列
+    pub middleware: Vec<String>,
+    /// 嵌套 `Server` 的路由，路径相对于嵌套位置
+    pub nested: Vec<RouteInfo>,
 }
 
 impl<State> std::fmt::Debug for Router<State> {
@@ -29,6 +75,12 @@ impl<State> std::fmt::Debug for Router<State> {
 pub(crate) struct Selection<'a, State> {
     pub(crate) endpoint: &'a DynEndpoint<State>,
     pub(crate) params: Captures<'static, 'static>,
+    /// 匹配到的路由模板，例如 `/users/:id`
+    pub(crate) route: Option<String>,
+    /// 路由名称
+    pub(crate) name: Option<String>,
+    /// 返回 `405` 时路径上注册的方法，其他情况为空
+    pub(crate) allow: Vec<Method>,
 }
 
 impl<State: Clone + Send + Sync + 'static> Router<State> {
@@ -36,65 +88,249 @@ impl<State: Clone + Send + Sync + 'static> Router<State> {
         Router {
             method_map: HashMap::default(),
             all_method_router: MethodRouter::new(),
+            routes: Vec::new(),
+            not_found: None,
+            method_not_allowed: MethodNotAllowed { fallback: None },
+            target_exemptions: Vec::new(),
         }
     }
 
-    pub(crate) fn add(
-        &mut self,
-        path: &str,
-        method: http_types::Method,
-        ep: Box<DynEndpoint<State>>,
-    ) {
+    /// 以 `prefix` 开头的请求目标使用 `limit` 作为长度上限
+    pub(crate) fn exempt_target(&mut self, prefix: String, limit: usize) {
+        self.target_exemptions.push((prefix, limit));
+    }
+
+    pub(crate) fn target_exemptions(&self) -> &[(String, usize)] {
+        &self.target_exemptions
+    }
+
+    /// 设置没有匹配到路由时的 endpoint
+    pub(crate) fn set_not_found(&mut self, ep: Box<DynEndpoint<State>>) {
+        self.not_found = Some(ep);
+    }
+
+    /// 设置路径存在但方法不匹配时的 endpoint
+    pub(crate) fn set_method_not_allowed(&mut self, ep: Box<DynEndpoint<State>>) {
+        self.method_not_allowed.fallback = Some(ep);
+    }
+
+    /// 记录路由的描述信息
+    pub(crate) fn describe(&mut self, info: RouteInfo) {
+        self.routes.push(info);
+    }
+
+    /// 按注册顺序返回所有路由的描述信息
+    pub(crate) fn routes(&self) -> &[RouteInfo] {
+        &self.routes
+    }
+
+    /// 展开嵌套服务后的所有路由，按注册顺序排列
+    ///
+    /// 嵌套路由的路径拼接到外层路径的通配符位置，中间件排在外层路由的中间件之后。
+    pub(crate) fn list_routes(&self) -> Vec<RouteInfo> {
+        let mut routes = Vec::new();
+        flatten(&self.routes, "", &[], &mut routes);
+        routes
+    }
+
+    pub(crate) fn add(&mut self, path: &str, method: http_types::Method, ep: RouteEntry<State>) {
         self.method_map
             .entry(method)
-            .or_insert_with(MethodRouter::new)
+            .or_default()
             .add(path, ep)
             .unwrap()
     }
 
-    pub(crate) fn add_all(&mut self, path: &str, ep: Box<DynEndpoint<State>>) {
+    pub(crate) fn add_all(&mut self, path: &str, ep: RouteEntry<State>) {
         self.all_method_router.add(path, ep).unwrap()
     }
 
+    /// 匹配路由，匹配在编码后的路径上进行，捕获的参数解码后返回
     pub(crate) fn route(&self, path: &str, method: http_types::Method) -> Selection<'_, State> {
+        let selection = self.select(path, method);
+        match decode_captures(selection.params) {
+            Some(params) => Selection {
+                endpoint: selection.endpoint,
+                params,
+                route: selection.route,
+                name: selection.name,
+                allow: selection.allow,
+            },
+            None => Selection {
+                endpoint: &bad_request,
+                params: Captures::default(),
+                route: None,
+                name: None,
+                allow: Vec::new(),
+            },
+        }
+    }
+
+    fn select(&self, path: &str, method: http_types::Method) -> Selection<'_, State> {
         if let Some(m) = self
             .method_map
             .get(&method)
             .and_then(|r| r.best_match(path))
         {
             Selection {
-                endpoint: m.handler(),
+                endpoint: &*m.handler().endpoint,
                 params: m.captures().into_owned(),
+                route: Some(m.route().to_string()),
+                name: m.handler().name.clone(),
+                allow: Vec::new(),
             }
         } else if let Some(m) = self.all_method_router.best_match(path) {
             Selection {
-                endpoint: m.handler(),
+                endpoint: &*m.handler().endpoint,
                 params: m.captures().into_owned(),
+                route: Some(m.route().to_string()),
+                name: m.handler().name.clone(),
+                allow: Vec::new(),
             }
         } else if method == http_types::Method::Head {
             // 如果是HTTP头请求，则检查endpoints映射中是否有回调
             // 如果没有，则返回到HTTP GET的逻辑，否则照常进行
 
-            self.route(path, http_types::Method::Get)
-        } else if self
-            .method_map
-            .iter()
-            .filter(|(k, _)| **k != method)
-            .any(|(_, r)| r.best_match(path).is_some())
-        {
+            self.select(path, http_types::Method::Get)
+        } else {
             // 如果此 `path` 可以由使用其他HTTP方法注册的回调处理
             // 应返回405 Method Not Allowed
-            Selection {
-                endpoint: &method_not_allowed,
-                params: Captures::default(),
+            let allow = self.allowed_methods(path);
+            if allow.is_empty() {
+                return self.not_found();
             }
-        } else {
             Selection {
-                endpoint: &not_found_endpoint,
+                endpoint: &self.method_not_allowed,
                 params: Captures::default(),
+                route: None,
+                name: None,
+                allow,
             }
         }
     }
+
+    /// 路径上注册的方法，注册了 `GET` 时包含 `HEAD`
+    fn allowed_methods(&self, path: &str) -> Vec<Method> {
+        let mut allow: Vec<Method> = self
+            .method_map
+            .iter()
+            .filter(|(_, r)| r.best_match(path).is_some())
+            .map(|(method, _)| *method)
+            .collect();
+        if allow.contains(&Method::Get) && !allow.contains(&Method::Head) {
+            allow.push(Method::Head);
+        }
+        allow.sort_by_key(|method| method.to_string());
+        allow
+    }
+
+    /// 没有匹配到路由时的结果
+    pub(crate) fn not_found(&self) -> Selection<'_, State> {
+        Selection {
+            endpoint: self.not_found.as_deref().unwrap_or(&not_found_endpoint),
+            params: Captures::default(),
+            route: None,
+            name: None,
+            allow: Vec::new(),
+        }
+    }
+}
+
+/// 返回 `405` 时路径上注册的方法，通过 [`Request::allowed_methods`] 读取
+#[derive(Debug, Clone)]
+pub(crate) struct AllowedMethods(pub(crate) Vec<Method>);
+
+/// 方法不匹配时调用的 endpoint
+///
+/// 没有设置 `fallback` 时返回 `405 Method Not Allowed`。
+/// 响应仍为 `405` 且没有 `Allow` 响应头时补上路径上注册的方法。
+struct MethodNotAllowed<State> {
+    fallback: Option<Box<DynEndpoint<State>>>,
+}
+
+#[async_trait]
+impl<State> Endpoint<State> for MethodNotAllowed<State>
+where
+    State: Clone + Send + Sync + 'static,
+{
+    async fn call(&self, req: Request<State>) -> crate::Result {
+        let allow = req.allowed_methods().map(|methods| {
+            methods
+                .iter()
+                .map(|method| method.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        });
+        let mut res = match &self.fallback {
+            Some(fallback) => fallback.call(req).await?,
+            None => Response::new(StatusCode::MethodNotAllowed),
+        };
+        if let Some(allow) = allow {
+            if res.status() == StatusCode::MethodNotAllowed && res.header(ALLOW).is_none() {
+                res.insert_header(ALLOW, allow);
+            }
+        }
+        Ok(res)
+    }
+}
+
+fn flatten(routes: &[RouteInfo], prefix: &str, middleware: &[String], out: &mut Vec<RouteInfo>) {
+    for route in routes {
+        let path = format!("{}{}", prefix, route.path);
+        let middleware: Vec<String> = middleware
+            .iter()
+            .chain(&route.middleware)
+            .cloned()
+            .collect();
+        if route.nested.is_empty() {
+            out.push(RouteInfo {
+                path,
+                middleware,
+                nested: Vec::new(),
+                ..route.clone()
+            });
+        } else {
+            let prefix = path
+                .strip_suffix('*')
+                .unwrap_or(&path)
+                .trim_end_matches('/');
+            flatten(&route.nested, prefix, &middleware, out);
+        }
+    }
+}
+
+/// 逐段解码百分号编码的路径
+///
+/// 解码后的段中包含 `/`（即 `%2F`）、NUL 字节或不是合法的 UTF-8 时返回 `None`，
+/// 避免绕过按段进行的目录穿越检查。`+` 在路径中不是空格，保持原样。
+pub(crate) fn decode_path(path: &str) -> Option<String> {
+    let mut decoded = String::with_capacity(path.len());
+    for (i, segment) in path.split('/').enumerate() {
+        if i > 0 {
+            decoded.push('/');
+        }
+        let segment = percent_decode_str(segment).decode_utf8().ok()?;
+        if segment.contains(['/', '\0']) {
+            return None;
+        }
+        decoded.push_str(&segment);
+    }
+    Some(decoded)
+}
+
+fn decode_captures(captures: Captures<'static, 'static>) -> Option<Captures<'static, 'static>> {
+    let mut decoded = Captures::new();
+    for (name, value) in captures.iter() {
+        decoded.push(Capture::new(name.to_string(), decode_path(value)?));
+    }
+    if let Some(wildcard) = captures.wildcard() {
+        decoded.set_wildcard(decode_path(wildcard)?);
+    }
+    Some(decoded)
+}
+
+async fn bad_request<State: Clone + Send + Sync + 'static>(_req: Request<State>) -> crate::Result {
+    Ok(Response::new(StatusCode::BadRequest))
 }
 
 async fn not_found_endpoint<State: Clone + Send + Sync + 'static>(
@@ -103,8 +339,163 @@ async fn not_found_endpoint<State: Clone + Send + Sync + 'static>(
     Ok(Response::new(StatusCode::NotFound))
 }
 
-async fn method_not_allowed<State: Clone + Send + Sync + 'static>(
-    _req: Request<State>,
-) -> crate::Result {
-    Ok(Response::new(StatusCode::MethodNotAllowed))
+#[cfg(test)]
+mod test {
+    use super::decode_path;
+    use crate as summer_boot;
+    use http_types::{Method, Request, Response, StatusCode, Url};
+
+    #[test]
+    fn decodes_path_segments() {
+        assert_eq!(decode_path("my%20report.pdf").unwrap(), "my report.pdf");
+        assert_eq!(decode_path("a/%E4%BD%A0%E5%A5%BD").unwrap(), "a/你好");
+        assert_eq!(decode_path("a+b").unwrap(), "a+b");
+        assert_eq!(decode_path("%2E%2E/etc").unwrap(), "../etc");
+        assert_eq!(decode_path("..%2Fetc"), None);
+        assert_eq!(decode_path("a%00b"), None);
+        assert_eq!(decode_path("%FF"), None);
+    }
+
+    async fn get(app: &summer_boot::Server<()>, path: &str) -> Response {
+        let url = Url::parse(&format!("http://localhost{}", path)).unwrap();
+        app.respond(Request::new(Method::Get, url)).await.unwrap()
+    }
+
+    #[async_std::test]
+    async fn decodes_params_and_wildcards() {
+        let mut app = summer_boot::new();
+        app.at("/users/:name")
+            .get(|req: summer_boot::Request<()>| async move { Ok(req.param("name")?.to_string()) });
+        app.at("/files/*")
+            .get(|req: summer_boot::Request<()>| async move {
+                Ok(req.wildcard().unwrap_or_default().to_string())
+            });
+
+        let mut res = get(&app, "/users/J%C3%B6rg%20Z").await;
+        assert_eq!(res.body_string().await.unwrap(), "Jörg Z");
+        let mut res = get(&app, "/users/a+b").await;
+        assert_eq!(res.body_string().await.unwrap(), "a+b");
+        let mut res = get(&app, "/files/my%20docs/%E6%8A%A5%E5%91%8A.pdf").await;
+        assert_eq!(res.body_string().await.unwrap(), "my docs/报告.pdf");
+
+        assert_eq!(
+            get(&app, "/users/a%2Fb").await.status(),
+            StatusCode::BadRequest
+        );
+        assert_eq!(
+            get(&app, "/files/..%2F..%2Fetc").await.status(),
+            StatusCode::BadRequest
+        );
+        assert_eq!(
+            get(&app, "/users/a%00").await.status(),
+            StatusCode::BadRequest
+        );
+    }
+
+    #[async_std::test]
+    async fn exposes_route_template() {
+        let route = |req: summer_boot::Request<()>| async move {
+            Ok(format!(
+                "{} {}",
+                req.route_pattern().unwrap_or("none"),
+                req.route_name().unwrap_or("none")
+            ))
+        };
+        let mut app = summer_boot::new();
+        app.at("/users/:id").name("get_user").get(route);
+        app.at("/admin").nest({
+            let mut admin = summer_boot::new();
+            admin.at("/jobs/:job").get(route);
+            admin
+        });
+
+        let mut res = get(&app, "/users/42").await;
+        assert_eq!(res.body_string().await.unwrap(), "/users/:id get_user");
+        let mut res = get(&app, "/admin/jobs/7").await;
+        assert_eq!(res.body_string().await.unwrap(), "/admin/jobs/:job none");
+    }
+
+    #[test]
+    fn lists_nested_routes() {
+        let mut app = summer_boot::new();
+        app.at("/users/:id")
+            .name("get_user")
+            .get(|_| async { Ok("user") });
+        app.at("/admin").nest({
+            let mut admin = summer_boot::new();
+            admin.at("/jobs/:job").post(|_| async { Ok("job") });
+            admin.at("/").all(|_| async { Ok("admin") });
+            admin
+        });
+
+        let routes: Vec<(Option<Method>, String)> = app
+            .list_routes()
+            .into_iter()
+            .map(|route| (route.method, route.path))
+            .collect();
+        assert_eq!(
+            routes,
+            [
+                (Some(Method::Get), "/users/:id".to_string()),
+                (Some(Method::Post), "/admin/jobs/:job".to_string()),
+                (None, "/admin/".to_string()),
+            ]
+        );
+        assert_eq!(app.list_routes()[0].name.as_deref(), Some("get_user"));
+        assert!(app
+            .list_routes()
+            .iter()
+            .all(|route| route.nested.is_empty()));
+    }
+
+    #[async_std::test]
+    async fn method_not_allowed_lists_allow() {
+        let mut app = summer_boot::new();
+        app.at("/users").get(|_| async { Ok("list") });
+        app.at("/users").post(|_| async { Ok("create") });
+
+        let url = Url::parse("http://localhost/users").unwrap();
+        let res: Response = app.respond(Request::new(Method::Put, url)).await.unwrap();
+        assert_eq!(res.status(), StatusCode::MethodNotAllowed);
+        assert_eq!(res["Allow"], "GET, HEAD, POST");
+    }
+
+    #[async_std::test]
+    async fn custom_fallbacks() {
+        let mut app = summer_boot::new();
+        app.at("/users").get(|_| async { Ok("list") });
+        app.at("/users").post(|_| async { Ok("create") });
+        app.at_not_found(|req: summer_boot::Request<()>| async move {
+            let mut res = summer_boot::Response::new(StatusCode::NotFound);
+            res.set_body(format!("no page at {}", req.url().path()));
+            Ok(res)
+        });
+        app.at_method_not_allowed(|req: summer_boot::Request<()>| async move {
+            let allow: Vec<String> = req
+                .allowed_methods()
+                .unwrap()
+                .iter()
+                .map(|method| method.to_string())
+                .collect();
+            let mut res = summer_boot::Response::new(StatusCode::MethodNotAllowed);
+            res.set_body(format!("{} accepts {}", req.url().path(), allow.join(" ")));
+            Ok(res)
+        });
+
+        let mut res = get(&app, "/missing").await;
+        assert_eq!(res.status(), StatusCode::NotFound);
+        assert_eq!(res.body_string().await.unwrap(), "no page at /missing");
+
+        let url = Url::parse("http://localhost/users").unwrap();
+        let mut res: Response = app
+            .respond(Request::new(Method::Delete, url))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::MethodNotAllowed);
+        assert_eq!(res["Allow"], "GET, HEAD, POST");
+        assert_eq!(
+            res.body_string().await.unwrap(),
+            "/users accepts GET HEAD POST"
+        );
+    }
 }
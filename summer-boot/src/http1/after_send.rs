@@ -0,0 +1,142 @@
+use std::future::Future;
+use std::io;
+use std::panic::AssertUnwindSafe;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+use async_std::task;
+use futures_util::FutureExt;
+
+use crate::log::{self, AfterSendOutcome, Observers};
+
+type Hook = Box<dyn FnOnce(SendContext) -> Pin<Box<dyn Future<Output = ()> + Send>> + Send>;
+
+/// 传给 [`Response::after_send`](crate::Response::after_send) 任务的连接信息和发送结果
+#[derive(Debug, Clone)]
+pub struct SendContext {
+    error: Option<Arc<io::Error>>,
+    peer_addr: Option<String>,
+    local_addr: Option<String>,
+}
+
+impl SendContext {
+    /// 响应是否完整写入了连接
+    #[must_use]
+    pub fn is_sent(&self) -> bool {
+        self.error.is_none()
+    }
+
+    /// 响应没有写完时的错误，例如客户端提前断开
+    #[must_use]
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.as_deref()
+    }
+
+    /// 连接的对端地址
+    #[must_use]
+    pub fn peer_addr(&self) -> Option<&str> {
+        self.peer_addr.as_deref()
+    }
+
+    /// 连接的本地地址
+    #[must_use]
+    pub fn local_addr(&self) -> Option<&str> {
+        self.local_addr.as_deref()
+    }
+}
+
+/// 响应写完之后执行的任务，保存在响应扩展中
+///
+/// 任务状态通知处理该请求的服务上的观察者。
+#[derive(Default)]
+pub(crate) struct AfterSend {
+    hooks: Mutex<Vec<Hook>>,
+    observers: Observers,
+}
+
+impl AfterSend {
+    pub(crate) fn push<F, Fut>(&self, hook: F)
+    where
+        F: FnOnce(SendContext) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.hooks
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(Box::new(move |cx| Box::pin(hook(cx))));
+    }
+
+    pub(crate) fn set_observers(&mut self, observers: Observers) {
+        self.observers = observers;
+    }
+
+    /// 开始写响应，返回的守卫被丢弃前没有标记结果时按写入失败运行任务
+    pub(crate) fn sending(
+        self,
+        peer_addr: Option<String>,
+        local_addr: Option<String>,
+    ) -> PendingAfterSend {
+        PendingAfterSend {
+            hooks: Some(self),
+            cx: SendContext {
+                error: None,
+                peer_addr,
+                local_addr,
+            },
+        }
+    }
+
+    /// 在执行器上运行所有任务，panic 会被捕获并记录
+    fn spawn(self, cx: SendContext) {
+        let hooks = self.hooks.into_inner().unwrap_or_else(|e| e.into_inner());
+        for hook in hooks {
+            self.observers.after_send(AfterSendOutcome::Queued);
+            let observers = self.observers.clone();
+            let hook = hook(cx.clone());
+            task::spawn(async move {
+                match AssertUnwindSafe(hook).catch_unwind().await {
+                    Ok(()) => observers.after_send(AfterSendOutcome::Completed),
+                    Err(panic) => {
+                        let message = panic
+                            .downcast_ref::<&str>()
+                            .map(|s| s.to_string())
+                            .or_else(|| panic.downcast_ref::<String>().cloned())
+                            .unwrap_or_default();
+                        log::error!("After-send hook panicked", { message: message });
+                        observers.after_send(AfterSendOutcome::Failed);
+                    }
+                }
+            });
+        }
+    }
+}
+
+/// 正在写入的响应的任务，写入出错或连接任务被取消时同样会运行
+pub(crate) struct PendingAfterSend {
+    hooks: Option<AfterSend>,
+    cx: SendContext,
+}
+
+impl PendingAfterSend {
+    /// 按写入结果运行任务
+    pub(crate) fn finish<T>(mut self, written: &io::Result<T>) {
+        if let Err(error) = written {
+            self.cx.error = Some(Arc::new(io::Error::new(error.kind(), error.to_string())));
+        }
+        if let Some(hooks) = self.hooks.take() {
+            hooks.spawn(self.cx.clone());
+        }
+    }
+}
+
+impl Drop for PendingAfterSend {
+    fn drop(&mut self) {
+        if let Some(hooks) = self.hooks.take() {
+            self.cx.error = Some(Arc::new(io::Error::new(
+                io::ErrorKind::Interrupted,
+                "connection closed before the response was written",
+            )));
+            hooks.spawn(self.cx.clone());
+        }
+    }
+}
@@ -22,6 +22,11 @@ impl BodyEncoder {
             None => Self::Chunked(ChunkedEncoder::new(body)),
         }
     }
+
+    /// 不论正文长度是否已知都使用分块编码
+    pub(crate) fn chunked(body: Body) -> Self {
+        Self::Chunked(ChunkedEncoder::new(body))
+    }
 }
 
 impl Read for BodyEncoder {
@@ -29,6 +29,11 @@ impl<R: Read> ChunkedDecoder<R> {
             trailer_sender: Some(trailer_sender),
         }
     }
+
+    /// 获取底层流的引用
+    pub(crate) fn get_ref(&self) -> &R {
+        &self.inner
+    }
 }
 
 /// 解码状态.
@@ -113,7 +118,7 @@ fn overflow() -> io::Error {
 ///
 /// 实现了Read trait 的结构体ChunkedDecoder方法的poll_read实现
 /// 它用于从一个Read类型的输入流中读取数据，并解析出分块编码（chunked encoding）的数据。
-/// 
+///
 /// State::ChunkSize：在这个状态下，代码读取一个字节，并根据字节的值计算出当前块的大小。
 /// State::ChunkSizeExpectLf：在这个状态下，代码期望读取到一个换行符（LF），如果当前块的大小为0，则进入State::Trailers状态，否则进入State::ChunkBody状态。
 /// State::ChunkBody：在这个状态下，代码读取当前块的数据，并将读取的字节数返回。
@@ -122,7 +127,7 @@ fn overflow() -> io::Error {
 /// State::Trailers：在这个状态下，代码读取剩余的数据作为 trailers，并解析出 trailers 的头部字段。
 /// State::TrailerSending：在这个状态下，代码等待 trailers 发送完成。
 /// State::Done：在这个状态下，代码表示读取操作已完成。
-/// 
+///
 impl<R: Read + Unpin> Read for ChunkedDecoder<R> {
     fn poll_read(
         mut self: Pin<&mut Self>,
@@ -225,7 +230,7 @@ impl<R: Read + Unpin> Read for ChunkedDecoder<R> {
                     }
                 }
                 State::TrailerSending(ref mut fut) => {
-                    let _ = ready!(Pin::new(fut).poll(cx));
+                    ready!(Pin::new(fut).poll(cx));
                     this.state = State::Done;
                 }
                 State::Done => return Poll::Ready(Ok(0)),
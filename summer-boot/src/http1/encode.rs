@@ -7,7 +7,7 @@ use async_std::io::{self, Cursor, Read};
 use async_std::task::{Context, Poll};
 use futures_util::ready;
 use http_types::headers::{CONTENT_LENGTH, DATE, TRANSFER_ENCODING};
-use http_types::{Method, Response};
+use http_types::{Method, Response, StatusCode};
 
 use super::body_encoder::BodyEncoder;
 use super::date::fmt_http_date;
@@ -20,12 +20,26 @@ pub(crate) enum EncoderState {
     End,
 }
 
+/// 自定义的状态原因短语，保存在响应扩展中
+#[derive(Debug, Clone)]
+pub(crate) struct ReasonPhrase(pub(crate) String);
+
+/// 通过 [`Response::force_chunked`](crate::Response::force_chunked) 或
+/// [`Response::force_content_length`](crate::Response::force_content_length) 指定的消息体分帧方式，
+/// 保存在响应扩展中
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Framing {
+    Chunked,
+    ContentLength,
+}
+
 /// streaming HTTP 编码
 #[derive(Debug)]
 pub struct Encoder {
     response: Response,
     state: EncoderState,
     method: Method,
+    canonical_header_names: bool,
 }
 
 impl Read for Encoder {
@@ -43,6 +57,8 @@ impl Read for Encoder {
 
                     if self.method == Method::Head {
                         EncoderState::End
+                    } else if self.forced_framing() == Some(Framing::Chunked) {
+                        EncoderState::Body(BodyEncoder::chunked(self.response.take_body()))
                     } else {
                         EncoderState::Body(BodyEncoder::new(self.response.take_body()))
                     }
@@ -66,36 +82,92 @@ impl Encoder {
             method,
             response,
             state: EncoderState::Start,
+            canonical_header_names: false,
         }
     }
 
-    fn finalize_headers(&mut self) {
-        // 如果正文没有流传输，可以提前设置内容长度。否则需要分块发送所有
-        if let Some(len) = self.response.len() {
-            self.response.insert_header(CONTENT_LENGTH, len.to_string());
-        } else {
-            self.response.insert_header(TRANSFER_ENCODING, "chunked");
+    /// 按规范大小写输出响应头名称，例如 `Content-Type`，默认保持小写
+    #[must_use]
+    pub fn canonical_header_names(mut self, enabled: bool) -> Self {
+        self.canonical_header_names = enabled;
+        self
+    }
+
+    /// 强制的分帧方式
+    ///
+    /// HEAD 请求以及 1xx、`204 No Content`、`304 Not Modified` 的响应没有正文，忽略强制的分帧方式
+    fn forced_framing(&self) -> Option<Framing> {
+        let status = self.response.status();
+        let bodyless = self.method == Method::Head
+            || status.is_informational()
+            || status == StatusCode::NoContent
+            || status == StatusCode::NotModified;
+        if bodyless {
+            return None;
+        }
+        self.response.ext().get::<Framing>().copied()
+    }
+
+    fn finalize_headers(&mut self) -> io::Result<()> {
+        let framed = self.response.header(CONTENT_LENGTH).is_some()
+            || self.response.header(TRANSFER_ENCODING).is_some();
+
+        if let Some(framing) = self.forced_framing() {
+            match framing {
+                Framing::Chunked => {
+                    self.response.remove_header(CONTENT_LENGTH);
+                    self.response.insert_header(TRANSFER_ENCODING, "chunked");
+                }
+                Framing::ContentLength => {
+                    // 正文在强制之后可能被替换成了流
+                    let len = self.response.len().ok_or_else(|| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            "Content-Length framing was forced but the body length is unknown",
+                        )
+                    })?;
+                    self.response.remove_header(TRANSFER_ENCODING);
+                    self.response.insert_header(CONTENT_LENGTH, len.to_string());
+                }
+            }
+        } else if self.method != Method::Head || !framed {
+            // HEAD 响应保留 `Server::respond` 按 GET 计算的长度
+            // 如果正文没有流传输，可以提前设置内容长度。否则需要分块发送所有
+            if let Some(len) = self.response.len() {
+                self.response.insert_header(CONTENT_LENGTH, len.to_string());
+            } else {
+                self.response.insert_header(TRANSFER_ENCODING, "chunked");
+            }
         }
 
         if self.response.header(DATE).is_none() {
             let date = fmt_http_date(SystemTime::now());
             self.response.insert_header(DATE, date);
         }
+        Ok(())
     }
 
     /// 第一次轮询时，将header编码到缓冲区。
     fn compute_head(&mut self) -> io::Result<Cursor<Vec<u8>>> {
         let mut head = Vec::with_capacity(128);
-        let reason = self.response.status().canonical_reason();
         let status = self.response.status();
+        let reason = match self.response.ext().get::<ReasonPhrase>() {
+            Some(reason) => reason.0.as_str(),
+            None => status.canonical_reason(),
+        };
         write!(head, "HTTP/1.1 {} {}\r\n", status, reason)?;
 
-        self.finalize_headers();
+        self.finalize_headers()?;
         let mut headers = self.response.iter().collect::<Vec<_>>();
         headers.sort_unstable_by_key(|(h, _)| h.as_str());
         for (header, values) in headers {
+            let name = if self.canonical_header_names {
+                canonical_header_name(header.as_str())
+            } else {
+                header.as_str().to_string()
+            };
             for value in values.iter() {
-                write!(head, "{}: {}\r\n", header, value)?;
+                write!(head, "{}: {}\r\n", name, value)?;
             }
         }
         write!(head, "\r\n")?;
@@ -103,6 +175,42 @@ impl Encoder {
     }
 }
 
+/// 不符合首字母大写规则的常见响应头
+const CANONICAL_EXCEPTIONS: [&str; 10] = [
+    "ETag",
+    "WWW-Authenticate",
+    "TE",
+    "Content-MD5",
+    "DNT",
+    "X-XSS-Protection",
+    "Sec-WebSocket-Accept",
+    "Sec-WebSocket-Protocol",
+    "Sec-WebSocket-Extensions",
+    "Sec-WebSocket-Version",
+];
+
+/// 每个 `-` 分隔的单词首字母大写，例如 `content-type` 转为 `Content-Type`
+fn canonical_header_name(name: &str) -> String {
+    if let Some(exception) = CANONICAL_EXCEPTIONS
+        .iter()
+        .find(|exception| exception.eq_ignore_ascii_case(name))
+    {
+        return exception.to_string();
+    }
+
+    let mut canonical = String::with_capacity(name.len());
+    let mut upper = true;
+    for c in name.chars() {
+        if upper {
+            canonical.push(c.to_ascii_uppercase());
+        } else {
+            canonical.push(c.to_ascii_lowercase());
+        }
+        upper = c == '-';
+    }
+    canonical
+}
+
 /// 用于分块编码的编码struct
 #[derive(Debug)]
 pub(crate) struct ChunkedEncoder<R> {
@@ -138,7 +246,7 @@ impl<R: Read + Unpin> Read for ChunkedEncoder<R> {
             self.done = true;
         }
         let start = format!("{:X}\r\n", bytes);
-        let start_length = start.as_bytes().len();
+        let start_length = start.len();
         let total = bytes + start_length + 2;
         buf.copy_within(..bytes, start_length);
         buf[..start_length].copy_from_slice(start.as_bytes());
@@ -161,3 +269,138 @@ fn max_bytes_to_read(buf_len: usize) -> usize {
 
     (bytes_remaining_after_two_cr_lns - max_bytes_of_hex_framing.ceil()) as usize
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use async_std::io::ReadExt;
+    use http_types::Body;
+
+    async fn status_line(res: impl Into<Response>) -> String {
+        let mut encoded = String::new();
+        Encoder::new(res.into(), Method::Get)
+            .read_to_string(&mut encoded)
+            .await
+            .unwrap();
+        encoded.lines().next().unwrap().to_string()
+    }
+
+    #[async_std::test]
+    async fn reason_phrase() {
+        let res = crate::Response::new(StatusCode::Ok);
+        assert_eq!(status_line(res).await, "HTTP/1.1 200 OK");
+
+        let mut res = crate::Response::new(StatusCode::Ok);
+        res.set_reason_phrase(Some("Totally\r\nFine".to_string()));
+        assert_eq!(status_line(res).await, "HTTP/1.1 200 TotallyFine");
+
+        let mut res = crate::Response::new(StatusCode::NotFound);
+        res.set_reason_phrase(Some(String::new()));
+        assert_eq!(status_line(res).await, "HTTP/1.1 404 ");
+
+        let mut res = crate::Response::new(StatusCode::NotFound);
+        res.set_reason_phrase(Some("Gone Fishing".to_string()));
+        res.set_reason_phrase(None);
+        assert_eq!(status_line(res).await, "HTTP/1.1 404 Not Found");
+    }
+
+    #[async_std::test]
+    async fn canonical_header_names() {
+        let encode = |canonical| async move {
+            let mut res = Response::new(StatusCode::Ok);
+            res.insert_header("content-type", "text/plain");
+            res.insert_header("etag", "\"1\"");
+            res.insert_header("x-request-id", "abc");
+            res.insert_header("date", "Thu, 01 Jan 1970 00:00:00 GMT");
+            let mut encoded = String::new();
+            Encoder::new(res, Method::Get)
+                .canonical_header_names(canonical)
+                .read_to_string(&mut encoded)
+                .await
+                .unwrap();
+            encoded
+        };
+
+        assert_eq!(
+            encode(true).await,
+            "HTTP/1.1 200 OK\r\n\
+             Content-Length: 0\r\n\
+             Content-Type: text/plain\r\n\
+             Date: Thu, 01 Jan 1970 00:00:00 GMT\r\n\
+             ETag: \"1\"\r\n\
+             X-Request-Id: abc\r\n\r\n"
+        );
+        assert!(encode(false)
+            .await
+            .contains("\r\ncontent-type: text/plain\r\n"));
+    }
+
+    async fn encode(res: crate::Response) -> io::Result<String> {
+        // 分块编码需要足够大的缓冲区，不能使用 `read_to_string`
+        let mut encoded = Vec::new();
+        io::copy(&mut Encoder::new(res.into(), Method::Get), &mut encoded).await?;
+        Ok(String::from_utf8(encoded).unwrap())
+    }
+
+    #[async_std::test]
+    async fn forced_framing() {
+        let mut res = crate::Response::new(StatusCode::Ok);
+        res.set_body("hello");
+        res.force_chunked();
+        let encoded = encode(res).await.unwrap();
+        assert!(encoded.contains("\r\ntransfer-encoding: chunked\r\n"));
+        assert!(!encoded.contains("content-length"));
+        assert!(encoded.ends_with("\r\n\r\n5\r\nhello\r\n0\r\n\r\n"));
+
+        let mut res = crate::Response::new(StatusCode::Ok);
+        res.set_body("hello");
+        res.insert_header(TRANSFER_ENCODING, "chunked");
+        res.force_content_length().unwrap();
+        let encoded = encode(res).await.unwrap();
+        assert!(encoded.contains("\r\ncontent-length: 5\r\n"));
+        assert!(!encoded.contains("transfer-encoding"));
+        assert!(encoded.ends_with("\r\n\r\nhello"));
+    }
+
+    #[async_std::test]
+    async fn forced_framing_is_ignored_without_body() {
+        for status in [StatusCode::NoContent, StatusCode::NotModified] {
+            let mut res = crate::Response::new(status);
+            res.force_chunked();
+            let encoded = encode(res).await.unwrap();
+            assert!(!encoded.contains("transfer-encoding"));
+            assert!(encoded.ends_with("\r\n\r\n"));
+        }
+
+        // HEAD 响应保留按 GET 计算的长度，不输出分块正文
+        let mut res = crate::Response::new(StatusCode::Ok);
+        res.insert_header(CONTENT_LENGTH, "5");
+        res.force_chunked();
+        let mut encoded = String::new();
+        Encoder::new(res.into(), Method::Head)
+            .read_to_string(&mut encoded)
+            .await
+            .unwrap();
+        assert!(encoded.contains("\r\ncontent-length: 5\r\n"));
+        assert!(!encoded.contains("transfer-encoding"));
+        assert!(encoded.ends_with("\r\n\r\n"));
+    }
+
+    #[async_std::test]
+    async fn content_length_needs_known_length() {
+        let stream = || Body::from_reader(io::Cursor::new(b"hello".to_vec()), None);
+
+        let mut res = crate::Response::new(StatusCode::Ok);
+        res.set_body(stream());
+        let err = res.force_content_length().unwrap_err();
+        assert_eq!(err.status(), StatusCode::InternalServerError);
+
+        // 强制之后替换为长度未知的正文，编码时报错而不是输出错误的响应
+        let mut res = crate::Response::new(StatusCode::Ok);
+        res.set_body("hello");
+        res.force_content_length().unwrap();
+        res.set_body(stream());
+        let err = encode(res).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -2,7 +2,8 @@
 
 use std::str::FromStr;
 use std::task::{Context, Poll};
-use std::{fmt, marker::PhantomData, pin::Pin, time::Duration};
+use std::time::{Duration, Instant};
+use std::{fmt, marker::PhantomData, pin::Pin};
 
 use async_std::future::{timeout, Future, TimeoutError};
 use async_std::io::{self, BufRead, BufReader, Read, Take, Write};
@@ -17,11 +18,21 @@ use http_types::{Body, Method, Request, Response, StatusCode, Url};
 use async_channel::Sender;
 use async_dup::{Arc, Mutex};
 
+use super::after_send::AfterSend;
+pub use super::after_send::SendContext;
 use super::decode::ChunkedDecoder;
 use super::encode::Encoder;
+use crate::tcp::Shutdown;
+use crate::utils::request_timing::{RequestTiming, TimedReader};
 
 const MAX_HEADERS: usize = 128;
 const MAX_HEAD_LENGTH: usize = 8 * 1024;
+/// 默认的请求目标长度上限
+const DEFAULT_MAX_TARGET_LENGTH: usize = 8 * 1024;
+/// 请求行中除请求目标之外的部分（方法、版本和空格）允许的长度
+const REQUEST_LINE_OVERHEAD: usize = 64;
+/// 每次从连接读取的最大字节数，也是一个连接上预读的上限
+const READ_BUFFER_CAPACITY: usize = 8 * 1024;
 
 const LF: u8 = b'\n';
 
@@ -30,22 +41,484 @@ const HTTP_1_1_VERSION: u8 = 1;
 
 const CONTINUE_HEADER_VALUE: &str = "100-continue";
 const CONTINUE_RESPONSE: &[u8] = b"HTTP/1.1 100 Continue\r\n\r\n";
+const PROCESSING_RESPONSE: &[u8] = b"HTTP/1.1 102 Processing\r\n\r\n";
 
 // http1 connection 配置服务器
+///
+/// 写响应时只有 socket 阻塞才会让出执行器，本地的快速连接上一个很大的流式响应会一直
+/// 占用线程，同一线程上的其他请求得不到调度。设置 [`ServerOptions::yield_after_bytes`]
+/// 或 [`ServerOptions::yield_after`] 后，连续写入超过阈值会主动 `yield_now`。
+/// 这样大响应写入期间小请求也能被调度，代价是大响应本身多几次调度。默认关闭。
 #[derive(Debug, Clone)]
 pub struct ServerOptions {
     /// 处理headers超时。默认值为60秒
     headers_timeout: Option<Duration>,
+    /// 连续写入多少字节后让出执行器
+    yield_after_bytes: Option<u64>,
+    /// 连续写入多长时间后让出执行器
+    yield_after: Option<Duration>,
+    /// 定长请求体之后多余字节的处理方式
+    trailing_data: TrailingData,
+    /// 是否在请求扩展中保存原始请求头
+    capture_raw_head: bool,
+    /// 是否记录请求体读取计时
+    request_timing: bool,
+    /// 是否按规范大小写输出响应头名称
+    canonical_header_names: bool,
+    /// 每个连接最多处理的请求数
+    max_requests_per_connection: Option<usize>,
+    /// keep-alive 连接上两个请求之间的最长空闲时间
+    idle_timeout: Option<Duration>,
+    /// 读取未消费请求体的超时时间
+    body_read_timeout: Option<Duration>,
+    /// 请求目标的长度上限
+    target_limits: TargetLimits,
+}
+
+/// 请求目标（请求行中的 URL）的长度上限
+///
+/// 按路径前缀豁免的路由使用各自的上限，其他请求使用全局上限。
+/// 前缀在侦听时从路由注册中收集，按长度从长到短排列，解析请求头之前对原始请求目标逐个比较。
+#[derive(Debug, Clone)]
+pub(crate) struct TargetLimits {
+    default: usize,
+    exemptions: std::sync::Arc<[(Box<str>, usize)]>,
+}
+
+impl Default for TargetLimits {
+    fn default() -> Self {
+        Self {
+            default: DEFAULT_MAX_TARGET_LENGTH,
+            exemptions: std::sync::Arc::new([]),
+        }
+    }
+}
+
+impl TargetLimits {
+    /// 所有上限中最大的一个，请求行超过它时不再继续读取
+    fn max(&self) -> usize {
+        self.exemptions
+            .iter()
+            .map(|(_, limit)| *limit)
+            .fold(self.default, usize::max)
+    }
+
+    /// 请求目标适用的上限，绝对形式的目标按其中的路径匹配
+    fn limit_for(&self, target: &[u8]) -> usize {
+        let path = ["http://", "https://"]
+            .iter()
+            .find_map(|scheme| target.strip_prefix(scheme.as_bytes()))
+            .map_or(target, |rest| {
+                let start = rest.iter().position(|&b| b == b'/').unwrap_or(rest.len());
+                &rest[start..]
+            });
+        self.exemptions
+            .iter()
+            .find(|(prefix, _)| matches_prefix(path, prefix.as_bytes()))
+            .map_or(self.default, |(_, limit)| *limit)
+    }
+}
+
+/// `prefix` 是否为 `path` 的完整路径段前缀，`/downloads` 匹配 `/downloads/a` 和 `/downloads?x`，不匹配 `/downloadsx`
+fn matches_prefix(path: &[u8], prefix: &[u8]) -> bool {
+    match path.strip_prefix(prefix) {
+        Some(rest) => {
+            prefix.ends_with(b"/") || rest.is_empty() || matches!(rest[0], b'/' | b'?' | b'#')
+        }
+        None => false,
+    }
+}
+
+/// 在最终响应之前发送 1xx 信息响应，保存在请求扩展中
+///
+/// `100 Continue` 也通过它写入。最终响应开始写入后不能再发送信息响应。
+///
+/// # Examples
+///
+/// ```
+/// use summer_boot::Request;
+///
+/// let mut app = summer_boot::new();
+/// app.at("/").get(|req: Request<()>| async move {
+///     if let Some(interim) = req.interim() {
+///         interim.early_hints(&["</style.css>; rel=preload; as=style"]).await?;
+///     }
+///     Ok("<html>...</html>")
+/// });
+/// ```
+#[derive(Clone)]
+pub struct Interim {
+    writer: std::sync::Arc<async_std::sync::Mutex<Option<Box<dyn Write + Send + Sync + Unpin>>>>,
+    /// 第一次读取请求体的通知，关闭后等待发送 `100 Continue` 的任务结束
+    body_read: Sender<()>,
+}
+
+impl Interim {
+    fn new<W: Write + Send + Sync + Unpin + 'static>(writer: W, body_read: Sender<()>) -> Self {
+        Self {
+            writer: std::sync::Arc::new(async_std::sync::Mutex::new(Some(Box::new(writer)))),
+            body_read,
+        }
+    }
+
+    /// 发送 `103 Early Hints`，每个 `links` 元素对应一个 `Link` 头
+    ///
+    /// 任一元素包含 CR、LF 等控制字符或非 ASCII 字符时返回 `InvalidInput`，不写入任何内容。
+    pub async fn early_hints(&self, links: &[&str]) -> io::Result<()> {
+        if let Some(link) = links.iter().find(|link| !is_header_value(link)) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid Link header value {:?}", link),
+            ));
+        }
+        let mut head = String::from("HTTP/1.1 103 Early Hints\r\n");
+        for link in links {
+            head.push_str("Link: ");
+            head.push_str(link);
+            head.push_str("\r\n");
+        }
+        head.push_str("\r\n");
+        self.write(head.as_bytes()).await
+    }
+
+    /// 发送 `102 Processing`，告诉客户端请求仍在处理
+    pub async fn processing(&self) -> io::Result<()> {
+        self.write(PROCESSING_RESPONSE).await
+    }
+
+    async fn write(&self, head: &[u8]) -> io::Result<()> {
+        let mut writer = self.writer.lock().await;
+        let writer = writer.as_mut().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::Other, "final response already started")
+        })?;
+        writer.write_all(head).await?;
+        writer.flush().await
+    }
+
+    /// 最终响应开始写入，之后的信息响应返回错误
+    async fn close(&self) {
+        self.body_read.close();
+        self.writer.lock().await.take();
+    }
+}
+
+/// 是否可以直接写入响应头的值：可见的 ASCII 字符、空格和制表符
+fn is_header_value(value: &str) -> bool {
+    value
+        .bytes()
+        .all(|b| b == b'\t' || (b' '..=b'~').contains(&b))
+}
+
+/// 请求结束时关闭 [`Interim`]，`accept_one` 提前返回或被取消时同样生效
+struct InterimGuard(Interim);
+
+impl Drop for InterimGuard {
+    fn drop(&mut self) {
+        self.0.body_read.close();
+        // 锁被占用时由持有者写完后释放
+        if let Some(mut writer) = self.0.writer.try_lock() {
+            writer.take();
+        }
+    }
+}
+
+impl fmt::Debug for Interim {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Interim").finish()
+    }
+}
+
+/// 解析前的原始请求头，保存在请求扩展中
+#[derive(Debug, Clone)]
+pub(crate) struct RawHead(pub(crate) Vec<u8>);
+
+/// 请求头读取或解析失败，保存已经收到的原始字节
+///
+/// 启用 [`ServerOptions::capture_raw_head`] 后，请求头有误时 [`Server::accept`]
+/// 返回的错误包含这个类型，通过 `downcast_ref` 获取原始字节。`Display` 与原来的错误相同。
+///
+/// ```
+/// use summer_boot::http::MalformedHead;
+///
+/// fn raw_head(error: &summer_boot::Error) -> Option<&[u8]> {
+///     error.downcast_ref::<MalformedHead>().map(MalformedHead::raw)
+/// }
+/// ```
+#[derive(Debug)]
+pub struct MalformedHead {
+    raw: Vec<u8>,
+    error: http_types::Error,
+}
+
+impl MalformedHead {
+    fn wrap(error: http_types::Error, raw: Vec<u8>) -> http_types::Error {
+        let status = error.status();
+        http_types::Error::new(status, MalformedHead { raw, error })
+    }
+
+    /// 收到的原始字节，最多为请求行加上 8 KiB 的请求头
+    pub fn raw(&self) -> &[u8] {
+        &self.raw
+    }
+}
+
+impl fmt::Display for MalformedHead {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.error, f)
+    }
+}
+
+impl std::error::Error for MalformedHead {}
+
+/// 严格模式下定长请求体之后出现了多余字节
+#[derive(Debug)]
+struct UnexpectedTrailingData;
+
+impl UnexpectedTrailingData {
+    /// 错误是否由多余字节引起，包括被 [`MalformedHead`] 包装的情况
+    fn matches(error: &http_types::Error) -> bool {
+        error.downcast_ref::<Self>().is_some()
+            || error
+                .downcast_ref::<MalformedHead>()
+                .is_some_and(|head| head.error.downcast_ref::<Self>().is_some())
+    }
+}
+
+impl fmt::Display for UnexpectedTrailingData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Unexpected bytes after request body")
+    }
+}
+
+impl std::error::Error for UnexpectedTrailingData {}
+
+/// RFC 7230 token 字符
+fn is_token(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b)
+}
+
+/// 客户端证书的 SHA-256 指纹，由终止 TLS 的传输层保存在请求扩展中
+///
+/// 内置的侦听器还不支持 TLS，自定义的 TLS 传输在完成握手后计算指纹，
+/// 然后插入到交给 [`accept`] 的每个请求中，处理器通过
+/// [`Request::peer_cert_fingerprint`](crate::Request::peer_cert_fingerprint) 读取。
+///
+/// # Examples
+///
+/// ```
+/// use summer_boot::http::PeerCertFingerprint;
+///
+/// let fingerprint = PeerCertFingerprint::from_der(b"");
+/// assert_eq!(
+///     fingerprint.as_str(),
+///     "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+/// );
+///
+/// let mut req = http_types::Request::get("https://localhost/");
+/// req.ext_mut().insert(fingerprint);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerCertFingerprint(String);
+
+impl PeerCertFingerprint {
+    /// 计算 DER 编码证书的指纹
+    #[must_use]
+    pub fn from_der(der: &[u8]) -> Self {
+        use sha2::{Digest, Sha256};
+
+        let digest = Sha256::digest(der);
+        let hex = digest.iter().map(|b| format!("{:02x}", b)).collect();
+        Self(hex)
+    }
+
+    /// 小写十六进制，不带分隔符
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// 定长请求体之后多余字节的处理方式
+///
+/// 部分客户端会在 `Content-Length` 请求体之后多发送一个 CRLF，
+/// 同一连接上的下一个请求头会跟在这个 CRLF 之后。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TrailingData {
+    /// 忽略请求体之后的一个 CRLF，默认
+    #[default]
+    Lenient,
+    /// 请求体之后、下一个请求的方法之前出现任何多余字节（包括一个 CRLF）时返回 `400` 并关闭连接
+    Strict,
 }
 
 impl Default for ServerOptions {
     fn default() -> Self {
         Self {
             headers_timeout: Some(Duration::from_secs(60)),
+            yield_after_bytes: None,
+            yield_after: None,
+            trailing_data: TrailingData::default(),
+            capture_raw_head: false,
+            request_timing: false,
+            canonical_header_names: false,
+            max_requests_per_connection: None,
+            idle_timeout: None,
+            body_read_timeout: None,
+            target_limits: TargetLimits::default(),
         }
     }
 }
 
+impl ServerOptions {
+    /// 连续写入 `bytes` 字节后让出执行器
+    #[must_use]
+    pub fn yield_after_bytes(mut self, bytes: u64) -> Self {
+        self.yield_after_bytes = Some(bytes);
+        self
+    }
+
+    /// 连续写入 `duration` 后让出执行器
+    #[must_use]
+    pub fn yield_after(mut self, duration: Duration) -> Self {
+        self.yield_after = Some(duration);
+        self
+    }
+
+    /// 设置定长请求体之后多余字节的处理方式
+    #[must_use]
+    pub fn trailing_data(mut self, mode: TrailingData) -> Self {
+        self.trailing_data = mode;
+        self
+    }
+
+    /// 在请求扩展中保存解析前的原始请求头，通过 [`Request::raw_head`](crate::Request::raw_head) 读取
+    ///
+    /// 用于排查客户端发送的畸形请求头。读取或解析请求头失败时，已经收到的字节通过
+    /// [`MalformedHead`] 随错误返回，内置的侦听器会写入日志。每个请求额外占用最多 8 KiB 内存，默认关闭。
+    #[must_use]
+    pub fn capture_raw_head(mut self, enabled: bool) -> Self {
+        self.capture_raw_head = enabled;
+        self
+    }
+
+    /// 记录请求体的读取时间，通过请求扩展中的 [`RequestTiming`] 读取
+    ///
+    /// [`LoggingSystem::with_stall_threshold`](crate::log::LoggingSystem::with_stall_threshold)
+    /// 依赖此项。每次读取请求体都需要加锁记录时间，默认关闭。
+    #[must_use]
+    pub fn request_timing(mut self, enabled: bool) -> Self {
+        self.request_timing = enabled;
+        self
+    }
+
+    /// 按规范大小写输出响应头名称，例如 `Content-Type` 而不是 `content-type`
+    ///
+    /// 用于兼容对大小写敏感的客户端。默认关闭，保持小写输出。
+    #[must_use]
+    pub fn canonical_header_names(mut self, enabled: bool) -> Self {
+        self.canonical_header_names = enabled;
+        self
+    }
+
+    /// 每个连接最多处理 `max` 个请求，最后一个响应带 `Connection: close` 并关闭连接
+    ///
+    /// 防止客户端在一个连接上无限发送请求，也让重启时的连接更快排空；
+    /// pipelining 时超过上限的请求不会被处理。默认不限制。
+    #[must_use]
+    pub fn max_requests_per_connection(mut self, max: usize) -> Self {
+        self.max_requests_per_connection = Some(max);
+        self
+    }
+
+    /// 等待请求头的最长时间
+    pub(crate) fn headers_timeout(&self) -> Option<Duration> {
+        self.headers_timeout
+    }
+
+    /// keep-alive 连接在上一个响应之后 `duration` 内没有收到完整的请求头时关闭连接
+    ///
+    /// 与 `headers_timeout` 同时生效时取较短的一个。默认不限制。
+    #[must_use]
+    pub fn idle_timeout(mut self, duration: Duration) -> Self {
+        self.idle_timeout = Some(duration);
+        self
+    }
+
+    /// 响应之后丢弃 endpoint 未读取的请求体，`duration` 内没有读完时关闭连接
+    ///
+    /// 防止客户端声明很大的请求体后缓慢发送而长期占用连接。默认不限制。
+    #[must_use]
+    pub fn body_read_timeout(mut self, duration: Duration) -> Self {
+        self.body_read_timeout = Some(duration);
+        self
+    }
+
+    /// 请求目标（路径和查询字符串）的长度上限，超过时返回 `414 URI Too Long` 并关闭连接
+    ///
+    /// 默认为 8 KiB。个别路由需要更长的目标时，例如带签名的下载链接，
+    /// 使用 [`Route::max_target_length`](crate::Route::max_target_length) 按路径前缀放宽。
+    #[must_use]
+    pub fn max_target_length(mut self, len: usize) -> Self {
+        self.target_limits.default = len;
+        self
+    }
+
+    /// 设置按路径前缀豁免的上限，由侦听器从路由注册中收集
+    #[must_use]
+    pub(crate) fn target_exemptions(mut self, mut exemptions: Vec<(String, usize)>) -> Self {
+        // 最长的前缀优先匹配
+        exemptions.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        exemptions.dedup_by(|(a, _), (b, _)| a == b);
+        self.target_limits.exemptions = exemptions
+            .into_iter()
+            .map(|(prefix, limit)| (prefix.into_boxed_str(), limit))
+            .collect();
+        self
+    }
+}
+
+/// 复制响应，按 `opts` 的配置周期性地让出执行器
+async fn copy_with_yield<R, W>(
+    reader: &mut R,
+    writer: &mut W,
+    opts: &ServerOptions,
+) -> io::Result<u64>
+where
+    R: Read + Unpin + ?Sized,
+    W: Write + Unpin + ?Sized,
+{
+    if opts.yield_after_bytes.is_none() && opts.yield_after.is_none() {
+        return io::copy(reader, writer).await;
+    }
+
+    let mut buf = vec![0; 8 * 1024];
+    let mut total = 0;
+    let mut written = 0;
+    let mut started = Instant::now();
+    loop {
+        let n = match reader.read(&mut buf).await {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        writer.write_all(&buf[..n]).await?;
+        total += n as u64;
+        written += n as u64;
+
+        let yield_bytes = opts.yield_after_bytes.is_some_and(|limit| written >= limit);
+        let yield_time = opts
+            .yield_after
+            .is_some_and(|limit| started.elapsed() >= limit);
+        if yield_bytes || yield_time {
+            task::yield_now().await;
+            written = 0;
+            started = Instant::now();
+        }
+    }
+    writer.flush().await?;
+    Ok(total)
+}
+
 /// 接受新的传入HTTP/1.1连接
 /// 默认情况支持KeepAlive请求。
 pub async fn accept<RW, F, Fut>(io: RW, endpoint: F) -> http_types::Result<()>
@@ -73,11 +546,35 @@ where
 }
 
 /// struct server
+///
+/// # Pipelining
+///
+/// 客户端可以不等响应连续发送多个请求（HTTP/1.1 pipelining），服务器按顺序逐个处理：
+/// 写完上一个响应并丢弃未读取的请求体之后才解码下一个请求，同一时间最多只有一个请求在处理。
+/// 后续请求不会被提前读入内存，预读的字节不超过一次读取的缓冲区（8 KiB），
+/// 其余数据留在 socket 中，由 TCP 流量控制限制客户端继续发送。
+/// 需要限制一个连接上的总请求数时使用 [`ServerOptions::max_requests_per_connection`]。
 #[derive(Debug)]
 pub struct Server<RW, F, Fut> {
     io: RW,
     endpoint: F,
     opts: ServerOptions,
+    /// 上一个请求之后已经从连接读取、属于后续请求的字节
+    pending: Vec<u8>,
+    /// 上一个请求是否为定长请求体
+    after_fixed_body: bool,
+    /// 连接上已经处理的请求数
+    served: usize,
+    /// 上一个请求是否完整结束
+    ///
+    /// 处理请求时置为 `false`，只有返回 [`ConnectionStatus::KeepAlive`] 时恢复。
+    /// 提前返回、出错或 `accept_one` 被取消后连接的状态不确定，不能再处理请求。
+    reusable: bool,
+    /// 触发后不再等待后续请求
+    shutdown: Option<Shutdown>,
+    /// 连接的对端和本地地址，设置到每个请求上
+    peer_addr: Option<String>,
+    local_addr: Option<String>,
     _phantom: PhantomData<Fut>,
 }
 
@@ -103,6 +600,13 @@ where
             io,
             endpoint,
             opts: Default::default(),
+            pending: Vec::new(),
+            after_fixed_body: false,
+            served: 0,
+            reusable: true,
+            shutdown: None,
+            peer_addr: None,
+            local_addr: None,
             _phantom: PhantomData,
         }
     }
@@ -113,6 +617,29 @@ where
         self
     }
 
+    /// 停机时关闭空闲的 keep-alive 连接，处理中的请求完成后响应 `Connection: close`
+    pub(crate) fn with_shutdown(mut self, shutdown: Shutdown) -> Self {
+        self.shutdown = Some(shutdown);
+        self
+    }
+
+    /// 连接的对端和本地地址，设置到每个请求上，也传给响应的 after-send 任务
+    pub(crate) fn with_addrs(
+        mut self,
+        peer_addr: Option<impl ToString>,
+        local_addr: Option<impl ToString>,
+    ) -> Self {
+        self.peer_addr = peer_addr.map(|addr| addr.to_string());
+        self.local_addr = local_addr.map(|addr| addr.to_string());
+        self
+    }
+
+    /// 已经从连接读取的字节，作为第一个请求的开头
+    pub(crate) fn with_pending(mut self, pending: Vec<u8>) -> Self {
+        self.pending = pending;
+        self
+    }
+
     /// accept in a loop
     pub async fn accept(&mut self) -> http_types::Result<()> {
         while ConnectionStatus::KeepAlive == self.accept_one().await? {}
@@ -126,21 +653,78 @@ where
         F: Fn(Request) -> Fut,
         Fut: Future<Output = http_types::Result<Response>>,
     {
+        if !self.reusable {
+            #[cfg(feature = "connection-debug")]
+            debug_assert!(false, "连接在请求未完整结束后被复用");
+            return Ok(ConnectionStatus::Close);
+        }
+        self.reusable = false;
+
         // 对新请求进行解码，如果解码时间超过超时持续时间，则超时。
-        let fut = decode(self.io.clone());
+        let trailing = self.after_fixed_body.then_some(self.opts.trailing_data);
+        let mut pending = std::mem::take(&mut self.pending);
+        // 停机时不再等待 keep-alive 连接上的下一个请求，已经收到的字节仍然按请求处理
+        let idle_shutdown = self
+            .shutdown
+            .clone()
+            .filter(|_| self.served > 0 && pending.is_empty());
+        let io = self.io.clone();
+        let (capture_raw_head, request_timing) =
+            (self.opts.capture_raw_head, self.opts.request_timing);
+        let target_limits = self.opts.target_limits.clone();
+        let fut = async move {
+            if let Some(shutdown) = idle_shutdown {
+                match next_bytes(io.clone(), &shutdown).await? {
+                    Some(bytes) => pending = bytes,
+                    None => return Ok(None),
+                }
+            }
+            decode_with(
+                io,
+                pending,
+                trailing,
+                capture_raw_head,
+                request_timing,
+                target_limits,
+            )
+            .await
+        };
 
-        let (req, mut body) = if let Some(timeout_duration) = self.opts.headers_timeout {
+        // keep-alive 连接上等待后续请求的时间同时受 `idle_timeout` 限制
+        let idle_timeout = self.opts.idle_timeout.filter(|_| self.served > 0);
+        let decode_timeout = match (self.opts.headers_timeout, idle_timeout) {
+            (Some(headers), Some(idle)) => Some(headers.min(idle)),
+            (headers, idle) => headers.or(idle),
+        };
+        let decoded = if let Some(timeout_duration) = decode_timeout {
             match timeout(timeout_duration, fut).await {
-                Ok(Ok(Some(r))) => r,
-                Ok(Ok(None)) | Err(TimeoutError { .. }) => return Ok(ConnectionStatus::Close), /* EOF或超时 */
-                Ok(Err(e)) => return Err(e),
+                Ok(decoded) => decoded,
+                Err(TimeoutError { .. }) => return Ok(ConnectionStatus::Close), /* 超时 */
             }
         } else {
-            match fut.await? {
-                Some(r) => r,
-                None => return Ok(ConnectionStatus::Close), /* EOF */
+            fut.await
+        };
+        let (mut req, mut body) = match decoded {
+            Ok(Some(r)) => r,
+            Ok(None) => return Ok(ConnectionStatus::Close), /* EOF */
+            // 严格模式下的多余字节和过长的请求目标回复错误后关闭连接
+            Err(e)
+                if UnexpectedTrailingData::matches(&e) || e.status() == StatusCode::UriTooLong =>
+            {
+                let mut res = Response::new(e.status());
+                res.insert_header(CONNECTION, "close");
+                let mut encoder = Encoder::new(res, Method::Get)
+                    .canonical_header_names(self.opts.canonical_header_names);
+                io::copy(&mut encoder, &mut self.io).await?;
+                return Err(e);
             }
+            Err(e) => return Err(e),
         };
+        self.served += 1;
+        let last_permitted = self
+            .opts
+            .max_requests_per_connection
+            .is_some_and(|max| self.served >= max);
 
         let has_upgrade_header = req.header(UPGRADE).is_some();
         let connection_header_as_str = req
@@ -156,9 +740,21 @@ where
         let upgrade_requested = has_upgrade_header && connection_header_is_upgrade;
 
         let method = req.method();
+        let interim = req.ext().get::<Interim>().cloned().map(InterimGuard);
+        if self.peer_addr.is_some() {
+            req.set_peer_addr(self.peer_addr.as_ref());
+        }
+        if self.local_addr.is_some() {
+            req.set_local_addr(self.local_addr.as_ref());
+        }
+        let peer_addr = req.peer_addr().map(str::to_string);
+        let local_addr = req.local_addr().map(str::to_string);
 
         // 将请求传递给endpoint并对响应进行编码
         let mut res = (self.endpoint)(req).await?;
+        if let Some(interim) = &interim {
+            interim.0.close().await;
+        }
 
         close_connection |= res
             .header(CONNECTION)
@@ -167,39 +763,140 @@ where
 
         let upgrade_provided = res.status() == StatusCode::SwitchingProtocols && res.has_upgrade();
 
+        // 发送端在所有返回路径上都会被发送或丢弃，等待升级的接收端不会一直挂起
         let upgrade_sender = if upgrade_requested && upgrade_provided {
             Some(res.send_upgrade())
         } else {
             None
         };
+        // 写出了 `101` 却没有完成升级，客户端不会再按 HTTP 发送后续请求
+        close_connection |=
+            res.status() == StatusCode::SwitchingProtocols && upgrade_sender.is_none();
+
+        // 达到请求数上限或正在停机时通知客户端不要再发送请求
+        let shutting_down = self.shutdown.as_ref().is_some_and(Shutdown::is_triggered);
+        if (last_permitted || shutting_down) && upgrade_sender.is_none() && !close_connection {
+            res.insert_header(CONNECTION, "close");
+            close_connection = true;
+        }
 
-        let mut encoder = Encoder::new(res, method);
-
-        let bytes_written = io::copy(&mut encoder, &mut self.io).await?;
+        let after_send = res
+            .ext_mut()
+            .remove::<AfterSend>()
+            .map(|after_send| after_send.sending(peer_addr, local_addr));
+        let mut encoder =
+            Encoder::new(res, method).canonical_header_names(self.opts.canonical_header_names);
+
+        let written = copy_with_yield(&mut encoder, &mut self.io, &self.opts).await;
+        // 响应已经写完或写入失败，不等待连接关闭
+        if let Some(after_send) = after_send {
+            after_send.finish(&written);
+        }
+        let bytes_written = written?;
         log::trace!("wrote {} response bytes", bytes_written);
 
-        let body_bytes_discarded = io::copy(&mut body, &mut io::sink()).await?;
+        let mut sink = io::sink();
+        let drain = io::copy(&mut body, &mut sink);
+        let body_bytes_discarded = match self.opts.body_read_timeout {
+            Some(duration) => match timeout(duration, drain).await {
+                Ok(discarded) => discarded?,
+                // 请求体没有读完，连接上的后续字节无法解析
+                Err(TimeoutError { .. }) => return Ok(ConnectionStatus::Close),
+            },
+            None => drain.await?,
+        };
         log::trace!(
             "discarded {} unread request body bytes",
             body_bytes_discarded
         );
 
+        // 请求体之后已经读取的字节属于下一个请求
+        self.after_fixed_body = matches!(body, BodyReader::Fixed(_));
+        self.pending = body.unread();
+
         if let Some(upgrade_sender) = upgrade_sender {
-            upgrade_sender.send(Connection::new(self.io.clone())).await;
+            // 客户端可能在握手之后立即发送了数据
+            let pending = std::mem::take(&mut self.pending);
+            let upgraded = Upgraded {
+                pending: io::Cursor::new(pending),
+                io: self.io.clone(),
+            };
+            upgrade_sender.send(Connection::new(upgraded)).await;
             Ok(ConnectionStatus::Close)
         } else if close_connection {
             Ok(ConnectionStatus::Close)
         } else {
+            self.reusable = true;
             Ok(ConnectionStatus::KeepAlive)
         }
     }
 }
 
+/// 协议升级后的连接，先读取升级前已经从连接读取的字节
+struct Upgraded<IO> {
+    pending: io::Cursor<Vec<u8>>,
+    io: IO,
+}
+
+impl<IO: Read + Unpin> Read for Upgraded<IO> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        if (this.pending.position() as usize) < this.pending.get_ref().len() {
+            return Pin::new(&mut this.pending).poll_read(cx, buf);
+        }
+        Pin::new(&mut this.io).poll_read(cx, buf)
+    }
+}
+
+impl<IO: Write + Unpin> Write for Upgraded<IO> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.io).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_close(cx)
+    }
+}
+
+/// 连接的读取端，先读取上一个请求留下的字节，再继续从连接读取
+pub type ConnReader<IO> = BufReader<io::Chain<io::Cursor<Vec<u8>>, IO>>;
+
 /// body_reader
 pub enum BodyReader<IO: Read + Unpin> {
-    Chunked(Arc<Mutex<ChunkedDecoder<BufReader<IO>>>>),
-    Fixed(Arc<Mutex<Take<BufReader<IO>>>>),
-    None,
+    Chunked(Arc<Mutex<ChunkedDecoder<ConnReader<IO>>>>),
+    Fixed(Arc<Mutex<Take<ConnReader<IO>>>>),
+    None(ConnReader<IO>),
+}
+
+impl<IO: Read + Unpin> BodyReader<IO> {
+    /// 已经从连接读取但不属于当前请求的字节，按到达顺序返回
+    pub fn unread(&self) -> Vec<u8> {
+        match self {
+            BodyReader::Chunked(r) => unread(r.lock().get_ref()),
+            BodyReader::Fixed(r) => unread(r.lock().get_ref()),
+            BodyReader::None(r) => unread(r),
+        }
+    }
+}
+
+fn unread<IO>(reader: &ConnReader<IO>) -> Vec<u8> {
+    let mut bytes = reader.buffer().to_vec();
+    let (pending, _) = reader.get_ref().get_ref();
+    let position = (pending.position() as usize).min(pending.get_ref().len());
+    bytes.extend_from_slice(&pending.get_ref()[position..]);
+    bytes
 }
 
 impl<IO: Read + Unpin> fmt::Debug for BodyReader<IO> {
@@ -207,7 +904,7 @@ impl<IO: Read + Unpin> fmt::Debug for BodyReader<IO> {
         match self {
             BodyReader::Chunked(_) => f.write_str("BodyReader::Chunked"),
             BodyReader::Fixed(_) => f.write_str("BodyReader::Fixed"),
-            BodyReader::None => f.write_str("BodyReader::None"),
+            BodyReader::None(_) => f.write_str("BodyReader::None"),
         }
     }
 }
@@ -221,7 +918,7 @@ impl<IO: Read + Unpin> Read for BodyReader<IO> {
         match &*self {
             BodyReader::Chunked(r) => Pin::new(&mut *r.lock()).poll_read(cx, buf),
             BodyReader::Fixed(r) => Pin::new(&mut *r.lock()).poll_read(cx, buf),
-            BodyReader::None => Poll::Ready(Ok(0)),
+            BodyReader::None(_) => Poll::Ready(Ok(0)),
         }
     }
 
@@ -296,38 +993,208 @@ impl<B: Read> Read for ReadNotifier<B> {
 }
 
 /// 解码服务器上的HTTP请求
-pub async fn decode<IO>(mut io: IO) -> http_types::Result<Option<(Request, BodyReader<IO>)>>
+pub async fn decode<IO>(io: IO) -> http_types::Result<Option<(Request, BodyReader<IO>)>>
+where
+    IO: Read + Write + Clone + Send + Sync + Unpin + 'static,
+{
+    decode_with(io, Vec::new(), None, false, false, TargetLimits::default()).await
+}
+
+/// 解码连接上的下一个请求
+///
+/// `pending` 为上一个请求之后已经读取的字节，上一个请求为定长请求体时
+/// `trailing` 决定请求头之前的 CRLF 如何处理。`capture_raw_head` 为 `true` 时
+/// 原始请求头保存在请求扩展中，`request_timing` 为 `true` 时记录请求体读取时间。
+/// 请求目标超过 `target_limits` 时返回 `414`。
+async fn decode_with<IO>(
+    io: IO,
+    pending: Vec<u8>,
+    trailing: Option<TrailingData>,
+    capture_raw_head: bool,
+    request_timing: bool,
+    target_limits: TargetLimits,
+) -> http_types::Result<Option<(Request, BodyReader<IO>)>>
 where
     IO: Read + Write + Clone + Send + Sync + Unpin + 'static,
 {
-    let mut reader = BufReader::new(io.clone());
+    let mut reader = BufReader::with_capacity(
+        READ_BUFFER_CAPACITY,
+        io::Cursor::new(pending).chain(io.clone()),
+    );
     let mut buf = Vec::new();
-    let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
-    let mut httparse_req = httparse::Request::new(&mut headers);
+    let head = match read_head(&mut reader, &mut buf, trailing, &target_limits).await {
+        Ok(false) => return Ok(None),
+        Ok(true) => parse_head(&buf),
+        Err(err) => Err(err),
+    };
+    let (mut req, content_length, chunked) = match head {
+        Ok(head) => head,
+        Err(err) if capture_raw_head => return Err(MalformedHead::wrap(err, buf)),
+        Err(err) => return Err(err),
+    };
+
+    if capture_raw_head {
+        req.ext_mut().insert(RawHead(buf));
+    }
+
+    // 建立一个通道以等待读取body, 允许我们避免在以下情况下发送100-continue
+    // 无需读取body即可响应，避免客户端上传body
+    let (body_read_sender, body_read_receiver) = async_channel::bounded(1);
+
+    let interim = Interim::new(io, body_read_sender.clone());
+    req.ext_mut().insert(interim.clone());
+
+    if Some(CONTINUE_HEADER_VALUE) == req.header(EXPECT).map(|h| h.as_str()) {
+        spawn_child(async move {
+            // /如果客户端需要100 continue标头，则生成任务等待正文上的第一次读取尝试。
+            // 最终响应已经开始写入时不再发送
+            if let Ok(()) = body_read_receiver.recv().await {
+                interim.write(CONTINUE_RESPONSE).await.ok();
+            };
+            // 请求结束时 `Interim` 关闭通道，即使请求体仍被持有，任务也会随请求一起结束
+        });
+    }
+
+    // 请求头解析完成，开始记录请求体读取时间
+    let timing = request_timing.then(|| {
+        let timing = RequestTiming::start();
+        req.ext_mut().insert(timing.clone());
+        timing
+    });
+
+    if chunked {
+        let trailer_sender = req.send_trailers();
+        let reader = ChunkedDecoder::new(reader, trailer_sender);
+        let reader = Arc::new(Mutex::new(reader));
+        let reader_clone = reader.clone();
+        let reader = ReadNotifier::new(TimedReader::new(reader, timing), body_read_sender);
+        let reader = BufReader::new(reader);
+        req.set_body(Body::from_reader(reader, None));
+        Ok(Some((req, BodyReader::Chunked(reader_clone))))
+    } else if let Some(len) = content_length {
+        let len = len.len();
+        let reader = Arc::new(Mutex::new(reader.take(len)));
+        req.set_body(Body::from_reader(
+            BufReader::new(ReadNotifier::new(
+                TimedReader::new(reader.clone(), timing),
+                body_read_sender,
+            )),
+            Some(len as usize),
+        ));
+        Ok(Some((req, BodyReader::Fixed(reader))))
+    } else {
+        Ok(Some((req, BodyReader::None(reader))))
+    }
+}
+
+/// 等待连接上的下一批字节，停机通知先到达时返回 `None`
+async fn next_bytes<IO>(mut io: IO, shutdown: &Shutdown) -> io::Result<Option<Vec<u8>>>
+where
+    IO: Read + Unpin,
+{
+    if shutdown.is_triggered() {
+        return Ok(None);
+    }
+    let mut buf = vec![0; READ_BUFFER_CAPACITY];
+    let read = Box::pin(io.read(&mut buf));
+    let stop = Box::pin(shutdown.wait());
+    let len = match futures_util::future::select(read, stop).await {
+        futures_util::future::Either::Left((len, _)) => len?,
+        futures_util::future::Either::Right(_) => return Ok(None),
+    };
+    buf.truncate(len);
+    Ok(Some(buf))
+}
+
+/// 读取请求头直到空行，字节追加到 `buf`，连接在请求开始前关闭时返回 `false`
+async fn read_head<R>(
+    reader: &mut R,
+    buf: &mut Vec<u8>,
+    trailing: Option<TrailingData>,
+    target_limits: &TargetLimits,
+) -> http_types::Result<bool>
+where
+    R: BufRead + Unpin,
+{
+    let mut trailing = trailing;
+    // 请求行的长度，读完请求行之前为 `None`
+    let mut request_line = None;
+    let max_request_line = target_limits.max() + REQUEST_LINE_OVERHEAD;
 
     // 一直从流中读取字节，直到到达流快结束的时候
     loop {
-        let bytes_read = reader.read_until(LF, &mut buf).await?;
+        let bytes_read = match request_line {
+            // 请求行最多读取到最大的上限，过长的请求目标不会完整读入内存
+            None => {
+                let remaining = (max_request_line + 1).saturating_sub(buf.len());
+                (&mut *reader)
+                    .take(remaining as u64)
+                    .read_until(LF, buf)
+                    .await?
+            }
+            Some(_) => reader.read_until(LF, buf).await?,
+        };
         // 不再从流中生成更多字节
         if bytes_read == 0 {
-            return Ok(None);
+            return Ok(false);
+        }
+
+        // 定长请求体之后、下一个请求方法之前的多余字节
+        if request_line.is_none() {
+            match trailing {
+                Some(TrailingData::Lenient) if buf == b"\r\n" => {
+                    // 最多忽略一个 CRLF
+                    trailing = None;
+                    buf.clear();
+                    continue;
+                }
+                // 请求方法是 token，第一个字节不是 token 字符时说明前面有多余字节
+                Some(TrailingData::Strict) if !buf.first().copied().is_some_and(is_token) => {
+                    return Err(http_types::Error::new(
+                        StatusCode::BadRequest,
+                        UnexpectedTrailingData,
+                    ));
+                }
+                _ => {}
+            }
         }
 
-        // 防止DDOS
+        let line_len = match request_line {
+            Some(line_len) => line_len,
+            None => {
+                http_types::ensure_status!(buf.ends_with(b"\n"), 414, "Request target is too long");
+                let target = request_target(buf);
+                http_types::ensure_status!(
+                    target.len() <= target_limits.limit_for(target),
+                    414,
+                    "Request target is too long"
+                );
+                request_line = Some(buf.len());
+                buf.len()
+            }
+        };
+
+        // 防止DDOS，请求行单独限制，请求头不包括请求行
         ensure!(
-            buf.len() < MAX_HEAD_LENGTH,
+            buf.len() - line_len < MAX_HEAD_LENGTH,
             "Head byte length should be less than 8kb"
         );
 
         // 找到了流的结束分割符
         let idx = buf.len() - 1;
         if idx >= 3 && &buf[idx - 3..=idx] == b"\r\n\r\n" {
-            break;
+            return Ok(true);
         }
     }
+}
+
+/// 解析完整的请求头，返回请求、`Content-Length` 和是否为分块传输
+fn parse_head(buf: &[u8]) -> http_types::Result<(Request, Option<ContentLength>, bool)> {
+    let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+    let mut httparse_req = httparse::Request::new(&mut headers);
 
     // 将header buf转换为httparse实例，并进行验证
-    let status = httparse_req.parse(&buf)?;
+    let status = httparse_req.parse(buf)?;
 
     ensure!(!status.is_partial(), "Malformed HTTP head");
 
@@ -368,43 +1235,43 @@ where
         "Unexpected Content-Length header"
     );
 
-    // 建立一个通道以等待读取body, 允许我们避免在以下情况下发送100-continue
-    // 无需读取body即可响应，避免客户端上传body
-    let (body_read_sender, body_read_receiver) = async_channel::bounded(1);
-
-    if Some(CONTINUE_HEADER_VALUE) == req.header(EXPECT).map(|h| h.as_str()) {
-        task::spawn(async move {
-            // /如果客户端需要100 continue标头，则生成任务等待正文上的第一次读取尝试。
-            if let Ok(()) = body_read_receiver.recv().await {
-                io.write_all(CONTINUE_RESPONSE).await.ok();
-            };
-            // 由于发件方已移动到body中，因此此任务将 在客户端断开连接时完成，无论发送了100-continue
-        });
-    }
-
     // 检查传输编码
-    if transfer_encoding
+    let chunked = transfer_encoding
         .map(|te| te.as_str().eq_ignore_ascii_case("chunked"))
-        .unwrap_or(false)
-    {
-        let trailer_sender = req.send_trailers();
-        let reader = ChunkedDecoder::new(reader, trailer_sender);
-        let reader = Arc::new(Mutex::new(reader));
-        let reader_clone = reader.clone();
-        let reader = ReadNotifier::new(reader, body_read_sender);
-        let reader = BufReader::new(reader);
-        req.set_body(Body::from_reader(reader, None));
-        Ok(Some((req, BodyReader::Chunked(reader_clone))))
-    } else if let Some(len) = content_length {
-        let len = len.len();
-        let reader = Arc::new(Mutex::new(reader.take(len)));
-        req.set_body(Body::from_reader(
-            BufReader::new(ReadNotifier::new(reader.clone(), body_read_sender)),
-            Some(len as usize),
-        ));
-        Ok(Some((req, BodyReader::Fixed(reader))))
-    } else {
-        Ok(Some((req, BodyReader::None)))
+        .unwrap_or(false);
+
+    Ok((req, content_length, chunked))
+}
+
+/// 派生属于当前请求的子任务，测试中统计尚未结束的子任务
+fn spawn_child<F>(future: F)
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    #[cfg(test)]
+    let future = {
+        let live = test::LiveChild::new();
+        async move {
+            let _live = live;
+            future.await
+        }
+    };
+    task::spawn(future);
+}
+
+/// 请求行中的请求目标，即第一个和最后一个空格之间的部分
+fn request_target(line: &[u8]) -> &[u8] {
+    let end = line
+        .iter()
+        .rposition(|b| !b.is_ascii_whitespace())
+        .map_or(0, |i| i + 1);
+    let line = &line[..end];
+    match (
+        line.iter().position(|&b| b == b' '),
+        line.iter().rposition(|&b| b == b' '),
+    ) {
+        (Some(start), Some(end)) if start < end => &line[start + 1..end],
+        _ => line,
     }
 }
 
@@ -430,3 +1297,668 @@ fn url_from_httparse_req(req: &httparse::Request<'_, '_>) -> http_types::Result<
         Err(format_err!("unexpected uri format"))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// 在当前的单线程执行器上并发运行一个大响应和 100 个小请求，返回小请求的 p99 延迟
+    async fn small_request_p99(opts: ServerOptions) -> Duration {
+        let start = Instant::now();
+        let big = {
+            let opts = opts.clone();
+            tokio::spawn(async move {
+                let mut reader = io::repeat(1).take(64 * 1024 * 1024);
+                let written = copy_with_yield(&mut reader, &mut io::sink(), &opts)
+                    .await
+                    .unwrap();
+                assert_eq!(written, 64 * 1024 * 1024);
+            })
+        };
+        let small: Vec<_> = (0..100)
+            .map(|_| {
+                let opts = opts.clone();
+                tokio::spawn(async move {
+                    let mut reader = io::Cursor::new(vec![1; 128]);
+                    copy_with_yield(&mut reader, &mut io::sink(), &opts)
+                        .await
+                        .unwrap();
+                    start.elapsed()
+                })
+            })
+            .collect();
+
+        big.await.unwrap();
+        let mut latencies: Vec<Duration> = futures_util::future::join_all(small)
+            .await
+            .into_iter()
+            .map(Result::unwrap)
+            .collect();
+        latencies.sort();
+        latencies[latencies.len() * 99 / 100 - 1]
+    }
+
+    /// 内存中的连接，读取预先写好的请求，记录写出的响应
+    #[derive(Clone)]
+    struct TestIo {
+        input: std::sync::Arc<std::sync::Mutex<std::io::Cursor<Vec<u8>>>>,
+        output: std::sync::Arc<std::sync::Mutex<Vec<u8>>>,
+        /// 输入读完后不返回 EOF，模拟保持连接但不再发送数据的客户端
+        stall: bool,
+        /// 写入时返回错误，模拟已经断开的客户端
+        broken: bool,
+    }
+
+    impl TestIo {
+        fn new(input: &[u8]) -> Self {
+            Self {
+                input: std::sync::Arc::new(std::sync::Mutex::new(std::io::Cursor::new(
+                    input.to_vec(),
+                ))),
+                output: Default::default(),
+                stall: false,
+                broken: false,
+            }
+        }
+
+        fn broken(input: &[u8]) -> Self {
+            Self {
+                broken: true,
+                ..Self::new(input)
+            }
+        }
+
+        fn stalling(input: &[u8]) -> Self {
+            Self {
+                stall: true,
+                ..Self::new(input)
+            }
+        }
+
+        fn output(&self) -> String {
+            String::from_utf8(self.output.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    impl Read for TestIo {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            match std::io::Read::read(&mut *self.input.lock().unwrap(), buf) {
+                Ok(0) if self.stall && !buf.is_empty() => Poll::Pending,
+                read => Poll::Ready(read),
+            }
+        }
+    }
+
+    impl Write for TestIo {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            if self.broken {
+                return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()));
+            }
+            self.output.lock().unwrap().extend_from_slice(buf);
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    const POST_THEN_CRLF_THEN_GET: &[u8] = b"POST /submit HTTP/1.1\r\nHost: example.com\r\nContent-Length: 5\r\n\r\nhello\r\nGET /next HTTP/1.1\r\nHost: example.com\r\n\r\n";
+
+    /// 处理连接上的所有请求，返回每个请求的方法、路径和请求体
+    async fn serve(io: TestIo, opts: ServerOptions) -> (Vec<String>, http_types::Result<()>) {
+        let seen = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let result = accept_with_opts(
+            io,
+            |mut req| {
+                let seen = seen.clone();
+                async move {
+                    let body = req.body_string().await?;
+                    seen.lock().unwrap().push(format!(
+                        "{} {} {:?}",
+                        req.method(),
+                        req.url().path(),
+                        body
+                    ));
+                    Ok(Response::new(StatusCode::Ok))
+                }
+            },
+            opts,
+        )
+        .await;
+        let seen = seen.lock().unwrap().clone();
+        (seen, result)
+    }
+
+    #[async_std::test]
+    async fn closes_after_max_requests() {
+        let get = "GET / HTTP/1.1\r\nHost: example.com\r\n\r\n".repeat(3);
+        let io = TestIo::new(get.as_bytes());
+        let (seen, result) = serve(
+            io.clone(),
+            ServerOptions::default().max_requests_per_connection(2),
+        )
+        .await;
+        result.unwrap();
+        assert_eq!(seen.len(), 2);
+        let output = io.output();
+        let responses: Vec<&str> = output.split("HTTP/1.1 200 OK").skip(1).collect();
+        assert_eq!(responses.len(), 2);
+        assert!(!responses[0].contains("connection: close"));
+        assert!(responses[1].contains("connection: close"));
+    }
+
+    #[async_std::test]
+    async fn closes_idle_keep_alive_connection() {
+        let io = TestIo::stalling(b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n");
+        let opts = ServerOptions::default().idle_timeout(Duration::from_millis(50));
+        let (seen, result) = timeout(Duration::from_secs(5), serve(io.clone(), opts))
+            .await
+            .expect("idle connection was not closed");
+        result.unwrap();
+        assert_eq!(seen, ["GET / \"\""]);
+        assert_eq!(io.output().matches("HTTP/1.1 200 OK").count(), 1);
+    }
+
+    #[async_std::test]
+    async fn closes_when_body_drain_times_out() {
+        // 声明 10 字节的请求体但只发送 2 字节，endpoint 不读取请求体
+        let io = TestIo::stalling(
+            b"POST / HTTP/1.1\r\nHost: example.com\r\nContent-Length: 10\r\n\r\nab",
+        );
+        let opts = ServerOptions::default().body_read_timeout(Duration::from_millis(50));
+        let result = timeout(
+            Duration::from_secs(5),
+            accept_with_opts(
+                io.clone(),
+                |_| async { Ok(Response::new(StatusCode::Accepted)) },
+                opts,
+            ),
+        )
+        .await
+        .expect("body drain did not time out");
+        result.unwrap();
+        assert_eq!(io.output().matches("HTTP/1.1 202 Accepted").count(), 1);
+    }
+
+    #[async_std::test]
+    async fn pipelined_requests_keep_their_bytes() {
+        let io = TestIo::new(b"POST /a HTTP/1.1\r\nHost: example.com\r\nContent-Length: 3\r\n\r\nabcGET /b HTTP/1.1\r\nHost: example.com\r\n\r\nGET /c HTTP/1.1\r\nHost: example.com\r\n\r\n");
+        let (seen, result) = serve(io.clone(), ServerOptions::default()).await;
+        result.unwrap();
+        assert_eq!(seen, ["POST /a \"abc\"", "GET /b \"\"", "GET /c \"\""]);
+        assert_eq!(io.output().matches("HTTP/1.1 200 OK").count(), 3);
+    }
+
+    #[async_std::test]
+    async fn pipelined_flood_is_read_incrementally() {
+        const GET: &[u8] = b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n";
+        let io = TestIo::new(&GET.repeat(1000));
+        let input = io.input.clone();
+        let read_ahead = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let served = read_ahead.clone();
+        accept(io.clone(), move |_| {
+            let consumed = input.lock().unwrap().position() as usize;
+            let mut served = served.lock().unwrap();
+            // 当前请求结束之后已经从连接读取的字节
+            let current_end = (served.len() + 1) * GET.len();
+            served.push(consumed.saturating_sub(current_end));
+            async { Ok(Response::new(StatusCode::Ok)) }
+        })
+        .await
+        .unwrap();
+
+        let read_ahead = read_ahead.lock().unwrap();
+        assert_eq!(read_ahead.len(), 1000);
+        assert!(read_ahead.iter().all(|&n| n <= READ_BUFFER_CAPACITY));
+        assert_eq!(io.output().matches("HTTP/1.1 200 OK").count(), 1000);
+    }
+
+    #[async_std::test]
+    async fn lenient_skips_crlf_after_body() {
+        let io = TestIo::new(POST_THEN_CRLF_THEN_GET);
+        let (seen, result) = serve(io.clone(), ServerOptions::default()).await;
+        result.unwrap();
+        assert_eq!(seen, ["POST /submit \"hello\"", "GET /next \"\""]);
+        assert_eq!(io.output().matches("HTTP/1.1 200 OK").count(), 2);
+    }
+
+    #[async_std::test]
+    async fn strict_rejects_any_bytes_before_the_method() {
+        for stray in [" ", "\t", "\n", "\0", "\r\n\r\n"] {
+            let input = format!(
+                "POST /submit HTTP/1.1\r\nHost: example.com\r\nContent-Length: 5\r\n\r\nhello{}GET /next HTTP/1.1\r\nHost: example.com\r\n\r\n",
+                stray
+            );
+            let io = TestIo::new(input.as_bytes());
+            let opts = ServerOptions::default().trailing_data(TrailingData::Strict);
+            let (seen, result) = serve(io.clone(), opts).await;
+            assert_eq!(seen, ["POST /submit \"hello\""], "{:?}", stray);
+            assert_eq!(result.unwrap_err().status(), StatusCode::BadRequest);
+            assert!(io.output().contains("HTTP/1.1 400 Bad Request\r\n"));
+        }
+
+        // 下一个请求紧跟在请求体之后时正常处理
+        let io = TestIo::new(b"POST /submit HTTP/1.1\r\nHost: example.com\r\nContent-Length: 5\r\n\r\nhelloGET /next HTTP/1.1\r\nHost: example.com\r\n\r\n");
+        let opts = ServerOptions::default().trailing_data(TrailingData::Strict);
+        let (seen, result) = serve(io, opts).await;
+        result.unwrap();
+        assert_eq!(seen, ["POST /submit \"hello\"", "GET /next \"\""]);
+    }
+
+    #[async_std::test]
+    async fn strict_rejects_crlf_after_body() {
+        let io = TestIo::new(POST_THEN_CRLF_THEN_GET);
+        let opts = ServerOptions::default().trailing_data(TrailingData::Strict);
+        let (seen, result) = serve(io.clone(), opts).await;
+        assert_eq!(result.unwrap_err().status(), StatusCode::BadRequest);
+        assert_eq!(seen, ["POST /submit \"hello\""]);
+        let output = io.output();
+        assert!(output.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(output.contains("HTTP/1.1 400 Bad Request\r\n"));
+        assert!(output.contains("connection: close\r\n"));
+    }
+
+    fn signed_get(path: &str, signature_len: usize) -> String {
+        format!(
+            "GET {}?sig={} HTTP/1.1\r\nHost: example.com\r\n\r\n",
+            path,
+            "a".repeat(signature_len)
+        )
+    }
+
+    #[async_std::test]
+    async fn long_targets_need_an_exempt_prefix() {
+        let opts = || {
+            ServerOptions::default()
+                .max_target_length(256)
+                .target_exemptions(vec![("/downloads/".to_string(), 4096)])
+        };
+
+        let io = TestIo::new(signed_get("/downloads/report.pdf", 2000).as_bytes());
+        let (seen, result) = serve(io.clone(), opts()).await;
+        result.unwrap();
+        assert_eq!(seen, ["GET /downloads/report.pdf \"\""]);
+
+        for path in ["/other/report.pdf", "/downloadsx/report.pdf"] {
+            let io = TestIo::new(signed_get(path, 2000).as_bytes());
+            let (seen, result) = serve(io.clone(), opts()).await;
+            assert_eq!(result.unwrap_err().status(), StatusCode::UriTooLong);
+            assert!(seen.is_empty());
+            let output = io.output();
+            assert!(
+                output.starts_with("HTTP/1.1 414 URI Too Long\r\n"),
+                "{}",
+                output
+            );
+            assert!(output.contains("connection: close\r\n"));
+        }
+
+        // 超过所有上限的请求行不会完整读入
+        let io = TestIo::new(signed_get("/downloads/report.pdf", 1 << 20).as_bytes());
+        let input = io.input.clone();
+        let (_, result) = serve(io, opts()).await;
+        assert_eq!(result.unwrap_err().status(), StatusCode::UriTooLong);
+        assert!(input.lock().unwrap().position() < 16 * 1024);
+    }
+
+    #[test]
+    fn matches_target_prefixes() {
+        let limits = ServerOptions::default()
+            .target_exemptions(vec![("/a".to_string(), 10), ("/a/b/".to_string(), 20)])
+            .target_limits;
+        assert_eq!(limits.limit_for(b"/a"), 10);
+        assert_eq!(limits.limit_for(b"/a?x=1"), 10);
+        assert_eq!(limits.limit_for(b"/a/b/c"), 20);
+        assert_eq!(limits.limit_for(b"http://example.com/a/b/c"), 20);
+        assert_eq!(limits.limit_for(b"/ab"), DEFAULT_MAX_TARGET_LENGTH);
+        assert_eq!(limits.max(), DEFAULT_MAX_TARGET_LENGTH);
+    }
+
+    #[async_std::test]
+    async fn captures_raw_head_when_enabled() {
+        const REQUEST: &[u8] = b"GET /a HTTP/1.1\r\nHost: example.com\r\nX-Odd:  spaced \r\n\r\n";
+        for enabled in [false, true] {
+            let (sender, receiver) = async_channel::unbounded();
+            let opts = ServerOptions::default().capture_raw_head(enabled);
+            accept_with_opts(
+                TestIo::new(REQUEST),
+                |req| {
+                    let sender = sender.clone();
+                    async move {
+                        let raw = req.ext().get::<RawHead>().map(|head| head.0.clone());
+                        sender.send(raw).await.unwrap();
+                        Ok(Response::new(StatusCode::Ok))
+                    }
+                },
+                opts,
+            )
+            .await
+            .unwrap();
+            let raw = receiver.recv().await.unwrap();
+            assert_eq!(raw.as_deref(), enabled.then_some(REQUEST));
+        }
+    }
+
+    #[async_std::test]
+    async fn captures_raw_head_of_malformed_requests() {
+        const REQUEST: &[u8] =
+            b"GET /a HTTP/1.1\r\nHost: example.com\r\nContent-Length: 1\r\nTransfer-Encoding: chunked\r\n\r\n";
+        for enabled in [false, true] {
+            let io = TestIo::new(REQUEST);
+            let opts = ServerOptions::default().capture_raw_head(enabled);
+            let error = accept_with_opts(
+                io.clone(),
+                |_| async { Ok(Response::new(StatusCode::Ok)) },
+                opts,
+            )
+            .await
+            .unwrap_err();
+            assert_eq!(error.status(), StatusCode::BadRequest);
+            assert_eq!(error.to_string(), "Unexpected Content-Length header");
+            let raw = error
+                .downcast_ref::<MalformedHead>()
+                .map(MalformedHead::raw);
+            assert_eq!(raw, enabled.then_some(REQUEST));
+        }
+    }
+
+    #[async_std::test]
+    async fn records_body_timing_when_enabled() {
+        const REQUEST: &[u8] =
+            b"POST /a HTTP/1.1\r\nHost: example.com\r\nContent-Length: 5\r\n\r\nhello";
+        for enabled in [false, true] {
+            let (sender, receiver) = async_channel::unbounded();
+            let opts = ServerOptions::default().request_timing(enabled);
+            accept_with_opts(
+                TestIo::new(REQUEST),
+                |mut req| {
+                    let sender = sender.clone();
+                    async move {
+                        req.body_string().await?;
+                        let bytes = req.ext().get::<RequestTiming>().map(|t| t.bytes_read());
+                        sender.send(bytes).await.unwrap();
+                        Ok(Response::new(StatusCode::Ok))
+                    }
+                },
+                opts,
+            )
+            .await
+            .unwrap();
+            let bytes = receiver.recv().await.unwrap();
+            assert_eq!(bytes, enabled.then_some(5));
+        }
+    }
+
+    #[async_std::test]
+    async fn interim_responses_precede_final_response() {
+        let io = TestIo::new(b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n");
+        let (sender, receiver) = async_channel::unbounded();
+        accept(io.clone(), |req| {
+            let sender = sender.clone();
+            async move {
+                let interim = req.ext().get::<Interim>().unwrap().clone();
+                interim.processing().await?;
+                interim
+                    .early_hints(&[
+                        "</style.css>; rel=preload; as=style",
+                        "</app.js>; rel=preload",
+                    ])
+                    .await?;
+                sender.send(interim).await.unwrap();
+                Ok(Response::new(StatusCode::Ok))
+            }
+        })
+        .await
+        .unwrap();
+
+        let output = io.output();
+        assert!(
+            output.starts_with(
+                "HTTP/1.1 102 Processing\r\n\r\n\
+                 HTTP/1.1 103 Early Hints\r\n\
+                 Link: </style.css>; rel=preload; as=style\r\n\
+                 Link: </app.js>; rel=preload\r\n\r\n\
+                 HTTP/1.1 200 OK\r\n"
+            ),
+            "{:?}",
+            output
+        );
+
+        // 最终响应之后不能再发送
+        let interim = receiver.recv().await.unwrap();
+        assert!(interim.processing().await.is_err());
+    }
+
+    #[async_std::test]
+    async fn early_hints_reject_header_injection() {
+        let io = TestIo::new(b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n");
+        accept(io.clone(), |req| async move {
+            let interim = req.ext().get::<Interim>().unwrap();
+            for link in [
+                "</a.css>\r\nSet-Cookie: a=b",
+                "</a.css>\n\r\nHTTP/1.1 200 OK",
+            ] {
+                let error = interim
+                    .early_hints(&["</ok.css>; rel=preload", link])
+                    .await
+                    .unwrap_err();
+                assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+            }
+            Ok(Response::new(StatusCode::Ok))
+        })
+        .await
+        .unwrap();
+        assert!(io.output().starts_with("HTTP/1.1 200 OK\r\n"));
+    }
+
+    #[async_std::test]
+    async fn after_send_hooks_run_after_response() {
+        let io = TestIo::new(b"GET /first HTTP/1.1\r\nHost: example.com\r\n\r\nGET /second HTTP/1.1\r\nHost: example.com\r\n\r\n");
+        let (sender, receiver) = async_channel::unbounded();
+        let conn = io.clone();
+        accept(io.clone(), |req| {
+            let sender = sender.clone();
+            let conn = conn.clone();
+            async move {
+                let mut res = crate::Response::new(StatusCode::Ok);
+                res.set_body(format!("body of {}", req.url().path()));
+                if req.url().path() == "/first" {
+                    res.after_send(|_| async move {
+                        sender.send(conn.output()).await.unwrap();
+                    });
+                    res.after_send(|_| async { panic!("hook failed") });
+                }
+                Ok(res.into())
+            }
+        })
+        .await
+        .unwrap();
+
+        // 任务运行时客户端已经收到完整的第一个响应
+        let seen = receiver.recv().await.unwrap();
+        assert!(seen.contains("\r\n\r\nbody of /first"), "{:?}", seen);
+
+        // panic 的任务不影响同一连接上的下一个请求
+        let output = io.output();
+        assert_eq!(output.matches("HTTP/1.1 200 OK").count(), 2);
+        assert!(output.ends_with("body of /second"));
+    }
+
+    #[async_std::test]
+    async fn after_send_hooks_see_write_failures_and_addresses() {
+        let io = TestIo::broken(b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n");
+        let (sender, receiver) = async_channel::unbounded();
+        let result = Server::new(io, |req| {
+            let sender = sender.clone();
+            async move {
+                assert_eq!(req.peer_addr(), Some("203.0.113.7:4000"));
+                let mut res = crate::Response::new(StatusCode::Ok);
+                res.after_send(|cx| async move {
+                    let kind = cx.error().map(io::Error::kind);
+                    let addrs = (
+                        cx.peer_addr().map(str::to_string),
+                        cx.local_addr().map(str::to_string),
+                    );
+                    sender.send((cx.is_sent(), kind, addrs)).await.unwrap();
+                });
+                Ok(res.into())
+            }
+        })
+        .with_addrs(Some("203.0.113.7:4000"), Some("10.0.0.1:80"))
+        .accept_one()
+        .await;
+        assert!(result.is_err());
+
+        let (sent, kind, (peer, local)) = receiver.recv().await.unwrap();
+        assert!(!sent);
+        assert_eq!(kind, Some(io::ErrorKind::BrokenPipe));
+        assert_eq!(peer.as_deref(), Some("203.0.113.7:4000"));
+        assert_eq!(local.as_deref(), Some("10.0.0.1:80"));
+    }
+
+    static LIVE_CHILDREN: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+    /// 存活期间计入 [`LIVE_CHILDREN`]，由 [`spawn_child`] 包装在子任务中
+    pub(super) struct LiveChild;
+
+    impl LiveChild {
+        pub(super) fn new() -> Self {
+            LIVE_CHILDREN.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            LiveChild
+        }
+    }
+
+    impl Drop for LiveChild {
+        fn drop(&mut self) {
+            LIVE_CHILDREN.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+        }
+    }
+
+    /// 等待所有子任务结束，超时返回仍存活的数量
+    async fn live_children() -> usize {
+        for _ in 0..100 {
+            if LIVE_CHILDREN.load(std::sync::atomic::Ordering::SeqCst) == 0 {
+                return 0;
+            }
+            task::sleep(Duration::from_millis(10)).await;
+        }
+        LIVE_CHILDREN.load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    const EXPECT_CONTINUE: &[u8] = b"POST /upload HTTP/1.1\r\nHost: example.com\r\nExpect: 100-continue\r\nContent-Length: 5\r\n\r\nhello";
+
+    // 只有这个测试会派生子任务，各阶段依次执行
+    #[async_std::test]
+    async fn continue_task_ends_with_request() {
+        // endpoint 持有请求体但不读取
+        let io = TestIo::new(EXPECT_CONTINUE);
+        let stash = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let status = Server::new(io.clone(), |req| {
+            let stash = stash.clone();
+            async move {
+                stash.lock().unwrap().push(req);
+                Ok(Response::new(StatusCode::Ok))
+            }
+        })
+        .accept_one()
+        .await
+        .unwrap();
+        assert_eq!(status, ConnectionStatus::KeepAlive);
+        assert_eq!(live_children().await, 0);
+        assert!(!io.output().contains("100 Continue"));
+
+        // endpoint 出错
+        let io = TestIo::new(EXPECT_CONTINUE);
+        Server::new(io, |_| async {
+            Err::<Response, _>(format_err!("endpoint failed"))
+        })
+        .accept_one()
+        .await
+        .unwrap_err();
+        assert_eq!(live_children().await, 0);
+
+        // endpoint 还没返回时 `accept_one` 被取消
+        let io = TestIo::new(EXPECT_CONTINUE);
+        let mut server = Server::new(io, |_| async_std::future::pending());
+        timeout(Duration::from_millis(50), server.accept_one())
+            .await
+            .unwrap_err();
+        assert_eq!(live_children().await, 0);
+
+        // 读取请求体时发送 100 Continue
+        let io = TestIo::new(EXPECT_CONTINUE);
+        Server::new(io.clone(), |mut req| async move {
+            req.body_string().await?;
+            Ok(Response::new(StatusCode::Ok))
+        })
+        .accept_one()
+        .await
+        .unwrap();
+        assert_eq!(live_children().await, 0);
+        assert!(io
+            .output()
+            .starts_with("HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 200 OK"));
+    }
+
+    #[cfg(not(feature = "connection-debug"))]
+    #[async_std::test]
+    async fn refuses_reuse_after_unclean_end() {
+        let get = "GET / HTTP/1.1\r\nHost: example.com\r\n\r\n".repeat(2);
+        let calls = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+
+        // 取消之后连接上的字节停在请求中间
+        let mut server = Server::new(TestIo::new(get.as_bytes()), |_| {
+            calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            async_std::future::pending()
+        });
+        timeout(Duration::from_millis(50), server.accept_one())
+            .await
+            .unwrap_err();
+        assert_eq!(server.accept_one().await.unwrap(), ConnectionStatus::Close);
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        let mut server = Server::new(TestIo::new(get.as_bytes()), |_| async {
+            Err::<Response, _>(format_err!("endpoint failed"))
+        });
+        server.accept_one().await.unwrap_err();
+        assert_eq!(server.accept_one().await.unwrap(), ConnectionStatus::Close);
+    }
+
+    #[async_std::test]
+    async fn closes_after_switching_protocols_without_upgrade() {
+        let get = "GET / HTTP/1.1\r\nHost: example.com\r\n\r\n".repeat(2);
+        let io = TestIo::new(get.as_bytes());
+        accept(io.clone(), |_| async {
+            Ok(Response::new(StatusCode::SwitchingProtocols))
+        })
+        .await
+        .unwrap();
+        assert_eq!(io.output().matches("HTTP/1.1 101").count(), 1);
+    }
+
+    // 只有一个工作线程，大响应不让出时小请求只能等它写完
+    #[tokio::test(flavor = "current_thread")]
+    async fn yielding_improves_fairness() {
+        let starved = small_request_p99(ServerOptions::default()).await;
+        let fair = small_request_p99(ServerOptions::default().yield_after_bytes(64 * 1024)).await;
+        assert!(
+            fair * 10 < starved,
+            "p99 without yielding {:?}, with yielding {:?}",
+            starved,
+            fair
+        );
+    }
+}
@@ -1,7 +1,8 @@
 pub mod http;
 // 其他为hhtp 私有处理
+pub(crate) mod after_send;
 mod body_encoder;
 mod body_reader;
-mod date;
+pub(crate) mod date;
 mod decode;
-mod encode;
+pub(crate) mod encode;
@@ -1,25 +1,54 @@
+#[cfg(feature = "actuator")]
+pub mod actuator;
 pub mod common;
+pub mod compat;
+#[cfg(feature = "autoconfigure")]
+pub mod config;
 pub mod log;
 
 mod context;
+#[cfg(feature = "distributed-routes")]
+pub mod contributed;
+#[cfg(feature = "cookies")]
+pub mod cookies;
 mod gateway;
 mod http1;
 mod server;
+pub mod sse;
 mod tcp;
+#[cfg(feature = "autoconfigure")]
+pub mod testing;
+#[cfg(all(unix, feature = "upgrade"))]
+pub mod upgrade;
 pub mod utils;
+pub mod ws;
 
 pub use http1::http;
 pub use utils::middleware::{Middleware, Next};
-pub use utils::request::Request;
+pub use utils::redirect::Redirect;
+pub use utils::request::{PathParam, Request};
 pub use utils::response::Response;
 pub use utils::response_builder::ResponseBuilder;
 pub use utils::util;
 
+pub use context::component::{inject, Component, ComponentRegistry};
+pub use context::serve_dir::{ServeDir, ServeDirOptions};
+pub use context::serve_embedded::{EmbeddedAsset, EmbeddedDir, ServeEmbedded};
+pub use context::serve_file::ServeFile;
+pub use gateway::method_router::{method_router, MethodRouter};
 pub use gateway::route::Route;
+pub use gateway::router::RouteInfo;
 pub use http_types::{self, Body, Error, Status, StatusCode};
 pub use server::endpoint::Endpoint;
+pub use server::recovery::{ErrorContext, RequestInfo};
 
-use server::server::Server;
+pub use server::server::Server;
+#[cfg(unix)]
+pub use tcp::UnixListener;
+pub use tcp::{
+    ConcurrentListener, FailoverListener, FailurePolicy, ListenerError, RestartPolicy, Sniffing,
+    TcpListener,
+};
 
 #[must_use]
 pub fn new() -> Server<()> {
@@ -54,6 +83,9 @@ macro_rules! macro_reexport {
 }
 
 macro_reexport!(auto_scan);
+macro_reexport!(component);
+#[cfg(feature = "distributed-routes")]
+pub use summer_boot_macro::contribute;
 macro_reexport!(main);
 macro_reexport!(post);
 macro_reexport!(get);
@@ -64,3 +96,4 @@ macro_reexport!(options);
 macro_reexport!(connect);
 macro_reexport!(patch);
 macro_reexport!(trace);
+macro_reexport!(route);
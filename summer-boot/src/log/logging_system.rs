@@ -1,19 +1,117 @@
-use crate::log;
-use crate::{Middleware, Next, Request};
+use super::observer::{Observers, RequestObservation};
+use crate::log::{self, sanitize, Level};
+use crate::utils::request_id::RequestId;
+use crate::utils::request_timing::RequestTiming;
+use crate::utils::retry_after::RetryAfter;
+use crate::{Middleware, Next, Request, StatusCode};
+
+use std::fmt;
+use std::io::Write;
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// 等待写入的访问日志最多缓存的行数，写入跟不上时丢弃新的记录
+const ACCESS_LOG_QUEUE: usize = 4096;
 
 /// 记录所有传入的请求和响应
 ///
-/// 此中间件在Summer Boot中默认启用
+/// 此中间件在Summer Boot中默认启用，通过 [`Server::logging`](crate::Server::logging)
+/// 使用修改过配置的实例替换默认实例。
 ///
-/// # Examples
+/// 只记录慢请求，客户端错误和服务端错误始终记录：
 ///
 /// ```
+/// use std::time::Duration;
+///
+/// let mut app = summer_boot::new();
+/// app.logging(summer_boot::log::LoggingSystem::new().slow_threshold(Duration::from_millis(500)));
+/// ```
+///
+/// 将客户端错误降级为 `Info`，避免公开接口产生大量警告日志：
+///
+/// ```
+/// use summer_boot::log::{Level, LoggingSystem};
+///
 /// let mut app = summer_boot::new();
-/// app.with(summer_boot::log::LoggingSystem::new());
+/// app.logging(LoggingSystem::new().with_client_error_level(Level::Info));
 /// ```
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Clone)]
 pub struct LoggingSystem {
-    _priv: (),
+    slow_threshold: Option<Duration>,
+    filter_client_errors: bool,
+    success_level: Level,
+    client_error_level: Level,
+    server_error_level: Level,
+    duration_buckets: Vec<Duration>,
+    stall_threshold: Option<Duration>,
+    access_log: Option<AccessLog>,
+}
+
+/// 访问日志的输出，由后台线程写入，磁盘较慢时不会阻塞处理请求的任务
+#[derive(Clone)]
+struct AccessLog(SyncSender<String>);
+
+impl AccessLog {
+    fn spawn(writer: Box<dyn Write + Send>) -> Self {
+        let (sender, receiver) = mpsc::sync_channel(ACCESS_LOG_QUEUE);
+        thread::Builder::new()
+            .name("summer-boot-access-log".to_string())
+            .spawn(move || write_lines(writer, receiver))
+            .expect("无法启动访问日志线程");
+        Self(sender)
+    }
+
+    fn send(&self, line: String) {
+        match self.0.try_send(line) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => log::warn!("Access log queue is full, line dropped"),
+            Err(TrySendError::Disconnected(_)) => log::error!("Access log writer has stopped"),
+        }
+    }
+}
+
+/// 写入收到的每一行，队列中已有的行写完后再统一 flush
+fn write_lines(mut writer: Box<dyn Write + Send>, receiver: Receiver<String>) {
+    while let Ok(line) = receiver.recv() {
+        let result = writeln!(writer, "{}", line)
+            .and_then(|_| {
+                receiver
+                    .try_iter()
+                    .try_for_each(|line| writeln!(writer, "{}", line))
+            })
+            .and_then(|_| writer.flush());
+        if let Err(error) = result {
+            log::error!("Failed to write access log", { error: error.to_string() });
+        }
+    }
+}
+
+/// logfmt 的值，加上引号并转义 `"` 和 `\`，值中的空格和 `key=value` 不会被解析成新的字段
+fn quoted(value: impl fmt::Display) -> String {
+    let value = value.to_string();
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        if c == '"' || c == '\\' {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+impl fmt::Debug for AccessLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("AccessLog")
+    }
+}
+
+impl Default for LoggingSystem {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 struct LoggingSystemHasBeenRun;
@@ -22,7 +120,143 @@ impl LoggingSystem {
     /// Create a new instance of `LogMiddleware`.
     #[must_use]
     pub fn new() -> Self {
-        Self { _priv: () }
+        Self {
+            slow_threshold: None,
+            filter_client_errors: false,
+            success_level: Level::Info,
+            client_error_level: Level::Warn,
+            server_error_level: Level::Error,
+            duration_buckets: Vec::new(),
+            stall_threshold: None,
+            access_log: None,
+        }
+    }
+
+    /// 设置 1xx、2xx 和 3xx 响应的日志级别，默认为 `Info`
+    #[must_use]
+    pub fn with_success_level(mut self, level: Level) -> Self {
+        self.success_level = level;
+        self
+    }
+
+    /// 设置 4xx 响应的日志级别，默认为 `Warn`
+    #[must_use]
+    pub fn with_client_error_level(mut self, level: Level) -> Self {
+        self.client_error_level = level;
+        self
+    }
+
+    /// 设置 5xx 响应的日志级别，默认为 `Error`
+    #[must_use]
+    pub fn with_server_error_level(mut self, level: Level) -> Self {
+        self.server_error_level = level;
+        self
+    }
+
+    /// 只记录耗时超过 `threshold` 的请求，客户端错误和服务端错误不受影响。
+    #[must_use]
+    pub fn slow_threshold(mut self, threshold: Duration) -> Self {
+        self.slow_threshold = Some(threshold);
+        self
+    }
+
+    /// 4xx 响应同样按 [`slow_threshold`](Self::slow_threshold) 过滤，默认关闭
+    ///
+    /// 适合公开接口上大量的 404 等客户端错误，服务端错误仍然始终记录。
+    #[must_use]
+    pub fn filter_client_errors(mut self, filter: bool) -> Self {
+        self.filter_client_errors = filter;
+        self
+    }
+
+    /// 按耗时边界分桶，响应日志中的 `bucket` 为第一个不小于耗时的边界毫秒数，
+    /// 超过所有边界时为 `+Inf`
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use summer_boot::log::LoggingSystem;
+    ///
+    /// let logging = LoggingSystem::new().with_duration_buckets([
+    ///     Duration::from_millis(10),
+    ///     Duration::from_millis(100),
+    ///     Duration::from_secs(1),
+    /// ]);
+    /// ```
+    #[must_use]
+    pub fn with_duration_buckets(mut self, buckets: impl IntoIterator<Item = Duration>) -> Self {
+        self.duration_buckets = buckets.into_iter().collect();
+        self.duration_buckets.sort();
+        self.duration_buckets.dedup();
+        self
+    }
+
+    /// 读取请求体时等待客户端超过 `threshold` 的请求记录一条警告
+    ///
+    /// 需要通过 [`ServerOptions::request_timing`](crate::http::ServerOptions::request_timing)
+    /// 开启请求体计时。
+    #[must_use]
+    pub fn with_stall_threshold(mut self, threshold: Duration) -> Self {
+        self.stall_threshold = Some(threshold);
+        self
+    }
+
+    /// 将访问日志写入单独的 `writer`，例如 `access.log` 文件
+    ///
+    /// 每个响应写入一行 logfmt 格式的记录，所有值都带引号，不再经过全局日志。
+    /// 服务端错误仍然会输出到全局日志。写入在后台线程中进行，
+    /// 积压超过 4096 行时丢弃新的记录并输出警告。
+    ///
+    /// ```no_run
+    /// use summer_boot::log::LoggingSystem;
+    ///
+    /// let file = std::fs::OpenOptions::new()
+    ///     .create(true)
+    ///     .append(true)
+    ///     .open("access.log")?;
+    /// let mut app = summer_boot::new();
+    /// app.logging(LoggingSystem::new().with_access_log(file));
+    /// # std::io::Result::Ok(())
+    /// ```
+    #[must_use]
+    pub fn with_access_log(mut self, writer: impl Write + Send + 'static) -> Self {
+        self.access_log = Some(AccessLog::spawn(Box::new(writer)));
+        self
+    }
+
+    /// 耗时所在的分桶，未配置分桶时返回 `None`
+    fn bucket_for(&self, elapsed: Duration) -> Option<String> {
+        if self.duration_buckets.is_empty() {
+            return None;
+        }
+        Some(
+            self.duration_buckets
+                .iter()
+                .find(|bound| elapsed <= **bound)
+                .map_or_else(|| "+Inf".to_string(), |bound| bound.as_millis().to_string()),
+        )
+    }
+
+    /// 根据状态码类别选择日志级别
+    fn level_for(&self, status: StatusCode) -> Level {
+        if status.is_server_error() {
+            self.server_error_level
+        } else if status.is_client_error() {
+            self.client_error_level
+        } else {
+            self.success_level
+        }
+    }
+
+    /// 判断响应是否需要记录
+    fn should_log(&self, status: StatusCode, elapsed: Duration) -> bool {
+        match self.slow_threshold {
+            Some(threshold) => {
+                status.is_server_error()
+                    || (status.is_client_error() && !self.filter_client_errors)
+                    || elapsed >= threshold
+            }
+            None => true,
+        }
     }
 
     /// Log a request and a response.
@@ -36,57 +270,115 @@ impl LoggingSystem {
         }
         req.set_ext(LoggingSystemHasBeenRun);
 
-        let path = req.url().path().to_owned();
-        let method = req.method().to_string();
-        log::info!("<-- Request received", {
-            method: method,
-            path: path,
-        });
+        let route = req.route_pattern().map(str::to_owned);
+        // 优先记录路由模板，避免日志中出现大量不同的路径
+        let path = sanitize(route.as_deref().unwrap_or_else(|| req.url().path()));
+        let http_method = req.method();
+        let method = http_method.to_string();
+        // 只在 RequestIdMiddleware 已经执行时才有，不直接读取请求头，避免与中间件配置的请求头不一致
+        let request_id = RequestId::from_request(&req);
+        let timing = req.ext::<RequestTiming>().cloned();
+        let observers = req.ext::<Observers>().cloned();
+        if self.slow_threshold.is_none() && self.access_log.is_none() {
+            log::info!("<-- Request received", {
+                request_id: request_id.as_ref().map_or_else(|| "-".to_string(), |id| sanitize(id.as_str())),
+                method: method,
+                path: path,
+            });
+        }
         let start = std::time::Instant::now();
         let response = next.run(req).await;
         let status = response.status();
-        if status.is_server_error() {
-            if let Some(error) = response.error() {
-                log::error!("Internal error --> Response sent", {
-                    message: format!("{:?}", error),
-                    error_type: error.type_name(),
-                    method: method,
-                    path: path,
-                    status: format!("{} - {}", status as u16, status.canonical_reason()),
-                    duration: format!("{:?}", start.elapsed()),
-                });
-            } else {
-                log::error!("Internal error --> Response sent", {
+        let elapsed = start.elapsed();
+        // RequestIdMiddleware 在本中间件之后执行时，请求ID保存在响应扩展中
+        let request_id = response
+            .ext::<RequestId>()
+            .cloned()
+            .or(request_id)
+            .map_or_else(|| "-".to_string(), |id| sanitize(id.as_str()));
+        if let Some(observers) = &observers {
+            observers.request(&RequestObservation {
+                method: http_method,
+                route: route.as_deref(),
+                status,
+                elapsed,
+                retry_after: response.ext::<RetryAfter>().copied(),
+            });
+        }
+        if !self.should_log(status, elapsed) {
+            return Ok(response);
+        }
+        if let (Some(threshold), Some(timing)) = (self.stall_threshold, &timing) {
+            let stall = timing.longest_stall();
+            if stall >= threshold {
+                log::warn!("client stalled {:?} mid-upload", stall, {
+                    request_id: request_id,
                     method: method,
                     path: path,
-                    status: format!("{} - {}", status as u16, status.canonical_reason()),
-                    duration: format!("{:?}", start.elapsed()),
+                    bytes_read: timing.bytes_read(),
                 });
             }
-        } else if status.is_client_error() {
-            if let Some(error) = response.error() {
-                log::warn!("Client error --> Response sent", {
-                    message: format!("{:?}", error),
-                    error_type: error.type_name(),
-                    method: method,
-                    path: path,
-                    status: format!("{} - {}", status as u16, status.canonical_reason()),
-                    duration: format!("{:?}", start.elapsed()),
-                });
-            } else {
-                log::warn!("Client error --> Response sent", {
-                    method: method,
-                    path: path,
-                    status: format!("{} - {}", status as u16, status.canonical_reason()),
-                    duration: format!("{:?}", start.elapsed()),
-                });
+        }
+        if let Some(retry) = response.ext::<RetryAfter>() {
+            log::info!("Retry-After sent", {
+                request_id: request_id,
+                method: method,
+                path: path,
+                cause: format!("{:?}", retry.cause()),
+                delay: format!("{:?}", retry.delay()),
+            });
+        }
+        if let Some(access_log) = &self.access_log {
+            let mut line = format!(
+                "time={} request_id={} method={} path={} status={} duration_ms={}",
+                quoted(crate::http1::date::fmt_http_date(SystemTime::now())),
+                quoted(&request_id),
+                quoted(&method),
+                quoted(&path),
+                quoted(status as u16),
+                quoted(elapsed.as_millis()),
+            );
+            if let Some(bucket) = self.bucket_for(elapsed) {
+                line.push_str(&format!(" bucket={}", quoted(bucket)));
+            }
+            access_log.send(line);
+            // 访问日志单独输出，全局日志只保留服务端错误
+            if !status.is_server_error() {
+                return Ok(response);
             }
+        }
+        let level = self.level_for(status);
+        let message = if status.is_server_error() {
+            "Internal error --> Response sent"
+        } else if status.is_client_error() {
+            "Client error --> Response sent"
+        } else {
+            "--> Response sent"
+        };
+        if let Some(error) = response
+            .error()
+            .filter(|_| status.is_client_error() || status.is_server_error())
+        {
+            log::log!(level, "{}", message, {
+                request_id: request_id,
+                message: sanitize(&format!("{:?}", error)),
+                error_type: error.type_name(),
+                method: method,
+                path: path,
+                status: format!("{} - {}", status as u16, status.canonical_reason()),
+                duration: format!("{:?}", elapsed),
+                duration_ms: elapsed.as_millis() as u64,
+                bucket: self.bucket_for(elapsed),
+            });
         } else {
-            log::info!("--> Response sent", {
+            log::log!(level, "{}", message, {
+                request_id: request_id,
                 method: method,
                 path: path,
                 status: format!("{} - {}", status as u16, status.canonical_reason()),
-                duration: format!("{:?}", start.elapsed()),
+                duration: format!("{:?}", elapsed),
+                duration_ms: elapsed.as_millis() as u64,
+                bucket: self.bucket_for(elapsed),
             });
         }
         Ok(response)
@@ -99,3 +391,200 @@ impl<State: Clone + Send + Sync + 'static> Middleware<State> for LoggingSystem {
         self.log(req, next).await
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn slow_threshold_gates_logging() {
+        let logging = LoggingSystem::new();
+        assert!(logging.should_log(StatusCode::Ok, Duration::ZERO));
+
+        let logging = logging.slow_threshold(Duration::from_millis(100));
+        assert!(!logging.should_log(StatusCode::Ok, Duration::from_millis(10)));
+        assert!(logging.should_log(StatusCode::NotFound, Duration::from_millis(10)));
+        assert!(logging.should_log(StatusCode::Ok, Duration::from_millis(100)));
+        assert!(logging.should_log(StatusCode::InternalServerError, Duration::ZERO));
+
+        let logging = logging.filter_client_errors(true);
+        assert!(!logging.should_log(StatusCode::NotFound, Duration::from_millis(10)));
+        assert!(logging.should_log(StatusCode::NotFound, Duration::from_millis(100)));
+        assert!(logging.should_log(StatusCode::InternalServerError, Duration::ZERO));
+    }
+
+    use crate::utils::request_id::RequestIdMiddleware;
+    use std::sync::{Arc, Mutex};
+
+    /// 共享的内存 writer
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Buffer {
+        /// 等待后台线程写入 `lines` 行
+        async fn lines(&self, lines: usize) -> Vec<String> {
+            for _ in 0..200 {
+                let log = String::from_utf8(self.0.lock().unwrap().clone()).unwrap();
+                if log.lines().count() >= lines {
+                    return log.lines().map(str::to_string).collect();
+                }
+                async_std::task::sleep(Duration::from_millis(10)).await;
+            }
+            panic!("access log has fewer than {} lines", lines);
+        }
+    }
+
+    /// 解析 logfmt 行，值必须带引号
+    fn fields(line: &str) -> Vec<(String, String)> {
+        let mut fields = Vec::new();
+        let mut rest = line;
+        while !rest.is_empty() {
+            let (key, value) = rest.split_once("=\"").expect("value is not quoted");
+            let mut chars = value.char_indices();
+            let mut parsed = String::new();
+            let end = loop {
+                match chars.next().expect("unterminated value") {
+                    (_, '\\') => parsed.push(chars.next().unwrap().1),
+                    (i, '"') => break i,
+                    (_, c) => parsed.push(c),
+                }
+            };
+            fields.push((key.to_string(), parsed));
+            rest = value[end + 1..].trim_start_matches(' ');
+        }
+        fields
+    }
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[async_std::test]
+    async fn writes_access_log() {
+        use http_types::{Method, Url};
+
+        let buffer = Buffer::default();
+        let mut app = crate::new();
+        app.logging(LoggingSystem::new().with_access_log(buffer.clone()));
+        app.at("/users/:id").get(|_| async { Ok("user") });
+
+        for path in ["/users/7", "/missing"] {
+            let url = Url::parse("http://localhost").unwrap().join(path).unwrap();
+            let _: http_types::Response = app
+                .respond(http_types::Request::new(Method::Get, url))
+                .await
+                .unwrap();
+        }
+
+        let lines = buffer.lines(2).await;
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("time=\""));
+        assert!(lines[0]
+            .contains(" request_id=\"-\" method=\"GET\" path=\"/users/:id\" status=\"200\" "));
+        assert!(lines[1].contains(" method=\"GET\" path=\"/missing\" status=\"404\" "));
+    }
+
+    #[async_std::test]
+    async fn access_log_escapes_request_values() {
+        use http_types::{Method, Url};
+
+        let buffer = Buffer::default();
+        let mut app = crate::new();
+        app.logging(LoggingSystem::new().with_access_log(buffer.clone()));
+        app.with(RequestIdMiddleware::new());
+        app.at("/").get(|_| async { Ok("ok") });
+
+        let mut req =
+            http_types::Request::new(Method::Get, Url::parse("http://localhost/").unwrap());
+        req.insert_header("X-Request-Id", "abc\r\ntime=\"fake\" status=200 \x1b[31m");
+        let _: http_types::Response = app.respond(req).await.unwrap();
+
+        let lines = buffer.lines(1).await;
+        assert_eq!(lines.len(), 1);
+        // 注入的 `time` 和 `status` 留在 request_id 的值中，不会成为新的字段
+        let fields = fields(&lines[0]);
+        let keys: Vec<&str> = fields.iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(
+            keys,
+            [
+                "time",
+                "request_id",
+                "method",
+                "path",
+                "status",
+                "duration_ms"
+            ]
+        );
+        assert_eq!(fields[1].1, "abc\\r\\ntime=\"fake\" status=200 \\u{1b}[31m");
+        assert_eq!(fields[4].1, "200");
+    }
+
+    #[async_std::test]
+    async fn access_log_uses_configured_request_id_header() {
+        use http_types::{Method, Url};
+
+        let buffer = Buffer::default();
+        let mut app = crate::new();
+        app.logging(LoggingSystem::new().with_access_log(buffer.clone()));
+        app.with(RequestIdMiddleware::new().header_name("X-Correlation-Id"));
+        app.at("/").get(|_| async { Ok("ok") });
+
+        let mut req =
+            http_types::Request::new(Method::Get, Url::parse("http://localhost/").unwrap());
+        req.insert_header("X-Request-Id", "spoofed");
+        req.insert_header("X-Correlation-Id", "corr-42");
+        let _: http_types::Response = app.respond(req).await.unwrap();
+
+        // 没有 RequestIdMiddleware 时不读取请求头
+        let plain_buffer = Buffer::default();
+        let mut plain = crate::new();
+        plain.logging(LoggingSystem::new().with_access_log(plain_buffer.clone()));
+        plain.at("/").get(|_| async { Ok("ok") });
+        let mut req =
+            http_types::Request::new(Method::Get, Url::parse("http://localhost/").unwrap());
+        req.insert_header("X-Request-Id", "spoofed");
+        let _: http_types::Response = plain.respond(req).await.unwrap();
+
+        assert_eq!(fields(&buffer.lines(1).await[0])[1].1, "corr-42");
+        assert_eq!(fields(&plain_buffer.lines(1).await[0])[1].1, "-");
+    }
+
+    #[test]
+    fn duration_buckets() {
+        let logging = LoggingSystem::new();
+        assert_eq!(logging.bucket_for(Duration::from_millis(5)), None);
+
+        let logging =
+            logging.with_duration_buckets([Duration::from_millis(100), Duration::from_millis(10)]);
+        let bucket = |ms| logging.bucket_for(Duration::from_millis(ms));
+        assert_eq!(bucket(5).as_deref(), Some("10"));
+        assert_eq!(bucket(10).as_deref(), Some("10"));
+        assert_eq!(bucket(11).as_deref(), Some("100"));
+        assert_eq!(bucket(101).as_deref(), Some("+Inf"));
+    }
+
+    #[test]
+    fn level_per_status_class() {
+        let logging = LoggingSystem::default();
+        assert_eq!(logging.level_for(StatusCode::Ok), Level::Info);
+        assert_eq!(logging.level_for(StatusCode::NotFound), Level::Warn);
+        assert_eq!(logging.level_for(StatusCode::BadGateway), Level::Error);
+
+        let logging = logging
+            .with_success_level(Level::Debug)
+            .with_client_error_level(Level::Info)
+            .with_server_error_level(Level::Warn);
+        assert_eq!(logging.level_for(StatusCode::Found), Level::Debug);
+        assert_eq!(logging.level_for(StatusCode::TooManyRequests), Level::Info);
+        assert_eq!(
+            logging.level_for(StatusCode::ServiceUnavailable),
+            Level::Warn
+        );
+    }
+}
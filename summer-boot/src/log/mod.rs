@@ -21,10 +21,16 @@ pub use kv_log_macro::{debug, error, info, log, trace, warn};
 pub use kv_log_macro::{max_level, Level};
 
 mod logging_system;
+pub(crate) mod observer;
+mod sanitize;
 
 pub use femme::LevelFilter;
 
 pub use logging_system::LoggingSystem;
+pub use observer::{AfterSendOutcome, RequestObservation, RequestObserver};
+pub use sanitize::{sanitize, MAX_VALUE_LEN};
+
+pub(crate) use observer::{ConnectionGuard, Observers};
 
 /// 开启日志记录
 pub fn start() {
@@ -0,0 +1,94 @@
+//! 请求观察者
+//!
+//! 观察者通过 [`Server::observe`](crate::Server::observe) 注册在服务上，
+//! [`LoggingSystem`](super::LoggingSystem) 记录请求耗时的同时通知该服务的观察者，
+//! 监听器在连接建立和关闭时也会通知观察者，指标收集可以基于此实现。
+
+use crate::http_types::Method;
+use crate::utils::retry_after::RetryAfter;
+use crate::StatusCode;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 一次请求的观察结果
+#[derive(Debug, Clone, Copy)]
+pub struct RequestObservation<'a> {
+    pub method: Method,
+    /// 匹配到的路由模板，没有匹配到路由时为 `None`
+    pub route: Option<&'a str>,
+    pub status: StatusCode,
+    pub elapsed: Duration,
+    /// 响应中告诉客户端的重试时间，参见 [`RetryAfter::apply`]
+    pub retry_after: Option<RetryAfter>,
+}
+
+/// 响应发送后任务的状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AfterSendOutcome {
+    /// 响应写完，任务已提交到执行器
+    Queued,
+    /// 任务正常结束
+    Completed,
+    /// 任务 panic
+    Failed,
+}
+
+/// 请求和连接的观察者
+pub trait RequestObserver: Send + Sync + 'static {
+    /// 请求处理完成
+    fn on_request(&self, observation: &RequestObservation<'_>);
+
+    /// 建立了新连接
+    fn on_connection_open(&self) {}
+
+    /// 连接已关闭
+    fn on_connection_close(&self) {}
+
+    /// 响应发送后任务的状态变化，参见 [`Response::after_send`](crate::Response::after_send)
+    fn on_after_send(&self, _outcome: AfterSendOutcome) {}
+}
+
+/// 一个服务上注册的观察者，克隆的服务共享
+///
+/// 处理请求时放在请求扩展中，供 `LoggingSystem` 使用。
+#[derive(Clone, Default)]
+pub(crate) struct Observers(Arc<Vec<Arc<dyn RequestObserver>>>);
+
+impl Observers {
+    pub(crate) fn push(&mut self, observer: Arc<dyn RequestObserver>) {
+        Arc::get_mut(&mut self.0)
+            .expect("服务器启动后无法注册观察者")
+            .push(observer);
+    }
+
+    fn each(&self, f: impl Fn(&dyn RequestObserver)) {
+        for observer in self.0.iter() {
+            f(observer.as_ref());
+        }
+    }
+
+    pub(crate) fn request(&self, observation: &RequestObservation<'_>) {
+        self.each(|observer| observer.on_request(observation));
+    }
+
+    pub(crate) fn after_send(&self, outcome: AfterSendOutcome) {
+        self.each(|observer| observer.on_after_send(outcome));
+    }
+}
+
+/// 连接存活期间持有，drop 时通知连接关闭
+pub(crate) struct ConnectionGuard(Observers);
+
+impl ConnectionGuard {
+    pub(crate) fn open(observers: &Observers) -> Self {
+        observers.each(|observer| observer.on_connection_open());
+        ConnectionGuard(observers.clone())
+    }
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.0.each(|observer| observer.on_connection_close());
+    }
+}
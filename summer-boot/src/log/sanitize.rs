@@ -0,0 +1,75 @@
+//! 清理写入日志的请求数据，防止日志注入
+
+/// 来自请求的值写入日志时的最大字节数，超出部分以 `...` 代替
+pub const MAX_VALUE_LEN: usize = 1024;
+
+/// 转义来自请求的值中的控制字符并限制长度
+///
+/// 换行、回车和制表符转义为 `\n`、`\r`、`\t`，其他控制字符（包括 ANSI 转义序列开头的 `ESC`）
+/// 和 Unicode 行分隔符转义为 `\u{..}`，反斜杠本身转义为 `\\`。
+/// 结果只有一行，不会伪造日志记录或改变终端状态。
+///
+/// # Examples
+///
+/// ```
+/// use summer_boot::log::sanitize;
+///
+/// assert_eq!(sanitize("/a\r\n\x1b[31m"), "/a\\r\\n\\u{1b}[31m");
+/// ```
+pub fn sanitize(value: &str) -> String {
+    let mut sanitized = String::with_capacity(value.len().min(MAX_VALUE_LEN));
+    let mut buf = [0u8; 4];
+    for c in value.chars() {
+        let escaped;
+        let piece = match c {
+            '\n' => "\\n",
+            '\r' => "\\r",
+            '\t' => "\\t",
+            '\\' => "\\\\",
+            c if c.is_control() || c == '\u{2028}' || c == '\u{2029}' => {
+                escaped = format!("\\u{{{:x}}}", c as u32);
+                escaped.as_str()
+            }
+            c => c.encode_utf8(&mut buf),
+        };
+        if sanitized.len() + piece.len() > MAX_VALUE_LEN {
+            sanitized.push_str("...");
+            break;
+        }
+        sanitized.push_str(piece);
+    }
+    sanitized
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const PATH: &str = "/login\r\n2024-01-01 INFO admin logged in \x1b[2J\x1b[31m";
+
+    #[test]
+    fn escapes_control_characters() {
+        let path = sanitize(PATH);
+        assert_eq!(
+            path,
+            "/login\\r\\n2024-01-01 INFO admin logged in \\u{1b}[2J\\u{1b}[31m"
+        );
+        assert!(!path.chars().any(char::is_control));
+        assert_eq!(
+            sanitize("a\\nb\t\u{7f}\u{2028}"),
+            "a\\\\nb\\t\\u{7f}\\u{2028}"
+        );
+        assert_eq!(sanitize("/users/7"), "/users/7");
+    }
+
+    #[test]
+    fn truncates_long_values() {
+        let long = "a".repeat(MAX_VALUE_LEN * 2);
+        let sanitized = sanitize(&long);
+        assert_eq!(sanitized.len(), MAX_VALUE_LEN + 3);
+        assert!(sanitized.ends_with("..."));
+
+        let escapes = "\x1b".repeat(MAX_VALUE_LEN);
+        assert!(sanitize(&escapes).len() <= MAX_VALUE_LEN + 3);
+    }
+}
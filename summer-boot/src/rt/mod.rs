@@ -1,7 +1,8 @@
 //! 提供了summer boot的运行时环境
 //! 当前提供环境主要是 tokio 下的 Runtime
 //!
-use tokio::runtime::Runtime;
+use std::io;
+use tokio::runtime::{Builder, Runtime};
 
 /// 运行时简单代理对象
 #[derive(Debug)]
@@ -9,7 +10,116 @@ pub struct SummerRuntime;
 
 impl SummerRuntime {
     /// 新建 tokio runtime 运行时对象
+    #[allow(clippy::new_ret_no_self)]
     pub fn new() -> Runtime {
         tokio::runtime::Runtime::new().unwrap()
     }
+
+    /// 通过 [`SummerRuntimeBuilder`] 配置运行时
+    #[must_use]
+    pub fn builder() -> SummerRuntimeBuilder {
+        SummerRuntimeBuilder::new()
+    }
+}
+
+/// 多线程 tokio 运行时的构建器，未设置的项使用 tokio 的默认值
+///
+/// # Examples
+///
+/// ```
+/// use summer_boot::rt::SummerRuntimeBuilder;
+///
+/// let runtime = SummerRuntimeBuilder::new()
+///     .worker_threads(2)
+///     .thread_name("summer-worker")
+///     .build()
+///     .unwrap();
+/// runtime.block_on(async { println!("Hello world") });
+/// ```
+#[derive(Debug)]
+pub struct SummerRuntimeBuilder {
+    builder: Builder,
+}
+
+impl SummerRuntimeBuilder {
+    /// 开启 IO 和定时器驱动，工作线程数默认为 CPU 核数
+    #[must_use]
+    pub fn new() -> Self {
+        let mut builder = Builder::new_multi_thread();
+        builder.enable_all();
+        Self { builder }
+    }
+
+    /// 工作线程数，必须大于 0
+    #[must_use]
+    pub fn worker_threads(mut self, n: usize) -> Self {
+        self.builder.worker_threads(n);
+        self
+    }
+
+    /// `spawn_blocking` 使用的最大线程数，必须大于 0
+    #[must_use]
+    pub fn max_blocking_threads(mut self, n: usize) -> Self {
+        self.builder.max_blocking_threads(n);
+        self
+    }
+
+    /// 运行时创建的线程名称
+    #[must_use]
+    pub fn thread_name(mut self, name: &str) -> Self {
+        self.builder.thread_name(name);
+        self
+    }
+
+    /// 每个线程启动时调用 `f`
+    #[must_use]
+    pub fn on_thread_start<F>(mut self, f: F) -> Self
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        self.builder.on_thread_start(f);
+        self
+    }
+
+    /// 创建运行时
+    pub fn build(mut self) -> io::Result<Runtime> {
+        self.builder.build()
+    }
+}
+
+impl Default for SummerRuntimeBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn builds_configured_runtime() {
+        let started = Arc::new(AtomicUsize::new(0));
+        let runtime = SummerRuntime::builder()
+            .worker_threads(2)
+            .max_blocking_threads(1)
+            .thread_name("summer-test")
+            .on_thread_start({
+                let started = started.clone();
+                move || {
+                    started.fetch_add(1, Ordering::SeqCst);
+                }
+            })
+            .build()
+            .unwrap();
+
+        let name = runtime
+            .block_on(runtime.spawn(async { std::thread::current().name().map(str::to_string) }))
+            .unwrap();
+        assert_eq!(name.as_deref(), Some("summer-test"));
+        assert_eq!(runtime.metrics().num_workers(), 2);
+        assert!(started.load(Ordering::SeqCst) >= 1);
+    }
 }
@@ -12,6 +12,7 @@ use crate::common::{
 };
 
 /// 异步接受传入连接。
+#[allow(dead_code)]
 pub trait Accept {
     /// 可以接受的连接类型。
     type Conn;
@@ -1,3 +1,5 @@
 mod accept;
 pub mod endpoint;
+pub mod recovery;
+#[allow(clippy::module_inception)]
 pub mod server;
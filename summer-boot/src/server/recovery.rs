@@ -0,0 +1,362 @@
+//! 错误响应的恢复钩子和错误页面
+//!
+//! 通过 [`Server::on_error_response`](crate::Server::on_error_response) 注册，
+//! 在整个中间件链之后检查状态码为 4xx 或 5xx 的响应，可以用另一个响应替换它，
+//! 例如 `500` 时返回缓存的旧内容。没有钩子替换的错误响应再交给
+//! [`Server::set_error_handler`](crate::Server::set_error_handler) 设置的处理器统一渲染。
+
+use crate::http_types::headers::{
+    HeaderName, HeaderValues, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, TRANSFER_ENCODING,
+};
+use crate::http_types::{Method, Url};
+use crate::log;
+use crate::{Error, Response, StatusCode};
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// 路由时复制的请求信息，endpoint 消费请求之后仍然可用
+///
+/// 只有注册了恢复钩子时才会复制。
+#[derive(Debug, Clone)]
+pub struct RequestInfo {
+    method: Method,
+    url: Url,
+    route: Option<String>,
+    headers: Vec<(HeaderName, HeaderValues)>,
+}
+
+impl RequestInfo {
+    pub(crate) fn capture(req: &http_types::Request, route: Option<&str>) -> Self {
+        Self {
+            method: req.method(),
+            url: req.url().clone(),
+            route: route.map(str::to_string),
+            headers: req
+                .iter()
+                .map(|(name, values)| (name.clone(), values.clone()))
+                .collect(),
+        }
+    }
+
+    /// 请求方法
+    #[must_use]
+    pub fn method(&self) -> Method {
+        self.method
+    }
+
+    /// 请求 URL，已经去掉 [`Server::strip_global_prefix`](crate::Server::strip_global_prefix) 设置的前缀
+    #[must_use]
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// 匹配到的路由模式，没有匹配的路由时为 `None`
+    #[must_use]
+    pub fn route_pattern(&self) -> Option<&str> {
+        self.route.as_deref()
+    }
+
+    /// 请求头，名称不区分大小写
+    #[must_use]
+    pub fn header(&self, name: impl Into<HeaderName>) -> Option<&HeaderValues> {
+        let name = name.into();
+        self.headers
+            .iter()
+            .find(|(header, _)| *header == name)
+            .map(|(_, values)| values)
+    }
+}
+
+/// 交给错误处理器的上下文
+#[derive(Debug)]
+pub struct ErrorContext {
+    request: RequestInfo,
+    status: StatusCode,
+    error: Option<Error>,
+}
+
+impl ErrorContext {
+    /// 路由时复制的请求信息
+    #[must_use]
+    pub fn request(&self) -> &RequestInfo {
+        &self.request
+    }
+
+    /// 原响应的状态码
+    #[must_use]
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// endpoint 或中间件返回的错误，路由生成的 `404`、`405` 等没有错误
+    #[must_use]
+    pub fn error(&self) -> Option<&Error> {
+        self.error.as_ref()
+    }
+
+    /// 取出错误
+    pub fn take_error(&mut self) -> Option<Error> {
+        self.error.take()
+    }
+
+    /// 错误说明，没有错误时为状态码的标准说明，例如 `Not Found`
+    #[must_use]
+    pub fn message(&self) -> String {
+        match &self.error {
+            Some(error) => error.to_string(),
+            None => self.status.canonical_reason().to_string(),
+        }
+    }
+}
+
+type BoxedHandler =
+    Box<dyn Fn(ErrorContext) -> Pin<Box<dyn Future<Output = Response> + Send>> + Send + Sync>;
+
+type BoxedHook = Box<
+    dyn Fn(RequestInfo, &Response) -> Pin<Box<dyn Future<Output = Option<Response>> + Send>>
+        + Send
+        + Sync,
+>;
+
+/// 按注册顺序调用的恢复钩子和错误处理器
+#[derive(Clone, Default)]
+pub(crate) struct ErrorHooks {
+    hooks: Arc<Vec<BoxedHook>>,
+    handler: Option<Arc<BoxedHandler>>,
+    allow_status_override: bool,
+}
+
+fn is_error(status: StatusCode) -> bool {
+    status.is_client_error() || status.is_server_error()
+}
+
+/// 描述原响应体的头部，渲染后的响应有自己的响应体，不复制
+const BODY_HEADERS: [HeaderName; 4] = [
+    CONTENT_TYPE,
+    CONTENT_LENGTH,
+    CONTENT_ENCODING,
+    TRANSFER_ENCODING,
+];
+
+/// 把原响应的头部复制到渲染后的响应上，错误处理器自己设置的头部优先
+///
+/// 路由生成的 `Allow`、中间件添加的 CORS、`Retry-After`、`WWW-Authenticate` 等头部因此得以保留。
+fn keep_headers(original: &Response, rendered: &mut Response) {
+    for (name, values) in original {
+        if BODY_HEADERS.contains(name) || rendered.header(name).is_some() {
+            continue;
+        }
+        rendered.insert_header(name, values);
+    }
+}
+
+impl ErrorHooks {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.hooks.is_empty() && self.handler.is_none()
+    }
+
+    pub(crate) fn set_handler<F, Fut>(&mut self, handler: F)
+    where
+        F: Fn(ErrorContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Response> + Send + 'static,
+    {
+        self.handler = Some(Arc::new(Box::new(move |cx| Box::pin(handler(cx)))));
+    }
+
+    pub(crate) fn allow_status_override(&mut self, allow: bool) {
+        self.allow_status_override = allow;
+    }
+
+    pub(crate) fn push<F, Fut>(&mut self, hook: F)
+    where
+        F: Fn(RequestInfo, &Response) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Option<Response>> + Send + 'static,
+    {
+        Arc::get_mut(&mut self.hooks)
+            .expect("服务器启动后无法注册恢复钩子")
+            .push(Box::new(move |info, res| Box::pin(hook(info, res))));
+    }
+
+    /// 响应为错误时依次调用钩子，第一个返回 `Some` 的响应替换原响应；
+    /// 都没有替换时交给错误处理器
+    pub(crate) async fn recover(&self, info: Option<RequestInfo>, mut res: Response) -> Response {
+        let Some(info) = info else {
+            return res;
+        };
+        if !is_error(res.status()) {
+            return res;
+        }
+        for hook in self.hooks.iter() {
+            if let Some(replacement) = hook(info.clone(), &res).await {
+                return replacement;
+            }
+        }
+        let Some(handler) = &self.handler else {
+            return res;
+        };
+
+        let status = res.status();
+        let cx = ErrorContext {
+            request: info,
+            status,
+            error: res.take_error(),
+        };
+        let mut rendered = handler(cx).await;
+        keep_headers(&res, &mut rendered);
+        if !is_error(rendered.status()) && !self.allow_status_override {
+            log::warn!(
+                "错误处理器把 {} 改为了 {}，保留原状态码",
+                status,
+                rendered.status()
+            );
+            rendered.set_status(status);
+        }
+        rendered
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::http_types::{self, Method, Url};
+    use crate::{Request, StatusCode};
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    fn request(method: Method, path: &str) -> http_types::Request {
+        let url = Url::parse("http://localhost").unwrap().join(path).unwrap();
+        let mut req = http_types::Request::new(method, url);
+        req.insert_header("X-Tenant", "acme");
+        req.set_body("payload");
+        req
+    }
+
+    #[async_std::test]
+    async fn replaces_or_keeps_error_responses() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut app = crate::new();
+        app.at("/report").post(|mut req: Request<()>| async move {
+            req.body_string().await?;
+            Err::<String, _>(crate::Error::from_str(500, "upstream failed"))
+        });
+        app.at("/ok").get(|_| async { Ok("fresh") });
+        let counter = calls.clone();
+        app.on_error_response(move |info, res| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            let failed = res.status() == StatusCode::InternalServerError;
+            async move {
+                // 请求体已经被 endpoint 读取，请求头仍然可用
+                let tenant = info.header("x-tenant")?.as_str().to_string();
+                if failed && info.route_pattern() == Some("/report") {
+                    let mut res = crate::Response::new(StatusCode::Ok);
+                    res.insert_header("Warning", "110 - \"Response is Stale\"");
+                    res.set_body(format!("cached report for {}", tenant));
+                    return Some(res);
+                }
+                None
+            }
+        });
+
+        let mut res: http_types::Response =
+            app.respond(request(Method::Post, "/report")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::Ok);
+        assert!(res.header("Warning").is_some());
+        assert_eq!(res.body_string().await.unwrap(), "cached report for acme");
+
+        // 钩子放弃处理时保留原响应
+        let res: http_types::Response =
+            app.respond(request(Method::Get, "/missing")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NotFound);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // 成功的响应不调用钩子
+        let res: http_types::Response = app.respond(request(Method::Get, "/ok")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::Ok);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    fn json_errors(app: &mut crate::Server<()>) {
+        app.set_error_handler(|cx| async move {
+            let mut res = crate::Response::new(cx.status());
+            res.set_body(serde_json::json!({ "error": cx.message() }));
+            res
+        });
+    }
+
+    #[async_std::test]
+    async fn renders_handler_errors_and_router_responses() {
+        let mut app = crate::new();
+        app.at("/fail")
+            .get(|_| async { Err::<String, _>(crate::Error::from_str(422, "name is required")) });
+        app.at("/only-get").get(|_| async { Ok("ok") });
+        json_errors(&mut app);
+
+        let cases = [
+            (Method::Get, "/fail", 422, "name is required"),
+            (Method::Get, "/missing", 404, "Not Found"),
+            (Method::Post, "/only-get", 405, "Method Not Allowed"),
+        ];
+        for (method, path, status, message) in cases {
+            let mut res: http_types::Response = app.respond(request(method, path)).await.unwrap();
+            assert_eq!(res.status(), status, "{}", path);
+            assert_eq!(res.content_type().unwrap().essence(), "application/json");
+            let body: serde_json::Value = res.body_json().await.unwrap();
+            assert_eq!(body, serde_json::json!({ "error": message }));
+        }
+
+        let res: http_types::Response = app
+            .respond(request(Method::Get, "/only-get"))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::Ok);
+    }
+
+    #[async_std::test]
+    async fn rendered_responses_keep_original_headers() {
+        let mut app = crate::new();
+        app.at("/only-get").get(|_| async { Ok("ok") });
+        app.at("/limited").get(|_| async {
+            let mut res = crate::Response::new(StatusCode::TooManyRequests);
+            res.insert_header("Retry-After", "30");
+            res.insert_header("X-Trace", "from-endpoint");
+            res.set_body("slow down");
+            Ok(res)
+        });
+        app.set_error_handler(|_| async {
+            let mut res = crate::Response::new(StatusCode::Ok);
+            res.insert_header("X-Trace", "from-handler");
+            res.set_body(serde_json::json!({ "error": true }));
+            res
+        });
+
+        let res: http_types::Response = app
+            .respond(request(Method::Post, "/only-get"))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::MethodNotAllowed);
+        assert!(res["Allow"].as_str().contains("GET"), "{:?}", res["Allow"]);
+
+        let res: http_types::Response =
+            app.respond(request(Method::Get, "/limited")).await.unwrap();
+        assert_eq!(res["Retry-After"], "30");
+        assert_eq!(res["X-Trace"], "from-handler");
+        assert_eq!(res.content_type().unwrap().essence(), "application/json");
+    }
+
+    #[async_std::test]
+    async fn keeps_error_status_unless_allowed() {
+        for allow in [false, true] {
+            let mut app = crate::new();
+            app.at("/fail")
+                .get(|_| async { Err::<String, _>(crate::Error::from_str(500, "boom")) });
+            app.set_error_handler(|_| async { crate::Response::new(StatusCode::Ok) });
+            app.allow_error_status_override(allow);
+            let res: http_types::Response =
+                app.respond(request(Method::Get, "/fail")).await.unwrap();
+            let expected = if allow { 200 } else { 500 };
+            assert_eq!(res.status(), expected);
+        }
+    }
+}
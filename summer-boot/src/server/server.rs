@@ -9,9 +9,13 @@ use crate::{Request, Route};
 use async_std::io;
 use async_std::sync::Arc;
 
-use gateway::router::{Router, Selection};
-use tcp::{Listener, ToListener};
+use super::recovery::{ErrorContext, ErrorHooks, RequestInfo};
+use crate::http1::after_send::AfterSend;
+use gateway::dot;
+use gateway::router::{AllowedMethods, RouteInfo, Router, Selection};
+use tcp::{Listener, Shutdown, ToListener};
 use utils::middleware::{Middleware, Next};
+use utils::trusted_proxy::TrustedProxies;
 
 // use summer_boot_autoconfigure;
 
@@ -20,15 +24,15 @@ use utils::middleware::{Middleware, Next};
 /// 服务器由 *state*, *endpoints* 和 *middleware* 组成。
 ///
 /// - 服务器状态是用户定义的，通过 [`summer_boot::Server::with_state`] 函数使用. 这个
-/// 状态可以用于所有应用 endpoints 共享引用.
+///   状态可以用于所有应用 endpoints 共享引用.
 ///
 /// - Endpoints 提供与指定URL [`summer_boot::Server::at`] 创建一个 *路由*
-/// 然后可以用于绑定注册到 endpoints
-/// 对于指定HTTP请求类型进行使用
+///   然后可以用于绑定注册到 endpoints
+///   对于指定HTTP请求类型进行使用
 ///
 /// - Middleware 通过附加request或
-/// response 处理, 例如压缩、默认请求头或日志记录。到
-/// 中间件添加到应用程序中，使用 [`summer_boot::Server::middleware`] 方法.
+///   response 处理, 例如压缩、默认请求头或日志记录。到
+///   中间件添加到应用程序中，使用 [`summer_boot::Server::middleware`] 方法.
 pub struct Server<State> {
     router: Arc<Router<State>>,
     state: State,
@@ -39,6 +43,18 @@ pub struct Server<State> {
     /// 在这里不在Vec使用互斥体，因为在执行期间添加中间件应该是一个错误。
     #[allow(clippy::rc_buffer)]
     middleware: Arc<Vec<Arc<dyn Middleware<State>>>>,
+    /// 路由前从请求路径中去掉的全局前缀
+    global_prefix: Option<Arc<str>>,
+    /// 为 `true` 时没有全局前缀的请求返回 `404`
+    strict_prefix: bool,
+    /// 受信任的反向代理，由 `Request::client_addr` 使用
+    trusted_proxies: Arc<TrustedProxies>,
+    /// 错误响应的恢复钩子
+    error_hooks: ErrorHooks,
+    /// 请求和连接的观察者
+    observers: log::Observers,
+    /// 侦听器停机时通知连接，克隆的实例共享
+    shutdown: Shutdown,
 }
 
 impl Server<()> {
@@ -89,6 +105,51 @@ impl Server<()> {
     }
 }
 
+#[cfg(feature = "distributed-routes")]
+impl Server<()> {
+    /// 注册所有通过 [`contribute`](crate::contribute) 贡献的路由
+    ///
+    /// 路由按 crate 名称、路径和方法排序后注册，与链接顺序无关。
+    /// 任何贡献的路由与已注册的路由（包括嵌套服务中的路由）或其他贡献的路由冲突时返回错误，
+    /// 不注册任何路由。冲突按去掉参数名和全局前缀后的路径判断，例如 `/users/:id` 与 `/users/:name`。
+    /// 收集方式的限制参见 [`contributed`](crate::contributed) 模块。
+    ///
+    /// ```no_run
+    /// let mut app = summer_boot::new();
+    /// app.at("/health").get(|_| async { Ok("ok") });
+    /// app.mount_contributed().expect("conflicting contributed routes");
+    /// ```
+    pub fn mount_contributed(&mut self) -> Result<&mut Self, crate::contributed::RouteConflict> {
+        self.mount_contributed_with(crate::contributed::MountOptions::new())
+    }
+
+    /// 同 [`Server::mount_contributed`]，可以为每个 crate 的路由设置前缀
+    ///
+    /// ```no_run
+    /// use summer_boot::contributed::MountOptions;
+    ///
+    /// let mut app = summer_boot::new();
+    /// app.mount_contributed_with(MountOptions::new().prefix("billing", "/billing"))
+    ///     .expect("conflicting contributed routes");
+    /// ```
+    pub fn mount_contributed_with(
+        &mut self,
+        options: crate::contributed::MountOptions,
+    ) -> Result<&mut Self, crate::contributed::RouteConflict> {
+        let routes = crate::contributed::contributed_routes();
+        let global_prefix = self
+            .global_prefix
+            .as_deref()
+            .filter(|_| !self.strict_prefix);
+        let planned =
+            crate::contributed::plan(&routes, &self.list_routes(), &options, global_prefix)?;
+        for (path, route) in planned {
+            route.register(&mut self.at(&path));
+        }
+        Ok(self)
+    }
+}
+
 impl Default for Server<()> {
     fn default() -> Self {
         Self::new()
@@ -135,10 +196,17 @@ where
         Self {
             router: Arc::new(Router::new()),
             middleware: Arc::new(vec![
-                // 暂时没有使用到cookies
                 Arc::new(log::LoggingSystem::new()),
+                #[cfg(feature = "cookies")]
+                Arc::new(crate::cookies::CookiesMiddleware::new()),
             ]),
             state,
+            global_prefix: None,
+            strict_prefix: false,
+            trusted_proxies: Arc::new(TrustedProxies::new()),
+            error_hooks: ErrorHooks::default(),
+            observers: log::Observers::default(),
+            shutdown: Shutdown::new(),
         }
     }
 
@@ -179,6 +247,41 @@ where
         Route::new(router, path.to_owned())
     }
 
+    /// 设置没有匹配到路由时调用的 endpoint，替换默认的 `404 Not Found`
+    ///
+    /// 中间件照常执行，endpoint 可以通过 `req.url()` 读取请求的路径。
+    /// 开启 [`strict_global_prefix`](Self::strict_global_prefix) 后没有全局前缀的请求也会调用它。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use summer_boot::{Response, StatusCode};
+    ///
+    /// let mut app = summer_boot::new();
+    /// app.at_not_found(|req: summer_boot::Request<()>| async move {
+    ///     let mut res = Response::new(StatusCode::NotFound);
+    ///     res.set_body(format!("{} 不存在", req.url().path()));
+    ///     Ok(res)
+    /// });
+    /// ```
+    pub fn at_not_found(&mut self, ep: impl Endpoint<State>) -> &mut Self {
+        Arc::get_mut(&mut self.router)
+            .expect("服务器启动后无法注册路由")
+            .set_not_found(Box::new(ep));
+        self
+    }
+
+    /// 设置路径存在但方法不匹配时调用的 endpoint，替换默认的 `405 Method Not Allowed`
+    ///
+    /// endpoint 通过 [`Request::allowed_methods`] 读取路径上注册的方法。
+    /// 返回的响应状态码为 `405` 且没有 `Allow` 响应头时会自动补上。
+    pub fn at_method_not_allowed(&mut self, ep: impl Endpoint<State>) -> &mut Self {
+        Arc::get_mut(&mut self.router)
+            .expect("服务器启动后无法注册路由")
+            .set_method_not_allowed(Box::new(ep));
+        self
+    }
+
     /// 向应用程序添加中间件。
     ///
     /// 中间件提供请求/响应
@@ -186,7 +289,9 @@ where
     /// 继续处理（可能修改响应）或立即返回响应
     /// 响应。有关详细信息，请参考 [`Middleware`] trait
     ///
-    /// 中间件只能在应用程序的 `顶层` 添加，并使用应用顺序
+    /// 中间件只能在应用程序的 `顶层` 添加，并使用应用顺序。
+    /// 默认的 [`LoggingSystem`](log::LoggingSystem) 已经记录了请求，
+    /// 需要修改日志配置时使用 [`logging`](Self::logging) 替换默认实例。
     pub fn with<M>(&mut self, middleware: M) -> &mut Self
     where
         M: Middleware<State>,
@@ -197,6 +302,159 @@ where
         self
     }
 
+    /// 替换默认的 [`LoggingSystem`](log::LoggingSystem)，用于设置慢请求阈值、日志级别或访问日志
+    ///
+    /// 新的实例仍然位于中间件链的最前面，记录的耗时包括其他中间件。
+    /// 通过 [`with`](Self::with) 添加的 `LoggingSystem` 不会记录已经被默认实例记录过的请求。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use summer_boot::log::LoggingSystem;
+    ///
+    /// let mut app = summer_boot::new();
+    /// app.logging(LoggingSystem::new().slow_threshold(Duration::from_millis(500)));
+    /// ```
+    pub fn logging(&mut self, logging: log::LoggingSystem) -> &mut Self {
+        let m = Arc::get_mut(&mut self.middleware).expect("服务器启动后无法注册中间件");
+        // with_state 把默认的 LoggingSystem 放在第一个
+        m[0] = Arc::new(logging);
+        self
+    }
+
+    /// 在路由之前从所有请求路径中去掉 `prefix`
+    ///
+    /// 用于网关把 `/service-a/*` 转发过来，但路由中不包含 `/service-a` 的情况。
+    /// 只匹配完整的路径段，`/service-a` 会变为 `/`，`/service-ab` 不匹配。
+    /// 没有前缀的请求按原路径路由，开启 [`strict_global_prefix`](Self::strict_global_prefix)
+    /// 后返回 `404 Not Found`。只对最外层的服务生效，嵌套的服务不会再次去掉前缀。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async_std::task::block_on(async {
+    /// use http_types::{Method, Request, Response, Url};
+    ///
+    /// let mut app = summer_boot::new();
+    /// app.strip_global_prefix("/service-a");
+    /// app.at("/users").get(|_| async { Ok("users") });
+    ///
+    /// let url = Url::parse("http://localhost/service-a/users").unwrap();
+    /// let res: Response = app.respond(Request::new(Method::Get, url)).await.unwrap();
+    /// assert_eq!(res.status(), 200);
+    /// # });
+    /// ```
+    pub fn strip_global_prefix(&mut self, prefix: &str) -> &mut Self {
+        let prefix = prefix.trim_end_matches('/');
+        self.global_prefix = if prefix.is_empty() {
+            None
+        } else if prefix.starts_with('/') {
+            Some(Arc::from(prefix))
+        } else {
+            Some(Arc::from(format!("/{}", prefix)))
+        };
+        self
+    }
+
+    /// 设置没有全局前缀的请求是否返回 `404 Not Found`，默认为 `false`
+    pub fn strict_global_prefix(&mut self, strict: bool) -> &mut Self {
+        self.strict_prefix = strict;
+        self
+    }
+
+    /// 设置受信任的反向代理
+    ///
+    /// 只有连接的对端地址属于这些网段时，[`Request::client_addr`] 才会读取
+    /// `Forwarded` 或 `X-Forwarded-For`。也可以通过配置文件中的 `server.trusted_proxies` 设置。
+    /// 只对最外层的服务生效。
+    pub fn trusted_proxies(&mut self, proxies: TrustedProxies) -> &mut Self {
+        self.trusted_proxies = Arc::new(proxies);
+        self
+    }
+
+    /// 注册请求和连接的观察者，例如收集指标
+    ///
+    /// 观察者只属于这个服务，由 `LoggingSystem` 和监听器通知。只对最外层的服务生效。
+    ///
+    /// # Panics
+    ///
+    /// 服务器被克隆（例如开始监听）之后调用会 panic。
+    pub fn observe(&mut self, observer: impl log::RequestObserver) -> &mut Self {
+        self.observers.push(Arc::new(observer));
+        self
+    }
+
+    /// 注册错误响应的恢复钩子
+    ///
+    /// 整个中间件链完成后，响应状态码为 4xx 或 5xx 时按注册顺序调用钩子，
+    /// 第一个返回 `Some` 的响应替换原响应，都返回 `None` 时保留原响应。
+    /// 钩子收到路由时复制的 [`RequestInfo`]，endpoint 读取请求体之后请求头仍然可用；
+    /// 需要的响应信息应在返回的 future 之前读取。只对最外层的服务生效。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use summer_boot::{Response, StatusCode};
+    ///
+    /// let mut app = summer_boot::new();
+    /// app.on_error_response(|info, res| {
+    ///     let unavailable = res.status() == StatusCode::ServiceUnavailable;
+    ///     async move {
+    ///         if unavailable && info.url().path() == "/prices" {
+    ///             let mut res = Response::new(StatusCode::Ok);
+    ///             res.set_body("[]");
+    ///             return Some(res);
+    ///         }
+    ///         None
+    ///     }
+    /// });
+    /// ```
+    pub fn on_error_response<F, Fut>(&mut self, hook: F) -> &mut Self
+    where
+        F: Fn(RequestInfo, &crate::Response) -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = Option<crate::Response>> + Send + 'static,
+    {
+        self.error_hooks.push(hook);
+        self
+    }
+
+    /// 设置错误处理器，统一渲染错误响应，例如 JSON 格式的错误或 HTML 错误页面
+    ///
+    /// 整个中间件链完成后，状态码为 4xx 或 5xx 且没有被 [`on_error_response`](Self::on_error_response)
+    /// 的钩子替换的响应都会交给处理器，包括 endpoint 返回的错误和路由生成的 `404`、`405`。
+    /// 处理器返回的响应替换原响应；如果它的状态码不是错误，默认仍使用原状态码，
+    /// 避免把 `500` 静默改为 `200`，需要时通过 [`allow_error_status_override`](Self::allow_error_status_override) 允许。
+    /// 原响应的头部（例如 `405` 的 `Allow`）会复制到新响应上，处理器自己设置的头部和描述响应体的头部除外。
+    /// 再次调用会替换之前的处理器。只对最外层的服务生效。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use summer_boot::Response;
+    ///
+    /// let mut app = summer_boot::new();
+    /// app.set_error_handler(|cx| async move {
+    ///     let mut res = Response::new(cx.status());
+    ///     res.set_body(serde_json::json!({ "error": cx.message() }));
+    ///     res
+    /// });
+    /// ```
+    pub fn set_error_handler<F, Fut>(&mut self, handler: F) -> &mut Self
+    where
+        F: Fn(ErrorContext) -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = crate::Response> + Send + 'static,
+    {
+        self.error_hooks.set_handler(handler);
+        self
+    }
+
+    /// 是否允许错误处理器返回非错误的状态码，默认为 `false`
+    pub fn allow_error_status_override(&mut self, allow: bool) -> &mut Self {
+        self.error_hooks.allow_status_override(allow);
+        self
+    }
+
     /// 使用提供的侦听器异步为应用程序提供服务。
     ///
     /// 这是调用 `summer_boot::Server::bind`, 记录`ListenInfo` 实例
@@ -224,6 +482,39 @@ where
         Ok(())
     }
 
+    /// 监听 `addr`，收到升级信号时把监听套接字交给新进程
+    ///
+    /// 当前进程是由升级启动的新进程时，直接使用继承的套接字而不绑定 `addr`。
+    /// 旧进程在新进程就绪后停止接受连接，等待处理中的连接结束后返回，之后应当退出进程。
+    /// 详见 [`upgrade`](crate::upgrade) 模块。
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use async_std::task::block_on;
+    /// # fn main() -> Result<(), std::io::Error> { block_on(async {
+    /// #
+    /// use summer_boot::upgrade::UpgradeConfig;
+    ///
+    /// let mut app = summer_boot::new();
+    /// app.at("/").get(|_| async { Ok("Hello, world!") });
+    /// app.listen_with_upgrade("127.0.0.1:8080", UpgradeConfig::new()).await?;
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    #[cfg(all(unix, feature = "upgrade"))]
+    pub async fn listen_with_upgrade(
+        self,
+        addr: impl std::net::ToSocketAddrs,
+        config: crate::upgrade::UpgradeConfig,
+    ) -> io::Result<()> {
+        let listener = match crate::upgrade::inherited_listener() {
+            Some(listener) => listener,
+            None => std::net::TcpListener::bind(addr)?,
+        };
+        crate::upgrade::serve(self, listener, config).await
+    }
+
     /// 开发中 todo
     ///
     /// 异步绑定侦听器。
@@ -274,17 +565,45 @@ where
         Req: Into<http_types::Request>,
         Res: From<http_types::Response>,
     {
-        let req = req.into();
+        let mut req = req.into();
         let Self {
             router,
             state,
             middleware,
+            global_prefix,
+            strict_prefix,
+            trusted_proxies,
+            error_hooks,
+            observers,
+            ..
         } = self.clone();
 
+        req.ext_mut().insert(trusted_proxies);
+        req.ext_mut().insert(observers.clone());
+
         let method = req.method().to_owned();
-        let Selection { endpoint, params } = router.route(&req.url().path(), method);
+        let stripped = match global_prefix {
+            Some(prefix) => strip_prefix(&mut req, &prefix),
+            None => true,
+        };
+        let Selection {
+            endpoint,
+            params,
+            route,
+            name,
+            allow,
+        } = if stripped || !strict_prefix {
+            router.route(req.url().path(), method)
+        } else {
+            router.not_found()
+        };
+        let info = (!error_hooks.is_empty()).then(|| RequestInfo::capture(&req, route.as_deref()));
         let route_params = vec![params];
-        let req = Request::new(state, req, route_params);
+        let mut req = Request::new(state, req, route_params);
+        req.set_route(route, name);
+        if !allow.is_empty() {
+            req.set_ext(AllowedMethods(allow));
+        }
 
         let next = Next {
             endpoint,
@@ -292,7 +611,14 @@ where
         };
 
         let res = next.run(req).await;
-        let res: http_types::Response = res.into();
+        let res = error_hooks.recover(info, res).await;
+        let mut res: http_types::Response = res.into();
+        if let Some(after_send) = res.ext_mut().get_mut::<AfterSend>() {
+            after_send.set_observers(observers);
+        }
+        if method == http_types::Method::Head {
+            strip_head_body(&mut res);
+        }
         Ok(res.into())
     }
 
@@ -310,6 +636,89 @@ where
     pub fn state(&self) -> &State {
         &self.state
     }
+
+    /// 生成路由树的 Graphviz DOT 描述，用于调试和查看应用结构。
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let mut app = summer_boot::new();
+    /// app.at("/users/:id").get(|_| async { Ok("user") });
+    /// assert!(app.routes_dot().starts_with("digraph routes {"));
+    /// ```
+    #[must_use]
+    pub fn routes_dot(&self) -> String {
+        let middleware: Vec<String> = self
+            .middleware
+            .iter()
+            .map(|m| m.name().to_string())
+            .collect();
+        dot::render(self.router.routes(), &middleware)
+    }
+
+    /// 列出所有已注册的路由，用于调试和生成文档
+    ///
+    /// 嵌套服务的路由会展开，路径为完整路径，例如 `/admin/jobs/:job`，`nested` 为空。
+    /// 按注册顺序排列，`method` 为 `None` 的路由匹配所有方法。
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let mut app = summer_boot::new();
+    /// app.at("/users/:id").get(|_| async { Ok("user") });
+    ///
+    /// let routes = app.list_routes();
+    /// assert_eq!(routes[0].path, "/users/:id");
+    /// assert_eq!(routes[0].method, Some(summer_boot::http_types::Method::Get));
+    /// ```
+    #[must_use]
+    pub fn list_routes(&self) -> Vec<RouteInfo> {
+        self.router.list_routes()
+    }
+
+    /// 请求目标长度的豁免前缀，加上全局前缀；没有开启严格前缀时原路径也豁免
+    pub(crate) fn target_exemptions(&self) -> Vec<(String, usize)> {
+        let mut exemptions = Vec::new();
+        for (prefix, limit) in self.router.target_exemptions() {
+            if let Some(global) = &self.global_prefix {
+                exemptions.push((format!("{}{}", global, prefix), *limit));
+                if self.strict_prefix {
+                    continue;
+                }
+            }
+            exemptions.push((prefix.clone(), *limit));
+        }
+        exemptions
+    }
+
+    /// 侦听器之间共享的停机通知
+    pub(crate) fn shutdown(&self) -> &Shutdown {
+        &self.shutdown
+    }
+
+    /// 注册在这个服务上的观察者
+    pub(crate) fn observers(&self) -> &log::Observers {
+        &self.observers
+    }
+
+    /// 作为嵌套服务时的路由信息，服务自身的中间件排在各路由中间件之前
+    pub(crate) fn nested_routes(&self) -> Vec<RouteInfo> {
+        self.router
+            .routes()
+            .iter()
+            .cloned()
+            .map(|mut info| {
+                let mut middleware: Vec<String> = self
+                    .middleware
+                    .iter()
+                    .map(|m| m.name().to_string())
+                    .collect();
+                middleware.append(&mut info.middleware);
+                info.middleware = middleware;
+                info
+            })
+            .collect()
+    }
 }
 
 impl<State: Send + Sync + 'static> std::fmt::Debug for Server<State> {
@@ -318,12 +727,47 @@ impl<State: Send + Sync + 'static> std::fmt::Debug for Server<State> {
     }
 }
 
+/// 从请求路径中去掉 `prefix`，路径不以 `prefix` 开头时返回 `false`
+fn strip_prefix(req: &mut http_types::Request, prefix: &str) -> bool {
+    let path = req.url().path();
+    let rest = match path.strip_prefix(prefix) {
+        Some("") => "/".to_string(),
+        Some(rest) if rest.starts_with('/') => rest.to_string(),
+        _ => return false,
+    };
+    req.url_mut().set_path(&rest);
+    true
+}
+
+/// HEAD 请求的响应不带 body，但保留 GET 会返回的 `Content-Length`
+fn strip_head_body(res: &mut http_types::Response) {
+    use http_types::headers::{CONTENT_LENGTH, TRANSFER_ENCODING};
+
+    if res.header(CONTENT_LENGTH).is_none() && res.header(TRANSFER_ENCODING).is_none() {
+        match res.len() {
+            Some(len) => res.insert_header(CONTENT_LENGTH, len.to_string()),
+            None => res.insert_header(TRANSFER_ENCODING, "chunked"),
+        };
+    }
+    let mime = res.content_type();
+    res.set_body(http_types::Body::empty());
+    if let Some(mime) = mime {
+        res.set_content_type(mime);
+    }
+}
+
 impl<State: Clone> Clone for Server<State> {
     fn clone(&self) -> Self {
         Self {
             router: self.router.clone(),
             state: self.state.clone(),
             middleware: self.middleware.clone(),
+            global_prefix: self.global_prefix.clone(),
+            strict_prefix: self.strict_prefix,
+            trusted_proxies: self.trusted_proxies.clone(),
+            error_hooks: self.error_hooks.clone(),
+            observers: self.observers.clone(),
+            shutdown: self.shutdown.clone(),
         }
     }
 }
@@ -344,9 +788,19 @@ impl<State: Clone + Sync + Send + 'static, InnerState: Clone + Sync + Send + 'st
         let middleware = self.middleware.clone();
         let state = self.state.clone();
 
-        let Selection { endpoint, params } = router.route(&path, method);
+        let Selection {
+            endpoint,
+            params,
+            route,
+            name,
+            allow,
+        } = router.route(&path, method);
         route_params.push(params);
-        let req = Request::new(state, req, route_params);
+        let mut req = Request::new(state, req, route_params);
+        req.set_route(route, name);
+        if !allow.is_empty() {
+            req.set_ext(AllowedMethods(allow));
+        }
 
         let next = Next {
             endpoint,
@@ -360,6 +814,7 @@ impl<State: Clone + Sync + Send + 'static, InnerState: Clone + Sync + Send + 'st
 #[cfg(test)]
 mod test {
     use crate as summer_boot;
+    use http_types::{Method, Request, Response, StatusCode, Url};
 
     #[test]
     fn allow_nested_server_with_same_state() {
@@ -374,4 +829,36 @@ mod test {
         let mut outer = summer_boot::new();
         outer.at("/foo").get(inner);
     }
+
+    async fn get(app: &summer_boot::Server<()>, path: &str) -> (StatusCode, String) {
+        let url = Url::parse("http://localhost").unwrap().join(path).unwrap();
+        let mut res: Response = app.respond(Request::new(Method::Get, url)).await.unwrap();
+        (res.status(), res.body_string().await.unwrap())
+    }
+
+    #[async_std::test]
+    async fn strips_global_prefix() {
+        let mut app = summer_boot::new();
+        app.strip_global_prefix("/service-a/");
+        app.at("/").get(|_| async { Ok("root") });
+        app.at("/users/:id")
+            .get(|req: summer_boot::Request<()>| async move {
+                Ok(format!("{} {}", req.param("id")?, req.url().path()))
+            });
+
+        assert_eq!(
+            get(&app, "/service-a/users/7").await,
+            (StatusCode::Ok, "7 /users/7".to_string())
+        );
+        assert_eq!(get(&app, "/service-a").await.1, "root");
+        assert_eq!(get(&app, "/users/7").await.0, StatusCode::Ok);
+        assert_eq!(
+            get(&app, "/service-ab/users/7").await.0,
+            StatusCode::NotFound
+        );
+
+        app.strict_global_prefix(true);
+        assert_eq!(get(&app, "/users/7").await.0, StatusCode::NotFound);
+        assert_eq!(get(&app, "/service-a/users/7").await.0, StatusCode::Ok);
+    }
 }
@@ -0,0 +1,284 @@
+//! Server-Sent Events
+//!
+//! 通过 [`endpoint`] 创建推送事件的 endpoint，响应使用 chunked 编码，
+//! 每个事件在发送后立即写入连接。
+//!
+//! # Examples
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! let mut app = summer_boot::new();
+//! app.at("/ticks").get(summer_boot::sse::endpoint(|_req, sender| async move {
+//!     for i in 0..3 {
+//!         sender.send("tick", &i.to_string(), Some(&i.to_string())).await?;
+//!         async_std::task::sleep(Duration::from_secs(1)).await;
+//!     }
+//!     Ok(())
+//! }));
+//! ```
+
+use crate::http_types::headers::{CACHE_CONTROL, CONTENT_TYPE};
+use crate::http_types::Body;
+use crate::{log, Endpoint, Request, Response, StatusCode};
+
+use async_std::io::{self, BufRead, Read};
+use async_trait::async_trait;
+use futures_util::Stream;
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+/// 默认的保活间隔
+const DEFAULT_KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// 等待写入连接的事件数量
+const CHANNEL_CAPACITY: usize = 16;
+
+/// 创建 Server-Sent Events endpoint
+///
+/// `handler` 的 future 由响应体驱动：客户端断开连接后响应体被丢弃，future 也随之取消。
+/// future 结束后响应结束。没有事件时每隔 15 秒发送一个注释保持连接，
+/// 间隔可以通过 [`SseEndpoint::keep_alive`] 修改。
+pub fn endpoint<State, F, Fut>(handler: F) -> SseEndpoint<F>
+where
+    State: Clone + Send + Sync + 'static,
+    F: Fn(Request<State>, Sender) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = crate::Result<()>> + Send + 'static,
+{
+    SseEndpoint {
+        handler,
+        keep_alive: DEFAULT_KEEP_ALIVE,
+    }
+}
+
+/// Server-Sent Events endpoint，通过 [`endpoint`] 创建
+pub struct SseEndpoint<F> {
+    handler: F,
+    keep_alive: Duration,
+}
+
+impl<F> SseEndpoint<F> {
+    /// 设置没有事件时发送保活注释的间隔
+    #[must_use]
+    pub fn keep_alive(mut self, interval: Duration) -> Self {
+        self.keep_alive = interval;
+        self
+    }
+}
+
+impl<F> fmt::Debug for SseEndpoint<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SseEndpoint")
+            .field("keep_alive", &self.keep_alive)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl<State, F, Fut> Endpoint<State> for SseEndpoint<F>
+where
+    State: Clone + Send + Sync + 'static,
+    F: Fn(Request<State>, Sender) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = crate::Result<()>> + Send + 'static,
+{
+    async fn call(&self, req: Request<State>) -> crate::Result {
+        let (sender, receiver) = async_channel::bounded(CHANNEL_CAPACITY);
+        let handler = (self.handler)(req, Sender { sender });
+        let task: Pin<Box<dyn Future<Output = ()> + Send>> = Box::pin(async move {
+            if let Err(error) = handler.await {
+                log::error!("sse handler error", { error: error.to_string() });
+            }
+        });
+
+        let reader = SseReader {
+            inner: Mutex::new(ReaderState {
+                task: Some(task),
+                receiver,
+                keep_alive: self.keep_alive,
+                timer: None,
+                buf: Vec::new(),
+                pos: 0,
+            }),
+        };
+
+        let mut res = Response::new(StatusCode::Ok);
+        res.insert_header(CONTENT_TYPE, "text/event-stream");
+        res.insert_header(CACHE_CONTROL, "no-cache");
+        // 禁止 nginx 等反向代理缓冲响应
+        res.insert_header("X-Accel-Buffering", "no");
+        // 长度未知，编码时使用 chunked
+        res.set_body(Body::from_reader(reader, None));
+        Ok(res)
+    }
+}
+
+/// 发送事件，客户端断开连接后发送返回错误
+#[derive(Debug, Clone)]
+pub struct Sender {
+    sender: async_channel::Sender<Vec<u8>>,
+}
+
+impl Sender {
+    /// 发送事件，`data` 中的每一行对应一个 `data:` 字段
+    pub async fn send(&self, name: &str, data: &str, id: Option<&str>) -> io::Result<()> {
+        self.push(encode_event(name, data, id)).await
+    }
+
+    /// 设置客户端断线后重连的间隔
+    pub async fn retry(&self, interval: Duration) -> io::Result<()> {
+        self.push(format!("retry: {}\n\n", interval.as_millis()).into_bytes())
+            .await
+    }
+
+    async fn push(&self, event: Vec<u8>) -> io::Result<()> {
+        self.sender.send(event).await.map_err(|_| {
+            io::Error::new(io::ErrorKind::ConnectionAborted, "sse client disconnected")
+        })
+    }
+}
+
+/// 按 SSE 规范编码事件，字段中的换行会被移除
+fn encode_event(name: &str, data: &str, id: Option<&str>) -> Vec<u8> {
+    let mut event = String::new();
+    if !name.is_empty() {
+        event.push_str("event: ");
+        event.push_str(&single_line(name));
+        event.push('\n');
+    }
+    if let Some(id) = id {
+        event.push_str("id: ");
+        event.push_str(&single_line(id));
+        event.push('\n');
+    }
+    // 规范中 `\r\n`、`\r` 和 `\n` 都是换行，单独的 `\r` 同样要拆分，否则会注入新的字段
+    for line in data.split("\r\n").flat_map(|line| line.split(['\r', '\n'])) {
+        event.push_str("data: ");
+        event.push_str(line);
+        event.push('\n');
+    }
+    event.push('\n');
+    event.into_bytes()
+}
+
+fn single_line(value: &str) -> String {
+    value.replace(['\r', '\n'], "")
+}
+
+struct ReaderState {
+    /// 用户的 future，结束后为 `None`
+    task: Option<Pin<Box<dyn Future<Output = ()> + Send>>>,
+    receiver: async_channel::Receiver<Vec<u8>>,
+    keep_alive: Duration,
+    timer: Option<Pin<Box<dyn Future<Output = ()> + Send>>>,
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl ReaderState {
+    fn reset_timer(&mut self) {
+        self.timer = Some(Box::pin(async_std::task::sleep(self.keep_alive)));
+    }
+
+    /// 准备下一段要写入的数据，返回 `false` 表示响应结束
+    fn poll_next_chunk(&mut self, cx: &mut Context<'_>) -> Poll<bool> {
+        if let Some(task) = self.task.as_mut() {
+            if task.as_mut().poll(cx).is_ready() {
+                // future 持有的 Sender 被丢弃，剩余的事件发送完后接收端结束
+                self.task = None;
+            }
+        }
+
+        match Pin::new(&mut self.receiver).poll_next(cx) {
+            Poll::Ready(Some(event)) => {
+                self.buf = event;
+                self.pos = 0;
+                self.reset_timer();
+                return Poll::Ready(true);
+            }
+            Poll::Ready(None) => return Poll::Ready(false),
+            Poll::Pending => {}
+        }
+
+        if self.timer.is_none() {
+            self.reset_timer();
+        }
+        let timer = self.timer.as_mut().expect("timer is set above");
+        if timer.as_mut().poll(cx).is_ready() {
+            self.buf = b":\n\n".to_vec();
+            self.pos = 0;
+            self.reset_timer();
+            return Poll::Ready(true);
+        }
+        Poll::Pending
+    }
+}
+
+/// 由用户 future 产生事件的响应体
+///
+/// 状态放在 `Mutex` 中只是为了满足 `Body` 对 `Sync` 的要求，读取时通过 `get_mut` 访问。
+struct SseReader {
+    inner: Mutex<ReaderState>,
+}
+
+impl BufRead for SseReader {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        let state = self.get_mut().inner.get_mut().unwrap();
+        if state.pos >= state.buf.len() && !futures_util::ready!(state.poll_next_chunk(cx)) {
+            return Poll::Ready(Ok(&[]));
+        }
+        Poll::Ready(Ok(&state.buf[state.pos..]))
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        let state = self.get_mut().inner.get_mut().unwrap();
+        state.pos = (state.pos + amt).min(state.buf.len());
+    }
+}
+
+impl Read for SseReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let available = futures_util::ready!(self.as_mut().poll_fill_buf(cx))?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Poll::Ready(Ok(n))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn encodes_events() {
+        assert_eq!(
+            String::from_utf8(encode_event("update", "line 1\r\nline 2", Some("7"))).unwrap(),
+            "event: update\nid: 7\ndata: line 1\ndata: line 2\n\n"
+        );
+        assert_eq!(
+            String::from_utf8(encode_event("", "", None)).unwrap(),
+            "data: \n\n"
+        );
+    }
+
+    #[test]
+    fn lone_carriage_returns_split_data() {
+        assert_eq!(
+            String::from_utf8(encode_event("message", "hi\revent: admin\rid: 999", None)).unwrap(),
+            "event: message\ndata: hi\ndata: event: admin\ndata: id: 999\n\n"
+        );
+        assert_eq!(
+            String::from_utf8(encode_event("", "a\r\n\rb\n", None)).unwrap(),
+            "data: a\ndata: \ndata: b\ndata: \n\n"
+        );
+    }
+}
@@ -1,19 +1,198 @@
-use crate::tcp::{ListenInfo, Listener, ToListener};
+use crate::tcp::{ListenInfo, Listener, Shutdown, ToListener};
+use crate::utils::panic_recovery::panic_message;
 use crate::Server;
 
+use std::error::Error;
 use std::fmt::{self, Debug, Display, Formatter};
+use std::panic::AssertUnwindSafe;
+use std::time::Duration;
 
-use async_std::io;
+use async_std::{io, task};
 use futures_util::stream::{futures_unordered::FuturesUnordered, StreamExt};
+use futures_util::FutureExt;
+
+/// 某个侦听器出错时 [`ConcurrentListener`] 的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FailurePolicy {
+    /// 取消其他侦听器并返回错误
+    #[default]
+    FailFast,
+    /// 记录错误，其他侦听器继续运行，全部结束后返回第一个错误
+    Continue,
+}
+
+/// 侦听器的 `accept` panic 后 [`ConcurrentListener`] 的重启策略
+///
+/// panic 后等待退避时间再重新调用该侦听器的 `accept`，每次等待时间翻倍，不超过 `max_backoff`。
+/// 重启次数用完后不再重启，停止整个服务并返回错误，让进程退出以便编排系统发现问题：
+/// 所有侦听器停止接受连接，处理中的请求完成后响应 `Connection: close`，空闲连接立即关闭，
+/// 最多等待 [`ConcurrentListener::with_drain_timeout`] 设置的时间。
+/// 默认最多重启 3 次，初始退避 100 毫秒，最长 10 秒。
+/// 返回错误后的重启（[`ConcurrentListener::with_max_listener_restarts`]）使用同样的退避时间。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RestartPolicy {
+    max_restarts: usize,
+    backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            max_restarts: 3,
+            backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RestartPolicy {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 不重启，第一次 panic 就停止服务
+    #[must_use]
+    pub fn never() -> Self {
+        Self::default().max_restarts(0)
+    }
+
+    /// 每个侦听器最多重启的次数
+    #[must_use]
+    pub fn max_restarts(mut self, max: usize) -> Self {
+        self.max_restarts = max;
+        self
+    }
+
+    /// 第一次重启前的等待时间
+    #[must_use]
+    pub fn backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// 最长的等待时间
+    #[must_use]
+    pub fn max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// 第 `restart` 次重启前的等待时间，从 1 开始
+    fn delay(&self, restart: usize) -> Duration {
+        let factor = 1u32 << restart.saturating_sub(1).min(16);
+        self.backoff.saturating_mul(factor).min(self.max_backoff)
+    }
+}
+
+/// 侦听器出错时返回的错误，包含出错侦听器的 [`ListenInfo`]
+///
+/// 作为 `io::Error` 的内部错误返回，`kind` 与原错误相同。
+#[derive(Debug)]
+pub struct ListenerError {
+    info: Vec<ListenInfo>,
+    error: io::Error,
+}
+
+impl ListenerError {
+    fn tag(info: Vec<ListenInfo>, error: io::Error) -> io::Error {
+        io::Error::new(error.kind(), Self { info, error })
+    }
+
+    /// 出错的侦听器
+    pub fn info(&self) -> &[ListenInfo] {
+        &self.info
+    }
+
+    /// 侦听器返回的原始错误
+    pub fn error(&self) -> &io::Error {
+        &self.error
+    }
+}
+
+impl Display for ListenerError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let listeners = self
+            .info
+            .iter()
+            .map(|info| info.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        write!(f, "listener {} failed: {}", listeners, self.error)
+    }
+}
+
+impl Error for ListenerError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// 单个侦听器的 `accept` 结束的方式
+enum Exit {
+    /// 正常结束或返回错误
+    Finished(io::Result<()>),
+    /// 重启次数用完，需要停止整个服务
+    Escalate(io::Error),
+}
 
-#[derive(Default)]
 pub struct ConcurrentListener<State> {
     listeners: Vec<Box<dyn Listener<State>>>,
+    failure_policy: FailurePolicy,
+    restart_policy: RestartPolicy,
+    max_listener_restarts: u32,
+    drain_timeout: Duration,
+    shutdown: Option<Shutdown>,
+}
+
+impl<State: Clone + Send + Sync + 'static> Default for ConcurrentListener<State> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<State: Clone + Send + Sync + 'static> ConcurrentListener<State> {
     pub fn new() -> Self {
-        Self { listeners: vec![] }
+        Self {
+            listeners: vec![],
+            failure_policy: FailurePolicy::default(),
+            restart_policy: RestartPolicy::default(),
+            max_listener_restarts: 0,
+            drain_timeout: Duration::from_secs(30),
+            shutdown: None,
+        }
+    }
+
+    /// 侦听器的 `accept` 返回错误后最多重启的次数，默认为 0，即不重启
+    ///
+    /// 用于短暂的系统错误，每次重启前按 [`RestartPolicy`] 的退避时间等待。
+    /// 重启次数用完后错误按 [`FailurePolicy`] 处理。
+    #[must_use]
+    pub fn with_max_listener_restarts(mut self, max: u32) -> Self {
+        self.max_listener_restarts = max;
+        self
+    }
+
+    /// 设置侦听器 panic 后的重启策略，默认为 [`RestartPolicy::default`]
+    #[must_use]
+    pub fn with_restart_policy(mut self, policy: RestartPolicy) -> Self {
+        self.restart_policy = policy;
+        self
+    }
+
+    /// 因 panic 停止服务时等待处理中的连接结束的最长时间，默认为 30 秒
+    #[must_use]
+    pub fn with_drain_timeout(mut self, timeout: Duration) -> Self {
+        self.drain_timeout = timeout;
+        self
+    }
+
+    /// 设置侦听器出错时的处理方式，默认为 [`FailurePolicy::FailFast`]
+    #[must_use]
+    pub fn with_failure_policy(mut self, policy: FailurePolicy) -> Self {
+        self.failure_policy = policy;
+        self
     }
 
     pub fn add<L>(&mut self, listener: L) -> io::Result<()>
@@ -39,6 +218,7 @@ where
     State: Clone + Send + Sync + 'static,
 {
     async fn bind(&mut self, app: Server<State>) -> io::Result<()> {
+        self.shutdown = Some(app.shutdown().clone());
         for listener in self.listeners.iter_mut() {
             listener.bind(app.clone()).await?;
         }
@@ -46,16 +226,81 @@ where
     }
 
     async fn accept(&mut self) -> io::Result<()> {
+        let policy = self.failure_policy;
+        let restart_policy = self.restart_policy;
+        let max_listener_restarts = self.max_listener_restarts;
         let mut futures_unordered = FuturesUnordered::new();
 
         for listener in self.listeners.iter_mut() {
-            futures_unordered.push(listener.accept());
+            let info = listener.info();
+            futures_unordered.push(async move {
+                let mut restarts = 0;
+                let mut error_restarts = 0;
+                loop {
+                    let listeners = || {
+                        info.iter()
+                            .map(ToString::to_string)
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    };
+                    let panic = match AssertUnwindSafe(listener.accept()).catch_unwind().await {
+                        Ok(Ok(())) => return Exit::Finished(Ok(())),
+                        Ok(Err(error)) if error_restarts < max_listener_restarts => {
+                            error_restarts += 1;
+                            crate::log::warn!("侦听器出错，准备重启", {
+                                listener: listeners(),
+                                error: error.to_string(),
+                                restarts: error_restarts,
+                            });
+                            task::sleep(restart_policy.delay(error_restarts as usize)).await;
+                            continue;
+                        }
+                        Ok(Err(error)) => {
+                            return Exit::Finished(Err(ListenerError::tag(info, error)))
+                        }
+                        Err(panic) => panic_message(panic.as_ref()),
+                    };
+                    crate::log::error!("侦听器 panic", {
+                        listener: listeners(),
+                        message: crate::log::sanitize(&panic),
+                        restarts: restarts,
+                    });
+                    if restarts >= restart_policy.max_restarts {
+                        let error = io::Error::new(
+                            io::ErrorKind::Other,
+                            format!("panicked after {} restarts: {}", restarts, panic),
+                        );
+                        return Exit::Escalate(ListenerError::tag(info, error));
+                    }
+                    restarts += 1;
+                    task::sleep(restart_policy.delay(restarts)).await;
+                }
+            });
         }
 
-        while let Some(result) = futures_unordered.next().await {
-            result?;
+        let mut first_error = None;
+        while let Some(exit) = futures_unordered.next().await {
+            let error = match exit {
+                Exit::Finished(Ok(())) => continue,
+                Exit::Finished(Err(error)) => error,
+                // 不论失败策略如何都停止服务
+                Exit::Escalate(error) => {
+                    // 取消所有侦听器，不再接受新连接
+                    drop(futures_unordered);
+                    self.drain().await;
+                    return Err(error);
+                }
+            };
+            match policy {
+                // 返回时丢弃 `futures_unordered`，其他侦听器的 accept 随之取消
+                FailurePolicy::FailFast => return Err(error),
+                FailurePolicy::Continue => {
+                    crate::log::error!("侦听器出错", { error: error.to_string() });
+                    first_error.get_or_insert(error);
+                }
+            }
         }
-        Ok(())
+        first_error.map_or(Ok(()), Err)
     }
 
     fn info(&self) -> Vec<ListenInfo> {
@@ -66,6 +311,20 @@ where
     }
 }
 
+impl<State> ConcurrentListener<State> {
+    /// 通知所有连接停机，等待处理中的连接结束
+    async fn drain(&self) {
+        let Some(shutdown) = &self.shutdown else {
+            return;
+        };
+        shutdown.trigger();
+        let remaining = shutdown.drain(self.drain_timeout).await;
+        if remaining > 0 {
+            crate::log::warn!("等待连接结束超时", { connections: remaining });
+        }
+    }
+}
+
 impl<State> Debug for ConcurrentListener<State> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         write!(f, "{:?}", self.listeners)
@@ -84,3 +343,390 @@ impl<State> Display for ConcurrentListener<State> {
         writeln!(f, "{}", string)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    /// 绑定成功，`accept` 在 `delay` 后结束，`fail` 为真时返回错误
+    #[derive(Debug)]
+    struct Rigged {
+        name: &'static str,
+        delay: Duration,
+        fail: bool,
+        finished: Arc<AtomicBool>,
+        dropped: Arc<AtomicBool>,
+    }
+
+    impl Rigged {
+        fn new(name: &'static str, delay: u64, fail: bool) -> Self {
+            Self {
+                name,
+                delay: Duration::from_millis(delay),
+                fail,
+                finished: Arc::default(),
+                dropped: Arc::default(),
+            }
+        }
+    }
+
+    struct DropFlag(Arc<AtomicBool>);
+
+    impl Drop for DropFlag {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    impl Display for Rigged {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", self.name)
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Listener<()> for Rigged {
+        async fn bind(&mut self, _app: Server<()>) -> io::Result<()> {
+            Ok(())
+        }
+
+        async fn accept(&mut self) -> io::Result<()> {
+            let _guard = DropFlag(self.dropped.clone());
+            async_std::task::sleep(self.delay).await;
+            self.finished.store(true, Ordering::SeqCst);
+            if self.fail {
+                Err(io::Error::new(io::ErrorKind::BrokenPipe, "rigged"))
+            } else {
+                Ok(())
+            }
+        }
+
+        fn info(&self) -> Vec<ListenInfo> {
+            vec![ListenInfo::new(
+                self.name.to_string(),
+                "test".to_string(),
+                false,
+            )]
+        }
+    }
+
+    impl ToListener<()> for Rigged {
+        type Listener = Self;
+
+        fn to_listener(self) -> io::Result<Self::Listener> {
+            Ok(self)
+        }
+    }
+
+    /// 前 `panics` 次调用 `accept` 时 panic，之后正常结束
+    #[derive(Debug)]
+    struct Panicking {
+        panics: usize,
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl Display for Panicking {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            write!(f, "panicking")
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Listener<()> for Panicking {
+        async fn bind(&mut self, _app: Server<()>) -> io::Result<()> {
+            Ok(())
+        }
+
+        async fn accept(&mut self) -> io::Result<()> {
+            let calls = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+            if calls <= self.panics {
+                panic!("connection setup bug");
+            }
+            Ok(())
+        }
+
+        fn info(&self) -> Vec<ListenInfo> {
+            vec![ListenInfo::new(
+                "panicking".to_string(),
+                "test".to_string(),
+                false,
+            )]
+        }
+    }
+
+    impl ToListener<()> for Panicking {
+        type Listener = Self;
+
+        fn to_listener(self) -> io::Result<Self::Listener> {
+            Ok(self)
+        }
+    }
+
+    /// 前 `failures` 次调用 `accept` 时返回错误，之后正常结束
+    #[derive(Debug)]
+    struct Flaky {
+        failures: usize,
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl Display for Flaky {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            write!(f, "flaky")
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Listener<()> for Flaky {
+        async fn bind(&mut self, _app: Server<()>) -> io::Result<()> {
+            Ok(())
+        }
+
+        async fn accept(&mut self) -> io::Result<()> {
+            let calls = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+            if calls <= self.failures {
+                return Err(io::Error::new(io::ErrorKind::Other, "too many open files"));
+            }
+            Ok(())
+        }
+
+        fn info(&self) -> Vec<ListenInfo> {
+            vec![ListenInfo::new(
+                "flaky".to_string(),
+                "test".to_string(),
+                false,
+            )]
+        }
+    }
+
+    impl ToListener<()> for Flaky {
+        type Listener = Self;
+
+        fn to_listener(self) -> io::Result<Self::Listener> {
+            Ok(self)
+        }
+    }
+
+    /// 收到通知后 panic
+    #[derive(Debug)]
+    struct PanicOnSignal(async_channel::Receiver<()>);
+
+    impl Display for PanicOnSignal {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            write!(f, "panic-on-signal")
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Listener<()> for PanicOnSignal {
+        async fn bind(&mut self, _app: Server<()>) -> io::Result<()> {
+            Ok(())
+        }
+
+        async fn accept(&mut self) -> io::Result<()> {
+            let _ = self.0.recv().await;
+            panic!("connection setup bug");
+        }
+
+        fn info(&self) -> Vec<ListenInfo> {
+            vec![ListenInfo::new(
+                "panic-on-signal".to_string(),
+                "test".to_string(),
+                false,
+            )]
+        }
+    }
+
+    impl ToListener<()> for PanicOnSignal {
+        type Listener = Self;
+
+        fn to_listener(self) -> io::Result<Self::Listener> {
+            Ok(self)
+        }
+    }
+
+    fn quick_restarts(max: usize) -> RestartPolicy {
+        RestartPolicy::new()
+            .max_restarts(max)
+            .backoff(Duration::from_millis(1))
+    }
+
+    fn tagged(error: &io::Error) -> &ListenerError {
+        error.get_ref().unwrap().downcast_ref().unwrap()
+    }
+
+    #[async_std::test]
+    async fn fail_fast_cancels_siblings() {
+        let failing = Rigged::new("failing", 10, true);
+        let healthy = Rigged::new("healthy", 60_000, false);
+        let (finished, dropped) = (healthy.finished.clone(), healthy.dropped.clone());
+
+        let mut listener = ConcurrentListener::new()
+            .with_listener(failing)
+            .with_listener(healthy);
+        listener.bind(crate::new()).await.unwrap();
+        let error = listener.accept().await.unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::BrokenPipe);
+        assert_eq!(tagged(&error).info()[0].connection(), "failing");
+        assert!(dropped.load(Ordering::SeqCst));
+        assert!(!finished.load(Ordering::SeqCst));
+    }
+
+    #[async_std::test]
+    async fn continue_waits_for_all_listeners() {
+        let failing = Rigged::new("failing", 10, true);
+        let healthy = Rigged::new("healthy", 50, false);
+        let finished = healthy.finished.clone();
+
+        let mut listener = ConcurrentListener::new()
+            .with_failure_policy(FailurePolicy::Continue)
+            .with_listener(failing)
+            .with_listener(healthy);
+        listener.bind(crate::new()).await.unwrap();
+        let error = listener.accept().await.unwrap_err();
+
+        assert!(finished.load(Ordering::SeqCst));
+        assert_eq!(tagged(&error).info()[0].connection(), "failing");
+        assert_eq!(tagged(&error).error().to_string(), "rigged");
+    }
+
+    #[async_std::test]
+    async fn restarts_panicked_listener() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut listener = ConcurrentListener::new()
+            .with_restart_policy(quick_restarts(3))
+            .with_listener(Panicking {
+                panics: 2,
+                calls: calls.clone(),
+            });
+        listener.bind(crate::new()).await.unwrap();
+
+        listener.accept().await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[async_std::test]
+    async fn escalates_when_restarts_run_out() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let healthy = Rigged::new("healthy", 60_000, false);
+        let dropped = healthy.dropped.clone();
+        let mut listener = ConcurrentListener::new()
+            .with_failure_policy(FailurePolicy::Continue)
+            .with_restart_policy(quick_restarts(2))
+            .with_listener(Panicking {
+                panics: usize::MAX,
+                calls: calls.clone(),
+            })
+            .with_listener(healthy);
+        listener.bind(crate::new()).await.unwrap();
+
+        let error = listener.accept().await.unwrap_err();
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert_eq!(tagged(&error).info()[0].connection(), "panicking");
+        assert_eq!(
+            tagged(&error).error().to_string(),
+            "panicked after 2 restarts: connection setup bug"
+        );
+        // 其他侦听器被取消
+        assert!(dropped.load(Ordering::SeqCst));
+    }
+
+    #[async_std::test]
+    async fn escalation_shuts_down_gracefully() {
+        use async_std::io::prelude::*;
+        use async_std::net::TcpStream;
+
+        let responded = Arc::new(AtomicBool::new(false));
+        let mut app = crate::new();
+        app.at("/slow").get({
+            let responded = responded.clone();
+            move |_| {
+                let responded = responded.clone();
+                async move {
+                    async_std::task::sleep(Duration::from_millis(300)).await;
+                    responded.store(true, Ordering::SeqCst);
+                    Ok("slow")
+                }
+            }
+        });
+
+        let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = tcp.local_addr().unwrap();
+        let (panic, signal) = async_channel::bounded(1);
+        let mut listener = ConcurrentListener::new()
+            .with_restart_policy(RestartPolicy::never())
+            .with_listener(crate::tcp::TcpListener::from_listener(tcp))
+            .with_listener(PanicOnSignal(signal));
+        listener.bind(app).await.unwrap();
+        let accepting = async_std::task::spawn(async move { listener.accept().await });
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET /slow HTTP/1.1\r\nhost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        async_std::task::sleep(Duration::from_millis(50)).await;
+        panic.send(()).await.unwrap();
+
+        // 等待处理中的请求完成后才返回
+        let error = accepting.await.unwrap_err();
+        assert!(responded.load(Ordering::SeqCst));
+        assert_eq!(
+            tagged(&error).error().to_string(),
+            "panicked after 0 restarts: connection setup bug"
+        );
+
+        // 请求完成后关闭 keep-alive 连接，不再接受新连接
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.to_ascii_lowercase().contains("connection: close"));
+        assert!(response.ends_with("slow"));
+        assert!(TcpStream::connect(addr).await.is_err());
+    }
+
+    #[async_std::test]
+    async fn restarts_failed_listener() {
+        for (max, failures, succeeds) in [(2, 2, true), (1, 2, false)] {
+            let calls = Arc::new(AtomicUsize::new(0));
+            let mut listener = ConcurrentListener::new()
+                .with_restart_policy(quick_restarts(0))
+                .with_max_listener_restarts(max)
+                .with_listener(Flaky {
+                    failures,
+                    calls: calls.clone(),
+                });
+            listener.bind(crate::new()).await.unwrap();
+
+            let result = listener.accept().await;
+            assert_eq!(result.is_ok(), succeeds);
+            assert_eq!(calls.load(Ordering::SeqCst), max as usize + 1);
+            if let Err(error) = result {
+                assert_eq!(tagged(&error).info()[0].connection(), "flaky");
+                assert_eq!(tagged(&error).error().to_string(), "too many open files");
+            }
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_max() {
+        let policy = RestartPolicy::new()
+            .backoff(Duration::from_millis(100))
+            .max_backoff(Duration::from_millis(350));
+        assert_eq!(policy.delay(1), Duration::from_millis(100));
+        assert_eq!(policy.delay(2), Duration::from_millis(200));
+        assert_eq!(policy.delay(3), Duration::from_millis(350));
+        assert_eq!(policy.delay(100), Duration::from_millis(350));
+        assert_eq!(RestartPolicy::never().max_restarts, 0);
+    }
+
+    #[test]
+    fn default_matches_new() {
+        let listener = ConcurrentListener::<()>::default();
+        assert_eq!(listener.drain_timeout, Duration::from_secs(30));
+        assert_eq!(listener.max_listener_restarts, 0);
+    }
+}
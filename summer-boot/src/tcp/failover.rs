@@ -1,4 +1,4 @@
-use crate::tcp::{Listener, ToListener};
+use crate::tcp::{is_transient_error, Listener, ToListener};
 use crate::Server;
 
 use std::fmt::{self, Debug, Display, Formatter};
@@ -7,13 +7,17 @@ use async_std::io;
 
 use crate::tcp::ListenInfo;
 
+/// 按顺序尝试多个侦听器，使用第一个可用的
+///
+/// 绑定时选择第一个绑定成功的侦听器。正在使用的侦听器 accept 出现非暂时性错误时，
+/// 关闭它并依次绑定后面的侦听器继续服务，所有侦听器都失败后才返回错误。
 #[derive(Default)]
 pub struct FailoverListener<State> {
     listeners: Vec<Option<Box<dyn Listener<State>>>>,
     index: Option<usize>,
+    server: Option<Server<State>>,
 }
 
-#[allow(dead_code)]
 impl<State> FailoverListener<State>
 where
     State: Clone + Send + Sync + 'static,
@@ -22,6 +26,7 @@ where
         Self {
             listeners: vec![],
             index: None,
+            server: None,
         }
     }
 
@@ -40,29 +45,44 @@ where
         self.add(listener).expect("无法添加侦听器");
         self
     }
-}
 
-#[async_trait::async_trait]
-impl<State> Listener<State> for FailoverListener<State>
-where
-    State: Clone + Send + Sync + 'static,
-{
-    async fn bind(&mut self, app: Server<State>) -> io::Result<()> {
-        for (index, listener) in self.listeners.iter_mut().enumerate() {
-            let listener = listener.as_deref_mut().expect("bind调用了两次");
+    /// 从 `start` 开始依次绑定，记录第一个绑定成功的侦听器
+    async fn bind_from(&mut self, start: usize) -> bool {
+        let app = self.server.clone().expect("`bind` 必须在之前调用");
+        for (index, slot) in self.listeners.iter_mut().enumerate().skip(start) {
+            let Some(listener) = slot.as_deref_mut() else {
+                continue;
+            };
             match listener.bind(app.clone()).await {
                 Ok(_) => {
                     self.index = Some(index);
-                    return Ok(());
+                    return true;
                 }
                 Err(e) => {
                     crate::log::info!("无法绑定", {
                         listener: listener.to_string(),
                         error: e.to_string()
                     });
+                    // 绑定失败的侦听器不再使用
+                    *slot = None;
                 }
             }
         }
+        false
+    }
+}
+
+#[async_trait::async_trait]
+impl<State> Listener<State> for FailoverListener<State>
+where
+    State: Clone + Send + Sync + 'static,
+{
+    async fn bind(&mut self, app: Server<State>) -> io::Result<()> {
+        assert!(self.server.is_none(), "bind调用了两次");
+        self.server = Some(app);
+        if self.bind_from(0).await {
+            return Ok(());
+        }
 
         Err(io::Error::new(
             io::ErrorKind::AddrNotAvailable,
@@ -71,16 +91,33 @@ where
     }
 
     async fn accept(&mut self) -> io::Result<()> {
-        match self.index {
-            Some(index) => {
-                let mut listener = self.listeners[index].take().expect("accept调用了两次");
-                listener.accept().await?;
-                Ok(())
-            }
-            None => Err(io::Error::new(
+        let Some(mut index) = self.index else {
+            return Err(io::Error::new(
                 io::ErrorKind::AddrNotAvailable,
                 "无法侦听任何提供的侦听器",
-            )),
+            ));
+        };
+
+        loop {
+            let listener = self.listeners[index]
+                .as_deref_mut()
+                .expect("accept调用了两次");
+            let error = match listener.accept().await {
+                Ok(()) => return Ok(()),
+                Err(e) if is_transient_error(&e) => return Err(e),
+                Err(e) => e,
+            };
+
+            crate::log::error!("侦听器出错，切换到下一个侦听器", {
+                listener: listener.to_string(),
+                error: error.to_string()
+            });
+            self.listeners[index] = None;
+            self.index = None;
+            if !self.bind_from(index + 1).await {
+                return Err(error);
+            }
+            index = self.index.expect("绑定成功后应当记录侦听器");
         }
     }
 
@@ -117,3 +154,130 @@ impl<State> Display for FailoverListener<State> {
         writeln!(f, "{}", string)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use async_std::io::{ReadExt, WriteExt};
+    use async_std::net::{TcpListener, TcpStream};
+    use async_std::task;
+
+    /// 回应 `limit` 个连接后 accept 返回错误，模拟失效的套接字
+    #[derive(Debug)]
+    struct Flaky {
+        listener: Option<TcpListener>,
+        limit: usize,
+    }
+
+    impl Display for Flaky {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            write!(f, "flaky")
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Listener<()> for Flaky {
+        async fn bind(&mut self, _app: Server<()>) -> io::Result<()> {
+            Ok(())
+        }
+
+        async fn accept(&mut self) -> io::Result<()> {
+            let listener = self.listener.take().unwrap();
+            for _ in 0..self.limit {
+                let (mut stream, _) = listener.accept().await?;
+                let mut buf = [0; 1024];
+                let _ = stream.read(&mut buf).await?;
+                stream
+                    .write_all(
+                        b"HTTP/1.1 200 OK\r\ncontent-length: 5\r\nconnection: close\r\n\r\nflaky",
+                    )
+                    .await?;
+            }
+            Err(io::Error::new(io::ErrorKind::NotFound, "stale socket"))
+        }
+
+        fn info(&self) -> Vec<ListenInfo> {
+            vec![ListenInfo::new(
+                "flaky".to_string(),
+                "test".to_string(),
+                false,
+            )]
+        }
+    }
+
+    impl ToListener<()> for Flaky {
+        type Listener = Self;
+
+        fn to_listener(self) -> io::Result<Self::Listener> {
+            Ok(self)
+        }
+    }
+
+    async fn get(addr: std::net::SocketAddr) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut raw = String::new();
+        stream.read_to_string(&mut raw).await.unwrap();
+        raw.split_once("\r\n\r\n").unwrap().1.to_string()
+    }
+
+    #[async_std::test]
+    async fn fails_over_when_accept_fails() {
+        let primary = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let primary_addr = primary.local_addr().unwrap();
+        let fallback = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let fallback_addr = fallback.local_addr().unwrap();
+
+        let mut app = crate::new();
+        app.at("/").get(|_| async { Ok("fallback") });
+        let mut listener = FailoverListener::new()
+            .with_listener(Flaky {
+                listener: Some(primary),
+                limit: 2,
+            })
+            .with_listener(fallback);
+        listener.bind(app).await.unwrap();
+        assert_eq!(listener.info()[0].connection(), "flaky");
+        task::spawn(async move { listener.accept().await });
+
+        assert_eq!(get(primary_addr).await, "flaky");
+        assert_eq!(get(primary_addr).await, "flaky");
+        for _ in 0..3 {
+            assert_eq!(get(fallback_addr).await, "fallback");
+        }
+    }
+
+    #[async_std::test]
+    async fn gives_up_when_all_listeners_fail() {
+        let flaky = |limit| async move {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            let flaky = Flaky {
+                listener: Some(listener),
+                limit,
+            };
+            (flaky, addr)
+        };
+        let (primary, primary_addr) = flaky(1).await;
+        let (fallback, fallback_addr) = flaky(1).await;
+
+        let mut listener = FailoverListener::new()
+            .with_listener(primary)
+            .with_listener(fallback);
+        listener.bind(crate::new()).await.unwrap();
+        let accept = task::spawn(async move {
+            let result = listener.accept().await;
+            (result, listener.info())
+        });
+
+        assert_eq!(get(primary_addr).await, "flaky");
+        assert_eq!(get(fallback_addr).await, "flaky");
+        let (result, info) = accept.await;
+        assert_eq!(result.unwrap_err().to_string(), "stale socket");
+        assert!(info.is_empty());
+    }
+}
@@ -4,6 +4,8 @@ use crate::Server;
 mod concurrent;
 mod failover;
 mod parsed;
+mod shutdown;
+mod sniff;
 mod tcp_listener;
 mod to_listener;
 mod to_listener_impls;
@@ -15,14 +17,18 @@ use std::fmt::{Debug, Display};
 use async_std::io;
 use async_trait::async_trait;
 
-pub use concurrent::ConcurrentListener;
+pub use concurrent::{ConcurrentListener, FailurePolicy, ListenerError, RestartPolicy};
 pub use failover::FailoverListener;
 pub use to_listener::ToListener;
 
 pub(crate) use parsed::ParsedListener;
-pub(crate) use tcp_listener::TcpListener;
+pub(crate) use shutdown::Shutdown;
+pub use sniff::Sniffing;
+#[cfg(all(unix, feature = "upgrade"))]
+pub(crate) use tcp_listener::handle_tcp_with;
+pub use tcp_listener::TcpListener;
 #[cfg(unix)]
-pub(crate) use unix::UnixListener;
+pub use unix::UnixListener;
 
 #[macro_export]
 macro_rules! read_to_end {
@@ -0,0 +1,78 @@
+//! 通知连接停止处理新的请求
+
+use async_channel::{Receiver, Sender};
+use async_std::task;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// 停机的通知，克隆的实例共享同一个状态
+///
+/// 触发后连接处理完当前请求就关闭，等待下一个请求的空闲 keep-alive 连接立即关闭。
+/// 同时记录仍在处理的连接数，停机时用 [`Shutdown::drain`] 等待连接结束。
+#[derive(Debug, Clone)]
+pub(crate) struct Shutdown {
+    sender: Sender<()>,
+    receiver: Receiver<()>,
+    connections: Arc<AtomicUsize>,
+}
+
+impl Shutdown {
+    pub(crate) fn new() -> Self {
+        // 不发送任何消息，关闭通道即为通知
+        let (sender, receiver) = async_channel::bounded(1);
+        Self {
+            sender,
+            receiver,
+            connections: Arc::default(),
+        }
+    }
+
+    pub(crate) fn trigger(&self) {
+        self.sender.close();
+    }
+
+    pub(crate) fn is_triggered(&self) -> bool {
+        self.sender.is_closed()
+    }
+
+    /// 等待触发，已经触发时立即返回
+    pub(crate) async fn wait(&self) {
+        let _ = self.receiver.recv().await;
+    }
+
+    /// 记录一个连接，返回的守卫销毁时连接结束
+    pub(crate) fn track(&self) -> Tracked {
+        self.connections.fetch_add(1, Ordering::SeqCst);
+        Tracked(self.connections.clone())
+    }
+
+    /// 最多等待 `timeout`，直到所有记录的连接结束，返回仍未结束的连接数
+    pub(crate) async fn drain(&self, timeout: Duration) -> usize {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = self.connections.load(Ordering::SeqCst);
+            if remaining == 0 || Instant::now() >= deadline {
+                return remaining;
+            }
+            task::sleep(Duration::from_millis(50)).await;
+        }
+    }
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 正在处理的连接，见 [`Shutdown::track`]
+#[derive(Debug)]
+pub(crate) struct Tracked(Arc<AtomicUsize>);
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
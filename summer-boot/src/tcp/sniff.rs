@@ -0,0 +1,442 @@
+//! 连接建立后识别 HAProxy PROXY 协议头和 TCP 健康检查
+//!
+//! 为识别协议读取的字节在交给 HTTP 服务器之前会原样放回，普通 HTTP 客户端不受影响。
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+use async_std::future::timeout;
+use async_std::io::{self, Read, ReadExt};
+
+/// PROXY 协议 v1 的前缀
+const PROXY_V1: &[u8] = b"PROXY ";
+/// PROXY 协议 v1 头的最大长度，包含结尾的 CRLF
+const PROXY_V1_MAX: usize = 107;
+/// PROXY 协议 v2 的签名
+const PROXY_V2: &[u8] = b"\r\n\r\n\0\r\nQUIT\n";
+/// TCP 健康检查的请求和响应
+const PING: &[u8] = b"PING\r\n";
+pub(crate) const PONG: &[u8] = b"PONG\r\n";
+
+/// 侦听器在 HTTP 之前识别的协议，默认都不开启
+///
+/// # Examples
+///
+/// ```no_run
+/// # async_std::task::block_on(async {
+/// use summer_boot::{Sniffing, TcpListener};
+///
+/// let listener = TcpListener::from_addrs(vec!["0.0.0.0:8080".parse().unwrap()])
+///     .with_sniffing(Sniffing::new().proxy_protocol(true).ping(true));
+/// summer_boot::new().listen(listener).await.unwrap();
+/// # });
+/// ```
+///
+/// 也可以通过地址参数开启，例如 `http://0.0.0.0:8080?proxy_protocol=true&ping=true`。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sniffing {
+    proxy_protocol: bool,
+    ping: bool,
+    timeout: Duration,
+}
+
+impl Default for Sniffing {
+    fn default() -> Self {
+        Self {
+            proxy_protocol: false,
+            ping: false,
+            timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+impl Sniffing {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 识别 PROXY 协议 v1 和 v2 头，用其中的客户端地址作为请求的对端地址
+    ///
+    /// 开启后每个连接都必须以 PROXY 协议头开头，否则直接关闭，避免绕过负载均衡器的客户端伪造地址；
+    /// 开启 [`Sniffing::ping`] 时健康检查除外。只应在负载均衡器之后开启。
+    #[must_use]
+    pub fn proxy_protocol(mut self, enabled: bool) -> Self {
+        self.proxy_protocol = enabled;
+        self
+    }
+
+    /// 连接以 `PING\r\n` 开头时回复 `PONG\r\n` 并关闭连接
+    #[must_use]
+    pub fn ping(mut self, enabled: bool) -> Self {
+        self.ping = enabled;
+        self
+    }
+
+    /// 收到第一批字节后等待识别完成的最长时间，超时后关闭连接，默认 5 秒
+    ///
+    /// 等待第一批字节的时间与普通 HTTP 连接相同，由 `ServerOptions` 的请求头超时限制，
+    /// 预先建立后暂时空闲的连接不会因此被关闭。
+    #[must_use]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 是否开启了任何识别
+    pub(crate) fn enabled(&self) -> bool {
+        self.proxy_protocol || self.ping
+    }
+}
+
+/// 识别的结果
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Sniffed {
+    /// 按 HTTP 处理，`pending` 为已经读取的字节，PROXY 协议头中有地址时替换连接的地址
+    Http {
+        pending: Vec<u8>,
+        peer: Option<SocketAddr>,
+        local: Option<SocketAddr>,
+    },
+    /// TCP 健康检查
+    Ping,
+    /// 读取到任何字节之前连接已关闭
+    Closed,
+}
+
+/// 对已有的字节做出的判断
+enum Step {
+    NeedMore,
+    Http,
+    Ping,
+    /// PROXY 协议头的长度
+    Proxy(usize),
+}
+
+fn classify(buf: &[u8], options: &Sniffing) -> io::Result<Step> {
+    let starts = |magic: &[u8]| {
+        let n = buf.len().min(magic.len());
+        buf[..n] == magic[..n]
+    };
+    if options.ping && starts(PING) {
+        return Ok(if buf.len() >= PING.len() {
+            Step::Ping
+        } else {
+            Step::NeedMore
+        });
+    }
+    if !options.proxy_protocol {
+        return Ok(Step::Http);
+    }
+    if starts(PROXY_V1) {
+        if buf.len() < PROXY_V1.len() {
+            return Ok(Step::NeedMore);
+        }
+        // 一次读到的字节可能超过头的最大长度，只在最大长度之内查找结尾
+        let head = &buf[..buf.len().min(PROXY_V1_MAX)];
+        return match head.windows(2).position(|w| w == b"\r\n") {
+            Some(end) => Ok(Step::Proxy(end + 2)),
+            None if buf.len() >= PROXY_V1_MAX => Err(invalid("PROXY v1 header is too long")),
+            None => Ok(Step::NeedMore),
+        };
+    }
+    if starts(PROXY_V2) {
+        if buf.len() < 16 {
+            return Ok(Step::NeedMore);
+        }
+        let len = 16 + u16::from_be_bytes([buf[14], buf[15]]) as usize;
+        return Ok(if buf.len() >= len {
+            Step::Proxy(len)
+        } else {
+            Step::NeedMore
+        });
+    }
+    Err(invalid("missing PROXY protocol header"))
+}
+
+/// 读取连接开头的字节并识别协议
+///
+/// 最多等待 `idle` 收到第一批字节，超时按连接已关闭处理；之后按 [`Sniffing::timeout`] 限制。
+pub(crate) async fn sniff<R: Read + Unpin>(
+    io: &mut R,
+    options: &Sniffing,
+    idle: Option<Duration>,
+) -> io::Result<Sniffed> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 512];
+    let step = loop {
+        let step = if buf.is_empty() {
+            Step::NeedMore
+        } else {
+            classify(&buf, options)?
+        };
+        if !matches!(step, Step::NeedMore) {
+            break step;
+        }
+        let n = if buf.is_empty() {
+            match idle {
+                Some(idle) => match timeout(idle, io.read(&mut chunk)).await {
+                    Ok(n) => n?,
+                    Err(_) => return Ok(Sniffed::Closed),
+                },
+                None => io.read(&mut chunk).await?,
+            }
+        } else {
+            timeout(options.timeout, io.read(&mut chunk))
+                .await
+                .map_err(|_| {
+                    io::Error::new(io::ErrorKind::TimedOut, "protocol sniffing timed out")
+                })??
+        };
+        if n == 0 {
+            if buf.is_empty() {
+                return Ok(Sniffed::Closed);
+            }
+            if options.proxy_protocol {
+                return Err(invalid("incomplete PROXY protocol header"));
+            }
+            // 数据不完整，交给 HTTP 解析器报告错误
+            break Step::Http;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    };
+
+    Ok(match step {
+        Step::Ping => Sniffed::Ping,
+        Step::Proxy(len) => {
+            let addrs = if buf.starts_with(PROXY_V1) {
+                parse_v1(&buf[..len])?
+            } else {
+                parse_v2(&buf[..len])?
+            };
+            Sniffed::Http {
+                pending: buf.split_off(len),
+                peer: addrs.map(|(peer, _)| peer),
+                local: addrs.map(|(_, local)| local),
+            }
+        }
+        Step::Http | Step::NeedMore => Sniffed::Http {
+            pending: buf,
+            peer: None,
+            local: None,
+        },
+    })
+}
+
+/// 解析 `PROXY TCP4 源地址 目标地址 源端口 目标端口\r\n`，`UNKNOWN` 返回 `None`
+fn parse_v1(header: &[u8]) -> io::Result<Option<(SocketAddr, SocketAddr)>> {
+    let header = std::str::from_utf8(header).map_err(|_| invalid("invalid PROXY v1 header"))?;
+    let mut parts = header.trim_end_matches("\r\n").split(' ').skip(1);
+    match parts.next() {
+        Some("TCP4") | Some("TCP6") => {}
+        Some("UNKNOWN") => return Ok(None),
+        _ => return Err(invalid("unsupported PROXY v1 protocol")),
+    }
+    let fields: Vec<&str> = parts.collect();
+    let [src, dst, src_port, dst_port] = fields[..] else {
+        return Err(invalid("invalid PROXY v1 header"));
+    };
+    let addr = |ip: &str, port: &str| -> io::Result<SocketAddr> {
+        let ip: IpAddr = ip
+            .parse()
+            .map_err(|_| invalid("invalid PROXY v1 address"))?;
+        let port: u16 = port.parse().map_err(|_| invalid("invalid PROXY v1 port"))?;
+        Ok(SocketAddr::new(ip, port))
+    };
+    Ok(Some((addr(src, src_port)?, addr(dst, dst_port)?)))
+}
+
+/// 解析二进制的 v2 头，`LOCAL` 命令和非 TCP/UDP over IP 的地址返回 `None`
+fn parse_v2(header: &[u8]) -> io::Result<Option<(SocketAddr, SocketAddr)>> {
+    let version_command = header[12];
+    if version_command >> 4 != 2 {
+        return Err(invalid("unsupported PROXY protocol version"));
+    }
+    match version_command & 0x0f {
+        0 => return Ok(None),
+        1 => {}
+        _ => return Err(invalid("unsupported PROXY v2 command")),
+    }
+    let addrs = &header[16..];
+    let port = |at: usize| u16::from_be_bytes([addrs[at], addrs[at + 1]]);
+    match header[13] >> 4 {
+        // AF_INET
+        1 if addrs.len() >= 12 => {
+            let ip =
+                |at: usize| Ipv4Addr::new(addrs[at], addrs[at + 1], addrs[at + 2], addrs[at + 3]);
+            Ok(Some((
+                SocketAddr::new(ip(0).into(), port(8)),
+                SocketAddr::new(ip(4).into(), port(10)),
+            )))
+        }
+        // AF_INET6
+        2 if addrs.len() >= 36 => {
+            let ip = |at: usize| {
+                let octets: [u8; 16] = addrs[at..at + 16].try_into().unwrap();
+                Ipv6Addr::from(octets)
+            };
+            Ok(Some((
+                SocketAddr::new(ip(0).into(), port(32)),
+                SocketAddr::new(ip(16).into(), port(34)),
+            )))
+        }
+        1 | 2 => Err(invalid("truncated PROXY v2 address")),
+        _ => Ok(None),
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tcp::{Listener, TcpListener};
+    use async_std::io::prelude::*;
+    use async_std::net::TcpStream;
+
+    /// 启动开启了 `sniffing` 的服务器，返回地址
+    async fn server(sniffing: Sniffing) -> SocketAddr {
+        let mut app = crate::new();
+        app.at("/").get(|req: crate::Request<()>| async move {
+            Ok(format!(
+                "{} {}",
+                req.peer_addr().unwrap_or_default(),
+                req.local_addr().unwrap_or_default()
+            ))
+        });
+        let std_listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = std_listener.local_addr().unwrap();
+        let mut listener = TcpListener::<()>::from_listener(std_listener).with_sniffing(sniffing);
+        listener.bind(app).await.unwrap();
+        async_std::task::spawn(async move { listener.accept().await });
+        addr
+    }
+
+    /// 发送 `preamble` 和一个 HTTP 请求，返回响应体
+    async fn request(addr: SocketAddr, preamble: &[u8]) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(preamble).await.unwrap();
+        // 分两次发送，验证识别时读取的字节不会丢失
+        stream.write_all(b"GE").await.unwrap();
+        stream
+            .write_all(b"T / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut res = String::new();
+        stream.read_to_string(&mut res).await.unwrap();
+        assert!(res.starts_with("HTTP/1.1 200"), "{}", res);
+        res.split("\r\n\r\n").nth(1).unwrap().to_string()
+    }
+
+    fn proxied() -> Sniffing {
+        Sniffing::new().proxy_protocol(true).ping(true)
+    }
+
+    #[async_std::test]
+    async fn plain_http_and_ping() {
+        let addr = server(Sniffing::new().ping(true)).await;
+        let body = request(addr, b"").await;
+        assert!(body.starts_with("127.0.0.1:"), "{}", body);
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(b"PING\r\n").await.unwrap();
+        let mut res = Vec::new();
+        stream.read_to_end(&mut res).await.unwrap();
+        assert_eq!(res, PONG);
+    }
+
+    #[async_std::test]
+    async fn proxy_protocol_v1() {
+        let addr = server(proxied()).await;
+        let body = request(addr, b"PROXY TCP4 203.0.113.7 10.0.0.1 56324 443\r\n").await;
+        assert_eq!(body, "203.0.113.7:56324 10.0.0.1:443");
+        let body = request(addr, b"PROXY TCP6 2001:db8::7 2001:db8::1 56324 443\r\n").await;
+        assert_eq!(body, "[2001:db8::7]:56324 [2001:db8::1]:443");
+        let body = request(addr, b"PROXY UNKNOWN\r\n").await;
+        assert!(body.starts_with("127.0.0.1:"), "{}", body);
+    }
+
+    #[async_std::test]
+    async fn proxy_protocol_v2() {
+        let addr = server(proxied()).await;
+        let mut header = PROXY_V2.to_vec();
+        header.extend_from_slice(&[0x21, 0x11, 0, 12]);
+        header.extend_from_slice(&[198, 51, 100, 9, 10, 0, 0, 1]);
+        header.extend_from_slice(&40000u16.to_be_bytes());
+        header.extend_from_slice(&8080u16.to_be_bytes());
+        let body = request(addr, &header).await;
+        assert_eq!(body, "198.51.100.9:40000 10.0.0.1:8080");
+
+        // LOCAL 命令使用连接本身的地址
+        let mut local = PROXY_V2.to_vec();
+        local.extend_from_slice(&[0x20, 0x00, 0, 0]);
+        let body = request(addr, &local).await;
+        assert!(body.starts_with("127.0.0.1:"), "{}", body);
+    }
+
+    #[async_std::test]
+    async fn proxy_header_is_required() {
+        let addr = server(proxied()).await;
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        let mut res = Vec::new();
+        stream.read_to_end(&mut res).await.unwrap();
+        assert!(res.is_empty());
+
+        // 健康检查不需要 PROXY 协议头
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(PING).await.unwrap();
+        let mut res = Vec::new();
+        stream.read_to_end(&mut res).await.unwrap();
+        assert_eq!(res, PONG);
+    }
+
+    #[async_std::test]
+    async fn idle_connections_are_not_timed_out() {
+        let sniffing = proxied().timeout(Duration::from_millis(50));
+        let addr = server(sniffing).await;
+        // 预先建立的连接空闲超过识别超时后再发送请求
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        async_std::task::sleep(Duration::from_millis(200)).await;
+        stream
+            .write_all(b"PROXY TCP4 203.0.113.7 10.0.0.1 56324 443\r\n")
+            .await
+            .unwrap();
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut res = String::new();
+        stream.read_to_string(&mut res).await.unwrap();
+        assert!(res.ends_with("203.0.113.7:56324 10.0.0.1:443"), "{}", res);
+
+        // 开始发送后仍然受识别超时限制
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(b"PROXY TCP4").await.unwrap();
+        let mut res = Vec::new();
+        let read = async_std::future::timeout(Duration::from_secs(5), stream.read_to_end(&mut res));
+        read.await.unwrap().unwrap();
+        assert!(res.is_empty());
+    }
+
+    #[test]
+    fn disabled_sniffers_pass_through() {
+        let options = Sniffing::new();
+        assert!(matches!(classify(b"PING\r\n", &options), Ok(Step::Http)));
+        assert!(matches!(classify(b"PROXY ", &options), Ok(Step::Http)));
+        let options = Sniffing::new().proxy_protocol(true);
+        assert!(classify(b"POST / HTTP/1.1", &options).is_err());
+        assert!(matches!(classify(b"PRO", &options), Ok(Step::NeedMore)));
+        assert!(classify(&[b"PROXY ".as_slice(), &[b'x'; 120]].concat(), &options).is_err());
+        // 结尾在同一次读取中但超过了最大长度
+        let long = [b"PROXY ".as_slice(), &[b'x'; 110], b"\r\nGET / HTTP/1.1"].concat();
+        assert!(classify(&long, &options).is_err());
+        let max = [b"PROXY ".as_slice(), &[b'x'; 99], b"\r\nGET"].concat();
+        assert!(matches!(classify(&max, &options), Ok(Step::Proxy(107))));
+    }
+}
@@ -1,6 +1,8 @@
-use super::{is_transient_error, ListenInfo};
+use super::sniff::{self, Sniffed, Sniffing};
+use super::{is_transient_error, ListenInfo, Shutdown};
 
 use super::Listener;
+use crate::log::ConnectionGuard;
 use crate::{http, log, Server};
 
 use std::fmt::{self, Display, Formatter};
@@ -9,11 +11,14 @@ use async_std::net::{self, SocketAddr, TcpStream};
 use async_std::prelude::*;
 use async_std::{io, task};
 
+/// TCP 侦听器，一般通过地址字符串创建，需要识别 PROXY 协议等时直接构建
 pub struct TcpListener<State> {
     addrs: Option<Vec<SocketAddr>>,
     listener: Option<net::TcpListener>,
     server: Option<Server<State>>,
     info: Option<ListenInfo>,
+    sniffing: Sniffing,
+    server_options: http::ServerOptions,
 }
 
 impl<State> TcpListener<State> {
@@ -23,6 +28,8 @@ impl<State> TcpListener<State> {
             listener: None,
             server: None,
             info: None,
+            sniffing: Sniffing::default(),
+            server_options: http::ServerOptions::default(),
         }
     }
 
@@ -32,23 +39,81 @@ impl<State> TcpListener<State> {
             listener: Some(tcp_listener.into()),
             server: None,
             info: None,
+            sniffing: Sniffing::default(),
+            server_options: http::ServerOptions::default(),
         }
     }
+
+    /// 在 HTTP 之前识别 PROXY 协议头或 TCP 健康检查
+    #[must_use]
+    pub fn with_sniffing(mut self, sniffing: Sniffing) -> Self {
+        self.sniffing = sniffing;
+        self
+    }
+
+    /// 设置每个连接的 HTTP/1.1 选项，例如 keep-alive 超时和每个连接的最大请求数
+    #[must_use]
+    pub fn with_server_options(mut self, opts: http::ServerOptions) -> Self {
+        self.server_options = opts;
+        self
+    }
 }
 
-fn handle_tcp<State: Clone + Send + Sync + 'static>(app: Server<State>, stream: TcpStream) {
+/// 在新任务中处理连接
+///
+/// 先按 `sniffing` 识别连接开头的协议，使用 `opts` 处理 HTTP。
+/// 连接记录在 `shutdown` 中，触发后不再等待后续请求。
+pub(crate) fn handle_tcp_with<State: Clone + Send + Sync + 'static>(
+    app: Server<State>,
+    mut stream: TcpStream,
+    sniffing: Sniffing,
+    opts: http::ServerOptions,
+    shutdown: Shutdown,
+) {
+    let tracked = shutdown.track();
     task::spawn(async move {
-        let local_addr = stream.local_addr().ok();
-        let peer_addr = stream.peer_addr().ok();
+        let _tracked = tracked;
+        let _connection = ConnectionGuard::open(app.observers());
+        let mut local_addr = stream.local_addr().ok();
+        let mut peer_addr = stream.peer_addr().ok();
+
+        let mut pending = Vec::new();
+        if sniffing.enabled() {
+            match sniff::sniff(&mut stream, &sniffing, opts.headers_timeout()).await {
+                Ok(Sniffed::Http {
+                    pending: sniffed,
+                    peer,
+                    local,
+                }) => {
+                    pending = sniffed;
+                    peer_addr = peer.or(peer_addr);
+                    local_addr = local.or(local_addr);
+                }
+                Ok(Sniffed::Ping) => {
+                    let _ = stream.write_all(sniff::PONG).await;
+                    return;
+                }
+                Ok(Sniffed::Closed) => return,
+                Err(error) => {
+                    log::warn!("protocol sniffing failed", { error: log::sanitize(&error.to_string()) });
+                    return;
+                }
+            }
+        }
 
-        let fut = http::accept(stream, |mut req| async {
-            req.set_local_addr(local_addr);
-            req.set_peer_addr(peer_addr);
-            app.respond(req).await
-        });
+        let mut server = http::Server::new(stream, |req| app.respond(req))
+            .with_addrs(peer_addr, local_addr)
+            .with_opts(opts)
+            .with_pending(pending)
+            .with_shutdown(shutdown);
 
-        if let Err(error) = fut.await {
-            log::error!("http1 error", { error: error.to_string() });
+        if let Err(error) = server.accept().await {
+            log::error!("http1 error", { error: log::sanitize(&error.to_string()) });
+            if let Some(head) = error.downcast_ref::<http::MalformedHead>() {
+                log::warn!("malformed request head", {
+                    raw_head: log::sanitize(&String::from_utf8_lossy(head.raw())),
+                });
+            }
         }
     });
 }
@@ -78,15 +143,21 @@ where
     }
 
     async fn accept(&mut self) -> io::Result<()> {
+        // 不取走侦听器，panic 后可以重新调用 `accept`
         let server = self
             .server
-            .take()
+            .clone()
             .expect("`Listener::bind` 必须在之前调用 `Listener::accept`");
         let listener = self
             .listener
-            .take()
+            .as_ref()
             .expect("`Listener::bind` 必须在之前调用 `Listener::accept`");
 
+        // 路由注册在侦听前已经完成，生成请求目标长度的豁免表
+        let opts = self
+            .server_options
+            .clone()
+            .target_exemptions(server.target_exemptions());
         let mut incoming = listener.incoming();
 
         while let Some(stream) = incoming.next().await {
@@ -100,7 +171,13 @@ where
                 }
 
                 Ok(stream) => {
-                    handle_tcp(server.clone(), stream);
+                    handle_tcp_with(
+                        server.clone(),
+                        stream,
+                        self.sniffing,
+                        opts.clone(),
+                        server.shutdown().clone(),
+                    );
                 }
             };
         }
@@ -120,6 +197,8 @@ impl<State> fmt::Debug for TcpListener<State> {
         f.debug_struct("TcpListener")
             .field("listener", &self.listener)
             .field("addrs", &self.addrs)
+            .field("sniffing", &self.sniffing)
+            .field("server_options", &self.server_options)
             .field(
                 "server",
                 if self.server.is_some() {
@@ -150,3 +229,61 @@ impl<State> Display for TcpListener<State> {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tcp::Listener;
+
+    #[async_std::test]
+    async fn applies_server_options() {
+        let mut app = crate::new();
+        app.at("/").get(|_| async { Ok("ok") });
+        let std_listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = std_listener.local_addr().unwrap();
+        let mut listener = TcpListener::<()>::from_listener(std_listener)
+            .with_server_options(http::ServerOptions::default().max_requests_per_connection(1));
+        listener.bind(app).await.unwrap();
+        task::spawn(async move { listener.accept().await });
+
+        // 连续发送两个请求，只有第一个被处理，之后连接关闭
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let get = "GET / HTTP/1.1\r\nHost: localhost\r\n\r\n";
+        stream.write_all(get.repeat(2).as_bytes()).await.unwrap();
+        let mut res = String::new();
+        stream.read_to_string(&mut res).await.unwrap();
+        assert_eq!(res.matches("HTTP/1.1 200 OK").count(), 1);
+        assert!(res.contains("connection: close"), "{}", res);
+    }
+
+    #[async_std::test]
+    async fn exempts_route_prefixes_from_target_limit() {
+        let mut app = crate::new();
+        app.at("/downloads/*")
+            .max_target_length(8192)
+            .get(|_| async { Ok("file") });
+        app.at("/other").get(|_| async { Ok("other") });
+        let std_listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = std_listener.local_addr().unwrap();
+        let mut listener = TcpListener::<()>::from_listener(std_listener)
+            .with_server_options(http::ServerOptions::default().max_target_length(1024));
+        listener.bind(app).await.unwrap();
+        task::spawn(async move { listener.accept().await });
+
+        let signature = "s".repeat(4000);
+        for (path, status) in [
+            ("/downloads/report.pdf", "200 OK"),
+            ("/other", "414 URI Too Long"),
+        ] {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            let get = format!(
+                "GET {}?sig={} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+                path, signature
+            );
+            stream.write_all(get.as_bytes()).await.unwrap();
+            let mut res = String::new();
+            stream.read_to_string(&mut res).await.unwrap();
+            assert!(res.starts_with(&format!("HTTP/1.1 {}", status)), "{}", res);
+        }
+    }
+}
@@ -1,6 +1,8 @@
 #[cfg(unix)]
 use super::UnixListener;
-use super::{ConcurrentListener, FailoverListener, ParsedListener, TcpListener, ToListener};
+use super::{
+    ConcurrentListener, FailoverListener, ParsedListener, Sniffing, TcpListener, ToListener,
+};
 use async_std::io;
 use http_types::url::Url;
 use std::net::ToSocketAddrs;
@@ -22,7 +24,15 @@ where
                         self.path()
                     ));
 
-                    Ok(ParsedListener::Unix(UnixListener::from_path(path)))
+                    let mut listener = UnixListener::from_path(path);
+                    // 支持通过 `?mode=0660` 设置套接字文件权限
+                    if let Some((_, mode)) = self.query_pairs().find(|(k, _)| k == "mode") {
+                        let mode = u32::from_str_radix(&mode, 8).map_err(|_| {
+                            io::Error::new(io::ErrorKind::InvalidInput, "无效的套接字权限")
+                        })?;
+                        listener = listener.with_mode(mode);
+                    }
+                    Ok(ParsedListener::Unix(listener))
                 }
 
                 #[cfg(not(unix))]
@@ -34,9 +44,29 @@ where
                 }
             }
 
-            "tcp" | "http" => Ok(ParsedListener::Tcp(TcpListener::from_addrs(
-                self.socket_addrs(|| Some(80))?,
-            ))),
+            "tcp" | "http" => {
+                // 支持通过 `?proxy_protocol=true&ping=true` 开启协议识别
+                let mut sniffing = Sniffing::new();
+                for (key, value) in self.query_pairs() {
+                    let enabled = match value.as_ref() {
+                        "true" | "1" => true,
+                        "false" | "0" => false,
+                        _ => {
+                            return Err(io::Error::new(
+                                io::ErrorKind::InvalidInput,
+                                format!("无效的参数 {}={}", key, value),
+                            ))
+                        }
+                    };
+                    sniffing = match key.as_ref() {
+                        "proxy_protocol" => sniffing.proxy_protocol(enabled),
+                        "ping" => sniffing.ping(enabled),
+                        _ => sniffing,
+                    };
+                }
+                let listener = TcpListener::from_addrs(self.socket_addrs(|| Some(80))?);
+                Ok(ParsedListener::Tcp(listener.with_sniffing(sniffing)))
+            }
 
             // 后续考虑支持ssl正在封装，tls暂时不做处理
             "tls" | "ssl" | "https" => Err(io::Error::new(
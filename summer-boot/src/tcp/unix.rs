@@ -1,21 +1,43 @@
 use super::{is_transient_error, ListenInfo};
 
 use super::Listener;
+use crate::log::ConnectionGuard;
 use crate::{http1, Server};
 
 use std::fmt::{self, Display, Formatter};
+use std::fs::Permissions;
+use std::os::unix::fs::{FileTypeExt, MetadataExt, PermissionsExt};
 
 use async_std::os::unix::net::{self, SocketAddr, UnixStream};
 use async_std::path::PathBuf;
 use async_std::prelude::*;
-use async_std::{io, task};
+use async_std::{fs, io, task};
 use kv_log_macro::error;
 
+/// Unix 套接字侦听器
+///
+/// 绑定路径时，如果路径上是没有进程监听的旧套接字文件会先删除它；仍有进程监听时返回
+/// `AddrInUse`。通过路径绑定的套接字文件在侦听器销毁时删除。
+///
+/// # Examples
+///
+/// ```no_run
+/// # async_std::task::block_on(async {
+/// use summer_boot::UnixListener;
+///
+/// let app = summer_boot::new();
+/// app.listen(UnixListener::from_path("/run/app.sock").with_mode(0o660))
+///     .await?;
+/// # std::io::Result::Ok(()) });
+/// ```
 pub struct UnixListener<State> {
     path: Option<PathBuf>,
     listener: Option<net::UnixListener>,
     server: Option<Server<State>>,
     info: Option<ListenInfo>,
+    mode: Option<u32>,
+    /// 由侦听器创建的套接字文件，销毁时删除
+    socket_file: Option<SocketFile>,
 }
 
 impl<State> UnixListener<State> {
@@ -25,6 +47,8 @@ impl<State> UnixListener<State> {
             listener: None,
             server: None,
             info: None,
+            mode: None,
+            socket_file: None,
         }
     }
 
@@ -34,22 +58,94 @@ impl<State> UnixListener<State> {
             listener: Some(unix_listener.into()),
             server: None,
             info: None,
+            mode: None,
+            socket_file: None,
         }
     }
+
+    /// 同 [`UnixListener::from_path`]，绑定后将套接字文件的权限设置为 `mode`
+    pub fn from_path_with_mode(path: impl Into<PathBuf>, mode: u32) -> Self {
+        Self::from_path(path).with_mode(mode)
+    }
+
+    /// 绑定后将套接字文件的权限设置为 `mode`，例如 `0o660` 只允许所属组连接
+    #[must_use]
+    pub fn with_mode(mut self, mode: u32) -> Self {
+        self.mode = Some(mode);
+        self
+    }
 }
 
-fn handle_unix<State: Clone + Send + Sync + 'static>(app: Server<State>, stream: UnixStream) {
+impl<State> Drop for UnixListener<State> {
+    fn drop(&mut self) {
+        if let Some(socket_file) = self.socket_file.take() {
+            socket_file.remove();
+        }
+    }
+}
+
+/// 侦听器创建的套接字文件，用设备号和 inode 识别，避免删除其他进程之后创建的同名文件
+struct SocketFile {
+    path: std::path::PathBuf,
+    dev: u64,
+    ino: u64,
+}
+
+impl SocketFile {
+    fn new(path: std::path::PathBuf) -> io::Result<Self> {
+        let metadata = std::fs::metadata(&path)?;
+        Ok(Self {
+            path,
+            dev: metadata.dev(),
+            ino: metadata.ino(),
+        })
+    }
+
+    fn remove(self) {
+        let unchanged = std::fs::symlink_metadata(&self.path)
+            .map(|metadata| metadata.dev() == self.dev && metadata.ino() == self.ino)
+            .unwrap_or(false);
+        if unchanged {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// 删除路径上没有进程监听的旧套接字文件，其他类型的文件保持不变
+fn remove_stale_socket(path: &std::path::Path) -> io::Result<()> {
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => {}
+        _ => return Ok(()),
+    }
+    match std::os::unix::net::UnixStream::connect(path) {
+        Ok(_) => Err(io::Error::new(
+            io::ErrorKind::AddrInUse,
+            format!("{} 上已有进程在监听", path.display()),
+        )),
+        Err(err) if err.kind() == io::ErrorKind::ConnectionRefused => std::fs::remove_file(path),
+        Err(_) => Ok(()),
+    }
+}
+
+fn handle_unix<State: Clone + Send + Sync + 'static>(
+    app: Server<State>,
+    stream: UnixStream,
+    opts: http1::http::ServerOptions,
+) {
+    let shutdown = app.shutdown().clone();
+    let tracked = shutdown.track();
     task::spawn(async move {
+        let _tracked = tracked;
+        let _connection = ConnectionGuard::open(app.observers());
         let local_addr = unix_socket_addr_to_string(stream.local_addr());
         let peer_addr = unix_socket_addr_to_string(stream.peer_addr());
-        let fut = http1::http::accept(stream, |mut req| async {
-            req.set_local_addr(local_addr.as_ref());
-            req.set_peer_addr(peer_addr.as_ref());
-            app.respond(req).await
-        });
-
-        if let Err(error) = fut.await {
-            error!("async-h1 error", { error: error.to_string() });
+        let mut server = http1::http::Server::new(stream, |req| app.respond(req))
+            .with_addrs(peer_addr, local_addr)
+            .with_opts(opts)
+            .with_shutdown(shutdown);
+
+        if let Err(error) = server.accept().await {
+            error!("async-h1 error", { error: crate::log::sanitize(&error.to_string()) });
         }
     });
 }
@@ -65,10 +161,21 @@ where
 
         if self.listener.is_none() {
             let path = self.path.take().expect("`bind` should only be called once");
-            let listener = net::UnixListener::bind(path).await?;
+            remove_stale_socket(path.as_ref())?;
+            let listener = net::UnixListener::bind(&path).await?;
+            self.socket_file = SocketFile::new(path.into()).ok();
             self.listener = Some(listener);
         }
 
+        if let Some(mode) = self.mode {
+            let listener = self.listener.as_ref().expect("listener is bound");
+            let addr = listener.local_addr()?;
+            let path = addr.as_pathname().ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidInput, "匿名套接字无法设置权限")
+            })?;
+            fs::set_permissions(path, Permissions::from_mode(mode)).await?;
+        }
+
         // Format the listen information.
         let conn_string = format!("{}", self);
         let transport = "uds".to_owned();
@@ -79,15 +186,19 @@ where
     }
 
     async fn accept(&mut self) -> io::Result<()> {
+        // 不取走侦听器，panic 后可以重新调用 `accept`
         let server = self
             .server
-            .take()
+            .clone()
             .expect("`Listener::bind` must be called before `Listener::accept`");
         let listener = self
             .listener
-            .take()
+            .as_ref()
             .expect("`Listener::bind` must be called before `Listener::accept`");
 
+        // 路由注册在侦听前已经完成，生成请求目标长度的豁免表
+        let opts =
+            http1::http::ServerOptions::default().target_exemptions(server.target_exemptions());
         let mut incoming = listener.incoming();
 
         while let Some(stream) = incoming.next().await {
@@ -101,7 +212,7 @@ where
                 }
 
                 Ok(stream) => {
-                    handle_unix(server.clone(), stream);
+                    handle_unix(server.clone(), stream, opts.clone());
                 }
             };
         }
@@ -121,6 +232,11 @@ impl<State> fmt::Debug for UnixListener<State> {
         f.debug_struct("UnixListener")
             .field("listener", &self.listener)
             .field("path", &self.path)
+            .field("mode", &self.mode)
+            .field(
+                "socket_file",
+                &self.socket_file.as_ref().map(|file| &file.path),
+            )
             .field(
                 "server",
                 if self.server.is_some() {
@@ -160,3 +276,76 @@ fn unix_socket_addr_to_string(result: io::Result<SocketAddr>) -> Option<String>
         .and_then(|p| p.canonicalize().ok())
         .map(|pathname| format!("http+unix://{}", pathname.display()))
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[async_std::test]
+    async fn sets_socket_mode() {
+        let dir = std::env::temp_dir().join("summer-boot-unix-mode");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("app.sock");
+        let _ = std::fs::remove_file(&path);
+
+        let listener = crate::new()
+            .bind(UnixListener::from_path_with_mode(path.clone(), 0o660))
+            .await
+            .unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o660);
+        drop(listener);
+        assert!(!path.exists());
+    }
+
+    #[async_std::test]
+    async fn rebinds_over_stale_socket() {
+        let dir = std::env::temp_dir().join("summer-boot-unix-stale");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("app.sock");
+        let _ = std::fs::remove_file(&path);
+
+        // 模拟异常退出后遗留的套接字文件
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+        assert!(path.exists());
+
+        let first = crate::new()
+            .bind(UnixListener::from_path(path.clone()))
+            .await
+            .unwrap();
+        // 仍在监听的套接字不会被删除
+        let err = crate::new()
+            .bind(UnixListener::from_path(path.clone()))
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AddrInUse);
+        assert!(path.exists());
+
+        drop(first);
+        assert!(!path.exists());
+
+        // 不是套接字的文件保持不变
+        std::fs::write(&path, "data").unwrap();
+        assert!(crate::new()
+            .bind(UnixListener::from_path(path.clone()))
+            .await
+            .is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "data");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn parses_mode_from_url() {
+        use crate::tcp::{ParsedListener, ToListener};
+
+        let url = http_types::Url::parse("http+unix:///tmp/app.sock?mode=660").unwrap();
+        match ToListener::<()>::to_listener(url).unwrap() {
+            ParsedListener::Unix(listener) => assert_eq!(listener.mode, Some(0o660)),
+            _ => panic!("expected a unix listener"),
+        }
+
+        let url = http_types::Url::parse("http+unix:///tmp/app.sock?mode=rw").unwrap();
+        let err = ToListener::<()>::to_listener(url).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+}
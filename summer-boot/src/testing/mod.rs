@@ -0,0 +1,9 @@
+//! 测试支持
+//!
+//! [`TestServer`] 使用 `test` 环境的配置：优先读取 `application-test.yml`，
+//! 不存在时回退到 `profiles.active` 指定的环境。进程的环境变量不会被修改，
+//! 同一进程中的其他测试仍然按原来的方式加载配置。
+
+mod test_server;
+
+pub use test_server::{TestServer, TestServerBuilder};
@@ -0,0 +1,178 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde_json::{Map, Value};
+use summer_boot_autoconfigure::{load_conf_with_profile, GlobalConfig};
+
+use crate::http_types::{self, Method, Url};
+use crate::{Middleware, Next, Request, Server};
+
+/// 测试时使用的环境
+const TEST_PROFILE: &str = "test";
+
+/// [`TestServer`] 的构建器
+#[derive(Debug, Default)]
+pub struct TestServerBuilder {
+    overrides: Vec<(String, Value)>,
+}
+
+impl TestServerBuilder {
+    /// 覆盖配置项，`key` 使用 `.` 分隔，例如 `server.port`
+    #[must_use]
+    pub fn config_override(mut self, key: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.overrides.push((key.into(), value.into()));
+        self
+    }
+
+    /// 加载 `test` 环境的配置，配置会插入到每个请求的扩展中
+    ///
+    /// 只影响这个测试服务，不会修改进程的环境变量，`application-test.yml`
+    /// 不存在时使用 `application.yml`。覆盖后的配置无法转换为 `GlobalConfig` 时 panic。
+    pub fn build<State>(self, mut app: Server<State>) -> TestServer<State>
+    where
+        State: Clone + Send + Sync + 'static,
+    {
+        let config = load_test_config();
+        let config = if self.overrides.is_empty() {
+            config
+        } else {
+            let mut value = config
+                .map(|config| serde_json::to_value(config).expect("config is serializable"))
+                .unwrap_or_else(|| Value::Object(Map::new()));
+            for (key, override_value) in self.overrides {
+                apply_override(&mut value, &key, override_value);
+            }
+            Some(serde_json::from_value(value).expect("invalid config override"))
+        };
+
+        let config = config.map(Arc::new);
+        if let Some(config) = &config {
+            app.with(ConfigMiddleware(config.clone()));
+        }
+        TestServer { app, config }
+    }
+}
+
+/// 在进程内处理请求的测试服务，不需要监听端口
+///
+/// ```
+/// # async_std::task::block_on(async {
+/// use summer_boot::testing::TestServer;
+///
+/// let mut app = summer_boot::new();
+/// app.at("/hello").get(|_| async { Ok("world") });
+///
+/// let server = TestServer::builder().build(app);
+/// let mut res = server.get("/hello").await.unwrap();
+/// assert_eq!(res.body_string().await.unwrap(), "world");
+/// # });
+/// ```
+pub struct TestServer<State> {
+    app: Server<State>,
+    config: Option<Arc<GlobalConfig>>,
+}
+
+impl<State: Send + Sync + 'static> std::fmt::Debug for TestServer<State> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TestServer")
+            .field("app", &self.app)
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+impl TestServer<()> {
+    #[must_use]
+    pub fn builder() -> TestServerBuilder {
+        TestServerBuilder::default()
+    }
+}
+
+impl<State> TestServer<State>
+where
+    State: Clone + Send + Sync + 'static,
+{
+    /// 应用覆盖后的配置，没有配置文件且没有覆盖时返回 `None`
+    ///
+    /// 请求处理中可以通过 `req.ext::<Arc<GlobalConfig>>()` 获取同一份配置。
+    pub fn config(&self) -> Option<&GlobalConfig> {
+        self.config.as_deref()
+    }
+
+    pub async fn respond(
+        &self,
+        req: impl Into<http_types::Request>,
+    ) -> http_types::Result<http_types::Response> {
+        self.app.respond(req).await
+    }
+
+    /// 发送 `GET` 请求，`path` 相对于 `http://localhost`
+    pub async fn get(&self, path: &str) -> http_types::Result<http_types::Response> {
+        let url = Url::parse("http://localhost")?.join(path)?;
+        self.respond(http_types::Request::new(Method::Get, url))
+            .await
+    }
+}
+
+fn load_test_config() -> Option<GlobalConfig> {
+    load_conf_with_profile(TEST_PROFILE).unwrap_or_else(|err| panic!("{}", err))
+}
+
+fn apply_override(config: &mut Value, key: &str, value: Value) {
+    let mut current = config;
+    let mut segments = key.split('.').peekable();
+    while let Some(segment) = segments.next() {
+        if !current.is_object() {
+            *current = Value::Object(Map::new());
+        }
+        let object = current.as_object_mut().expect("converted to object above");
+        if segments.peek().is_none() {
+            object.insert(segment.to_string(), value);
+            return;
+        }
+        current = object
+            .entry(segment)
+            .or_insert_with(|| Value::Object(Map::new()));
+    }
+}
+
+#[derive(Debug)]
+struct ConfigMiddleware(Arc<GlobalConfig>);
+
+#[async_trait]
+impl<State> Middleware<State> for ConfigMiddleware
+where
+    State: Clone + Send + Sync + 'static,
+{
+    async fn handle(&self, mut req: Request<State>, next: Next<'_, State>) -> crate::Result {
+        req.set_ext(self.0.clone());
+        Ok(next.run(req).await)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[async_std::test]
+    async fn applies_overrides() {
+        let mut app = crate::new();
+        app.at("/port").get(|req: Request<()>| async move {
+            let config = req.ext::<Arc<GlobalConfig>>().unwrap();
+            Ok(config.server.as_ref().unwrap().port.to_string())
+        });
+
+        let server = TestServer::builder()
+            .config_override("server", json!({ "port": 8080, "context_path": "/" }))
+            .config_override("server.port", 0)
+            .build(app);
+
+        let config = server.config().unwrap();
+        assert_eq!(config.server.as_ref().unwrap().context_path, "/");
+        assert!(config.mysql.is_none());
+
+        let mut res = server.get("/port").await.unwrap();
+        assert_eq!(res.body_string().await.unwrap(), "0");
+    }
+}
@@ -0,0 +1,465 @@
+//! 通过传递监听套接字实现不停机升级
+//!
+//! 收到升级信号（默认 `SIGUSR2`）后，旧进程启动新的可执行文件，
+//! 按 systemd 的 `LISTEN_FDS` 约定把监听套接字作为文件描述符 3 传给它，
+//! 同时通过文件描述符 4 上的套接字等待新进程就绪。新进程检测到继承的套接字后直接开始接受连接，
+//! 并写入一个字节通知旧进程；旧进程收到通知后停止接受连接，等待处理中的连接结束后从
+//! [`Server::listen_with_upgrade`] 返回。新进程在就绪之前退出时，旧进程记录错误并继续运行。
+//! 切换期间的连接留在内核的等待队列中，不会被拒绝。
+//! 停止接受连接后，空闲的 keep-alive 连接立即关闭，处理中的请求完成后响应 `Connection: close`。
+//!
+//! [`Server::listen_with_upgrade`]: crate::Server::listen_with_upgrade
+
+use crate::tcp::{handle_tcp_with, is_transient_error, Sniffing};
+use crate::{http, log, Server};
+
+use async_std::net::TcpListener;
+use async_std::os::unix::net::UnixStream;
+use async_std::prelude::*;
+use async_std::{io, task};
+use futures_util::future;
+use futures_util::FutureExt as _;
+
+use std::env;
+use std::ffi::OsString;
+use std::io::Write as _;
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+use std::os::unix::process::CommandExt;
+use std::path::PathBuf;
+use std::process::{Child, Command};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+pub use async_signal::Signal;
+
+/// 继承的套接字数量
+pub const LISTEN_FDS: &str = "LISTEN_FDS";
+/// 继承的套接字所属的进程，设置时必须与当前进程一致
+pub const LISTEN_PID: &str = "LISTEN_PID";
+/// 第一个继承的文件描述符
+pub const LISTEN_FDS_START: RawFd = 3;
+
+/// 新进程通知旧进程就绪的文件描述符
+const READY_FD: RawFd = 4;
+/// 设置时表示 [`READY_FD`] 是旧进程等待就绪通知的套接字
+const READY_FD_ENV: &str = "SUMMER_UPGRADE_READY_FD";
+/// 新进程关闭就绪通知套接字后，等待它退出的最长时间
+const REAP_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// 继承的套接字是否已经被取出，文件描述符只能交给一个 `TcpListener`
+static INHERITED_TAKEN: AtomicBool = AtomicBool::new(false);
+/// 是否已经通知旧进程就绪，只通知一次
+static READY_NOTIFIED: AtomicBool = AtomicBool::new(false);
+
+/// 不停机升级的配置
+///
+/// # Examples
+///
+/// ```no_run
+/// use summer_boot::upgrade::{Signal, UpgradeConfig};
+/// use std::time::Duration;
+///
+/// # async_std::task::block_on(async {
+/// let mut app = summer_boot::new();
+/// app.at("/").get(|_| async { Ok("Hello, world!") });
+///
+/// let config = UpgradeConfig::new()
+///     .signal(Signal::Hup)
+///     .drain_timeout(Duration::from_secs(10))
+///     .server_options(
+///         summer_boot::http::ServerOptions::default()
+///             .idle_timeout(Duration::from_secs(75)),
+///     );
+/// app.listen_with_upgrade("127.0.0.1:8080", config).await?;
+/// # std::io::Result::Ok(()) });
+/// ```
+#[derive(Debug, Clone)]
+pub struct UpgradeConfig {
+    signal: Signal,
+    program: Option<PathBuf>,
+    args: Option<Vec<OsString>>,
+    drain_timeout: Duration,
+    sniffing: Sniffing,
+    server_options: http::ServerOptions,
+}
+
+impl UpgradeConfig {
+    /// 收到 `SIGUSR2` 时以相同的参数重新执行当前可执行文件，最多等待连接结束 30 秒
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            signal: Signal::Usr2,
+            program: None,
+            args: None,
+            drain_timeout: Duration::from_secs(30),
+            sniffing: Sniffing::default(),
+            server_options: http::ServerOptions::default(),
+        }
+    }
+
+    /// 触发升级的信号
+    #[must_use]
+    pub fn signal(mut self, signal: Signal) -> Self {
+        self.signal = signal;
+        self
+    }
+
+    /// 新进程的可执行文件，默认为当前可执行文件
+    ///
+    /// 部署时通常先替换磁盘上的文件再发送信号，所以一般不需要设置。
+    #[must_use]
+    pub fn program(mut self, program: impl Into<PathBuf>) -> Self {
+        self.program = Some(program.into());
+        self
+    }
+
+    /// 新进程的参数，默认与当前进程相同
+    #[must_use]
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<OsString>,
+    {
+        self.args = Some(args.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// 停止接受连接后等待处理中的请求结束的最长时间
+    ///
+    /// 空闲的 keep-alive 连接立即关闭，不占用这段时间。超时后剩余的连接随进程退出被关闭。
+    #[must_use]
+    pub fn drain_timeout(mut self, timeout: Duration) -> Self {
+        self.drain_timeout = timeout;
+        self
+    }
+
+    /// 在 HTTP 之前识别 PROXY 协议头或 TCP 健康检查，同 [`TcpListener::with_sniffing`]
+    ///
+    /// [`TcpListener::with_sniffing`]: crate::TcpListener::with_sniffing
+    #[must_use]
+    pub fn sniffing(mut self, sniffing: Sniffing) -> Self {
+        self.sniffing = sniffing;
+        self
+    }
+
+    /// 每个连接的 HTTP/1.1 选项，同 [`TcpListener::with_server_options`]
+    ///
+    /// 路由上设置的请求目标长度豁免会自动加入。
+    ///
+    /// [`TcpListener::with_server_options`]: crate::TcpListener::with_server_options
+    #[must_use]
+    pub fn server_options(mut self, opts: http::ServerOptions) -> Self {
+        self.server_options = opts;
+        self
+    }
+}
+
+impl Default for UpgradeConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 取出从上一个进程继承的监听套接字
+///
+/// 只接受 `LISTEN_PID` 为当前进程且文件描述符 3 是正在监听的 TCP 套接字的情况，
+/// 同一进程中只有第一次调用会返回套接字。环境变量保持不变：运行时的其他线程可能同时
+/// 读取环境变量，此时修改并不安全；当前进程启动的子进程 `LISTEN_PID` 不一致，会忽略它们。
+pub fn inherited_listener() -> Option<std::net::TcpListener> {
+    let pid = env::var(LISTEN_PID).ok()?;
+    if pid != std::process::id().to_string() {
+        return None;
+    }
+    if env::var(LISTEN_FDS).ok()?.parse::<u32>().ok()? < 1 {
+        return None;
+    }
+    if !is_tcp_listener(LISTEN_FDS_START) || INHERITED_TAKEN.swap(true, Ordering::SeqCst) {
+        return None;
+    }
+    // 保证后续启动的子进程不会意外继承
+    // SAFETY: 上面已经确认文件描述符 3 是传给当前进程的监听套接字，并且只取出一次
+    unsafe {
+        let flags = libc::fcntl(LISTEN_FDS_START, libc::F_GETFD);
+        if flags == -1 {
+            return None;
+        }
+        libc::fcntl(LISTEN_FDS_START, libc::F_SETFD, flags | libc::FD_CLOEXEC);
+        Some(std::net::TcpListener::from_raw_fd(LISTEN_FDS_START))
+    }
+}
+
+/// `fd` 是否为正在监听的 IPv4 或 IPv6 流套接字
+fn is_tcp_listener(fd: RawFd) -> bool {
+    matches!(socket_family(fd), Some(libc::AF_INET | libc::AF_INET6))
+        && int_option(fd, libc::SO_TYPE) == Some(libc::SOCK_STREAM)
+        && int_option(fd, libc::SO_ACCEPTCONN) == Some(1)
+}
+
+/// `fd` 是否为 Unix 流套接字
+fn is_unix_stream(fd: RawFd) -> bool {
+    socket_family(fd) == Some(libc::AF_UNIX)
+        && int_option(fd, libc::SO_TYPE) == Some(libc::SOCK_STREAM)
+}
+
+/// 套接字的地址族，`fd` 不是套接字时返回 `None`
+fn socket_family(fd: RawFd) -> Option<libc::c_int> {
+    // SAFETY: 全零的 `sockaddr_storage` 是合法的值
+    let mut addr: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+    let mut len = std::mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+    // SAFETY: `addr` 足够容纳任何地址，`len` 为它的大小
+    let ret = unsafe {
+        libc::getsockname(
+            fd,
+            &mut addr as *mut libc::sockaddr_storage as *mut libc::sockaddr,
+            &mut len,
+        )
+    };
+    (ret == 0).then_some(addr.ss_family as libc::c_int)
+}
+
+/// 读取 `SOL_SOCKET` 层的整数选项
+fn int_option(fd: RawFd, name: libc::c_int) -> Option<libc::c_int> {
+    let mut value: libc::c_int = 0;
+    let mut len = std::mem::size_of::<libc::c_int>() as libc::socklen_t;
+    // SAFETY: `value` 和 `len` 在调用期间有效，`len` 与 `value` 的大小一致
+    let ret = unsafe {
+        libc::getsockopt(
+            fd,
+            libc::SOL_SOCKET,
+            name,
+            &mut value as *mut libc::c_int as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    (ret == 0).then_some(value)
+}
+
+/// 通知启动当前进程的旧进程已经开始使用继承的套接字
+///
+/// 只在取出了继承的套接字、`LISTEN_PID` 为当前进程且文件描述符 4 是 Unix 流套接字时通知一次。
+fn notify_ready() {
+    if !INHERITED_TAKEN.load(Ordering::SeqCst)
+        || env::var(LISTEN_PID).ok() != Some(std::process::id().to_string())
+        || env::var(READY_FD_ENV).ok() != Some(READY_FD.to_string())
+        || !is_unix_stream(READY_FD)
+        || READY_NOTIFIED.swap(true, Ordering::SeqCst)
+    {
+        return;
+    }
+    // SAFETY: 上面已经确认文件描述符 4 是旧进程传入的套接字，并且只取出一次
+    let mut ready = unsafe { std::os::unix::net::UnixStream::from_raw_fd(READY_FD) };
+    if let Err(error) = ready.write_all(&[1]) {
+        log::warn!("无法通知旧进程就绪", { error: error.to_string() });
+    }
+}
+
+/// 已启动、尚未就绪的新进程
+struct PendingUpgrade {
+    child: Child,
+    ready: UnixStream,
+}
+
+enum Event {
+    Accept(Option<io::Result<async_std::net::TcpStream>>),
+    Signal,
+    Ready(io::Result<usize>),
+}
+
+pub(crate) async fn serve<State>(
+    app: Server<State>,
+    listener: std::net::TcpListener,
+    config: UpgradeConfig,
+) -> io::Result<()>
+where
+    State: Clone + Send + Sync + 'static,
+{
+    // 整个过程中保持注册，排空期间再次收到信号不会终止进程
+    let mut signals = async_signal::Signals::new([config.signal])?;
+    let listener = TcpListener::from(listener);
+    log::info!("Server listening on http://{}", listener.local_addr()?);
+
+    let shutdown = app.shutdown().clone();
+    let opts = config
+        .server_options
+        .clone()
+        .target_exemptions(app.target_exemptions());
+    let mut incoming = listener.incoming();
+    notify_ready();
+
+    let mut pending: Option<PendingUpgrade> = None;
+    let mut buf = [0];
+    loop {
+        let accept = incoming.next().map(Event::Accept);
+        let signal = signals.next().map(|_| Event::Signal);
+        let event = match pending.as_mut() {
+            Some(upgrade) => {
+                let ready = upgrade.ready.read(&mut buf).map(Event::Ready);
+                let other = future::select(signal, ready).map(|either| either.factor_first().0);
+                future::select(accept, other).await.factor_first().0
+            }
+            None => future::select(accept, signal).await.factor_first().0,
+        };
+        match event {
+            Event::Accept(Some(Ok(stream))) => {
+                handle_tcp_with(
+                    app.clone(),
+                    stream,
+                    config.sniffing,
+                    opts.clone(),
+                    shutdown.clone(),
+                );
+            }
+            Event::Accept(Some(Err(ref e))) if is_transient_error(e) => continue,
+            Event::Accept(Some(Err(error))) => {
+                let delay = Duration::from_millis(500);
+                log::error!("Error: {}. for {:?}.", error, delay);
+                task::sleep(delay).await;
+            }
+            Event::Accept(None) => return Ok(()),
+            Event::Signal if pending.is_some() => {
+                log::warn!("新进程尚未就绪，忽略升级信号");
+            }
+            Event::Signal => match spawn_upgrade(&listener, &config) {
+                Ok(upgrade) => {
+                    log::info!("已启动新进程，等待其就绪", { pid: upgrade.child.id() });
+                    pending = Some(upgrade);
+                }
+                Err(error) => {
+                    log::error!("无法启动新进程，继续运行", { error: error.to_string() });
+                }
+            },
+            Event::Ready(Ok(n)) if n > 0 => {
+                if let Some(upgrade) = pending {
+                    log::info!("新进程已就绪，停止接受连接", { pid: upgrade.child.id() });
+                }
+                break;
+            }
+            Event::Ready(_) => {
+                if let Some(upgrade) = pending.take() {
+                    reap(upgrade.child).await;
+                }
+            }
+        }
+    }
+
+    drop(incoming);
+    drop(listener);
+    shutdown.trigger();
+    let remaining = shutdown.drain(config.drain_timeout).await;
+    if remaining > 0 {
+        log::warn!("等待连接结束超时", { connections: remaining });
+    }
+    Ok(())
+}
+
+/// 新进程没有通知就绪就关闭了套接字，记录它的退出状态，仍在运行时终止它
+async fn reap(mut child: Child) {
+    let started = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => {
+                log::error!("新进程未就绪就退出，继续运行", {
+                    pid: child.id(),
+                    status: status.to_string(),
+                });
+                return;
+            }
+            Ok(None) if started.elapsed() < REAP_TIMEOUT => {
+                task::sleep(Duration::from_millis(10)).await;
+            }
+            _ => break,
+        }
+    }
+    log::error!("新进程没有通知就绪，终止它并继续运行", { pid: child.id() });
+    let _ = child.kill();
+    task::spawn_blocking(move || child.wait());
+}
+
+/// 启动新进程，监听套接字作为文件描述符 3 传入，就绪通知套接字作为文件描述符 4 传入
+///
+/// fork 之前无法得知子进程的 pid，通过 `sh` 设置 `LISTEN_PID` 后 `exec`，进程 id 保持不变。
+fn spawn_upgrade(listener: &TcpListener, config: &UpgradeConfig) -> io::Result<PendingUpgrade> {
+    let program = match &config.program {
+        Some(program) => program.clone(),
+        None => env::current_exe()?,
+    };
+    let args = match &config.args {
+        Some(args) => args.clone(),
+        None => env::args_os().skip(1).collect(),
+    };
+
+    let (ready, theirs) = std::os::unix::net::UnixStream::pair()?;
+    let fd = listener.as_raw_fd();
+    let ready_fd = theirs.as_raw_fd();
+    let mut command = Command::new("/bin/sh");
+    command
+        .arg("-c")
+        .arg("LISTEN_PID=$$; export LISTEN_PID; exec \"$0\" \"$@\"")
+        .arg(program)
+        .args(args)
+        .env(LISTEN_FDS, "1")
+        .env("LISTEN_FDNAMES", "summer-boot")
+        .env(READY_FD_ENV, READY_FD.to_string())
+        .env_remove(LISTEN_PID);
+    // SAFETY: 只调用 async-signal-safe 的 `dup2` 和 `fcntl`
+    unsafe {
+        command.pre_exec(move || {
+            let mut ready_fd = ready_fd;
+            // 先移开，避免被监听套接字覆盖
+            if ready_fd == LISTEN_FDS_START {
+                ready_fd = libc::fcntl(ready_fd, libc::F_DUPFD_CLOEXEC, READY_FD + 1);
+                if ready_fd == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            inherit_fd(fd, LISTEN_FDS_START)?;
+            inherit_fd(ready_fd, READY_FD)
+        });
+    }
+    let child = command.spawn()?;
+    // 子进程退出后读取到 EOF
+    drop(theirs);
+    Ok(PendingUpgrade {
+        child,
+        ready: UnixStream::from(ready),
+    })
+}
+
+/// 把 `fd` 放到 `target` 并在 `exec` 后保留，只在 `pre_exec` 中调用
+fn inherit_fd(fd: RawFd, target: RawFd) -> io::Result<()> {
+    // SAFETY: 只调用 async-signal-safe 的 `dup2` 和 `fcntl`
+    unsafe {
+        if fd == target {
+            let flags = libc::fcntl(fd, libc::F_GETFD);
+            if flags == -1 || libc::fcntl(fd, libc::F_SETFD, flags & !libc::FD_CLOEXEC) == -1 {
+                return Err(io::Error::last_os_error());
+            }
+        } else if libc::dup2(fd, target) == -1 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn only_listening_tcp_sockets_are_inherited() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        assert!(is_tcp_listener(listener.as_raw_fd()));
+
+        let stream = std::net::TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        assert!(!is_tcp_listener(stream.as_raw_fd()));
+
+        let file = std::fs::File::open("Cargo.toml").unwrap();
+        assert!(!is_tcp_listener(file.as_raw_fd()));
+
+        let path = env::temp_dir().join(format!("summer-upgrade-{}", std::process::id()));
+        let unix = std::os::unix::net::UnixListener::bind(&path).unwrap();
+        assert!(!is_tcp_listener(unix.as_raw_fd()));
+        drop(unix);
+        std::fs::remove_file(path).unwrap();
+    }
+}
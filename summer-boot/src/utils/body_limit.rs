@@ -0,0 +1,174 @@
+//! 请求体大小限制中间件
+
+use crate::http_types::headers::CONTENT_LENGTH;
+use crate::{Body, Middleware, Next, Request, Response, StatusCode};
+
+use async_std::io::{self, BufReader, Read};
+use async_trait::async_trait;
+
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// 限制请求体的大小
+///
+/// 带有 `Content-Length` 的请求在调用 endpoint 之前检查，超出限制直接返回
+/// `413 Payload Too Large`；没有 `Content-Length` 的请求（例如 chunked）在读取
+/// 请求体时计数，超出限制后读取出错，响应状态同样为 `413`。
+///
+/// # Examples
+///
+/// ```
+/// use summer_boot::utils::body_limit::BodyLimitMiddleware;
+///
+/// let mut app = summer_boot::new();
+/// app.with(BodyLimitMiddleware::new(1024 * 1024));
+/// ```
+#[derive(Debug, Clone)]
+pub struct BodyLimitMiddleware {
+    limit: usize,
+}
+
+impl BodyLimitMiddleware {
+    /// 创建一个新的实例，`limit` 为允许的最大字节数
+    #[must_use]
+    pub fn new(limit: usize) -> Self {
+        Self { limit }
+    }
+}
+
+/// 请求体超出限制时读取返回的错误
+#[derive(Debug)]
+struct PayloadTooLarge(usize);
+
+impl fmt::Display for PayloadTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "请求体超过 {} 字节的限制", self.0)
+    }
+}
+
+impl std::error::Error for PayloadTooLarge {}
+
+/// 统计读取字节数的 reader
+struct LimitedReader {
+    inner: Body,
+    limit: usize,
+    read: usize,
+}
+
+impl Read for LimitedReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        let n = futures_util::ready!(Pin::new(&mut this.inner).poll_read(cx, buf))?;
+        this.read += n;
+        if this.read > this.limit {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                PayloadTooLarge(this.limit),
+            )));
+        }
+        Poll::Ready(Ok(n))
+    }
+}
+
+fn is_payload_too_large(res: &Response) -> bool {
+    res.downcast_error::<io::Error>()
+        .and_then(|e| e.get_ref())
+        .is_some_and(|e| e.is::<PayloadTooLarge>())
+}
+
+#[async_trait]
+impl<State: Clone + Send + Sync + 'static> Middleware<State> for BodyLimitMiddleware {
+    async fn handle(&self, mut req: Request<State>, next: Next<'_, State>) -> crate::Result {
+        let content_length = req
+            .header(CONTENT_LENGTH)
+            .and_then(|values| values.as_str().trim().parse::<usize>().ok());
+
+        match content_length {
+            Some(len) if len > self.limit => {
+                return Ok(Response::new(StatusCode::PayloadTooLarge));
+            }
+            Some(_) => return Ok(next.run(req).await),
+            None => {}
+        }
+
+        let body = req.take_body();
+        let mime = body.mime().clone();
+        let mut limited = Body::from_reader(
+            BufReader::new(LimitedReader {
+                inner: body,
+                limit: self.limit,
+                read: 0,
+            }),
+            None,
+        );
+        limited.set_mime(mime);
+        req.set_body(limited);
+
+        let mut res = next.run(req).await;
+        if is_payload_too_large(&res) {
+            res.set_status(StatusCode::PayloadTooLarge);
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate as summer_boot;
+    use http_types::{Method, Request, Response, Url};
+
+    fn app() -> summer_boot::Server<()> {
+        let mut app = summer_boot::new();
+        app.with(BodyLimitMiddleware::new(8));
+        app.at("/upload")
+            .post(|mut req: summer_boot::Request<()>| async move {
+                let body = req.body_bytes().await?;
+                Ok(body.len().to_string())
+            });
+        app
+    }
+
+    fn upload(body: Body) -> Request {
+        let url = Url::parse("http://localhost/upload").unwrap();
+        let mut req = Request::new(Method::Post, url);
+        req.set_body(body);
+        req
+    }
+
+    #[async_std::test]
+    async fn rejects_large_content_length() {
+        let mut req = upload(Body::from("0123456789"));
+        req.insert_header(CONTENT_LENGTH, "10");
+        let res: Response = app().respond(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::PayloadTooLarge);
+    }
+
+    #[async_std::test]
+    async fn rejects_large_chunked_body() {
+        let reader = io::Cursor::new(b"0123456789".to_vec());
+        let res: Response = app()
+            .respond(upload(Body::from_reader(reader, None)))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::PayloadTooLarge);
+    }
+
+    #[async_std::test]
+    async fn accepts_small_bodies() {
+        let mut res: Response = app().respond(upload(Body::from("0123"))).await.unwrap();
+        assert_eq!(res.body_string().await.unwrap(), "4");
+
+        let reader = io::Cursor::new(b"01234567".to_vec());
+        let mut res: Response = app()
+            .respond(upload(Body::from_reader(reader, None)))
+            .await
+            .unwrap();
+        assert_eq!(res.body_string().await.unwrap(), "8");
+    }
+}
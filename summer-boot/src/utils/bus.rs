@@ -0,0 +1,391 @@
+//! 进程内的发布/订阅
+//!
+//! 用于在请求之间传递消息，例如 `POST /messages` 发布的消息推送给所有
+//! 通过 SSE 订阅的连接。每个订阅者有独立的有界缓冲区，处理不及时时按
+//! [`LagPolicy`] 丢弃最旧的消息或断开订阅。
+
+use futures_util::Stream;
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+/// 订阅者缓冲区满时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LagPolicy {
+    /// 丢弃缓冲区中最旧的消息
+    DropOldest,
+    /// 断开订阅，订阅者读完缓冲区中的消息后结束
+    Disconnect,
+}
+
+/// 按主题发布/订阅消息
+///
+/// `Bus` 可以廉价地 clone，所有 clone 共享相同的订阅者，通常放在 server 的 state 中。
+///
+/// # Examples
+///
+/// ```no_run
+/// use futures_util::StreamExt;
+/// use summer_boot::utils::bus::Bus;
+/// use summer_boot::Request;
+///
+/// #[derive(Clone)]
+/// struct State {
+///     bus: Bus<String>,
+/// }
+///
+/// let mut app = summer_boot::with_state(State { bus: Bus::new(64) });
+/// app.at("/messages").post(|mut req: Request<State>| async move {
+///     let message = req.body_string().await?;
+///     req.state().bus.publish("room:1", message);
+///     Ok("")
+/// });
+/// app.at("/events").get(summer_boot::sse::endpoint(|req: Request<State>, sender| async move {
+///     let mut messages = req.state().bus.subscribe("room:1");
+///     while let Some(message) = messages.next().await {
+///         sender.send("message", &message, None).await?;
+///     }
+///     Ok(())
+/// }));
+/// ```
+pub struct Bus<T> {
+    inner: Arc<BusInner<T>>,
+}
+
+struct BusInner<T> {
+    capacity: usize,
+    policy: LagPolicy,
+    topics: Mutex<HashMap<String, Vec<Arc<Slot<T>>>>>,
+    next_id: AtomicU64,
+    published: AtomicU64,
+    dropped: AtomicU64,
+}
+
+struct Slot<T> {
+    id: u64,
+    state: Mutex<SlotState<T>>,
+}
+
+struct SlotState<T> {
+    queue: VecDeque<T>,
+    waker: Option<Waker>,
+    closed: bool,
+}
+
+/// [`Bus`] 的统计信息
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BusMetrics {
+    /// 有订阅者的主题数量
+    pub topics: usize,
+    /// 所有主题的订阅者数量
+    pub subscribers: usize,
+    /// 发布的消息数量
+    pub published: u64,
+    /// 因缓冲区满被丢弃的消息数量
+    pub dropped: u64,
+}
+
+impl<T> Bus<T>
+where
+    T: Clone + Send + 'static,
+{
+    /// 创建 `Bus`，`capacity` 是每个订阅者缓冲的消息数量，默认丢弃最旧的消息
+    ///
+    /// # Panics
+    ///
+    /// `capacity` 为 0 时 panic。
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        Self::with_lag_policy(capacity, LagPolicy::DropOldest)
+    }
+
+    /// 创建 `Bus`，订阅者缓冲区满时按 `policy` 处理
+    ///
+    /// 缓冲区大小和处理方式由所有 clone 共享，创建后不能修改。
+    ///
+    /// # Panics
+    ///
+    /// `capacity` 为 0 时 panic。
+    #[must_use]
+    pub fn with_lag_policy(capacity: usize, policy: LagPolicy) -> Self {
+        assert!(capacity > 0, "bus capacity must be greater than 0");
+        Self {
+            inner: Arc::new(BusInner {
+                capacity,
+                policy,
+                topics: Mutex::new(HashMap::new()),
+                next_id: AtomicU64::new(0),
+                published: AtomicU64::new(0),
+                dropped: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// 向主题的所有订阅者发布消息，返回收到消息的订阅者数量
+    pub fn publish(&self, topic: &str, message: T) -> usize {
+        self.inner.published.fetch_add(1, Ordering::Relaxed);
+
+        let mut topics = self.inner.topics.lock().unwrap();
+        let slots = match topics.get_mut(topic) {
+            Some(slots) => slots,
+            None => return 0,
+        };
+
+        let mut delivered = 0;
+        slots.retain(|slot| {
+            let mut state = slot.state.lock().unwrap();
+            if state.queue.len() >= self.inner.capacity {
+                self.inner.dropped.fetch_add(1, Ordering::Relaxed);
+                match self.inner.policy {
+                    LagPolicy::DropOldest => {
+                        state.queue.pop_front();
+                    }
+                    LagPolicy::Disconnect => {
+                        state.closed = true;
+                        if let Some(waker) = state.waker.take() {
+                            waker.wake();
+                        }
+                        return false;
+                    }
+                }
+            }
+            state.queue.push_back(message.clone());
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+            delivered += 1;
+            true
+        });
+        if slots.is_empty() {
+            topics.remove(topic);
+        }
+        delivered
+    }
+
+    /// 订阅主题，返回的 [`Subscription`] 被丢弃时自动取消订阅
+    ///
+    /// 在 [`sse::endpoint`](crate::sse::endpoint) 中订阅时，客户端断开连接后
+    /// handler 的 future 被取消，订阅也随之取消。
+    pub fn subscribe(&self, topic: &str) -> Subscription<T> {
+        let slot = Arc::new(Slot {
+            id: self.inner.next_id.fetch_add(1, Ordering::Relaxed),
+            state: Mutex::new(SlotState {
+                queue: VecDeque::with_capacity(self.inner.capacity),
+                waker: None,
+                closed: false,
+            }),
+        });
+        self.inner
+            .topics
+            .lock()
+            .unwrap()
+            .entry(topic.to_string())
+            .or_default()
+            .push(slot.clone());
+        Subscription {
+            bus: self.inner.clone(),
+            topic: topic.to_string(),
+            slot,
+        }
+    }
+
+    /// 主题的订阅者数量
+    pub fn subscribers(&self, topic: &str) -> usize {
+        self.inner
+            .topics
+            .lock()
+            .unwrap()
+            .get(topic)
+            .map_or(0, Vec::len)
+    }
+
+    pub fn metrics(&self) -> BusMetrics {
+        let topics = self.inner.topics.lock().unwrap();
+        BusMetrics {
+            topics: topics.len(),
+            subscribers: topics.values().map(Vec::len).sum(),
+            published: self.inner.published.load(Ordering::Relaxed),
+            dropped: self.inner.dropped.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl<T> Clone for Bus<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for Bus<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Bus")
+            .field("capacity", &self.inner.capacity)
+            .field("policy", &self.inner.policy)
+            .finish()
+    }
+}
+
+/// 主题的订阅，以 [`Stream`] 的形式读取消息
+///
+/// 被 [`LagPolicy::Disconnect`] 断开后，读完缓冲区中的消息时结束。
+pub struct Subscription<T> {
+    bus: Arc<BusInner<T>>,
+    topic: String,
+    slot: Arc<Slot<T>>,
+}
+
+impl<T> Subscription<T> {
+    pub fn topic(&self) -> &str {
+        &self.topic
+    }
+}
+
+impl<T> Stream for Subscription<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let mut state = self.slot.state.lock().unwrap();
+        if let Some(message) = state.queue.pop_front() {
+            return Poll::Ready(Some(message));
+        }
+        if state.closed {
+            return Poll::Ready(None);
+        }
+        state.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl<T> Drop for Subscription<T> {
+    fn drop(&mut self) {
+        let mut topics = self.bus.topics.lock().unwrap();
+        if let Some(slots) = topics.get_mut(&self.topic) {
+            slots.retain(|slot| slot.id != self.slot.id);
+            if slots.is_empty() {
+                topics.remove(&self.topic);
+            }
+        }
+    }
+}
+
+impl<T> fmt::Debug for Subscription<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Subscription")
+            .field("topic", &self.topic)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate as summer_boot;
+    use crate::Request;
+
+    use async_std::io::{ReadExt, WriteExt};
+    use async_std::net::{TcpListener, TcpStream};
+    use async_std::task;
+    use futures_util::StreamExt;
+    use std::time::Duration;
+
+    #[async_std::test]
+    async fn fans_out_to_all_subscribers() {
+        let bus = Bus::new(8);
+        let mut first = bus.subscribe("room:1");
+        let mut second = bus.subscribe("room:1");
+        let _other = bus.subscribe("room:2");
+
+        assert_eq!(bus.publish("room:1", "hello"), 2);
+        assert_eq!(first.next().await, Some("hello"));
+        assert_eq!(second.next().await, Some("hello"));
+        assert_eq!(bus.publish("room:3", "nobody"), 0);
+
+        assert_eq!(
+            bus.metrics(),
+            BusMetrics {
+                topics: 2,
+                subscribers: 3,
+                published: 2,
+                dropped: 0,
+            }
+        );
+    }
+
+    #[async_std::test]
+    async fn slow_subscriber_hits_lag_policy() {
+        let bus = Bus::new(2);
+        let mut slow = bus.subscribe("room:1");
+        for i in 0..4 {
+            bus.publish("room:1", i);
+        }
+        assert_eq!(slow.next().await, Some(2));
+        assert_eq!(slow.next().await, Some(3));
+        assert_eq!(bus.metrics().dropped, 2);
+
+        let bus = Bus::with_lag_policy(2, LagPolicy::Disconnect);
+        // clone 共享缓冲区大小和处理方式
+        let mut slow = bus.clone().subscribe("room:1");
+        for i in 0..3 {
+            bus.publish("room:1", i);
+        }
+        assert_eq!(bus.subscribers("room:1"), 0);
+        assert_eq!(slow.next().await, Some(0));
+        assert_eq!(slow.next().await, Some(1));
+        assert_eq!(slow.next().await, None);
+        assert_eq!(bus.metrics().dropped, 1);
+    }
+
+    #[async_std::test]
+    async fn disconnect_cleans_up_subscription() {
+        let bus = Bus::new(8);
+        let mut app = summer_boot::with_state(bus.clone());
+        // 断开连接在写入保活注释失败时发现
+        let events = crate::sse::endpoint(|req: Request<Bus<String>>, sender| async move {
+            let mut messages = req.state().subscribe("room:1");
+            while let Some(message) = messages.next().await {
+                sender.send("message", &message, None).await?;
+            }
+            Ok(())
+        })
+        .keep_alive(Duration::from_millis(10));
+        app.at("/events").get(events);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        task::spawn(app.listen(listener));
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET /events HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        while bus.subscribers("room:1") == 0 {
+            task::sleep(Duration::from_millis(5)).await;
+        }
+        bus.publish("room:1", "hello".to_string());
+
+        let mut raw = String::new();
+        let mut buf = [0; 1024];
+        while !raw.contains("data: hello\n\n") {
+            let n = stream.read(&mut buf).await.unwrap();
+            assert!(n > 0);
+            raw.push_str(std::str::from_utf8(&buf[..n]).unwrap());
+        }
+
+        drop(stream);
+        for _ in 0..200 {
+            if bus.subscribers("room:1") == 0 {
+                assert_eq!(bus.metrics().topics, 0);
+                return;
+            }
+            task::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("subscription was not removed after the client disconnected");
+    }
+}
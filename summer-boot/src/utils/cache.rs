@@ -0,0 +1,334 @@
+//! 响应缓存中间件，需要开启 `cache` feature
+
+use crate::http_types::headers::{HeaderName, HeaderValues, CACHE_CONTROL, SET_COOKIE};
+use crate::http_types::Method;
+use crate::{Body, Middleware, Next, Request, Response, StatusCode};
+
+use async_trait::async_trait;
+use moka::future::Cache;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+const X_CACHE: &str = "X-Cache";
+
+/// 缓存 `GET` / `HEAD` 请求的成功响应
+///
+/// 缓存键由请求方法、路径、查询参数以及 `vary` 指定的请求头组成。
+/// 状态码不在 200-299 之间、设置了 `Set-Cookie`，或者 `Cache-Control` 中有
+/// `no-store`、`no-cache`、`private` 的响应不会被缓存，避免把一个用户的会话或私有页面返回给其他用户。
+/// 长度未知的响应（例如 SSE 和其他流式响应）和超过 [`max_body_size`](Self::max_body_size)
+/// 的响应原样返回，不会被读入内存。
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use summer_boot::utils::cache::CacheMiddleware;
+///
+/// let mut app = summer_boot::new();
+/// app.with(
+///     CacheMiddleware::new()
+///         .ttl(Duration::from_secs(60))
+///         .vary(&["Accept-Language"]),
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct CacheMiddleware {
+    cache: Cache<String, Arc<CachedResponse>>,
+    ttl: Duration,
+    max_entries: u64,
+    max_body_size: usize,
+    vary: Vec<HeaderName>,
+}
+
+#[derive(Debug)]
+struct CachedResponse {
+    status: StatusCode,
+    headers: Vec<(HeaderName, HeaderValues)>,
+    body: Vec<u8>,
+}
+
+impl Default for CacheMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CacheMiddleware {
+    /// 创建一个新的实例，默认缓存 60 秒，最多 10000 条，单个响应体最大 1 MiB
+    #[must_use]
+    pub fn new() -> Self {
+        let ttl = Duration::from_secs(60);
+        let max_entries = 10_000;
+        Self {
+            cache: Self::build(ttl, max_entries),
+            ttl,
+            max_entries,
+            max_body_size: 1024 * 1024,
+            vary: Vec::new(),
+        }
+    }
+
+    /// 设置缓存过期时间
+    #[must_use]
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self.cache = Self::build(self.ttl, self.max_entries);
+        self
+    }
+
+    /// 设置最大缓存条数
+    #[must_use]
+    pub fn max_entries(mut self, max_entries: u64) -> Self {
+        self.max_entries = max_entries;
+        self.cache = Self::build(self.ttl, self.max_entries);
+        self
+    }
+
+    /// 设置可以缓存的最大响应体字节数
+    #[must_use]
+    pub fn max_body_size(mut self, bytes: usize) -> Self {
+        self.max_body_size = bytes;
+        self
+    }
+
+    /// 设置参与缓存键计算的请求头
+    #[must_use]
+    pub fn vary(mut self, headers: &[&str]) -> Self {
+        self.vary = headers.iter().map(|name| HeaderName::from(*name)).collect();
+        self
+    }
+
+    fn build(ttl: Duration, max_entries: u64) -> Cache<String, Arc<CachedResponse>> {
+        Cache::builder()
+            .max_capacity(max_entries)
+            .time_to_live(ttl)
+            .build()
+    }
+
+    fn key<State>(&self, req: &Request<State>) -> String {
+        let url = req.url();
+        let mut key = format!(
+            "{} {}?{}",
+            req.method(),
+            url.path(),
+            url.query().unwrap_or("")
+        );
+        for name in &self.vary {
+            key.push('\n');
+            key.push_str(name.as_str());
+            key.push(':');
+            if let Some(values) = req.header(name) {
+                key.push_str(values.as_str());
+            }
+        }
+        key
+    }
+}
+
+/// 禁止共享缓存保存响应的 `Cache-Control` 指令，`private="..."` 等带参数的形式同样生效
+const UNCACHEABLE_DIRECTIVES: [&str; 3] = ["no-store", "no-cache", "private"];
+
+fn is_cacheable(res: &Response) -> bool {
+    let forbidden = res.header(CACHE_CONTROL).is_some_and(|values| {
+        values
+            .iter()
+            .flat_map(|value| value.as_str().split(','))
+            .map(|directive| directive.split('=').next().unwrap_or_default().trim())
+            .any(|name| {
+                UNCACHEABLE_DIRECTIVES
+                    .iter()
+                    .any(|forbidden| name.eq_ignore_ascii_case(forbidden))
+            })
+    });
+    res.status().is_success() && res.header(SET_COOKIE).is_none() && !forbidden
+}
+
+impl CachedResponse {
+    fn to_response(&self) -> Response {
+        let mut res = Response::new(self.status);
+        for (name, values) in &self.headers {
+            res.insert_header(name, values);
+        }
+        res.set_body(Body::from_bytes(self.body.clone()));
+        res
+    }
+}
+
+#[async_trait]
+impl<State: Clone + Send + Sync + 'static> Middleware<State> for CacheMiddleware {
+    async fn handle(&self, req: Request<State>, next: Next<'_, State>) -> crate::Result {
+        if !matches!(req.method(), Method::Get | Method::Head) {
+            return Ok(next.run(req).await);
+        }
+
+        let key = self.key(&req);
+        if let Some(cached) = self.cache.get(&key).await {
+            let mut res = cached.to_response();
+            res.insert_header(X_CACHE, "HIT");
+            return Ok(res);
+        }
+
+        let mut res = next.run(req).await;
+        let fits = res.len().is_some_and(|len| len <= self.max_body_size);
+        if !fits || !is_cacheable(&res) {
+            return Ok(res);
+        }
+
+        let body = res.take_body().into_bytes().await?;
+        let cached = CachedResponse {
+            status: res.status(),
+            // Cookie 只属于当前请求的用户，不能重放给其他请求
+            headers: res
+                .iter()
+                .filter(|(name, _)| **name != SET_COOKIE)
+                .map(|(name, values)| (name.clone(), values.clone()))
+                .collect(),
+            body,
+        };
+        let mut res = cached.to_response();
+        self.cache.insert(key, Arc::new(cached)).await;
+        res.insert_header(X_CACHE, "MISS");
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate as summer_boot;
+    use http_types::{Method, Request, Response, Url};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn app() -> summer_boot::Server<Arc<AtomicUsize>> {
+        let mut app = summer_boot::with_state(Arc::new(AtomicUsize::new(0)));
+        app.with(CacheMiddleware::new().vary(&["Accept-Language"]));
+        app.at("/count")
+            .get(|req: summer_boot::Request<Arc<AtomicUsize>>| async move {
+                Ok(req.state().fetch_add(1, Ordering::SeqCst).to_string())
+            });
+        app.at("/no-store")
+            .get(|req: summer_boot::Request<Arc<AtomicUsize>>| async move {
+                let count = req.state().fetch_add(1, Ordering::SeqCst);
+                Ok(summer_boot::Response::builder(200)
+                    .header(CACHE_CONTROL, "no-store")
+                    .body(count.to_string())
+                    .build())
+            });
+        for (path, name, value) in [
+            ("/private", "Cache-Control", "max-age=60, private"),
+            ("/no-cache", "Cache-Control", "no-cache=\"Set-Cookie\""),
+            ("/session", "Set-Cookie", "session=alice"),
+        ] {
+            app.at(path).get(
+                move |req: summer_boot::Request<Arc<AtomicUsize>>| async move {
+                    let count = req.state().fetch_add(1, Ordering::SeqCst);
+                    Ok(summer_boot::Response::builder(200)
+                        .header(name, value)
+                        .body(count.to_string())
+                        .build())
+                },
+            );
+        }
+        app
+    }
+
+    async fn get(
+        app: &summer_boot::Server<Arc<AtomicUsize>>,
+        path: &str,
+        lang: Option<&str>,
+    ) -> (String, Option<String>) {
+        let url = Url::parse("http://localhost").unwrap().join(path).unwrap();
+        let mut req = Request::new(Method::Get, url);
+        if let Some(lang) = lang {
+            req.insert_header("Accept-Language", lang);
+        }
+        let mut res: Response = app.respond(req).await.unwrap();
+        let cache = res.header(X_CACHE).map(|v| v.as_str().to_string());
+        (res.body_string().await.unwrap(), cache)
+    }
+
+    #[async_std::test]
+    async fn caches_successful_responses() {
+        let app = app();
+        assert_eq!(
+            get(&app, "/count", None).await,
+            ("0".into(), Some("MISS".into()))
+        );
+        assert_eq!(
+            get(&app, "/count", None).await,
+            ("0".into(), Some("HIT".into()))
+        );
+        assert_eq!(get(&app, "/count?a=1", None).await.0, "1");
+    }
+
+    #[async_std::test]
+    async fn vary_headers_are_part_of_the_key() {
+        let app = app();
+        assert_eq!(get(&app, "/count", Some("en")).await.0, "0");
+        assert_eq!(get(&app, "/count", Some("zh")).await.0, "1");
+        assert_eq!(get(&app, "/count", Some("en")).await.1, Some("HIT".into()));
+    }
+
+    #[async_std::test]
+    async fn skips_no_store_and_errors() {
+        let app = app();
+        assert_eq!(get(&app, "/no-store", None).await, ("0".into(), None));
+        assert_eq!(get(&app, "/no-store", None).await, ("1".into(), None));
+        assert_eq!(get(&app, "/missing", None).await.1, None);
+        assert_eq!(get(&app, "/missing", None).await.1, None);
+    }
+
+    #[async_std::test]
+    async fn skips_streamed_and_large_bodies() {
+        let mut app = summer_boot::new();
+        app.with(CacheMiddleware::new().max_body_size(16));
+        // 不会结束的事件流，缓存读取整个响应体时请求永远不会返回
+        app.at("/events")
+            .get(summer_boot::sse::endpoint(|_req, sender| async move {
+                loop {
+                    sender.send("tick", "1", None).await?;
+                }
+            }));
+        app.at("/stream").get(|_| async {
+            let reader = async_std::io::Cursor::new(b"streamed".to_vec());
+            Ok(summer_boot::Response::builder(200)
+                .body(Body::from_reader(reader, None))
+                .build())
+        });
+        app.at("/large").get(|_| async { Ok("x".repeat(17)) });
+        app.at("/small").get(|_| async { Ok("x".repeat(16)) });
+
+        let request = |path: &str| {
+            let url = Url::parse("http://localhost").unwrap().join(path).unwrap();
+            Request::new(Method::Get, url)
+        };
+        let res: Response =
+            async_std::future::timeout(Duration::from_secs(5), app.respond(request("/events")))
+                .await
+                .expect("cache buffered the event stream")
+                .unwrap();
+        assert_eq!(res.content_type().unwrap().essence(), "text/event-stream");
+        assert!(res.header(X_CACHE).is_none());
+
+        for path in ["/stream", "/large", "/stream", "/large"] {
+            let res: Response = app.respond(request(path)).await.unwrap();
+            assert!(res.header(X_CACHE).is_none(), "{}", path);
+        }
+        let res: Response = app.respond(request("/small")).await.unwrap();
+        assert_eq!(res.header(X_CACHE).unwrap(), "MISS");
+    }
+
+    #[async_std::test]
+    async fn skips_private_responses() {
+        let app = app();
+        for path in ["/private", "/no-cache", "/session"] {
+            let (first, cache) = get(&app, path, None).await;
+            assert_eq!(cache, None, "{}", path);
+            let (second, cache) = get(&app, path, None).await;
+            assert_eq!(cache, None, "{}", path);
+            assert_ne!(first, second, "{}", path);
+        }
+    }
+}
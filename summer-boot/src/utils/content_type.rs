@@ -0,0 +1,224 @@
+//! 带参数的 Content-Type
+
+use crate::http_types::mime::{self, Mime};
+use crate::{Middleware, Next, Request};
+
+use async_trait::async_trait;
+
+use std::str::FromStr;
+
+/// 构造带参数的 [`Mime`]
+///
+/// `set_content_type` 会原样输出参数，例如 `charset` 和 `boundary`。
+///
+/// # Examples
+///
+/// ```
+/// use summer_boot::http_types::mime;
+/// use summer_boot::utils::content_type::ContentType;
+///
+/// let mime = ContentType::with_param(mime::HTML, "charset", "iso-8859-1")?;
+/// assert_eq!(mime.to_string(), "text/html;charset=iso-8859-1");
+/// assert_eq!(ContentType::text_utf8().to_string(), "text/plain;charset=utf-8");
+/// assert_eq!(ContentType::json_utf8().to_string(), "application/json;charset=utf-8");
+/// # summer_boot::Result::Ok(())
+/// ```
+#[derive(Debug)]
+pub struct ContentType {
+    _priv: (),
+}
+
+impl ContentType {
+    /// `text/plain;charset=utf-8`
+    #[must_use]
+    pub fn text_utf8() -> Mime {
+        mime::PLAIN
+    }
+
+    /// `application/json;charset=utf-8`
+    #[must_use]
+    pub fn json_utf8() -> Mime {
+        Mime::from_str("application/json;charset=utf-8").expect("固定的 Content-Type")
+    }
+
+    /// 设置参数，已有的同名参数会被替换
+    ///
+    /// 值不是 token 时会加上引号；参数名不是合法的 token 或值中包含控制字符时返回错误。
+    pub fn with_param(mime: impl Into<Mime>, name: &str, value: &str) -> crate::Result<Mime> {
+        if name.is_empty() || !name.bytes().all(is_token) {
+            return Err(crate::Error::from_str(
+                crate::StatusCode::InternalServerError,
+                format!("无效的 Content-Type 参数名: {:?}", name),
+            ));
+        }
+        if value.bytes().any(|b| b.is_ascii_control() && b != b'\t') {
+            return Err(crate::Error::from_str(
+                crate::StatusCode::InternalServerError,
+                format!("无效的 Content-Type 参数值: {:?}", value),
+            ));
+        }
+        let mut mime = mime.into();
+        mime.remove_param(name);
+        let value = if !value.is_empty() && value.bytes().all(is_token) {
+            value.to_string()
+        } else {
+            format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+        };
+        Mime::from_str(&format!("{};{}={}", mime, name, value))
+    }
+}
+
+/// RFC 7230 token 字符
+fn is_token(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b)
+}
+
+/// 为缺少 `charset` 的 `text/*` 响应设置默认字符集
+///
+/// 可以通过配置文件中的 `server.charset` 开启。
+///
+/// # Examples
+///
+/// ```
+/// use summer_boot::utils::content_type::DefaultCharset;
+///
+/// let mut app = summer_boot::new();
+/// app.with(DefaultCharset::new("utf-8"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct DefaultCharset {
+    charset: String,
+}
+
+impl DefaultCharset {
+    /// 创建一个新的实例
+    #[must_use]
+    pub fn new(charset: impl Into<String>) -> Self {
+        Self {
+            charset: charset.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl<State: Clone + Send + Sync + 'static> Middleware<State> for DefaultCharset {
+    async fn handle(&self, req: Request<State>, next: Next<'_, State>) -> crate::Result {
+        let mut res = next.run(req).await;
+        if let Some(mime) = res.content_type() {
+            if mime.basetype() == "text" && mime.param("charset").is_none() {
+                // 配置的字符集无效时保留原来的 Content-Type
+                if let Ok(mime) = ContentType::with_param(mime, "charset", &self.charset) {
+                    res.set_content_type(mime);
+                }
+            }
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate as summer_boot;
+    use crate::http1::encode::Encoder;
+    use crate::{Response, StatusCode};
+    use async_std::io::ReadExt;
+    use http_types::{Method, Url};
+
+    /// 返回编码后的 `content-type` 响应头
+    async fn content_type(app: &summer_boot::Server<()>, path: &str) -> String {
+        let url = Url::parse("http://localhost").unwrap().join(path).unwrap();
+        let res: http_types::Response = app
+            .respond(http_types::Request::new(Method::Get, url))
+            .await
+            .unwrap();
+        let mut encoded = String::new();
+        Encoder::new(res, Method::Get)
+            .read_to_string(&mut encoded)
+            .await
+            .unwrap();
+        encoded
+            .lines()
+            .find_map(|line| line.strip_prefix("content-type: "))
+            .unwrap()
+            .to_string()
+    }
+
+    fn app() -> summer_boot::Server<()> {
+        let mut app = summer_boot::new();
+        app.at("/string").get(|_| async { Ok("hello".to_string()) });
+        app.at("/latin1").get(|_| async {
+            let mut res = Response::new(StatusCode::Ok);
+            res.set_body("caf\u{e9}");
+            res.set_content_type(ContentType::with_param(
+                mime::HTML,
+                "charset",
+                "iso-8859-1",
+            )?);
+            Ok(res)
+        });
+        app.at("/multipart").get(|_| async {
+            Ok(Response::builder(StatusCode::Ok)
+                .body("--x--")
+                .content_type(ContentType::with_param(
+                    "multipart/form-data",
+                    "boundary",
+                    "a b",
+                )?)
+                .build())
+        });
+        app.at("/csv").get(|_| async {
+            Ok(Response::builder(StatusCode::Ok)
+                .body("a,b")
+                .content_type("text/csv")
+                .build())
+        });
+        app
+    }
+
+    #[async_std::test]
+    async fn preserves_parameters() {
+        let app = app();
+        assert_eq!(
+            content_type(&app, "/string").await,
+            "text/plain;charset=utf-8"
+        );
+        assert_eq!(
+            content_type(&app, "/latin1").await,
+            "text/html;charset=iso-8859-1"
+        );
+        assert_eq!(
+            content_type(&app, "/multipart").await,
+            "multipart/form-data;boundary=\"a b\""
+        );
+        assert_eq!(content_type(&app, "/csv").await, "text/csv");
+    }
+
+    #[async_std::test]
+    async fn default_charset() {
+        let mut app = app();
+        app.with(DefaultCharset::new("utf-8"));
+        assert_eq!(content_type(&app, "/csv").await, "text/csv;charset=utf-8");
+        assert_eq!(
+            content_type(&app, "/latin1").await,
+            "text/html;charset=iso-8859-1"
+        );
+        assert_eq!(
+            content_type(&app, "/string").await,
+            "text/plain;charset=utf-8"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_parameter_names() {
+        assert!(ContentType::with_param(mime::HTML, "char set", "utf-8").is_err());
+        assert!(ContentType::with_param(mime::HTML, "", "utf-8").is_err());
+        assert!(ContentType::with_param(mime::HTML, "charset", "utf-8\r\nX: y").is_err());
+        assert_eq!(
+            ContentType::with_param(mime::HTML, "charset", "utf-8")
+                .unwrap()
+                .to_string(),
+            "text/html;charset=utf-8"
+        );
+    }
+}
@@ -0,0 +1,354 @@
+//! 跨域资源共享（CORS）中间件
+
+use crate::http_types::headers::{ORIGIN, VARY};
+use crate::http_types::Method;
+use crate::{Middleware, Next, Request, Response, StatusCode};
+
+use async_trait::async_trait;
+
+use std::time::Duration;
+
+const ALLOW_ORIGIN: &str = "Access-Control-Allow-Origin";
+const ALLOW_METHODS: &str = "Access-Control-Allow-Methods";
+const ALLOW_HEADERS: &str = "Access-Control-Allow-Headers";
+const ALLOW_CREDENTIALS: &str = "Access-Control-Allow-Credentials";
+const ALLOW_PRIVATE_NETWORK: &str = "Access-Control-Allow-Private-Network";
+const EXPOSE_HEADERS: &str = "Access-Control-Expose-Headers";
+const MAX_AGE: &str = "Access-Control-Max-Age";
+const REQUEST_METHOD: &str = "Access-Control-Request-Method";
+const REQUEST_HEADERS: &str = "Access-Control-Request-Headers";
+const REQUEST_PRIVATE_NETWORK: &str = "Access-Control-Request-Private-Network";
+
+/// 预检结果的缓存方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PreflightCache {
+    /// 不返回 `Access-Control-Max-Age`，由浏览器使用默认值
+    Default,
+    MaxAge(Duration),
+    /// 返回 `Access-Control-Max-Age: 0`，浏览器每次都发送预检请求
+    Disabled,
+}
+
+/// 允许的来源
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum AllowOrigin {
+    Any,
+    List(Vec<String>),
+}
+
+/// 处理跨域请求和 `OPTIONS` 预检请求
+///
+/// 预检请求直接返回 `204 No Content`，不再调用下游。不允许的来源不添加任何 CORS 响应头。
+///
+/// 开启 [`allow_private_network`](CorsMiddleware::allow_private_network) 后，
+/// 对带有 `Access-Control-Request-Private-Network: true` 的预检请求（Chrome 的
+/// Private Network Access）返回 `Access-Control-Allow-Private-Network: true`。
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use summer_boot::utils::cors::CorsMiddleware;
+///
+/// let mut app = summer_boot::new();
+/// app.with(
+///     CorsMiddleware::new()
+///         .allow_origin("https://app.example.com")
+///         .allow_credentials(true)
+///         .allow_private_network(true)
+///         .max_age(Duration::from_secs(600)),
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct CorsMiddleware {
+    origin: AllowOrigin,
+    methods: String,
+    headers: Option<String>,
+    expose_headers: Option<String>,
+    credentials: bool,
+    private_network: bool,
+    cache: PreflightCache,
+}
+
+impl CorsMiddleware {
+    /// 创建一个新的实例，允许任意来源，允许的请求头与预检请求中的相同
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            origin: AllowOrigin::Any,
+            methods: "GET, POST, PUT, PATCH, DELETE, HEAD, OPTIONS".to_string(),
+            headers: None,
+            expose_headers: None,
+            credentials: false,
+            private_network: false,
+            cache: PreflightCache::Default,
+        }
+    }
+
+    /// 添加允许的来源，调用后只允许添加过的来源
+    #[must_use]
+    pub fn allow_origin(mut self, origin: impl Into<String>) -> Self {
+        let origin = origin.into();
+        match &mut self.origin {
+            AllowOrigin::List(origins) => origins.push(origin),
+            AllowOrigin::Any => self.origin = AllowOrigin::List(vec![origin]),
+        }
+        self
+    }
+
+    /// 设置允许的请求方法，例如 `"GET, POST"`
+    #[must_use]
+    pub fn allow_methods(mut self, methods: impl Into<String>) -> Self {
+        self.methods = methods.into();
+        self
+    }
+
+    /// 设置允许的请求头，未设置时返回预检请求中的 `Access-Control-Request-Headers`
+    #[must_use]
+    pub fn allow_headers(mut self, headers: impl Into<String>) -> Self {
+        self.headers = Some(headers.into());
+        self
+    }
+
+    /// 设置浏览器可以读取的响应头
+    #[must_use]
+    pub fn expose_headers(mut self, headers: impl Into<String>) -> Self {
+        self.expose_headers = Some(headers.into());
+        self
+    }
+
+    /// 是否允许携带 Cookie 等凭据，只对通过 [`allow_origin`](CorsMiddleware::allow_origin)
+    /// 添加的来源生效
+    ///
+    /// 允许任意来源时仍然返回 `*` 且不返回 `Access-Control-Allow-Credentials`，
+    /// 否则任意网站都可以带着用户的凭据读取响应。
+    #[must_use]
+    pub fn allow_credentials(mut self, credentials: bool) -> Self {
+        self.credentials = credentials;
+        self
+    }
+
+    /// 是否允许公网页面访问内网服务（Private Network Access），默认为 `false`
+    #[must_use]
+    pub fn allow_private_network(mut self, allow: bool) -> Self {
+        self.private_network = allow;
+        self
+    }
+
+    /// 设置浏览器缓存预检结果的时间，按秒返回 `Access-Control-Max-Age`
+    #[must_use]
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.cache = PreflightCache::MaxAge(max_age);
+        self
+    }
+
+    /// 禁止浏览器缓存预检结果，用于调试
+    #[must_use]
+    pub fn disable_preflight_cache(mut self) -> Self {
+        self.cache = PreflightCache::Disabled;
+        self
+    }
+
+    fn is_allowed(&self, origin: &str) -> bool {
+        match &self.origin {
+            AllowOrigin::Any => true,
+            AllowOrigin::List(origins) => origins.iter().any(|o| o == origin),
+        }
+    }
+
+    /// 所有跨域响应共用的响应头
+    fn apply_origin(&self, res: &mut Response, origin: &str) {
+        if self.origin == AllowOrigin::Any {
+            res.insert_header(ALLOW_ORIGIN, "*");
+            return;
+        }
+        res.insert_header(ALLOW_ORIGIN, origin);
+        res.append_header(VARY, "Origin");
+        if self.credentials {
+            res.insert_header(ALLOW_CREDENTIALS, "true");
+        }
+    }
+
+    fn preflight<State>(&self, req: &Request<State>, origin: &str) -> Response {
+        let mut res = Response::new(StatusCode::NoContent);
+        self.apply_origin(&mut res, origin);
+        res.insert_header(ALLOW_METHODS, self.methods.as_str());
+
+        let headers = self
+            .headers
+            .clone()
+            .or_else(|| req.header(REQUEST_HEADERS).map(|h| h.as_str().to_string()));
+        if let Some(headers) = headers {
+            res.insert_header(ALLOW_HEADERS, headers);
+        }
+        if self.private_network
+            && req
+                .header(REQUEST_PRIVATE_NETWORK)
+                .is_some_and(|h| h.as_str().eq_ignore_ascii_case("true"))
+        {
+            res.insert_header(ALLOW_PRIVATE_NETWORK, "true");
+        }
+        match self.cache {
+            PreflightCache::Default => {}
+            PreflightCache::MaxAge(max_age) => {
+                res.insert_header(MAX_AGE, max_age.as_secs().to_string())
+            }
+            PreflightCache::Disabled => res.insert_header(MAX_AGE, "0"),
+        }
+        for vary in [REQUEST_METHOD, REQUEST_HEADERS, REQUEST_PRIVATE_NETWORK] {
+            res.append_header(VARY, vary);
+        }
+        res
+    }
+}
+
+impl Default for CorsMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl<State: Clone + Send + Sync + 'static> Middleware<State> for CorsMiddleware {
+    async fn handle(&self, req: Request<State>, next: Next<'_, State>) -> crate::Result {
+        let origin = match req.header(ORIGIN).map(|h| h.as_str().to_string()) {
+            Some(origin) if self.is_allowed(&origin) => origin,
+            _ => return Ok(next.run(req).await),
+        };
+
+        if req.method() == Method::Options && req.header(REQUEST_METHOD).is_some() {
+            return Ok(self.preflight(&req, &origin));
+        }
+
+        let mut res = next.run(req).await;
+        self.apply_origin(&mut res, &origin);
+        if let Some(expose) = &self.expose_headers {
+            res.insert_header(EXPOSE_HEADERS, expose.as_str());
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate as summer_boot;
+    use http_types::{Request, Url};
+
+    /// 按 Chrome 对内网地址发送的预检请求整理
+    const CHROME_PNA_PREFLIGHT: &str = "\
+OPTIONS /api/devices HTTP/1.1
+Host: 192.168.1.20:8080
+Accept: */*
+Access-Control-Request-Method: POST
+Access-Control-Request-Headers: content-type,x-request-id
+Access-Control-Request-Private-Network: true
+Origin: https://dashboard.example.com
+User-Agent: Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36
+Sec-Fetch-Mode: cors
+Sec-Fetch-Site: cross-site
+Sec-Fetch-Dest: empty
+Referer: https://dashboard.example.com/
+Accept-Encoding: gzip, deflate, br, zstd
+Accept-Language: en-US,en;q=0.9
+";
+
+    /// 按 Chrome 对公网地址发送的普通预检请求整理
+    const CHROME_PREFLIGHT: &str = "\
+OPTIONS /api/devices HTTP/1.1
+Host: api.example.com
+Accept: */*
+Access-Control-Request-Method: PUT
+Access-Control-Request-Headers: authorization
+Origin: https://dashboard.example.com
+User-Agent: Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36
+Sec-Fetch-Mode: cors
+Sec-Fetch-Site: same-site
+Sec-Fetch-Dest: empty
+";
+
+    fn fixture(raw: &str) -> Request {
+        let mut lines = raw.lines();
+        let mut request_line = lines.next().unwrap().split(' ');
+        let method: Method = request_line.next().unwrap().parse().unwrap();
+        let path = request_line.next().unwrap();
+        let mut req = Request::new(
+            method,
+            Url::parse("http://localhost").unwrap().join(path).unwrap(),
+        );
+        for line in lines {
+            let (name, value) = line.split_once(": ").unwrap();
+            req.append_header(name, value);
+        }
+        req
+    }
+
+    async fn respond(cors: CorsMiddleware, req: Request) -> http_types::Response {
+        let mut app = summer_boot::new();
+        app.with(cors);
+        app.at("/api/devices")
+            .post(|_| async { Ok("created") })
+            .put(|_| async { Ok("updated") });
+        app.respond(req).await.unwrap()
+    }
+
+    #[async_std::test]
+    async fn private_network_preflight() {
+        let cors = CorsMiddleware::new()
+            .allow_origin("https://dashboard.example.com")
+            .allow_private_network(true)
+            .max_age(Duration::from_secs(600));
+        let res = respond(cors, fixture(CHROME_PNA_PREFLIGHT)).await;
+        assert_eq!(res.status(), StatusCode::NoContent);
+        assert_eq!(res[ALLOW_ORIGIN], "https://dashboard.example.com");
+        assert_eq!(res[ALLOW_PRIVATE_NETWORK], "true");
+        assert_eq!(res[ALLOW_HEADERS], "content-type,x-request-id");
+        assert_eq!(res[MAX_AGE], "600");
+
+        let res = respond(CorsMiddleware::new(), fixture(CHROME_PNA_PREFLIGHT)).await;
+        assert_eq!(res[ALLOW_ORIGIN], "*");
+        assert!(res.header(ALLOW_PRIVATE_NETWORK).is_none());
+        assert!(res.header(MAX_AGE).is_none());
+
+        let cors = CorsMiddleware::new().allow_private_network(true);
+        let res = respond(cors, fixture(CHROME_PREFLIGHT)).await;
+        assert!(res.header(ALLOW_PRIVATE_NETWORK).is_none());
+    }
+
+    #[async_std::test]
+    async fn disabled_preflight_cache_and_rejected_origin() {
+        let cors = CorsMiddleware::new()
+            .max_age(Duration::from_secs(600))
+            .disable_preflight_cache();
+        let res = respond(cors, fixture(CHROME_PREFLIGHT)).await;
+        assert_eq!(res[MAX_AGE], "0");
+
+        let cors = CorsMiddleware::new().allow_origin("https://other.example.com");
+        let res = respond(cors, fixture(CHROME_PREFLIGHT)).await;
+        assert!(res.header(ALLOW_ORIGIN).is_none());
+        assert_ne!(res.status(), StatusCode::NoContent);
+    }
+
+    #[async_std::test]
+    async fn simple_request_with_credentials() {
+        let mut req = Request::new(
+            Method::Post,
+            Url::parse("http://localhost/api/devices").unwrap(),
+        );
+        req.insert_header("Origin", "https://dashboard.example.com");
+        let cors = CorsMiddleware::new()
+            .allow_origin("https://dashboard.example.com")
+            .allow_credentials(true)
+            .expose_headers("X-Request-Id");
+        let mut res = respond(cors, req.clone()).await;
+        assert_eq!(res[ALLOW_ORIGIN], "https://dashboard.example.com");
+        assert_eq!(res[ALLOW_CREDENTIALS], "true");
+        assert_eq!(res[EXPOSE_HEADERS], "X-Request-Id");
+        assert_eq!(res.body_string().await.unwrap(), "created");
+
+        // 允许任意来源时不反射请求的来源，也不允许凭据
+        let res = respond(CorsMiddleware::new().allow_credentials(true), req).await;
+        assert_eq!(res[ALLOW_ORIGIN], "*");
+        assert!(res.header(ALLOW_CREDENTIALS).is_none());
+    }
+}
@@ -0,0 +1,271 @@
+//! 请求体解压中间件
+
+use crate::http_types::headers::{CONTENT_ENCODING, CONTENT_LENGTH};
+use crate::{Body, Middleware, Next, Request, Response, StatusCode};
+
+use async_compression::futures::bufread::{DeflateDecoder, GzipDecoder};
+use async_std::io::{self, BufReader, Read};
+use async_trait::async_trait;
+
+use std::fmt;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+/// 解压 `Content-Encoding` 为 `gzip` 或 `deflate` 的请求体
+///
+/// 为了防止压缩炸弹，解压时统计输出的字节数：超过 `max_size`，或者在输出超过
+/// 64 KiB 后解压比超过 `max_ratio`，读取立即出错，响应状态为 `413 Payload Too Large`。
+/// 不支持的编码返回 `415 Unsupported Media Type`。
+///
+/// # Examples
+///
+/// ```
+/// use summer_boot::utils::decompression::DecompressionMiddleware;
+///
+/// let mut app = summer_boot::new();
+/// app.with(
+///     DecompressionMiddleware::new()
+///         .max_size(8 * 1024 * 1024)
+///         .max_ratio(50),
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct DecompressionMiddleware {
+    max_size: u64,
+    max_ratio: u64,
+}
+
+/// 低于该大小时不检查解压比，避免小请求因为头部开销被误判
+const RATIO_THRESHOLD: u64 = 64 * 1024;
+
+impl Default for DecompressionMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DecompressionMiddleware {
+    /// 创建一个新的实例，默认解压后最多 10 MiB，解压比最多 100
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            max_size: 10 * 1024 * 1024,
+            max_ratio: 100,
+        }
+    }
+
+    /// 设置解压后允许的最大字节数
+    #[must_use]
+    pub fn max_size(mut self, max_size: u64) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// 设置允许的最大解压比（解压后字节数 / 压缩字节数）
+    #[must_use]
+    pub fn max_ratio(mut self, max_ratio: u64) -> Self {
+        self.max_ratio = max_ratio;
+        self
+    }
+}
+
+/// 解压后的请求体超出限制
+#[derive(Debug)]
+enum DecompressionBomb {
+    Size(u64),
+    Ratio(u64),
+}
+
+impl fmt::Display for DecompressionBomb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecompressionBomb::Size(limit) => write!(f, "解压后的请求体超过 {} 字节的限制", limit),
+            DecompressionBomb::Ratio(limit) => write!(f, "请求体的解压比超过 {} 的限制", limit),
+        }
+    }
+}
+
+impl std::error::Error for DecompressionBomb {}
+
+/// 统计读取的压缩字节数
+struct CountingReader {
+    inner: Body,
+    read: Arc<AtomicU64>,
+}
+
+impl Read for CountingReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        let n = futures_util::ready!(Pin::new(&mut this.inner).poll_read(cx, buf))?;
+        this.read.fetch_add(n as u64, Ordering::Relaxed);
+        Poll::Ready(Ok(n))
+    }
+}
+
+/// 在解压输出上检查大小和解压比
+struct BombGuard<R> {
+    inner: R,
+    compressed: Arc<AtomicU64>,
+    decompressed: u64,
+    max_size: u64,
+    max_ratio: u64,
+}
+
+impl<R: Read + Unpin> Read for BombGuard<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        let n = futures_util::ready!(Pin::new(&mut this.inner).poll_read(cx, buf))?;
+        this.decompressed += n as u64;
+
+        let compressed = this.compressed.load(Ordering::Relaxed).max(1);
+        let error = if this.decompressed > this.max_size {
+            Some(DecompressionBomb::Size(this.max_size))
+        } else if this.decompressed > RATIO_THRESHOLD
+            && this.decompressed / compressed > this.max_ratio
+        {
+            Some(DecompressionBomb::Ratio(this.max_ratio))
+        } else {
+            None
+        };
+        match error {
+            Some(error) => Poll::Ready(Err(io::Error::new(io::ErrorKind::InvalidData, error))),
+            None => Poll::Ready(Ok(n)),
+        }
+    }
+}
+
+fn is_decompression_bomb(res: &Response) -> bool {
+    res.downcast_error::<io::Error>()
+        .and_then(|e| e.get_ref())
+        .is_some_and(|e| e.is::<DecompressionBomb>())
+}
+
+#[async_trait]
+impl<State: Clone + Send + Sync + 'static> Middleware<State> for DecompressionMiddleware {
+    async fn handle(&self, mut req: Request<State>, next: Next<'_, State>) -> crate::Result {
+        let encoding = match req.header(CONTENT_ENCODING) {
+            Some(values) => values.as_str().trim().to_ascii_lowercase(),
+            None => return Ok(next.run(req).await),
+        };
+
+        let body = req.take_body();
+        let mime = body.mime().clone();
+        let compressed = Arc::new(AtomicU64::new(0));
+        let counting = BufReader::new(CountingReader {
+            inner: body,
+            read: compressed.clone(),
+        });
+        let decoded: Box<dyn Read + Unpin + Send + Sync> = match encoding.as_str() {
+            "gzip" | "x-gzip" => Box::new(GzipDecoder::new(counting)),
+            "deflate" => Box::new(DeflateDecoder::new(counting)),
+            "identity" => Box::new(counting),
+            _ => return Ok(Response::new(StatusCode::UnsupportedMediaType)),
+        };
+
+        let mut body = Body::from_reader(
+            BufReader::new(BombGuard {
+                inner: decoded,
+                compressed,
+                decompressed: 0,
+                max_size: self.max_size,
+                max_ratio: self.max_ratio,
+            }),
+            None,
+        );
+        body.set_mime(mime);
+        req.remove_header(CONTENT_ENCODING);
+        req.remove_header(CONTENT_LENGTH);
+        req.set_body(body);
+
+        let mut res = next.run(req).await;
+        if is_decompression_bomb(&res) {
+            res.set_status(StatusCode::PayloadTooLarge);
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate as summer_boot;
+    use async_compression::futures::bufread::GzipEncoder;
+    use async_std::io::ReadExt;
+    use http_types::{Method, Request, Response, Url};
+
+    fn app(middleware: DecompressionMiddleware) -> summer_boot::Server<()> {
+        let mut app = summer_boot::new();
+        app.with(middleware);
+        app.at("/upload")
+            .post(|mut req: summer_boot::Request<()>| async move {
+                let body = req.body_bytes().await?;
+                Ok(body.len().to_string())
+            });
+        app
+    }
+
+    async fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        GzipEncoder::new(data).read_to_end(&mut out).await.unwrap();
+        out
+    }
+
+    fn upload(body: Vec<u8>, encoding: &str) -> Request {
+        let url = Url::parse("http://localhost/upload").unwrap();
+        let mut req = Request::new(Method::Post, url);
+        req.insert_header(CONTENT_ENCODING, encoding);
+        req.set_body(body);
+        req
+    }
+
+    #[async_std::test]
+    async fn decompresses_gzip() {
+        let body = gzip(b"hello summer boot").await;
+        let mut res: Response = app(DecompressionMiddleware::new())
+            .respond(upload(body, "gzip"))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::Ok);
+        assert_eq!(res.body_string().await.unwrap(), "17");
+    }
+
+    #[async_std::test]
+    async fn rejects_high_ratio_payload() {
+        // 4 MiB 的 0 压缩后只有几 KiB
+        let bomb = gzip(&vec![0; 4 * 1024 * 1024]).await;
+        assert!(bomb.len() < 16 * 1024);
+
+        let res: Response = app(DecompressionMiddleware::new())
+            .respond(upload(bomb.clone(), "gzip"))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::PayloadTooLarge);
+
+        let res: Response = app(DecompressionMiddleware::new()
+            .max_ratio(u64::MAX)
+            .max_size(1024 * 1024))
+        .respond(upload(bomb, "gzip"))
+        .await
+        .unwrap();
+        assert_eq!(res.status(), StatusCode::PayloadTooLarge);
+    }
+
+    #[async_std::test]
+    async fn rejects_unknown_encoding() {
+        let res: Response = app(DecompressionMiddleware::new())
+            .respond(upload(b"data".to_vec(), "br"))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::UnsupportedMediaType);
+    }
+}
@@ -0,0 +1,275 @@
+//! 按客户端 IP 过滤请求的中间件
+
+use crate::{Middleware, Next, Request, Response, StatusCode};
+
+use async_trait::async_trait;
+use ipnet::IpNet;
+
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, RwLock};
+
+/// IP 过滤规则
+#[derive(Debug, Clone, Default)]
+pub struct IpFilterConfig {
+    allow: Vec<IpNet>,
+    deny: Vec<IpNet>,
+}
+
+impl IpFilterConfig {
+    /// 不限制任何地址
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 添加允许的网段，设置后不在允许列表中的地址都被拒绝
+    #[must_use]
+    pub fn allow(mut self, net: IpNet) -> Self {
+        self.allow.push(net);
+        self
+    }
+
+    /// 添加拒绝的网段，优先于允许列表
+    #[must_use]
+    pub fn deny(mut self, net: IpNet) -> Self {
+        self.deny.push(net);
+        self
+    }
+
+    /// 地址是否被允许访问，无法确定地址时只在没有允许列表时放行
+    fn permits(&self, ip: Option<IpAddr>) -> bool {
+        match ip {
+            Some(ip) => {
+                !self.deny.iter().any(|net| net.contains(&ip))
+                    && (self.allow.is_empty() || self.allow.iter().any(|net| net.contains(&ip)))
+            }
+            None => self.allow.is_empty(),
+        }
+    }
+}
+
+/// 按允许和拒绝列表过滤客户端 IP，被拒绝的请求返回 `403 Forbidden`
+///
+/// 客户端 IP 取自连接的对端地址，没有时使用 `X-Forwarded-For` 的第一个地址。
+/// 克隆的实例共享同一份规则，可以通过 [`IpFilterMiddleware::reload`] 在运行时替换。
+///
+/// # Examples
+///
+/// ```
+/// use summer_boot::utils::ip_filter::{IpFilterConfig, IpFilterMiddleware};
+///
+/// let filter = IpFilterMiddleware::new(
+///     IpFilterConfig::new()
+///         .allow("10.0.0.0/8".parse().unwrap())
+///         .deny("10.0.13.0/24".parse().unwrap()),
+/// );
+///
+/// let mut app = summer_boot::new();
+/// app.with(filter.clone());
+/// app.at("/").get(|_| async { Ok("ok") });
+///
+/// // 之后替换规则
+/// filter.reload(IpFilterConfig::new().deny("192.168.0.0/16".parse().unwrap()));
+/// ```
+#[derive(Debug, Clone)]
+pub struct IpFilterMiddleware {
+    config: Arc<RwLock<Arc<IpFilterConfig>>>,
+}
+
+impl IpFilterMiddleware {
+    #[must_use]
+    pub fn new(config: IpFilterConfig) -> Self {
+        Self {
+            config: Arc::new(RwLock::new(Arc::new(config))),
+        }
+    }
+
+    /// 替换过滤规则，处理中的请求继续使用旧规则
+    pub fn reload(&self, config: IpFilterConfig) {
+        *self.config.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(config);
+    }
+
+    /// 当前的过滤规则
+    pub fn config(&self) -> Arc<IpFilterConfig> {
+        self.config
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+#[async_trait]
+impl<State: Clone + Send + Sync + 'static> Middleware<State> for IpFilterMiddleware {
+    async fn handle(&self, req: Request<State>, next: Next<'_, State>) -> crate::Result {
+        if self.config().permits(client_ip(&req)) {
+            Ok(next.run(req).await)
+        } else {
+            Ok(Response::new(StatusCode::Forbidden))
+        }
+    }
+}
+
+/// 只允许本机和内部网段访问，其他客户端返回 `403 Forbidden`
+///
+/// 与 [`IpFilterMiddleware`] 不同，客户端地址由 [`Request::client_addr`] 确定，
+/// 只有来自 [`Server::trusted_proxies`](crate::Server::trusted_proxies) 的请求才会读取转发请求头，
+/// 适合保护 actuator 等管理接口。
+///
+/// # Examples
+///
+/// ```
+/// use summer_boot::utils::ip_filter::InternalOnlyMiddleware;
+///
+/// let mut app = summer_boot::new();
+/// app.at("/admin")
+///     .with(InternalOnlyMiddleware::new().allow("10.0.0.0/8".parse().unwrap()))
+///     .get(|_| async { Ok("ok") });
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct InternalOnlyMiddleware {
+    networks: Vec<IpNet>,
+}
+
+impl InternalOnlyMiddleware {
+    /// 只允许本机回环地址
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 允许的内部网段
+    #[must_use]
+    pub fn allow(mut self, net: IpNet) -> Self {
+        self.networks.push(net);
+        self
+    }
+}
+
+#[async_trait]
+impl<State: Clone + Send + Sync + 'static> Middleware<State> for InternalOnlyMiddleware {
+    async fn handle(&self, req: Request<State>, next: Next<'_, State>) -> crate::Result {
+        if req.remote_is_loopback() || req.remote_in_networks(&self.networks) {
+            Ok(next.run(req).await)
+        } else {
+            Ok(Response::new(StatusCode::Forbidden))
+        }
+    }
+}
+
+/// 对端地址，没有时取 `X-Forwarded-For` 的第一个地址
+fn client_ip<State>(req: &Request<State>) -> Option<IpAddr> {
+    let ip = match req.peer_addr() {
+        Some(peer) => parse_ip(peer),
+        None => req
+            .header("X-Forwarded-For")
+            .and_then(|values| values.as_str().split(',').next())
+            .and_then(|hop| parse_ip(hop.trim())),
+    }?;
+    // IPv4 映射的 IPv6 地址按 IPv4 匹配
+    Some(match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+        ip => ip,
+    })
+}
+
+/// 解析 `1.2.3.4`、`1.2.3.4:80` 或 `[::1]:80`
+fn parse_ip(addr: &str) -> Option<IpAddr> {
+    addr.parse::<IpAddr>()
+        .ok()
+        .or_else(|| addr.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use http_types::{Method, Request, Response, Url};
+
+    fn get(peer: Option<&str>, forwarded: Option<&str>) -> Request {
+        let url = Url::parse("http://localhost/").unwrap();
+        let mut req = Request::new(Method::Get, url);
+        req.set_peer_addr(peer);
+        if let Some(forwarded) = forwarded {
+            req.insert_header("X-Forwarded-For", forwarded);
+        }
+        req
+    }
+
+    #[async_std::test]
+    async fn filters_and_reloads() {
+        let filter = IpFilterMiddleware::new(
+            IpFilterConfig::new()
+                .allow("10.0.0.0/8".parse().unwrap())
+                .deny("10.0.13.0/24".parse().unwrap()),
+        );
+        let mut app = crate::new();
+        app.with(filter.clone());
+        app.at("/").get(|_| async { Ok("ok") });
+
+        let status = |req| {
+            let app = app.clone();
+            async move {
+                let res: Response = app.respond(req).await.unwrap();
+                res.status()
+            }
+        };
+
+        assert_eq!(status(get(Some("10.1.2.3:4000"), None)).await, 200);
+        assert_eq!(status(get(Some("10.0.13.7:4000"), None)).await, 403);
+        assert_eq!(status(get(Some("192.168.1.1:4000"), None)).await, 403);
+        assert_eq!(status(get(Some("[::ffff:10.1.2.3]:4000"), None)).await, 200);
+        // 对端地址优先于 `X-Forwarded-For`
+        assert_eq!(
+            status(get(Some("192.168.1.1:4000"), Some("10.1.2.3"))).await,
+            403
+        );
+        assert_eq!(status(get(None, Some("10.1.2.3, 192.168.1.1"))).await, 200);
+        assert_eq!(status(get(None, None)).await, 403);
+
+        filter.reload(IpFilterConfig::new().deny("10.0.0.0/8".parse().unwrap()));
+        assert_eq!(status(get(Some("10.1.2.3:4000"), None)).await, 403);
+        assert_eq!(status(get(Some("192.168.1.1:4000"), None)).await, 200);
+        assert_eq!(status(get(None, None)).await, 200);
+    }
+
+    #[async_std::test]
+    async fn internal_only() {
+        let mut app = crate::new();
+        app.trusted_proxies(
+            crate::utils::trusted_proxy::TrustedProxies::parse(["10.0.0.1"]).unwrap(),
+        );
+        app.at("/")
+            .with(InternalOnlyMiddleware::new().allow("fd00::/8".parse().unwrap()))
+            .get(|_| async { Ok("ok") });
+
+        let status = |req| {
+            let app = app.clone();
+            async move {
+                let res: Response = app.respond(req).await.unwrap();
+                res.status()
+            }
+        };
+
+        assert_eq!(status(get(Some("127.0.0.1:4000"), None)).await, 200);
+        assert_eq!(status(get(Some("[::1]:4000"), None)).await, 200);
+        assert_eq!(
+            status(get(Some("[::ffff:127.0.0.1]:4000"), None)).await,
+            200
+        );
+        assert_eq!(status(get(Some("[fd12::5]:4000"), None)).await, 200);
+        assert_eq!(status(get(Some("192.168.1.1:4000"), None)).await, 403);
+        assert_eq!(status(get(None, None)).await, 403);
+        // 不受信任的对端不能通过请求头伪造本机地址
+        assert_eq!(
+            status(get(Some("192.168.1.1:4000"), Some("127.0.0.1"))).await,
+            403
+        );
+        assert_eq!(
+            status(get(Some("10.0.0.1:4000"), Some("192.168.1.1"))).await,
+            403
+        );
+        assert_eq!(
+            status(get(Some("10.0.0.1:4000"), Some("127.0.0.1"))).await,
+            200
+        );
+    }
+}
@@ -1,5 +1,25 @@
+pub mod body_limit;
+pub mod bus;
+#[cfg(feature = "cache")]
+pub mod cache;
+pub mod content_type;
+pub mod cors;
+pub mod decompression;
+pub mod ip_filter;
 pub mod middleware;
+pub mod multipart;
+pub mod negotiation;
+pub mod panic_recovery;
+pub mod redirect;
 pub mod request;
+pub mod request_id;
+pub mod request_timing;
+pub mod require_header;
 pub mod response;
 pub mod response_builder;
+pub mod retry_after;
+pub mod timeout;
+#[cfg(feature = "opentelemetry")]
+pub mod tracing;
+pub mod trusted_proxy;
 pub mod util;
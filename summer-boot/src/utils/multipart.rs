@@ -0,0 +1,657 @@
+//! `multipart/form-data` 请求体的流式解析
+//!
+//! 通过 [`Request::body_multipart`](crate::Request::body_multipart) 获取。
+
+use crate::http_types::{Body, Mime, StatusCode};
+
+use async_std::io::prelude::*;
+use async_std::io::{self, Read, Write};
+use futures_util::Stream;
+
+use std::fmt;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+/// 默认允许的最大 part 数量
+pub const DEFAULT_MAX_PARTS: usize = 100;
+
+/// 默认每个 part 允许的最大字节数
+pub const DEFAULT_MAX_PART_SIZE: u64 = 16 * 1024 * 1024;
+
+/// part 头部允许的最大字节数
+const MAX_HEADER_SIZE: usize = 8 * 1024;
+
+/// 每次从请求体读取的字节数
+const READ_SIZE: usize = 8 * 1024;
+
+/// 解析 multipart 请求体时的错误
+///
+/// 格式错误为 `400 Bad Request`，超出限制为 `413 Payload Too Large`。
+/// 通过 [`Read`] 读取 [`Part`] 时返回的 `io::Error` 包含该错误，
+/// [`Part::read_chunk`] 和 [`Part::copy_to`] 则直接返回带有对应状态码的 [`crate::Error`]。
+#[derive(Debug, Clone)]
+pub struct MultipartError {
+    status: StatusCode,
+    message: String,
+}
+
+impl MultipartError {
+    fn bad_request(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::BadRequest,
+            message: message.into(),
+        }
+    }
+
+    fn too_large(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::PayloadTooLarge,
+            message: message.into(),
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+}
+
+impl fmt::Display for MultipartError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for MultipartError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    /// 第一个分隔符之前的内容
+    Preamble,
+    /// 分隔符之后，判断是下一个 part 还是结束
+    Delimiter,
+    Body,
+    Done,
+}
+
+struct Inner {
+    body: Body,
+    buf: Vec<u8>,
+    eof: bool,
+    /// `\r\n--boundary`
+    delimiter: Vec<u8>,
+    phase: Phase,
+    /// 当前 part 的序号，从 1 开始
+    part: usize,
+    part_read: u64,
+    max_parts: usize,
+    max_part_size: u64,
+    error: Option<MultipartError>,
+}
+
+impl Inner {
+    fn fail(&mut self, err: MultipartError) -> MultipartError {
+        self.phase = Phase::Done;
+        self.error.get_or_insert(err).clone()
+    }
+
+    /// 从请求体读取更多数据，返回 `false` 表示已经读完
+    fn poll_fill(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<bool>> {
+        if self.eof {
+            return Poll::Ready(Ok(false));
+        }
+        let len = self.buf.len();
+        self.buf.resize(len + READ_SIZE, 0);
+        let result = Pin::new(&mut self.body).poll_read(cx, &mut self.buf[len..]);
+        let n = match result {
+            Poll::Ready(Ok(n)) => n,
+            Poll::Ready(Err(e)) => {
+                self.buf.truncate(len);
+                return Poll::Ready(Err(e));
+            }
+            Poll::Pending => {
+                self.buf.truncate(len);
+                return Poll::Pending;
+            }
+        };
+        self.buf.truncate(len + n);
+        self.eof = n == 0;
+        Poll::Ready(Ok(n > 0))
+    }
+
+    fn find(&self, needle: &[u8]) -> Option<usize> {
+        self.buf
+            .windows(needle.len())
+            .position(|window| window == needle)
+    }
+
+    /// 读取当前 part 的数据，`out` 为 `None` 时丢弃。返回 `0` 表示 part 结束。
+    fn poll_body(
+        &mut self,
+        cx: &mut Context<'_>,
+        out: Option<&mut [u8]>,
+    ) -> Poll<io::Result<usize>> {
+        loop {
+            if self.phase != Phase::Body {
+                return Poll::Ready(Ok(0));
+            }
+            let available = match self.find(&self.delimiter) {
+                Some(0) => {
+                    self.buf.drain(..self.delimiter.len());
+                    self.phase = Phase::Delimiter;
+                    return Poll::Ready(Ok(0));
+                }
+                Some(pos) => pos,
+                // 末尾可能是被截断的分隔符，保留到下次读取
+                None => self.buf.len().saturating_sub(self.delimiter.len() - 1),
+            };
+            if available == 0 {
+                match futures_util::ready!(self.poll_fill(cx)) {
+                    Ok(true) => continue,
+                    Ok(false) => {
+                        let err = self.fail(MultipartError::bad_request(
+                            "multipart body ended before the closing boundary",
+                        ));
+                        return Poll::Ready(Err(io::Error::new(io::ErrorKind::InvalidData, err)));
+                    }
+                    Err(e) => return Poll::Ready(Err(e)),
+                }
+            }
+
+            let n = match out {
+                Some(out) => {
+                    let n = available.min(out.len());
+                    out[..n].copy_from_slice(&self.buf[..n]);
+                    n
+                }
+                None => available,
+            };
+            self.buf.drain(..n);
+            self.part_read += n as u64;
+            if self.part_read > self.max_part_size {
+                let err = self.fail(MultipartError::too_large(format!(
+                    "multipart part exceeds the limit of {} bytes",
+                    self.max_part_size
+                )));
+                return Poll::Ready(Err(io::Error::new(io::ErrorKind::InvalidData, err)));
+            }
+            return Poll::Ready(Ok(n));
+        }
+    }
+
+    /// 解析下一个 part 的头部，出错时错误记录在 `error` 中并只返回一次
+    fn poll_next_part(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Head, MultipartError>>> {
+        loop {
+            match self.phase {
+                Phase::Done => return Poll::Ready(self.error.take().map(Err)),
+                Phase::Body => {
+                    // 跳过当前 part 中没有读取的数据
+                    if let Err(e) = futures_util::ready!(self.poll_body(cx, None)) {
+                        if self.error.is_none() {
+                            self.fail(MultipartError::bad_request(e.to_string()));
+                        }
+                    }
+                }
+                Phase::Preamble => match self.find(&self.delimiter) {
+                    Some(pos) => {
+                        self.buf.drain(..pos + self.delimiter.len());
+                        self.phase = Phase::Delimiter;
+                    }
+                    None => {
+                        let keep = self.delimiter.len() - 1;
+                        if self.buf.len() > keep {
+                            self.buf.drain(..self.buf.len() - keep);
+                        }
+                        futures_util::ready!(
+                            self.poll_fill_or_fail(cx, "missing multipart boundary")
+                        );
+                    }
+                },
+                Phase::Delimiter => {
+                    if self.buf.len() < 2 {
+                        futures_util::ready!(
+                            self.poll_fill_or_fail(cx, "missing multipart boundary end")
+                        );
+                    } else if self.buf.starts_with(b"--") {
+                        // 结束分隔符之后的内容忽略
+                        self.phase = Phase::Done;
+                    } else if !self.buf.starts_with(b"\r\n") {
+                        self.fail(MultipartError::bad_request(
+                            "invalid characters after multipart boundary",
+                        ));
+                    } else {
+                        match self.parse_head() {
+                            Some(Ok(_)) if self.part >= self.max_parts => {
+                                self.fail(MultipartError::too_large(format!(
+                                    "multipart body has more than {} parts",
+                                    self.max_parts
+                                )));
+                            }
+                            Some(Ok(head)) => {
+                                self.part += 1;
+                                self.part_read = 0;
+                                self.phase = Phase::Body;
+                                return Poll::Ready(Some(Ok(head)));
+                            }
+                            Some(Err(err)) => {
+                                self.fail(err);
+                            }
+                            None if self.buf.len() > MAX_HEADER_SIZE => {
+                                self.fail(MultipartError::bad_request(
+                                    "multipart part headers are too large",
+                                ));
+                            }
+                            None => {
+                                futures_util::ready!(
+                                    self.poll_fill_or_fail(cx, "incomplete multipart part headers")
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// 读取更多数据，请求体已经读完或读取出错时记录错误
+    fn poll_fill_or_fail(&mut self, cx: &mut Context<'_>, message: &str) -> Poll<()> {
+        match futures_util::ready!(self.poll_fill(cx)) {
+            Ok(true) => {}
+            Ok(false) => {
+                self.fail(MultipartError::bad_request(message));
+            }
+            Err(e) => {
+                self.fail(MultipartError::bad_request(e.to_string()));
+            }
+        }
+        Poll::Ready(())
+    }
+
+    /// 解析分隔符之后的 part 头部，数据不完整时返回 `None`
+    fn parse_head(&mut self) -> Option<Result<Head, MultipartError>> {
+        // buf 以分隔符后的 \r\n 开头，头部为空时紧跟着 \r\n
+        let end = if self.buf[2..].starts_with(b"\r\n") {
+            4
+        } else {
+            self.find(b"\r\n\r\n").filter(|&pos| pos >= 2)? + 4
+        };
+        let mut headers = [httparse::EMPTY_HEADER; 16];
+        let head = match httparse::parse_headers(&self.buf[2..end], &mut headers) {
+            Ok(httparse::Status::Complete(_)) => Head::from_headers(&headers),
+            Ok(httparse::Status::Partial) | Err(_) => Err(MultipartError::bad_request(
+                "invalid multipart part headers",
+            )),
+        };
+        self.buf.drain(..end);
+        Some(head)
+    }
+}
+
+/// part 的头部信息
+#[derive(Debug, Default)]
+struct Head {
+    name: Option<String>,
+    filename: Option<String>,
+    content_type: Option<Mime>,
+}
+
+impl Head {
+    fn from_headers(headers: &[httparse::Header<'_>]) -> Result<Self, MultipartError> {
+        let mut head = Head::default();
+        for header in headers.iter().take_while(|h| !h.name.is_empty()) {
+            let value = std::str::from_utf8(header.value)
+                .map_err(|_| MultipartError::bad_request("invalid multipart part headers"))?;
+            if header.name.eq_ignore_ascii_case("content-disposition") {
+                for param in value.split(';').skip(1) {
+                    let Some((key, value)) = param.split_once('=') else {
+                        continue;
+                    };
+                    let value = value.trim().trim_matches('"').to_string();
+                    match key.trim().to_ascii_lowercase().as_str() {
+                        "name" => head.name = Some(value),
+                        "filename" => head.filename = Some(value),
+                        _ => {}
+                    }
+                }
+            } else if header.name.eq_ignore_ascii_case("content-type") {
+                head.content_type = Mime::from_str(value.trim()).ok();
+            }
+        }
+        Ok(head)
+    }
+}
+
+/// `multipart/form-data` 请求体中 part 的流
+///
+/// part 需要按顺序读取，获取下一个 part 时当前 part 没有读取的数据会被丢弃。
+/// 格式错误时返回 `400 Bad Request`，超出限制时返回 `413 Payload Too Large`。
+///
+/// # Examples
+///
+/// ```
+/// use async_std::io;
+/// use async_std::stream::StreamExt;
+/// use summer_boot::Request;
+///
+/// let mut app = summer_boot::new();
+/// app.at("/upload").post(|mut req: Request<()>| async move {
+///     let mut multipart = req.body_multipart()?.max_parts(10);
+///     let mut uploaded = 0;
+///     while let Some(part) = multipart.next().await {
+///         let mut part = part?;
+///         if part.filename().is_some() {
+///             uploaded += part.copy_to(&mut io::sink()).await?;
+///         }
+///     }
+///     Ok(format!("{} bytes uploaded", uploaded))
+/// });
+/// ```
+pub struct Multipart {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl Multipart {
+    pub(crate) fn new(boundary: &str, body: Body) -> Self {
+        let mut delimiter = b"\r\n--".to_vec();
+        delimiter.extend_from_slice(boundary.as_bytes());
+        Self {
+            inner: Arc::new(Mutex::new(Inner {
+                body,
+                // 第一个分隔符前没有换行，补上后可以与后续分隔符统一处理
+                buf: b"\r\n".to_vec(),
+                eof: false,
+                delimiter,
+                phase: Phase::Preamble,
+                part: 0,
+                part_read: 0,
+                max_parts: DEFAULT_MAX_PARTS,
+                max_part_size: DEFAULT_MAX_PART_SIZE,
+                error: None,
+            })),
+        }
+    }
+
+    /// 设置允许的最大 part 数量，默认为 [`DEFAULT_MAX_PARTS`]
+    #[must_use]
+    pub fn max_parts(self, max_parts: usize) -> Self {
+        self.inner.lock().unwrap().max_parts = max_parts;
+        self
+    }
+
+    /// 设置每个 part 允许的最大字节数，默认为 [`DEFAULT_MAX_PART_SIZE`]
+    #[must_use]
+    pub fn max_part_size(self, max_part_size: u64) -> Self {
+        self.inner.lock().unwrap().max_part_size = max_part_size;
+        self
+    }
+}
+
+impl fmt::Debug for Multipart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let inner = self.inner.lock().unwrap();
+        f.debug_struct("Multipart")
+            .field("part", &inner.part)
+            .field("max_parts", &inner.max_parts)
+            .field("max_part_size", &inner.max_part_size)
+            .finish()
+    }
+}
+
+impl Stream for Multipart {
+    type Item = crate::Result<Part>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut inner = self.inner.lock().unwrap();
+        let head = match futures_util::ready!(inner.poll_next_part(cx)) {
+            Some(Ok(head)) => head,
+            Some(Err(err)) => return Poll::Ready(Some(Err(crate::Error::new(err.status, err)))),
+            None => return Poll::Ready(None),
+        };
+        Poll::Ready(Some(Ok(Part {
+            inner: self.inner.clone(),
+            index: inner.part,
+            name: head.name,
+            filename: head.filename,
+            content_type: head.content_type,
+        })))
+    }
+}
+
+/// multipart 请求体中的一个 part，数据通过 [`Read`] 流式读取
+pub struct Part {
+    inner: Arc<Mutex<Inner>>,
+    index: usize,
+    name: Option<String>,
+    filename: Option<String>,
+    content_type: Option<Mime>,
+}
+
+impl Part {
+    /// `Content-Disposition` 中的 `name`
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// `Content-Disposition` 中的 `filename`，普通字段为 `None`
+    pub fn filename(&self) -> Option<&str> {
+        self.filename.as_deref()
+    }
+
+    pub fn content_type(&self) -> Option<&Mime> {
+        self.content_type.as_ref()
+    }
+
+    /// 读取 part 的下一段数据，返回 `0` 表示 part 结束
+    ///
+    /// 与 [`Read`] 不同，解析错误带有 [`MultipartError`] 的状态码。
+    pub async fn read_chunk(&mut self, buf: &mut [u8]) -> crate::Result<usize> {
+        self.read(buf).await.map_err(into_error)
+    }
+
+    /// 把 part 剩余的数据写入 `writer`，返回写入的字节数
+    ///
+    /// 解析错误带有 [`MultipartError`] 的状态码，写入错误为 `500 Internal Server Error`。
+    pub async fn copy_to<W: Write + Unpin + ?Sized>(
+        &mut self,
+        writer: &mut W,
+    ) -> crate::Result<u64> {
+        let mut buf = vec![0; READ_SIZE];
+        let mut copied = 0;
+        loop {
+            let n = self.read_chunk(&mut buf).await?;
+            if n == 0 {
+                return Ok(copied);
+            }
+            writer.write_all(&buf[..n]).await?;
+            copied += n as u64;
+        }
+    }
+}
+
+/// 把读取 part 时的 `io::Error` 转换为带有 [`MultipartError`] 状态码的错误
+fn into_error(e: io::Error) -> crate::Error {
+    let status = e
+        .get_ref()
+        .and_then(|e| e.downcast_ref::<MultipartError>())
+        .map_or(StatusCode::InternalServerError, MultipartError::status);
+    crate::Error::new(status, e)
+}
+
+impl fmt::Debug for Part {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Part")
+            .field("name", &self.name)
+            .field("filename", &self.filename)
+            .field("content_type", &self.content_type)
+            .finish()
+    }
+}
+
+impl Read for Part {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.part != self.index || buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        if let Some(err) = inner.error.clone() {
+            return Poll::Ready(Err(io::Error::new(io::ErrorKind::InvalidData, err)));
+        }
+        inner.poll_body(cx, Some(buf))
+    }
+}
+
+/// 从 `Content-Type` 中获取 boundary
+pub(crate) fn boundary(content_type: Option<Mime>) -> crate::Result<String> {
+    let mime = content_type
+        .filter(|mime| mime.essence() == "multipart/form-data")
+        .ok_or_else(|| {
+            crate::Error::from_str(
+                StatusCode::UnsupportedMediaType,
+                "expected a multipart/form-data request body",
+            )
+        })?;
+    mime.param("boundary")
+        .map(|boundary| boundary.as_str().trim_matches('"').to_string())
+        .filter(|boundary| !boundary.is_empty())
+        .ok_or_else(|| crate::Error::from_str(StatusCode::BadRequest, "missing multipart boundary"))
+}
+
+#[cfg(test)]
+mod test {
+    use crate as summer_boot;
+    use crate::http_types::{Method, Request, Response, Url};
+    use crate::StatusCode;
+
+    use async_std::stream::StreamExt;
+    use xxhash_rust::xxh3::{xxh3_64, Xxh3};
+
+    const BOUNDARY: &str = "summer-boot-boundary";
+
+    fn app() -> summer_boot::Server<()> {
+        let mut app = summer_boot::new();
+        app.at("/upload")
+            .post(|mut req: summer_boot::Request<()>| async move {
+                let mut multipart = req.body_multipart()?.max_part_size(8 * 1024 * 1024);
+                let mut lines = vec![];
+                while let Some(part) = multipart.next().await {
+                    let mut part = part?;
+                    let name = part.name().unwrap_or_default().to_string();
+                    let filename = part.filename().unwrap_or("-").to_string();
+                    let content_type = part
+                        .content_type()
+                        .map_or("-".to_string(), |mime| mime.essence().to_string());
+                    let mut hasher = Xxh3::new();
+                    let mut len = 0;
+                    let mut buf = vec![0; 3000];
+                    loop {
+                        let n = part.read_chunk(&mut buf).await?;
+                        if n == 0 {
+                            break;
+                        }
+                        len += n;
+                        hasher.update(&buf[..n]);
+                    }
+                    lines.push(format!(
+                        "{} {} {} {} {:016x}",
+                        name,
+                        filename,
+                        content_type,
+                        len,
+                        hasher.digest()
+                    ));
+                }
+                Ok(lines.join("\n"))
+            });
+        app
+    }
+
+    async fn upload(body: Vec<u8>) -> (StatusCode, String) {
+        let mut req = Request::new(Method::Post, Url::parse("http://localhost/upload").unwrap());
+        req.insert_header(
+            "Content-Type",
+            format!("multipart/form-data; boundary={}", BOUNDARY),
+        );
+        req.set_body(body);
+        let mut res: Response = app().respond(req).await.unwrap();
+        (res.status(), res.body_string().await.unwrap())
+    }
+
+    #[async_std::test]
+    async fn streams_file_and_field() {
+        let file: Vec<u8> = (0..3 * 1024 * 1024 + 17)
+            .map(|i: u32| (i.wrapping_mul(2_654_435_761) >> 13) as u8)
+            .collect();
+
+        let mut body = Vec::new();
+        body.extend_from_slice(b"preamble\r\n");
+        body.extend_from_slice(format!("--{}\r\n", BOUNDARY).as_bytes());
+        body.extend_from_slice(b"Content-Disposition: form-data; name=\"title\"\r\n\r\n");
+        body.extend_from_slice(b"holiday photos");
+        body.extend_from_slice(format!("\r\n--{}\r\n", BOUNDARY).as_bytes());
+        body.extend_from_slice(
+            b"Content-Disposition: form-data; name=\"file\"; filename=\"photo.bin\"\r\n",
+        );
+        body.extend_from_slice(b"Content-Type: application/octet-stream\r\n\r\n");
+        body.extend_from_slice(&file);
+        body.extend_from_slice(format!("\r\n--{}--\r\n", BOUNDARY).as_bytes());
+
+        let (status, lines) = upload(body).await;
+        assert_eq!(status, StatusCode::Ok);
+        assert_eq!(
+            lines,
+            format!(
+                "title - - 14 {:016x}\nfile photo.bin application/octet-stream {} {:016x}",
+                xxh3_64(b"holiday photos"),
+                file.len(),
+                xxh3_64(&file)
+            )
+        );
+    }
+
+    #[async_std::test]
+    async fn malformed_boundary_is_bad_request() {
+        let body = format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\nvalue\r\n--other--\r\n",
+            BOUNDARY
+        );
+        let (status, _) = upload(body.into_bytes()).await;
+        assert_eq!(status, StatusCode::BadRequest);
+
+        let (status, _) = upload(b"no boundary here".to_vec()).await;
+        assert_eq!(status, StatusCode::BadRequest);
+    }
+
+    #[async_std::test]
+    async fn enforces_limits() {
+        let mut body = Vec::new();
+        body.extend_from_slice(format!("--{}\r\n", BOUNDARY).as_bytes());
+        body.extend_from_slice(b"Content-Disposition: form-data; name=\"big\"\r\n\r\n");
+        body.extend_from_slice(&vec![b'x'; 9 * 1024 * 1024]);
+        body.extend_from_slice(format!("\r\n--{}--\r\n", BOUNDARY).as_bytes());
+        let (status, _) = upload(body).await;
+        assert_eq!(status, StatusCode::PayloadTooLarge);
+
+        let mut body = Vec::new();
+        for i in 0..=super::DEFAULT_MAX_PARTS {
+            body.extend_from_slice(format!("--{}\r\n", BOUNDARY).as_bytes());
+            body.extend_from_slice(
+                format!(
+                    "Content-Disposition: form-data; name=\"f{}\"\r\n\r\nv\r\n",
+                    i
+                )
+                .as_bytes(),
+            );
+        }
+        body.extend_from_slice(format!("--{}--\r\n", BOUNDARY).as_bytes());
+        let (status, _) = upload(body).await;
+        assert_eq!(status, StatusCode::PayloadTooLarge);
+    }
+}
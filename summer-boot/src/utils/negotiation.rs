@@ -0,0 +1,282 @@
+//! 根据 `Accept` 选择 JSON 或 XML 响应
+
+use crate::http_types::headers::{ACCEPT, CONTENT_TYPE, VARY};
+use crate::http_types::mime::{self, Mime};
+use crate::{Body, Middleware, Next, Request, Response, StatusCode};
+
+use async_trait::async_trait;
+
+use std::str::FromStr;
+
+/// 协商后的响应格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Json,
+    Xml,
+    TextXml,
+}
+
+/// 可以输出的格式，`Accept` 中权重相同时靠前的优先
+const FORMATS: [(Format, &str, &str); 3] = [
+    (Format::Json, "application", "json"),
+    (Format::Xml, "application", "xml"),
+    (Format::TextXml, "text", "xml"),
+];
+
+/// 根据请求的 `Accept` 输出 JSON 或 XML
+///
+/// 处理器照常返回 JSON 响应，客户端更偏好 `application/xml` 或 `text/xml` 时转换为 XML。
+/// 没有 `Accept` 或者权重相同时保持 JSON，JSON 和 XML 都不被接受时返回 `406`。
+/// 只处理 `Content-Type` 为 `application/json` 的成功响应，并添加 `Vary: Accept`；
+/// 4xx 和 5xx 响应原样返回，错误信息不会被 `406` 替换。
+///
+/// # Examples
+///
+/// ```
+/// use summer_boot::utils::negotiation::ContentNegotiationMiddleware;
+/// use summer_boot::{Request, Response};
+///
+/// let mut app = summer_boot::new();
+/// app.with(ContentNegotiationMiddleware::new().root_element("order"));
+/// app.at("/order").get(|_req: Request<()>| async {
+///     let mut res = Response::new(200);
+///     res.body_json(&serde_json::json!({ "id": 7 }))?;
+///     Ok(res)
+/// });
+/// ```
+#[derive(Debug, Clone)]
+pub struct ContentNegotiationMiddleware {
+    root: String,
+}
+
+impl ContentNegotiationMiddleware {
+    /// 转换为 XML 时根元素名为 `response`
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            root: "response".to_string(),
+        }
+    }
+
+    /// 设置转换为 XML 时的根元素名
+    #[must_use]
+    pub fn root_element(mut self, name: impl Into<String>) -> Self {
+        self.root = name.into();
+        self
+    }
+}
+
+impl Default for ContentNegotiationMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl<State: Clone + Send + Sync + 'static> Middleware<State> for ContentNegotiationMiddleware {
+    async fn handle(&self, req: Request<State>, next: Next<'_, State>) -> crate::Result {
+        let accept: Option<String> = req.header(ACCEPT).map(|values| {
+            values
+                .iter()
+                .map(|value| value.as_str())
+                .collect::<Vec<_>>()
+                .join(",")
+        });
+
+        let mut res = next.run(req).await;
+        let is_json = res
+            .content_type()
+            .is_some_and(|mime| mime.essence() == mime::JSON.essence());
+        if !is_json || !res.status().is_success() {
+            return Ok(res);
+        }
+        res.append_header(VARY, "Accept");
+
+        let format = match accept.as_deref().map(negotiate) {
+            None => return Ok(res),
+            Some(Some(format)) => format,
+            Some(None) => {
+                let mut res = Response::new(StatusCode::NotAcceptable);
+                res.insert_header(VARY, "Accept");
+                return Ok(res);
+            }
+        };
+        if format == Format::Json {
+            return Ok(res);
+        }
+
+        let value: serde_json::Value = res.take_body().into_json().await?;
+        let xml = quick_xml::se::to_string_with_root(&self.root, &value)
+            .map_err(|e| crate::Error::new(StatusCode::InternalServerError, e))?;
+        res.set_body(Body::from_string(xml));
+        let content_type = match format {
+            Format::TextXml => Mime::from_str("text/xml;charset=utf-8")?,
+            _ => mime::XML,
+        };
+        res.insert_header(CONTENT_TYPE, content_type.to_string());
+        Ok(res)
+    }
+}
+
+/// 选出 `Accept` 中权重最高的格式，都不被接受时返回 `None`
+fn negotiate(accept: &str) -> Option<Format> {
+    let ranges: Vec<(&str, &str, f32)> = accept.split(',').filter_map(parse_range).collect();
+
+    let mut best: Option<(Format, f32)> = None;
+    for (format, kind, subtype) in FORMATS {
+        // 最具体的媒体范围决定权重
+        let quality = ranges
+            .iter()
+            .filter(|(k, s, _)| {
+                (*k == "*" || k.eq_ignore_ascii_case(kind))
+                    && (*s == "*" || s.eq_ignore_ascii_case(subtype))
+            })
+            .max_by_key(|(k, s, _)| (*k != "*") as u8 + (*s != "*") as u8)
+            .map_or(0.0, |(_, _, q)| *q);
+        if quality > 0.0 && best.map_or(true, |(_, q)| quality > q) {
+            best = Some((format, quality));
+        }
+    }
+    best.map(|(format, _)| format)
+}
+
+/// 解析 `type/subtype;q=0.5`，格式错误的项被忽略
+fn parse_range(range: &str) -> Option<(&str, &str, f32)> {
+    let mut parts = range.split(';');
+    let (kind, subtype) = parts.next()?.trim().split_once('/')?;
+    let mut quality = 1.0;
+    for param in parts {
+        if let Some((name, value)) = param.split_once('=') {
+            if name.trim().eq_ignore_ascii_case("q") {
+                quality = value.trim().parse().ok()?;
+            }
+        }
+    }
+    Some((kind.trim(), subtype.trim(), quality))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http_types::{Method, Request as HttpRequest, Url};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Order {
+        id: u32,
+        item: String,
+        paid: bool,
+    }
+
+    fn order() -> Order {
+        Order {
+            id: 7,
+            item: "tea".to_string(),
+            paid: true,
+        }
+    }
+
+    #[test]
+    fn picks_preferred_format() {
+        assert_eq!(negotiate("application/json"), Some(Format::Json));
+        assert_eq!(negotiate("application/xml"), Some(Format::Xml));
+        assert_eq!(negotiate("text/xml, */*;q=0.5"), Some(Format::TextXml));
+        assert_eq!(
+            negotiate("application/xml;q=0.9, application/json"),
+            Some(Format::Json)
+        );
+        assert_eq!(negotiate("*/*"), Some(Format::Json));
+        assert_eq!(
+            negotiate("application/*;q=0.8, application/json;q=0"),
+            Some(Format::Xml)
+        );
+        assert_eq!(negotiate("text/html"), None);
+    }
+
+    #[async_std::test]
+    async fn round_trips_through_json_and_xml() {
+        let mut app = crate::new();
+        app.with(ContentNegotiationMiddleware::new().root_element("Order"));
+        app.at("/order").post(|mut req: Request<()>| async move {
+            let is_xml = req
+                .content_type()
+                .is_some_and(|mime| mime.essence() == mime::XML.essence());
+            let order: Order = if is_xml {
+                req.body_xml().await?
+            } else {
+                req.body_json().await?
+            };
+            let mut res = Response::new(StatusCode::Ok);
+            res.body_json(&order)?;
+            Ok(res)
+        });
+
+        let request = |content_type: Mime, body: String, accept: &str| {
+            let mut req =
+                HttpRequest::new(Method::Post, Url::parse("http://localhost/order").unwrap());
+            req.set_body(body);
+            req.set_content_type(content_type);
+            req.insert_header("Accept", accept);
+            req
+        };
+
+        // XML 请求，XML 响应
+        let mut res = Response::new(StatusCode::Ok);
+        res.body_xml(&order()).unwrap();
+        let xml = res.take_body().into_string().await.unwrap();
+        assert_eq!(
+            xml,
+            "<Order><id>7</id><item>tea</item><paid>true</paid></Order>"
+        );
+        let mut res: crate::http_types::Response = app
+            .respond(request(mime::XML, xml.clone(), "application/xml"))
+            .await
+            .unwrap();
+        assert_eq!(res.content_type(), Some(mime::XML));
+        assert_eq!(res["Vary"], "Accept");
+        let body = res.body_string().await.unwrap();
+        assert_eq!(quick_xml::de::from_str::<Order>(&body).unwrap(), order());
+
+        // JSON 请求，JSON 响应
+        let json = serde_json::to_string(&order()).unwrap();
+        let mut res: crate::http_types::Response = app
+            .respond(request(
+                mime::JSON,
+                json,
+                "application/json, application/xml;q=0.5",
+            ))
+            .await
+            .unwrap();
+        assert_eq!(res.content_type().unwrap().essence(), "application/json");
+        assert_eq!(res.body_json::<Order>().await.unwrap(), order());
+
+        let res: crate::http_types::Response = app
+            .respond(request(mime::XML, xml, "text/html"))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::NotAcceptable);
+    }
+
+    #[async_std::test]
+    async fn passes_error_responses_through() {
+        let mut app = crate::new();
+        app.with(ContentNegotiationMiddleware::new());
+        app.at("/order").get(|_| async {
+            let mut res = Response::new(StatusCode::UnprocessableEntity);
+            res.body_json(&serde_json::json!({ "error": "item is required" }))?;
+            Ok(res)
+        });
+
+        for accept in ["application/xml", "text/html"] {
+            let mut req =
+                HttpRequest::new(Method::Get, Url::parse("http://localhost/order").unwrap());
+            req.insert_header("Accept", accept);
+            let mut res: crate::http_types::Response = app.respond(req).await.unwrap();
+            assert_eq!(res.status(), StatusCode::UnprocessableEntity, "{}", accept);
+            assert_eq!(res.content_type().unwrap().essence(), "application/json");
+            assert!(res.header("Vary").is_none());
+            let body: serde_json::Value = res.body_json().await.unwrap();
+            assert_eq!(body["error"], "item is required");
+        }
+    }
+}
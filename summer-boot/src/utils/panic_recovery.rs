@@ -0,0 +1,183 @@
+//! panic 恢复中间件
+
+use crate::log;
+use crate::{Middleware, Next, Request, Response, StatusCode};
+
+use async_trait::async_trait;
+use futures_util::FutureExt;
+
+use std::backtrace::Backtrace;
+use std::cell::RefCell;
+use std::panic::AssertUnwindSafe;
+use std::sync::Once;
+
+thread_local! {
+    /// 当前线程上最近一次 panic 的调用栈，由 panic 钩子记录
+    static BACKTRACE: RefCell<Option<Backtrace>> = const { RefCell::new(None) };
+}
+
+static INSTALL_HOOK: Once = Once::new();
+
+/// panic 的内容，无法转为字符串时为空
+pub(crate) fn panic_message(panic: &(dyn std::any::Any + Send)) -> String {
+    panic
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_default()
+}
+
+/// 在 panic 钩子中记录调用栈，之后仍然调用原来的钩子
+///
+/// 捕获 panic 时栈已经展开，只能在钩子里记录发生 panic 的位置。
+fn install_hook() {
+    INSTALL_HOOK.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            BACKTRACE.with(|backtrace| *backtrace.borrow_mut() = Some(Backtrace::capture()));
+            previous(info);
+        }));
+    });
+}
+
+/// 捕获下游的 panic 并返回 `500 Internal Server Error`
+///
+/// panic 消息和调用栈写入错误日志，调用栈需要设置 `RUST_BACKTRACE=1` 才会采集。
+/// 响应体默认只有通用的错误描述；开启 [`PanicRecoveryMiddleware::debug_mode`] 后
+/// 响应体包含 panic 消息，对应配置文件中的 `server.debug_mode`，只应在开发环境使用。
+///
+/// 第一次创建时会安装一个记录调用栈的 panic 钩子，原有的钩子仍然会被调用。
+///
+/// # Examples
+///
+/// ```
+/// use summer_boot::utils::panic_recovery::PanicRecoveryMiddleware;
+/// use summer_boot::Request;
+///
+/// let mut app = summer_boot::new();
+/// app.with(PanicRecoveryMiddleware::new());
+/// app.at("/boom").get(|_: Request<()>| async move {
+///     let items: Vec<u32> = Vec::new();
+///     Ok(format!("{}", items[0]))
+/// });
+/// ```
+#[derive(Debug, Clone)]
+pub struct PanicRecoveryMiddleware {
+    debug_mode: bool,
+}
+
+impl Default for PanicRecoveryMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PanicRecoveryMiddleware {
+    /// 创建一个新的实例，响应体中不包含 panic 消息
+    #[must_use]
+    pub fn new() -> Self {
+        install_hook();
+        Self { debug_mode: false }
+    }
+
+    /// 使用配置文件中的 `server.debug_mode`
+    #[cfg(feature = "autoconfigure")]
+    #[must_use]
+    pub fn from_config(config: &summer_boot_autoconfigure::Server) -> Self {
+        Self::new().debug_mode(config.debug_mode)
+    }
+
+    /// 开发模式，响应体中包含 panic 消息
+    #[must_use]
+    pub fn debug_mode(mut self, enabled: bool) -> Self {
+        self.debug_mode = enabled;
+        self
+    }
+}
+
+#[async_trait]
+impl<State: Clone + Send + Sync + 'static> Middleware<State> for PanicRecoveryMiddleware {
+    async fn handle(&self, req: Request<State>, next: Next<'_, State>) -> crate::Result {
+        let method = req.method();
+        let path = req.url().path().to_string();
+        let panic = match AssertUnwindSafe(next.run(req)).catch_unwind().await {
+            Ok(res) => return Ok(res),
+            Err(panic) => panic_message(panic.as_ref()),
+        };
+        let backtrace = BACKTRACE.with(|backtrace| backtrace.borrow_mut().take());
+        log::error!("endpoint panicked", {
+            method: method.to_string(),
+            path: log::sanitize(&path),
+            message: log::sanitize(&panic),
+            backtrace: backtrace.map(|backtrace| backtrace.to_string()).unwrap_or_default(),
+        });
+
+        let mut res = Response::new(StatusCode::InternalServerError);
+        if self.debug_mode {
+            res.set_body(format!("Internal Server Error: {}", panic));
+        } else {
+            res.set_body("Internal Server Error");
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http_types::{Method, Url};
+
+    async fn get(app: &crate::Server<()>, path: &str) -> (StatusCode, String) {
+        let url = Url::parse("http://localhost").unwrap().join(path).unwrap();
+        let mut res: http_types::Response = app
+            .respond(http_types::Request::new(Method::Get, url))
+            .await
+            .unwrap();
+        (res.status(), res.body_string().await.unwrap())
+    }
+
+    fn app(middleware: PanicRecoveryMiddleware) -> crate::Server<()> {
+        let mut app = crate::new();
+        app.with(middleware);
+        app.at("/ok").get(|_| async { Ok("ok") });
+        app.at("/boom").get(|_| async {
+            panic!("secret {}", "token");
+            #[allow(unreachable_code)]
+            Ok("unreachable")
+        });
+        app
+    }
+
+    #[async_std::test]
+    async fn hides_panic_message() {
+        let app = app(PanicRecoveryMiddleware::new());
+        assert_eq!(
+            get(&app, "/boom").await,
+            (
+                StatusCode::InternalServerError,
+                "Internal Server Error".to_string()
+            )
+        );
+        // panic 之后继续处理其他请求
+        assert_eq!(get(&app, "/ok").await, (StatusCode::Ok, "ok".to_string()));
+    }
+
+    #[async_std::test]
+    async fn shows_panic_message_in_debug_mode() {
+        let app = app(PanicRecoveryMiddleware::new().debug_mode(true));
+        let (status, body) = get(&app, "/boom").await;
+        assert_eq!(status, StatusCode::InternalServerError);
+        assert_eq!(body, "Internal Server Error: secret token");
+    }
+
+    #[cfg(feature = "autoconfigure")]
+    #[async_std::test]
+    async fn reads_debug_mode_from_config() {
+        let config = summer_boot_autoconfigure::Server {
+            debug_mode: true,
+            ..Default::default()
+        };
+        let (_, body) = get(&app(PanicRecoveryMiddleware::from_config(&config)), "/boom").await;
+        assert_eq!(body, "Internal Server Error: secret token");
+    }
+}
@@ -0,0 +1,123 @@
+//! 重定向响应
+
+use crate::http_types::headers::LOCATION;
+use crate::{Response, StatusCode};
+
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+
+/// `Location` 中需要编码的字符，非 ASCII 字符总是会被编码
+const LOCATION_VALUE: &AsciiSet = &CONTROLS.add(b' ').add(b'"').add(b'<').add(b'>');
+
+/// 将地址编码为合法的 `Location` 响应头，已经编码过的 `%XX` 保持不变
+pub(crate) fn location_value(url: &str) -> String {
+    utf8_percent_encode(url, LOCATION_VALUE).to_string()
+}
+
+/// 重定向到另一个地址，转换为带 `Location` 响应头的 3xx 响应
+///
+/// # Examples
+///
+/// ```
+/// use summer_boot::utils::redirect::Redirect;
+/// use summer_boot::Request;
+///
+/// let mut app = summer_boot::new();
+/// app.at("/account").get(|_req: Request<()>| async { Ok(Redirect::see_other("/login")) });
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redirect {
+    status: StatusCode,
+    location: String,
+}
+
+impl Redirect {
+    /// `308 Permanent Redirect`，客户端保留请求方法和请求体
+    #[must_use]
+    pub fn permanent(url: impl AsRef<str>) -> Self {
+        Self::new(StatusCode::PermanentRedirect, url)
+    }
+
+    /// `307 Temporary Redirect`，客户端保留请求方法和请求体
+    #[must_use]
+    pub fn temporary(url: impl AsRef<str>) -> Self {
+        Self::new(StatusCode::TemporaryRedirect, url)
+    }
+
+    /// `303 See Other`，客户端使用 `GET` 请求新地址，常用于表单提交之后
+    #[must_use]
+    pub fn see_other(url: impl AsRef<str>) -> Self {
+        Self::new(StatusCode::SeeOther, url)
+    }
+
+    fn new(status: StatusCode, url: impl AsRef<str>) -> Self {
+        Self {
+            status,
+            location: location_value(url.as_ref()),
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// 编码后的地址，非 ASCII 字符和空格等按 UTF-8 百分号编码
+    pub fn location(&self) -> &str {
+        &self.location
+    }
+}
+
+impl From<Redirect> for Response {
+    fn from(redirect: Redirect) -> Response {
+        let mut res = Response::new(redirect.status);
+        res.insert_header(LOCATION, redirect.location);
+        res
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate as summer_boot;
+    use http_types::{Method, Request, Url};
+
+    #[async_std::test]
+    async fn redirect_responses() {
+        let mut app = summer_boot::new();
+        app.at("/old")
+            .get(|_| async { Ok(Redirect::permanent("/new")) });
+        app.at("/later")
+            .get(|_| async { Ok(Redirect::temporary("https://example.com/later")) });
+        app.at("/submit")
+            .post(|_| async { Ok(Redirect::see_other("/done")) });
+        app.at("/builder")
+            .get(|_| async { Ok(Response::builder(StatusCode::Found).redirect("/elsewhere")) });
+        app.at("/not-redirect")
+            .get(|_| async { Ok(Response::builder(StatusCode::Ok).redirect("/elsewhere")) });
+        app.at("/unicode")
+            .get(|_| async { Ok(Redirect::see_other("/文档 1")) });
+        app.at("/builder-unicode").get(|_| async {
+            Ok(Response::builder(StatusCode::MovedPermanently).redirect("/文档?q=%20"))
+        });
+
+        let cases = [
+            (Method::Get, "/old", 308, "/new"),
+            (Method::Get, "/later", 307, "https://example.com/later"),
+            (Method::Post, "/submit", 303, "/done"),
+            (Method::Get, "/builder", 302, "/elsewhere"),
+            (Method::Get, "/not-redirect", 302, "/elsewhere"),
+            (Method::Get, "/unicode", 303, "/%E6%96%87%E6%A1%A3%201"),
+            (
+                Method::Get,
+                "/builder-unicode",
+                301,
+                "/%E6%96%87%E6%A1%A3?q=%20",
+            ),
+        ];
+        for (method, path, status, location) in cases {
+            let url = Url::parse("http://localhost").unwrap().join(path).unwrap();
+            let res: http_types::Response = app.respond(Request::new(method, url)).await.unwrap();
+            assert_eq!(res.status(), status);
+            assert_eq!(res["Location"], location);
+        }
+    }
+}
@@ -2,14 +2,24 @@ use async_std::io::{self, prelude::*};
 use async_std::task::{Context, Poll};
 use routefinder::Captures;
 
+use std::fmt;
 use std::ops::Index;
 use std::pin::Pin;
+use std::str::FromStr;
 
 use crate::http_types::format_err;
 use crate::http_types::headers::{self, HeaderName, HeaderValues, ToHeaderValues};
+use crate::http_types::proxies::Forwarded;
 use crate::http_types::{self, Body, Method, Mime, StatusCode, Url, Version};
+use crate::utils::multipart::{self, Multipart};
+use crate::utils::trusted_proxy::{self, TrustedProxies};
 use crate::Response;
 
+#[cfg(feature = "cookies")]
+use crate::cookies::CookieData;
+#[cfg(feature = "cookies")]
+use crate::http_types::cookies::Cookie;
+
 pin_project_lite::pin_project! {
     /// HTTP request.
     ///
@@ -24,6 +34,13 @@ pin_project_lite::pin_project! {
     }
 }
 
+/// 匹配到的路由模板和名称
+#[derive(Debug, Clone)]
+struct MatchedRoute {
+    pattern: String,
+    name: Option<String>,
+}
+
 impl<State> Request<State> {
     /// 创建一个新的 `Request`.
     pub(crate) fn new(
@@ -62,6 +79,23 @@ impl<State> Request<State> {
         self.req.method()
     }
 
+    /// 请求方法是否安全（RFC 7231 4.2.1），即 `GET`、`HEAD`、`OPTIONS` 或 `TRACE`
+    #[must_use]
+    pub fn is_safe(&self) -> bool {
+        matches!(
+            self.method(),
+            Method::Get | Method::Head | Method::Options | Method::Trace
+        )
+    }
+
+    /// 请求方法是否幂等（RFC 7231 4.2.2），即安全方法以及 `PUT` 和 `DELETE`
+    ///
+    /// 重试、缓存等中间件可以据此判断请求能否重复发送。
+    #[must_use]
+    pub fn is_idempotent(&self) -> bool {
+        self.is_safe() || matches!(self.method(), Method::Put | Method::Delete)
+    }
+
     /// 访问请求的完整URI方法。
     #[must_use]
     pub fn url(&self) -> &Url {
@@ -110,11 +144,74 @@ impl<State> Request<State> {
     /// 1. `Forwarded` head `for` key
     /// 2. 第一个 `X-Forwarded-For` header
     /// 3. 传输的对等地址
+    ///
+    /// 返回的是未经校验的原始值，任何客户端都可以通过请求头伪造，
+    /// 需要可信的客户端地址时使用 [`client_addr`](Self::client_addr)。
     #[must_use]
     pub fn remote(&self) -> Option<&str> {
         self.req.remote()
     }
 
+    /// 获取客户端地址
+    ///
+    /// 对端地址不属于 [`Server::trusted_proxies`](crate::Server::trusted_proxies) 时返回对端地址，
+    /// 否则从右向左遍历 `Forwarded` 或 `X-Forwarded-For` 中的地址，返回第一个不受信任的地址。
+    /// 对端地址不是 IP 地址（例如 Unix socket）时返回 `None`。
+    #[must_use]
+    pub fn client_addr(&self) -> Option<std::net::IpAddr> {
+        let peer = trusted_proxy::parse_addr(self.peer_addr()?)?;
+        let Some(proxies) = self.ext::<std::sync::Arc<TrustedProxies>>() else {
+            return Some(peer);
+        };
+        Some(proxies.client_addr(peer, || self.forwarded_chain()))
+    }
+
+    /// 客户端地址是否为本机回环地址，例如 `127.0.0.1` 或 `::1`
+    ///
+    /// 使用 [`client_addr`](Self::client_addr) 确定的地址，无法确定地址时返回 `false`。
+    #[must_use]
+    pub fn remote_is_loopback(&self) -> bool {
+        self.client_addr().is_some_and(|ip| ip.is_loopback())
+    }
+
+    /// 客户端地址是否属于任一网段
+    ///
+    /// 使用 [`client_addr`](Self::client_addr) 确定的地址，IPv4 映射的 IPv6 地址按 IPv4 匹配，
+    /// 无法确定地址时返回 `false`。
+    #[must_use]
+    pub fn remote_in_networks(&self, networks: &[ipnet::IpNet]) -> bool {
+        self.client_addr()
+            .is_some_and(|ip| networks.iter().any(|net| net.contains(&ip)))
+    }
+
+    /// 所有 `Forwarded` 的 `for` 地址，没有时使用 `X-Forwarded-For`，按从左到右的顺序
+    ///
+    /// 多个同名请求头按出现的顺序拼接。
+    fn forwarded_chain(&self) -> Vec<String> {
+        if let Some(values) = self.header("Forwarded") {
+            return values
+                .iter()
+                .filter_map(|value| Forwarded::parse(value.as_str()).ok())
+                .flat_map(|forwarded| {
+                    forwarded
+                        .forwarded_for()
+                        .into_iter()
+                        .map(str::to_string)
+                        .collect::<Vec<_>>()
+                })
+                .collect();
+        }
+        self.header("X-Forwarded-For")
+            .map(|values| {
+                values
+                    .iter()
+                    .flat_map(|value| value.as_str().split(','))
+                    .map(|hop| hop.trim().to_string())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
     /// 获取此请求的目标主机。
     ///
     /// 按以下优先级确定：
@@ -275,6 +372,31 @@ impl<State> Request<State> {
             .ok_or_else(|| format_err!("Param \"{}\" not found", key.to_string()))
     }
 
+    /// 获取路由参数并转换为 `T`
+    ///
+    /// 转换失败时返回 `400 Bad Request`，参数不存在时与 [`param`](Self::param) 相同。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use summer_boot::{Request, Result};
+    ///
+    /// async fn user(req: Request<()>) -> Result<String> {
+    ///     let id: u64 = req.param_as("id")?;
+    ///     Ok(format!("user {}", id))
+    /// }
+    ///
+    /// let mut app = summer_boot::new();
+    /// app.at("/users/:id").get(user);
+    /// ```
+    pub fn param_as<T>(&self, key: &str) -> crate::Result<T>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        parse_param(key, self.param(key)?)
+    }
+
     /// 从路由中提取通配符（如果存在）
     ///
     /// 以 `&str` 形式返回参数，该参数是从此 `Request` 借用的。
@@ -305,6 +427,100 @@ impl<State> Request<State> {
             .find_map(|captures| captures.wildcard())
     }
 
+    /// 匹配到的路由模板，例如 `/users/:id`
+    ///
+    /// 嵌套的 `Server` 会把内层的模板拼接到外层模板的通配符位置。
+    /// 没有匹配到路由（404、405）时返回 `None`。
+    #[must_use]
+    pub fn route_pattern(&self) -> Option<&str> {
+        self.req
+            .ext()
+            .get::<MatchedRoute>()
+            .map(|route| route.pattern.as_str())
+    }
+
+    /// 匹配到的路由名称，通过 [`Route::name`](crate::Route::name) 设置
+    #[must_use]
+    pub fn route_name(&self) -> Option<&str> {
+        self.req
+            .ext()
+            .get::<MatchedRoute>()
+            .and_then(|route| route.name.as_deref())
+    }
+
+    /// 路径存在但方法不匹配时，路径上注册的方法
+    ///
+    /// 供 [`Server::at_method_not_allowed`](crate::Server::at_method_not_allowed)
+    /// 设置的 endpoint 生成 `Allow` 响应头，注册了 `GET` 时包含 `HEAD`。其他情况返回 `None`。
+    #[must_use]
+    pub fn allowed_methods(&self) -> Option<&[http_types::Method]> {
+        self.req
+            .ext()
+            .get::<crate::gateway::router::AllowedMethods>()
+            .map(|allow| allow.0.as_slice())
+    }
+
+    /// 请求ID，由 [`RequestIdMiddleware`](crate::utils::request_id::RequestIdMiddleware) 设置
+    ///
+    /// 未启用该中间件时返回 `None`。
+    #[must_use]
+    pub fn id(&self) -> Option<&str> {
+        self.req
+            .ext()
+            .get::<crate::utils::request_id::RequestId>()
+            .map(|id| id.as_str())
+    }
+
+    /// 在最终响应之前发送 `102 Processing` 或 `103 Early Hints`
+    ///
+    /// 只有 HTTP/1.1 连接上的请求才有值。
+    #[must_use]
+    pub fn interim(&self) -> Option<&crate::http::Interim> {
+        self.req.ext().get::<crate::http::Interim>()
+    }
+
+    /// 解析前的原始请求头，包括请求行和结尾的空行
+    ///
+    /// 只有启用 [`ServerOptions::capture_raw_head`](crate::http::ServerOptions::capture_raw_head)
+    /// 时才有值，长度不超过请求头的上限 8 KiB。
+    #[must_use]
+    pub fn raw_head(&self) -> Option<&[u8]> {
+        self.req
+            .ext()
+            .get::<crate::http1::http::RawHead>()
+            .map(|head| head.0.as_slice())
+    }
+
+    /// 客户端证书的 SHA-256 指纹，小写十六进制
+    ///
+    /// 只有传输层终止了双向 TLS 并保存了 [`PeerCertFingerprint`](crate::http::PeerCertFingerprint) 时才有值。
+    #[must_use]
+    pub fn peer_cert_fingerprint(&self) -> Option<String> {
+        self.req
+            .ext()
+            .get::<crate::http::PeerCertFingerprint>()
+            .map(|fingerprint| fingerprint.as_str().to_string())
+    }
+
+    /// 记录匹配到的路由模板和名称
+    pub(crate) fn set_route(&mut self, pattern: Option<String>, name: Option<String>) {
+        let outer = self.req.ext_mut().remove::<MatchedRoute>();
+        let Some(pattern) = pattern else {
+            return;
+        };
+        let route = match outer {
+            Some(outer) => MatchedRoute {
+                pattern: match outer.pattern.strip_suffix('*') {
+                    Some(prefix) => format!("{}{}", prefix.trim_end_matches('/'), pattern),
+                    None => pattern,
+                },
+                name: name.or(outer.name),
+            },
+            None => MatchedRoute { pattern, name },
+        };
+        self.req.ext_mut().insert(route);
+    }
+
     ///
     /// 使用[serde_qs](https://docs.rs/serde_qs)将URL查询组件解析为结构
     /// 将整个查询作为未解析的字符串获取，使用 `request.url().query()`。
@@ -434,6 +650,17 @@ impl<State> Request<State> {
         Ok(res)
     }
 
+    /// 读取整个请求body并按 XML 反序列化
+    ///
+    /// # Errors
+    ///
+    /// 读取body时遇到的I/O错误会立即返回，body不是目标类型 `T` 的有效 XML 时返回 `422`
+    pub async fn body_xml<T: serde::de::DeserializeOwned>(&mut self) -> crate::Result<T> {
+        let body = self.req.body_string().await?;
+        quick_xml::de::from_str(&body)
+            .map_err(|e| crate::Error::new(StatusCode::UnprocessableEntity, e))
+    }
+
     /// 将请求主体解析为表单
     ///
     /// ```rust
@@ -471,6 +698,16 @@ impl<State> Request<State> {
         Ok(res)
     }
 
+    /// 将请求主体解析为 `multipart/form-data`，返回按顺序读取的 part 流
+    ///
+    /// 文件数据从请求体流式读取，不会整体缓存在内存中。
+    /// `Content-Type` 不是 `multipart/form-data` 时返回 `415 Unsupported Media Type`，
+    /// 缺少 boundary 时返回 `400 Bad Request`。使用方式见 [`Multipart`]。
+    pub fn body_multipart(&mut self) -> crate::Result<Multipart> {
+        let boundary = multipart::boundary(self.content_type())?;
+        Ok(Multipart::new(&boundary, self.take_body()))
+    }
+
     /// 按Cookie的名称返回 `Cookie`
     #[cfg(feature = "cookies")]
     #[must_use]
@@ -518,6 +755,68 @@ impl<State> Request<State> {
     }
 }
 
+/// 将路由参数转换为 `T`，失败时返回 `400 Bad Request`
+fn parse_param<T>(key: &str, value: &str) -> crate::Result<T>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    value.parse().map_err(|e: T::Err| {
+        crate::Error::from_str(
+            StatusCode::BadRequest,
+            format!(
+                "Param \"{}\" is not a valid {}: {}",
+                key,
+                std::any::type_name::<T>(),
+                e
+            ),
+        )
+    })
+}
+
+/// 路由中唯一的参数，转换为 `T`
+///
+/// 路由必须只有一个命名参数，例如 `/users/:id`。
+///
+/// # Examples
+///
+/// ```
+/// use summer_boot::{PathParam, Request, Result};
+///
+/// async fn user(req: Request<()>) -> Result<String> {
+///     let PathParam(id) = PathParam::<u64>::try_from(&req)?;
+///     Ok(format!("user {}", id))
+/// }
+///
+/// let mut app = summer_boot::new();
+/// app.at("/users/:id").get(user);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PathParam<T>(pub T);
+
+impl<State, T> TryFrom<&Request<State>> for PathParam<T>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    type Error = crate::Error;
+
+    fn try_from(req: &Request<State>) -> crate::Result<Self> {
+        let params: Vec<(&str, &str)> = req
+            .route_params
+            .iter()
+            .flat_map(|captures| captures.iter())
+            .collect();
+        match params.as_slice() {
+            [(key, value)] => parse_param(key, value).map(PathParam),
+            _ => Err(format_err!(
+                "PathParam requires exactly one route param, found {}",
+                params.len()
+            )),
+        }
+    }
+}
+
 impl<State> AsRef<http_types::Request> for Request<State> {
     fn as_ref(&self) -> &http_types::Request {
         &self.req
@@ -630,3 +929,119 @@ impl<State> Index<&str> for Request<State> {
         &self.req[name]
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate as summer_boot;
+
+    #[derive(Debug, PartialEq)]
+    enum Color {
+        Red,
+        Green,
+    }
+
+    impl FromStr for Color {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s {
+                "red" => Ok(Color::Red),
+                "green" => Ok(Color::Green),
+                other => Err(format!("unknown color {}", other)),
+            }
+        }
+    }
+
+    async fn get(app: &summer_boot::Server<()>, path: &str) -> (StatusCode, String) {
+        let url = Url::parse("http://localhost").unwrap().join(path).unwrap();
+        let mut res: http_types::Response = app
+            .respond(http_types::Request::new(Method::Get, url))
+            .await
+            .unwrap();
+        (res.status(), res.body_string().await.unwrap())
+    }
+
+    #[async_std::test]
+    async fn typed_params() {
+        let mut app = summer_boot::new();
+        app.at("/int/:n").get(|req: Request<()>| async move {
+            let n: i32 = req.param_as("n")?;
+            Ok((n * 2).to_string())
+        });
+        app.at("/uuid/:id").get(|req: Request<()>| async move {
+            let PathParam(id) = PathParam::<uuid::Uuid>::try_from(&req)?;
+            Ok(id.to_string())
+        });
+        app.at("/color/:color").get(|req: Request<()>| async move {
+            let color: Color = req.param_as("color")?;
+            Ok(format!("{:?}", color))
+        });
+        app.at("/pair/:a/:b").get(|req: Request<()>| async move {
+            let PathParam(a) = PathParam::<i32>::try_from(&req)?;
+            Ok(a.to_string())
+        });
+
+        assert_eq!(get(&app, "/int/21").await, (StatusCode::Ok, "42".into()));
+        assert_eq!(get(&app, "/int/abc").await.0, StatusCode::BadRequest);
+
+        let id = "67e55044-10b1-426f-9247-bb680e5fe0c8";
+        assert_eq!(
+            get(&app, &format!("/uuid/{}", id)).await,
+            (StatusCode::Ok, id.into())
+        );
+        assert_eq!(get(&app, "/uuid/nope").await.0, StatusCode::BadRequest);
+
+        assert_eq!(
+            get(&app, "/color/green").await,
+            (StatusCode::Ok, "Green".into())
+        );
+        assert_eq!(get(&app, "/color/blue").await.0, StatusCode::BadRequest);
+
+        assert_eq!(
+            get(&app, "/pair/1/2").await.0,
+            StatusCode::InternalServerError
+        );
+    }
+
+    fn request(method: Method) -> Request<()> {
+        http_types::Request::new(method, "http://localhost/").into()
+    }
+
+    #[test]
+    fn peer_cert_fingerprint() {
+        use crate::http::PeerCertFingerprint;
+
+        assert_eq!(request(Method::Get).peer_cert_fingerprint(), None);
+
+        let mut req = http_types::Request::new(Method::Get, "https://localhost/");
+        req.ext_mut()
+            .insert(PeerCertFingerprint::from_der(b"client certificate"));
+        let req: Request<()> = req.into();
+        let fingerprint = req.peer_cert_fingerprint().unwrap();
+        assert_eq!(fingerprint.len(), 64);
+        assert!(fingerprint
+            .chars()
+            .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c)));
+        assert_eq!(
+            fingerprint,
+            PeerCertFingerprint::from_der(b"client certificate").as_str()
+        );
+    }
+
+    #[test]
+    fn method_properties() {
+        for method in [Method::Get, Method::Head, Method::Options, Method::Trace] {
+            assert!(request(method).is_safe(), "{}", method);
+            assert!(request(method).is_idempotent(), "{}", method);
+        }
+        for method in [Method::Put, Method::Delete] {
+            assert!(!request(method).is_safe(), "{}", method);
+            assert!(request(method).is_idempotent(), "{}", method);
+        }
+        for method in [Method::Post, Method::Patch, Method::Connect] {
+            assert!(!request(method).is_safe(), "{}", method);
+            assert!(!request(method).is_idempotent(), "{}", method);
+        }
+    }
+}
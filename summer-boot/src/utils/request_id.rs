@@ -0,0 +1,174 @@
+//! 请求ID中间件
+
+use crate::http_types::headers::HeaderName;
+use crate::{Middleware, Next, Request};
+
+use async_trait::async_trait;
+
+use std::fmt;
+
+/// 请求ID的请求头和响应头
+pub const X_REQUEST_ID: &str = "X-Request-Id";
+
+/// 请求ID，保存在请求和响应的扩展中
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(String);
+
+impl RequestId {
+    /// 生成新的请求ID
+    #[must_use]
+    pub fn generate() -> Self {
+        RequestId(uuid::Uuid::new_v4().to_string())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// 从请求扩展中读取 [`RequestIdMiddleware`] 分配的ID，不会读取请求头或生成新的ID
+    ///
+    /// 请求头名称由中间件配置，中间件执行之前无法确定，所以只读取扩展。
+    pub fn from_request<State>(req: &Request<State>) -> Option<Self> {
+        req.ext::<RequestId>().cloned()
+    }
+
+    /// 从请求头中读取，忽略空值
+    fn from_header<State>(req: &Request<State>, name: &HeaderName) -> Option<Self> {
+        req.header(name)
+            .map(|values| values.as_str().trim())
+            .filter(|id| !id.is_empty())
+            .map(|id| RequestId(id.to_string()))
+    }
+}
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// 为每个请求分配请求ID
+///
+/// 优先使用请求中的 `X-Request-Id`，没有时生成一个 UUID 并写入请求头。请求ID保存到请求扩展中，
+/// 并通过 `X-Request-Id` 响应头返回。`LoggingSystem` 会在日志中输出请求ID。
+/// 请求头名称可以通过 [`RequestIdMiddleware::header_name`] 修改。
+///
+/// # Examples
+///
+/// ```
+/// use summer_boot::utils::request_id::RequestIdMiddleware;
+/// use summer_boot::Request;
+///
+/// let mut app = summer_boot::new();
+/// app.with(RequestIdMiddleware::new().header_name("X-Correlation-Id"));
+/// app.at("/").get(|req: Request<()>| async move {
+///     Ok(req.id().unwrap_or_default().to_string())
+/// });
+/// ```
+#[derive(Debug, Clone)]
+pub struct RequestIdMiddleware {
+    header: HeaderName,
+}
+
+impl RequestIdMiddleware {
+    /// 创建一个新的实例，使用 `X-Request-Id` 请求头
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            header: X_REQUEST_ID.into(),
+        }
+    }
+
+    /// 设置读取和返回请求ID使用的请求头
+    #[must_use]
+    pub fn header_name(mut self, name: impl Into<HeaderName>) -> Self {
+        self.header = name.into();
+        self
+    }
+}
+
+impl Default for RequestIdMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl<State: Clone + Send + Sync + 'static> Middleware<State> for RequestIdMiddleware {
+    async fn handle(&self, mut req: Request<State>, next: Next<'_, State>) -> crate::Result {
+        let id = RequestId::from_request(&req)
+            .or_else(|| RequestId::from_header(&req, &self.header))
+            .unwrap_or_else(RequestId::generate);
+        // 生成的ID同时写入请求头，转发请求的处理函数可以直接使用
+        if req.header(&self.header).is_none() {
+            req.insert_header(&self.header, id.as_str());
+        }
+        req.set_ext(id.clone());
+
+        let mut res = next.run(req).await;
+        res.insert_header(&self.header, id.as_str());
+        res.insert_ext(id);
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate as summer_boot;
+    use http_types::{Method, Request, Response, Url};
+
+    fn app() -> summer_boot::Server<()> {
+        let mut app = summer_boot::new();
+        app.with(RequestIdMiddleware::new());
+        app.at("/")
+            .get(|req: summer_boot::Request<()>| async move { Ok(req.id().unwrap().to_string()) });
+        app.at("/header")
+            .get(|req: summer_boot::Request<()>| async move {
+                Ok(req.header(X_REQUEST_ID).unwrap().as_str().to_string())
+            });
+        app
+    }
+
+    #[async_std::test]
+    async fn echoes_incoming_id() {
+        let mut req = Request::new(Method::Get, Url::parse("http://localhost/").unwrap());
+        req.insert_header(X_REQUEST_ID, "abc-123");
+        let mut res: Response = app().respond(req).await.unwrap();
+        assert_eq!(res[X_REQUEST_ID], "abc-123");
+        assert_eq!(res.body_string().await.unwrap(), "abc-123");
+    }
+
+    #[async_std::test]
+    async fn generates_missing_id() {
+        let req = Request::new(Method::Get, Url::parse("http://localhost/").unwrap());
+        let mut res: Response = app().respond(req).await.unwrap();
+        let id = res[X_REQUEST_ID].as_str().to_string();
+        assert!(uuid::Uuid::parse_str(&id).is_ok());
+        assert_eq!(
+            res.ext().get::<RequestId>().map(RequestId::as_str),
+            Some(id.as_str())
+        );
+        assert_eq!(res.body_string().await.unwrap(), id);
+
+        let req = Request::new(Method::Get, Url::parse("http://localhost/header").unwrap());
+        let mut res: Response = app().respond(req).await.unwrap();
+        let id = res[X_REQUEST_ID].as_str().to_string();
+        assert_eq!(res.body_string().await.unwrap(), id);
+    }
+
+    #[async_std::test]
+    async fn custom_header_name() {
+        let mut app = summer_boot::new();
+        app.with(RequestIdMiddleware::new().header_name("X-Correlation-Id"));
+        app.at("/")
+            .get(|req: summer_boot::Request<()>| async move { Ok(req.id().unwrap().to_string()) });
+
+        let mut req = Request::new(Method::Get, Url::parse("http://localhost/").unwrap());
+        req.insert_header("X-Correlation-Id", "corr-42");
+        let mut res: Response = app.respond(req).await.unwrap();
+        assert_eq!(res["X-Correlation-Id"], "corr-42");
+        assert!(res.header(X_REQUEST_ID).is_none());
+        assert_eq!(res.body_string().await.unwrap(), "corr-42");
+    }
+}
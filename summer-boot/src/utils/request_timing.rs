@@ -0,0 +1,209 @@
+//! 请求体读取计时
+
+use async_std::io::{self, Read};
+
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// 请求体读取的计时信息，保存在请求扩展中
+///
+/// 请求头解析完成时开始计时。只有读取请求体时等待客户端的时间才算作停顿，
+/// 处理器没有读取请求体的时间不计入，因此可以区分慢上传的客户端和慢处理器。
+/// 需要通过 [`ServerOptions::request_timing`](crate::http::ServerOptions::request_timing) 开启。
+///
+/// # Examples
+///
+/// ```
+/// use summer_boot::utils::request_timing::RequestTiming;
+/// use summer_boot::Request;
+///
+/// let mut app = summer_boot::new();
+/// app.at("/upload").post(|mut req: Request<()>| async move {
+///     let body = req.body_bytes().await?;
+///     if let Some(timing) = req.ext::<RequestTiming>() {
+///         println!("{} bytes, longest stall {:?}", body.len(), timing.longest_stall());
+///     }
+///     Ok("ok")
+/// });
+/// ```
+#[derive(Debug, Clone)]
+pub struct RequestTiming {
+    inner: Arc<Mutex<Timing>>,
+}
+
+#[derive(Debug)]
+struct Timing {
+    head_complete: Instant,
+    first_poll: Option<Instant>,
+    first_byte: Option<Instant>,
+    finished: Option<Instant>,
+    pending_since: Option<Instant>,
+    longest_stall: Duration,
+    bytes_read: u64,
+}
+
+impl RequestTiming {
+    /// 请求头解析完成时创建
+    pub(crate) fn start() -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Timing {
+                head_complete: Instant::now(),
+                first_poll: None,
+                first_byte: None,
+                finished: None,
+                pending_since: None,
+                longest_stall: Duration::ZERO,
+                bytes_read: 0,
+            })),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Timing> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 从请求头解析完成到读取到第一个请求体字节的时间
+    pub fn time_to_first_byte(&self) -> Option<Duration> {
+        let timing = self.lock();
+        timing
+            .first_byte
+            .map(|first| first.duration_since(timing.head_complete))
+    }
+
+    /// 从第一次读取到请求体读取完毕的时间，请求体未读完时返回 `None`
+    pub fn body_read_duration(&self) -> Option<Duration> {
+        let timing = self.lock();
+        Some(timing.finished?.duration_since(timing.first_poll?))
+    }
+
+    /// 读取请求体时等待客户端的最长时间，正在等待的时间也计算在内
+    pub fn longest_stall(&self) -> Duration {
+        let timing = self.lock();
+        let current = timing
+            .pending_since
+            .map_or(Duration::ZERO, |since| since.elapsed());
+        timing.longest_stall.max(current)
+    }
+
+    /// 已读取的请求体字节数
+    pub fn bytes_read(&self) -> u64 {
+        self.lock().bytes_read
+    }
+
+    fn record(&self, poll: &Poll<io::Result<usize>>) {
+        let now = Instant::now();
+        let mut timing = self.lock();
+        timing.first_poll.get_or_insert(now);
+        match poll {
+            Poll::Pending => {
+                timing.pending_since.get_or_insert(now);
+            }
+            Poll::Ready(result) => {
+                if let Some(since) = timing.pending_since.take() {
+                    timing.longest_stall = timing.longest_stall.max(now - since);
+                }
+                match result {
+                    Ok(0) => {
+                        timing.finished.get_or_insert(now);
+                    }
+                    Ok(n) => {
+                        timing.first_byte.get_or_insert(now);
+                        timing.bytes_read += *n as u64;
+                    }
+                    Err(_) => {}
+                }
+            }
+        }
+    }
+}
+
+/// 记录读取时间的请求体，没有开启计时时直接读取
+pub(crate) struct TimedReader<R> {
+    reader: R,
+    timing: Option<RequestTiming>,
+}
+
+impl<R> TimedReader<R> {
+    pub(crate) fn new(reader: R, timing: Option<RequestTiming>) -> Self {
+        Self { reader, timing }
+    }
+}
+
+impl<R: Read + Unpin> Read for TimedReader<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let poll = Pin::new(&mut self.reader).poll_read(cx, buf);
+        if let Some(timing) = &self.timing {
+            timing.record(&poll);
+        }
+        poll
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use async_std::io::ReadExt;
+    use async_std::task;
+
+    use std::collections::VecDeque;
+    use std::future::Future;
+
+    /// 按给定延迟依次返回数据块
+    struct DelayedReader {
+        chunks: VecDeque<(Duration, &'static [u8])>,
+        sleep: Option<Pin<Box<dyn Future<Output = ()> + Send>>>,
+    }
+
+    impl Read for DelayedReader {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            let Some((delay, chunk)) = self.chunks.front().copied() else {
+                return Poll::Ready(Ok(0));
+            };
+            let sleep = self
+                .sleep
+                .get_or_insert_with(|| Box::pin(task::sleep(delay)));
+            if sleep.as_mut().poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+            self.sleep = None;
+            self.chunks.pop_front();
+            buf[..chunk.len()].copy_from_slice(chunk);
+            Poll::Ready(Ok(chunk.len()))
+        }
+    }
+
+    #[async_std::test]
+    async fn records_stall() {
+        let timing = RequestTiming::start();
+        let reader = DelayedReader {
+            chunks: VecDeque::from([
+                (Duration::from_millis(20), &b"hello "[..]),
+                (Duration::from_millis(200), &b"world"[..]),
+            ]),
+            sleep: None,
+        };
+        let mut reader = TimedReader::new(reader, Some(timing.clone()));
+        assert_eq!(timing.time_to_first_byte(), None);
+
+        let mut body = String::new();
+        reader.read_to_string(&mut body).await.unwrap();
+        assert_eq!(body, "hello world");
+        assert_eq!(timing.bytes_read(), 11);
+
+        let stall = timing.longest_stall();
+        assert!(stall >= Duration::from_millis(200), "{:?}", stall);
+        assert!(stall < Duration::from_secs(2), "{:?}", stall);
+        assert!(timing.time_to_first_byte().unwrap() >= Duration::from_millis(20));
+        assert!(timing.body_read_duration().unwrap() >= stall);
+    }
+}
@@ -0,0 +1,165 @@
+//! 必需请求头校验中间件
+
+use crate::http_types::headers::HeaderName;
+use crate::{Middleware, Next, Request, Response, StatusCode};
+
+use async_trait::async_trait;
+
+use std::fmt;
+use std::sync::Arc;
+
+type Predicate = Arc<dyn Fn(&str) -> bool + Send + Sync + 'static>;
+
+/// 要求请求必须携带指定的请求头
+///
+/// 缺少请求头或校验失败时直接返回 `400 Bad Request`，不再调用下游，
+/// 可以通过 [`RequireHeaderMiddleware::status`] 改为 `401 Unauthorized`。
+///
+/// # Examples
+///
+/// ```
+/// use summer_boot::utils::require_header::RequireHeaderMiddleware;
+///
+/// let mut app = summer_boot::new();
+/// app.at("/api")
+///     .with(
+///         RequireHeaderMiddleware::new("X-Api-Key")
+///             .validate(|key| key.len() == 32)
+///             .and("X-Tenant-Id"),
+///     )
+///     .get(|_| async { Ok("ok") });
+/// ```
+#[derive(Clone)]
+pub struct RequireHeaderMiddleware {
+    headers: Vec<(HeaderName, Option<Predicate>)>,
+    status: StatusCode,
+}
+
+impl RequireHeaderMiddleware {
+    /// 创建一个新的实例，要求请求携带 `name`
+    #[must_use]
+    pub fn new(name: impl Into<HeaderName>) -> Self {
+        Self {
+            headers: vec![(name.into(), None)],
+            status: StatusCode::BadRequest,
+        }
+    }
+
+    /// 追加一个必需的请求头
+    #[must_use]
+    pub fn and(mut self, name: impl Into<HeaderName>) -> Self {
+        self.headers.push((name.into(), None));
+        self
+    }
+
+    /// 校验最近添加的请求头的值
+    #[must_use]
+    pub fn validate<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&str) -> bool + Send + Sync + 'static,
+    {
+        if let Some((_, slot)) = self.headers.last_mut() {
+            *slot = Some(Arc::new(predicate));
+        }
+        self
+    }
+
+    /// 设置校验失败时返回的状态码
+    #[must_use]
+    pub fn status(mut self, status: StatusCode) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// 返回第一个不满足要求的请求头
+    fn rejected<State>(&self, req: &Request<State>) -> Option<&HeaderName> {
+        self.headers.iter().find_map(|(name, predicate)| {
+            let valid = match req.header(name) {
+                Some(values) => predicate
+                    .as_ref()
+                    .map_or(true, |predicate| predicate(values.last().as_str())),
+                None => false,
+            };
+            (!valid).then_some(name)
+        })
+    }
+}
+
+impl fmt::Debug for RequireHeaderMiddleware {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RequireHeaderMiddleware")
+            .field(
+                "headers",
+                &self
+                    .headers
+                    .iter()
+                    .map(|(name, _)| name)
+                    .collect::<Vec<_>>(),
+            )
+            .field("status", &self.status)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl<State: Clone + Send + Sync + 'static> Middleware<State> for RequireHeaderMiddleware {
+    async fn handle(&self, req: Request<State>, next: Next<'_, State>) -> crate::Result {
+        match self.rejected(&req) {
+            Some(name) => {
+                let mut res = Response::new(self.status);
+                res.set_body(format!("missing or invalid header: {}", name));
+                Ok(res)
+            }
+            None => Ok(next.run(req).await),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate as summer_boot;
+    use http_types::{Method, Request, Response, Url};
+
+    fn get(headers: &[(&str, &str)]) -> Request {
+        let url = Url::parse("http://localhost/api").unwrap();
+        let mut req = Request::new(Method::Get, url);
+        for (name, value) in headers {
+            req.insert_header(*name, *value);
+        }
+        req
+    }
+
+    #[async_std::test]
+    async fn rejects_missing_or_invalid_headers() {
+        let mut app = summer_boot::new();
+        app.at("/api")
+            .with(
+                RequireHeaderMiddleware::new("X-Api-Key")
+                    .validate(|key| key == "secret")
+                    .and("X-Tenant-Id")
+                    .status(StatusCode::Unauthorized),
+            )
+            .get(|_| async { Ok("ok") });
+
+        let mut res: Response = app
+            .respond(get(&[("X-Api-Key", "secret"), ("X-Tenant-Id", "acme")]))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::Ok);
+        assert_eq!(res.body_string().await.unwrap(), "ok");
+
+        let mut res: Response = app.respond(get(&[("X-Api-Key", "secret")])).await.unwrap();
+        assert_eq!(res.status(), StatusCode::Unauthorized);
+        assert_eq!(
+            res.body_string().await.unwrap(),
+            "missing or invalid header: x-tenant-id"
+        );
+
+        let res: Response = app
+            .respond(get(&[("X-Api-Key", "wrong"), ("X-Tenant-Id", "acme")]))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::Unauthorized);
+    }
+}
@@ -1,18 +1,35 @@
 use std::convert::TryInto;
 use std::fmt::{Debug, Display};
+use std::future::Future;
 use std::ops::Index;
 
 use serde::Serialize;
 
+use crate::http1::after_send::{AfterSend, SendContext};
+use crate::http1::encode::{Framing, ReasonPhrase};
 use crate::http_types::headers::{self, HeaderName, HeaderValues, ToHeaderValues};
 use crate::http_types::{self, Body, Error, Mime, StatusCode};
+use crate::utils::content_type::ContentType;
 use crate::ResponseBuilder;
 
+#[cfg(feature = "cookies")]
+use crate::http_types::cookies::Cookie;
+
+/// 响应中对 Cookie 的修改，由 `CookiesMiddleware` 转换为 `Set-Cookie` 响应头
+#[cfg(feature = "cookies")]
+#[derive(Debug)]
+pub(crate) enum CookieEvent {
+    Added(Cookie<'static>),
+    Removed(Cookie<'static>),
+}
+
 /// HTTP response
 #[derive(Debug)]
 pub struct Response {
     pub(crate) res: http_types::Response,
     pub(crate) error: Option<Error>,
+    #[cfg(feature = "cookies")]
+    pub(crate) cookie_events: Vec<CookieEvent>,
 }
 
 impl Response {
@@ -24,7 +41,34 @@ impl Response {
         S::Error: Debug,
     {
         let res = http_types::Response::new(status);
-        Self { res, error: None }
+        Self {
+            res,
+            error: None,
+            #[cfg(feature = "cookies")]
+            cookie_events: vec![],
+        }
+    }
+
+    /// 创建以 `json` 为body的响应，`Content-Type` 为 `application/json;charset=utf-8`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use serde_json::json;
+    /// use summer_boot::Response;
+    ///
+    /// let res = Response::json(201, &json!({ "id": 1 })).unwrap();
+    /// assert_eq!(res.status(), 201);
+    /// assert_eq!(res.content_type().unwrap().to_string(), "application/json;charset=utf-8");
+    /// ```
+    pub fn json<S>(status: S, json: &impl Serialize) -> crate::Result<Self>
+    where
+        S: TryInto<StatusCode>,
+        S::Error: Debug,
+    {
+        let mut res = Response::new(status);
+        res.body_json(json)?;
+        Ok(res)
     }
 
     #[must_use]
@@ -41,6 +85,64 @@ impl Response {
         self.res.status()
     }
 
+    /// 自定义的状态原因短语，未设置时返回 `None`，编码时使用标准的原因短语
+    #[must_use]
+    pub fn reason_phrase(&self) -> Option<&str> {
+        self.res.ext().get::<ReasonPhrase>().map(|r| r.0.as_str())
+    }
+
+    /// 设置状态行中的原因短语，可以为空字符串，`None` 恢复为标准的原因短语
+    ///
+    /// 回车和换行符会被移除。
+    ///
+    /// ```
+    /// # use summer_boot::{Response, StatusCode};
+    /// let mut res = Response::new(StatusCode::Ok);
+    /// res.set_reason_phrase(Some("All Good".to_string()));
+    /// assert_eq!(res.reason_phrase(), Some("All Good"));
+    /// ```
+    pub fn set_reason_phrase(&mut self, reason: Option<String>) {
+        match reason {
+            Some(reason) => {
+                let reason = reason.replace(['\r', '\n'], "");
+                self.res.ext_mut().insert(ReasonPhrase(reason));
+            }
+            None => {
+                self.res.ext_mut().remove::<ReasonPhrase>();
+            }
+        }
+    }
+
+    /// 注册在响应写完之后执行的任务，例如审计日志或 webhook
+    ///
+    /// 任务在最后一个字节写入连接后提交到执行器，不会延迟客户端，也不会等待连接关闭。
+    /// 写入失败或连接被关闭时任务同样会运行，通过 [`SendContext::is_sent`] 区分，
+    /// [`SendContext`] 中还有连接的对端和本地地址。
+    /// 任务 panic 会被捕获并记录。可以多次调用注册多个任务。
+    ///
+    /// ```
+    /// let mut res = summer_boot::Response::new(200);
+    /// res.after_send(|cx| async move {
+    ///     if cx.is_sent() {
+    ///         // 发送 webhook
+    ///     }
+    /// });
+    /// ```
+    pub fn after_send<F, Fut>(&mut self, hook: F)
+    where
+        F: FnOnce(SendContext) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        if self.res.ext().get::<AfterSend>().is_none() {
+            self.res.ext_mut().insert(AfterSend::default());
+        }
+        self.res
+            .ext()
+            .get::<AfterSend>()
+            .expect("after-send hooks are inserted above")
+            .push(hook);
+    }
+
     pub fn set_status<S>(&mut self, status: S)
     where
         S: TryInto<StatusCode>,
@@ -61,6 +163,41 @@ impl Response {
         Some(self.res.len()? == 0)
     }
 
+    /// 强制使用 `Transfer-Encoding: chunked` 发送正文，忽略 `Content-Length` 响应头
+    ///
+    /// 用于互操作测试或要求分块响应的代理，默认根据正文长度是否已知自动选择。
+    /// HEAD 请求以及 1xx、`204`、`304` 的响应没有正文，不受影响。
+    ///
+    /// ```
+    /// let mut res = summer_boot::Response::new(200);
+    /// res.set_body("hello");
+    /// res.force_chunked();
+    /// ```
+    pub fn force_chunked(&mut self) {
+        self.res.ext_mut().insert(Framing::Chunked);
+    }
+
+    /// 强制使用 `Content-Length` 发送正文，忽略 `Transfer-Encoding` 响应头
+    ///
+    /// 正文长度未知时返回错误。强制之后又把正文替换为长度未知的流时，编码响应会失败并关闭连接。
+    /// 与 [`Response::force_chunked`] 相同，对没有正文的响应不起作用。
+    ///
+    /// ```
+    /// let mut res = summer_boot::Response::new(200);
+    /// res.set_body("hello");
+    /// res.force_content_length().unwrap();
+    /// ```
+    pub fn force_content_length(&mut self) -> crate::Result<()> {
+        if self.res.len().is_none() {
+            return Err(Error::from_str(
+                StatusCode::InternalServerError,
+                "cannot force Content-Length framing on a body of unknown length",
+            ));
+        }
+        self.res.ext_mut().insert(Framing::ContentLength);
+        Ok(())
+    }
+
     #[must_use]
     pub fn header(&self, name: impl Into<HeaderName>) -> Option<&HeaderValues> {
         self.res.header(name)
@@ -125,8 +262,31 @@ impl Response {
         self.res.swap_body(body)
     }
 
+    /// 将 `json` 序列化为响应body，并设置 `Content-Type: application/json;charset=utf-8`
+    ///
+    /// 已通过 [`set_content_type`](Self::set_content_type) 等显式设置的类型保持不变，
+    /// 由之前的body推断出的类型会被替换。
     pub fn body_json(&mut self, json: &impl Serialize) -> crate::Result<()> {
-        self.res.set_body(Body::from_json(json)?);
+        let body = json_body(json)?;
+        let previous = self.res.take_body();
+        let inferred = self
+            .content_type()
+            .map_or(true, |mime| mime.to_string() == previous.mime().to_string());
+        if inferred {
+            self.res.set_content_type(body.mime().clone());
+        }
+        self.res.set_body(body);
+        Ok(())
+    }
+
+    /// 将 `value` 序列化为 XML 作为响应body，并设置 `Content-Type: application/xml`
+    ///
+    /// 根元素的名称为类型名，例如结构体 `Order` 对应 `<Order>`。
+    pub fn body_xml(&mut self, value: &impl Serialize) -> crate::Result<()> {
+        let xml = quick_xml::se::to_string(value)
+            .map_err(|e| Error::new(StatusCode::InternalServerError, e))?;
+        self.res.set_body(Body::from_string(xml));
+        self.res.set_content_type(http_types::mime::XML);
         Ok(())
     }
 
@@ -143,11 +303,13 @@ impl Response {
         Ok(())
     }
 
+    /// 添加 Cookie，需要开启 `cookies` feature
     #[cfg(feature = "cookies")]
     pub fn insert_cookie(&mut self, cookie: Cookie<'static>) {
         self.cookie_events.push(CookieEvent::Added(cookie));
     }
 
+    /// 删除 Cookie，客户端会收到 `Max-Age=0` 的 `Set-Cookie`
     #[cfg(feature = "cookies")]
     pub fn remove_cookie(&mut self, cookie: Cookie<'static>) {
         self.cookie_events.push(CookieEvent::Removed(cookie));
@@ -181,6 +343,24 @@ impl Response {
         self.res.ext_mut().insert(val);
     }
 
+    /// 对响应进行修改后返回自身，方便在返回值位置链式调用
+    ///
+    /// ```
+    /// # use summer_boot::{Response, StatusCode};
+    /// let cached = true;
+    /// let res = Response::new(StatusCode::Ok).tap_mut(|res| {
+    ///     if cached {
+    ///         res.insert_header("Cache-Control", "max-age=60");
+    ///     }
+    /// });
+    /// assert_eq!(res["Cache-Control"], "max-age=60");
+    /// ```
+    #[must_use]
+    pub fn tap_mut(mut self, f: impl FnOnce(&mut Self)) -> Self {
+        f(&mut self);
+        self
+    }
+
     pub fn from_res<T>(value: T) -> Self
     where
         T: Into<http_types::Response>,
@@ -219,6 +399,13 @@ impl AsMut<http_types::Headers> for Response {
     }
 }
 
+/// 序列化为 JSON 的body，类型为 `application/json;charset=utf-8`
+pub(crate) fn json_body(json: &impl Serialize) -> crate::Result<Body> {
+    let mut body = Body::from_json(json)?;
+    body.set_mime(ContentType::json_utf8());
+    Ok(body)
+}
+
 impl From<Response> for http_types::Response {
     fn from(response: Response) -> http_types::Response {
         response.res
@@ -235,7 +422,7 @@ impl From<http_types::Body> for Response {
 
 impl From<serde_json::Value> for Response {
     fn from(json_value: serde_json::Value) -> Self {
-        Body::from_json(&json_value)
+        json_body(&json_value)
             .map(|body| body.into())
             .unwrap_or_else(|_| Response::new(StatusCode::InternalServerError))
     }
@@ -329,3 +516,93 @@ impl Index<&str> for Response {
         &self.res[name]
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn content_type(res: &Response) -> String {
+        res.content_type().unwrap().to_string()
+    }
+
+    #[test]
+    fn body_json_sets_content_type() {
+        let mut res = Response::new(StatusCode::Ok);
+        res.body_json(&json!({ "id": 1 })).unwrap();
+        assert_eq!(content_type(&res), "application/json;charset=utf-8");
+
+        // 由之前的body推断出的类型会被替换
+        let mut res = Response::from("hello");
+        res.body_json(&json!([1, 2])).unwrap();
+        assert_eq!(content_type(&res), "application/json;charset=utf-8");
+
+        // 显式设置的类型保持不变
+        let mut res = Response::new(StatusCode::Ok);
+        res.set_content_type("application/problem+json");
+        res.body_json(&json!({ "title": "oops" })).unwrap();
+        assert_eq!(content_type(&res), "application/problem+json");
+    }
+
+    #[test]
+    fn builder_body_json_sets_content_type() {
+        let res = Response::builder(StatusCode::Created)
+            .body_json(&json!({ "id": 1 }))
+            .unwrap()
+            .build();
+        assert_eq!(content_type(&res), "application/json;charset=utf-8");
+
+        let res = Response::builder(StatusCode::Ok)
+            .content_type("application/vnd.api+json")
+            .body_json(&json!({ "id": 1 }))
+            .unwrap()
+            .build();
+        assert_eq!(content_type(&res), "application/vnd.api+json");
+    }
+
+    #[async_std::test]
+    async fn streams_reader_as_chunked_body() {
+        use crate::http1::encode::Encoder;
+        use async_std::io::ReadExt;
+
+        let rows = async_std::io::Cursor::new(b"id,name\n1,summer\n".to_vec());
+        let res = Response::builder(StatusCode::Ok)
+            .streaming(rows, "text/csv")
+            .build();
+        let mut encoded = String::new();
+        Encoder::new(res.into(), http_types::Method::Get)
+            .read_to_string(&mut encoded)
+            .await
+            .unwrap();
+        let (head, body) = encoded.split_once("\r\n\r\n").unwrap();
+        assert!(head.contains("transfer-encoding: chunked"), "{}", head);
+        assert!(head.contains("content-type: text/csv"), "{}", head);
+        assert!(!head.contains("content-length"), "{}", head);
+        // 分块的大小取决于编码器的缓冲区，逐块还原内容
+        let mut decoded = String::new();
+        let mut rest = body;
+        loop {
+            let (size, tail) = rest.split_once("\r\n").unwrap();
+            let size = usize::from_str_radix(size, 16).unwrap();
+            if size == 0 {
+                assert_eq!(tail, "\r\n");
+                break;
+            }
+            decoded.push_str(&tail[..size]);
+            rest = tail[size..].strip_prefix("\r\n").unwrap();
+        }
+        assert_eq!(decoded, "id,name\n1,summer\n");
+    }
+
+    #[async_std::test]
+    async fn json_value_into_response() {
+        let res: Response = json!({ "id": 1 }).into();
+        assert_eq!(content_type(&res), "application/json;charset=utf-8");
+
+        let mut res = Response::json(StatusCode::Created, &json!({ "id": 1 })).unwrap();
+        assert_eq!(res.status(), StatusCode::Created);
+        assert_eq!(content_type(&res), "application/json;charset=utf-8");
+        let body: serde_json::Value = res.take_body().into_json().await.unwrap();
+        assert_eq!(body, json!({ "id": 1 }));
+    }
+}
@@ -1,7 +1,9 @@
+use async_std::io::{BufReader, Read};
 use serde::Serialize;
 
-use crate::http_types::headers::{HeaderName, ToHeaderValues};
+use crate::http_types::headers::{HeaderName, ToHeaderValues, LOCATION};
 use crate::http_types::{Body, Mime, StatusCode};
+use crate::utils::redirect::location_value;
 use crate::Response;
 use std::convert::TryInto;
 
@@ -27,6 +29,16 @@ impl ResponseBuilder {
         self
     }
 
+    /// 设置 `Location` 响应头，状态码使用创建构建器时传入的 3xx 状态码
+    ///
+    /// 传入的状态码不是 3xx 时改为 `302 Found`。非 ASCII 字符和空格等按 UTF-8 百分号编码。
+    pub fn redirect(mut self, url: impl AsRef<str>) -> Self {
+        if !self.0.status().is_redirection() {
+            self.0.set_status(StatusCode::Found);
+        }
+        self.header(LOCATION, location_value(url.as_ref()))
+    }
+
     pub fn content_type(mut self, content_type: impl Into<Mime>) -> Self {
         self.0.set_content_type(content_type);
         self
@@ -37,8 +49,10 @@ impl ResponseBuilder {
         self
     }
 
-    pub fn body_json(self, json: &impl Serialize) -> crate::Result<Self> {
-        Ok(self.body(Body::from_json(json)?))
+    /// 同 [`Response::body_json`]，显式设置的 `Content-Type` 保持不变
+    pub fn body_json(mut self, json: &impl Serialize) -> crate::Result<Self> {
+        self.0.body_json(json)?;
+        Ok(self)
     }
 
     pub fn body_string(self, string: String) -> Self {
@@ -52,6 +66,33 @@ impl ResponseBuilder {
     pub async fn body_file(self, path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
         Ok(self.body(Body::from_file(path).await?))
     }
+
+    /// 以 `reader` 的内容作为响应body，使用 `Transfer-Encoding: chunked` 发送
+    ///
+    /// 内容边读边写，不会整体读入内存，适合数据库查询结果、生成的内容或转发上游响应。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use summer_boot::http_types::mime;
+    /// use summer_boot::{Response, StatusCode};
+    ///
+    /// let rows = async_std::io::Cursor::new(b"id,name\n1,summer\n".to_vec());
+    /// let res = Response::builder(StatusCode::Ok)
+    ///     .streaming(rows, "text/csv")
+    ///     .build();
+    /// assert_eq!(res.len(), None);
+    /// ```
+    pub fn streaming<R>(mut self, reader: R, content_type: impl Into<Mime>) -> Self
+    where
+        R: Read + Send + Sync + 'static,
+    {
+        let reader = BufReader::new(Box::pin(reader));
+        self.0.set_content_type(content_type);
+        self.0.set_body(Body::from_reader(reader, None));
+        self.0.force_chunked();
+        self
+    }
 }
 
 impl From<ResponseBuilder> for Response {
@@ -0,0 +1,254 @@
+//! 统一计算 `Retry-After` 响应头
+//!
+//! 限流、过载保护和停机时返回的 429/503 响应都通过 [`RetryAfter`] 计算重试时间，
+//! 计算结果同时写入响应扩展，日志中间件会记录告诉客户端的退避时间，
+//! 并通过 [`RequestObservation::retry_after`](crate::log::RequestObservation::retry_after)
+//! 交给指标收集。
+//! [`TimeoutMiddleware`](crate::utils::timeout::TimeoutMiddleware) 以 `503` 或 `429`
+//! 超时时使用 [`RetryAfter::load_shed`]。
+
+use crate::http1::date::fmt_http_date;
+use crate::http_types::headers::RETRY_AFTER;
+use crate::Response;
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+/// 返回 `Retry-After` 的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryAfterCause {
+    /// 令牌桶限流，按令牌补充时间计算
+    RateLimit,
+    /// 过载保护，按最近的请求完成速率估算排空时间
+    LoadShed,
+    /// 停机中，按剩余的宽限期计算
+    Shutdown,
+}
+
+/// `Retry-After` 的格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RetryAfterFormat {
+    /// 秒数，例如 `Retry-After: 120`
+    #[default]
+    Seconds,
+    /// HTTP-date，例如 `Retry-After: Wed, 21 Oct 2015 07:28:00 GMT`
+    HttpDate,
+}
+
+/// 计算得到的重试时间
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryAfter {
+    cause: RetryAfterCause,
+    delay: Duration,
+    format: RetryAfterFormat,
+}
+
+/// 重试时间的上下限，避免客户端立即重试或等待过久
+const MIN_DELAY: Duration = Duration::from_secs(1);
+const MAX_DELAY: Duration = Duration::from_secs(300);
+
+impl RetryAfter {
+    fn new(cause: RetryAfterCause, delay: Duration) -> Self {
+        Self {
+            cause,
+            delay: delay.clamp(MIN_DELAY, MAX_DELAY),
+            format: RetryAfterFormat::default(),
+        }
+    }
+
+    /// 令牌桶限流：还需要 `needed - available` 个令牌，每秒补充 `refill_per_sec` 个
+    pub fn rate_limit(needed: f64, available: f64, refill_per_sec: f64) -> Self {
+        let missing = (needed - available).max(0.0);
+        let delay = if refill_per_sec > 0.0 {
+            Duration::from_secs_f64(missing / refill_per_sec)
+        } else {
+            MAX_DELAY
+        };
+        Self::new(RetryAfterCause::RateLimit, delay)
+    }
+
+    /// 过载保护：按 `estimator` 的完成速率估算 `queued` 个请求的排空时间
+    pub fn load_shed(estimator: &DrainEstimator, queued: usize) -> Self {
+        Self::new(RetryAfterCause::LoadShed, estimator.drain_time(queued))
+    }
+
+    /// 停机：剩余的宽限期
+    pub fn shutdown(remaining: Duration) -> Self {
+        Self::new(RetryAfterCause::Shutdown, remaining)
+    }
+
+    /// 设置响应头的格式
+    #[must_use]
+    pub fn format(mut self, format: RetryAfterFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn cause(&self) -> RetryAfterCause {
+        self.cause
+    }
+
+    /// 重试时间，已按上下限截断
+    pub fn delay(&self) -> Duration {
+        self.delay
+    }
+
+    /// 响应头的值，秒数向上取整
+    pub fn header_value(&self, now: SystemTime) -> String {
+        let secs = self.delay.as_secs() + u64::from(self.delay.subsec_nanos() > 0);
+        match self.format {
+            RetryAfterFormat::Seconds => secs.to_string(),
+            RetryAfterFormat::HttpDate => fmt_http_date(now + Duration::from_secs(secs)),
+        }
+    }
+
+    /// 写入 `Retry-After` 响应头，并把自身保存到响应扩展中
+    pub fn apply(&self, res: &mut Response) {
+        res.insert_header(RETRY_AFTER, self.header_value(SystemTime::now()));
+        res.insert_ext(*self);
+    }
+}
+
+/// 按请求完成速率的指数加权移动平均（EWMA）估算排空时间
+#[derive(Debug)]
+pub struct DrainEstimator {
+    alpha: f64,
+    state: Mutex<DrainState>,
+}
+
+#[derive(Debug, Default)]
+struct DrainState {
+    last: Option<Instant>,
+    /// 每秒完成的请求数
+    rate: Option<f64>,
+}
+
+impl Default for DrainEstimator {
+    fn default() -> Self {
+        Self::new(0.2)
+    }
+}
+
+impl DrainEstimator {
+    /// `alpha` 为新样本的权重，取值范围 `(0, 1]`
+    pub fn new(alpha: f64) -> Self {
+        assert!(alpha > 0.0 && alpha <= 1.0, "alpha 必须在 (0, 1] 之间");
+        Self {
+            alpha,
+            state: Mutex::new(DrainState::default()),
+        }
+    }
+
+    /// 记录一个请求完成
+    pub fn record_completion(&self) {
+        self.record_completion_at(Instant::now());
+    }
+
+    /// 记录一个在 `now` 完成的请求
+    pub fn record_completion_at(&self, now: Instant) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(last) = state.last {
+            let elapsed = now.saturating_duration_since(last).as_secs_f64();
+            // 同一时刻完成的请求合并到下一个间隔中
+            if elapsed <= 0.0 {
+                return;
+            }
+            let sample = 1.0 / elapsed;
+            state.rate = Some(match state.rate {
+                Some(rate) => self.alpha * sample + (1.0 - self.alpha) * rate,
+                None => sample,
+            });
+        }
+        state.last = Some(now);
+    }
+
+    /// 当前估算的完成速率（每秒请求数），样本不足时返回 `None`
+    pub fn rate(&self) -> Option<f64> {
+        self.state.lock().unwrap().rate
+    }
+
+    /// 排空 `queued` 个请求所需的时间，没有速率数据时返回上限
+    pub fn drain_time(&self, queued: usize) -> Duration {
+        match self.rate() {
+            Some(rate) if rate > 0.0 => Duration::from_secs_f64(queued as f64 / rate),
+            _ => MAX_DELAY,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http1::date::parse_http_date;
+
+    #[test]
+    fn rate_limit_uses_refill_time() {
+        let retry = RetryAfter::rate_limit(1.0, 0.0, 0.5);
+        assert_eq!(retry.cause(), RetryAfterCause::RateLimit);
+        assert_eq!(retry.delay(), Duration::from_secs(2));
+
+        let retry = RetryAfter::rate_limit(5.0, 2.5, 2.0);
+        assert_eq!(retry.delay(), Duration::from_millis(1250));
+        assert_eq!(retry.header_value(SystemTime::now()), "2");
+
+        assert_eq!(RetryAfter::rate_limit(1.0, 0.0, 0.0).delay(), MAX_DELAY);
+        assert_eq!(RetryAfter::rate_limit(1.0, 1.0, 10.0).delay(), MIN_DELAY);
+    }
+
+    #[test]
+    fn load_shed_tracks_completion_rate() {
+        let estimator = DrainEstimator::new(0.5);
+        assert_eq!(estimator.drain_time(10), MAX_DELAY);
+
+        // 稳定负载：每 100ms 完成一个请求
+        let start = Instant::now();
+        for i in 0..20 {
+            estimator.record_completion_at(start + Duration::from_millis(100 * i));
+        }
+        let rate = estimator.rate().unwrap();
+        assert!((rate - 10.0).abs() < 1e-6, "rate = {}", rate);
+        let retry = RetryAfter::load_shed(&estimator, 50);
+        assert_eq!(retry.cause(), RetryAfterCause::LoadShed);
+        assert_eq!(retry.header_value(SystemTime::now()), "5");
+
+        // 负载变慢后速率逐步下降，排空时间变长
+        let slow = start + Duration::from_millis(1900);
+        for i in 1..=10 {
+            estimator.record_completion_at(slow + Duration::from_millis(500 * i));
+        }
+        let rate = estimator.rate().unwrap();
+        assert!((rate - 2.0).abs() < 0.01, "rate = {}", rate);
+        assert_eq!(
+            RetryAfter::load_shed(&estimator, 50).header_value(SystemTime::now()),
+            "25"
+        );
+    }
+
+    #[test]
+    fn shutdown_uses_remaining_grace_period() {
+        let retry = RetryAfter::shutdown(Duration::from_secs(30));
+        assert_eq!(retry.cause(), RetryAfterCause::Shutdown);
+        assert_eq!(retry.delay(), Duration::from_secs(30));
+        assert_eq!(RetryAfter::shutdown(Duration::ZERO).delay(), MIN_DELAY);
+    }
+
+    #[test]
+    fn header_formats() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_445_412_480);
+        let retry = RetryAfter::shutdown(Duration::from_secs(120));
+        assert_eq!(retry.header_value(now), "120");
+
+        let retry = retry.format(RetryAfterFormat::HttpDate);
+        let value = retry.header_value(now);
+        assert_eq!(value, "Wed, 21 Oct 2015 07:30:00 GMT");
+        assert_eq!(
+            parse_http_date(&value).unwrap(),
+            now + Duration::from_secs(120)
+        );
+
+        let mut res = Response::new(503);
+        retry.apply(&mut res);
+        assert!(res.header(RETRY_AFTER).is_some());
+        assert_eq!(res.ext::<RetryAfter>(), Some(&retry));
+    }
+}
@@ -0,0 +1,350 @@
+//! 请求超时中间件
+
+use crate::utils::retry_after::{DrainEstimator, RetryAfter};
+use crate::{Middleware, Next, Request, Response, StatusCode};
+
+use async_std::future;
+use async_trait::async_trait;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// 限制下游处理的时间
+///
+/// 超时后下游的 future 会被直接 drop，处理函数中尚未完成的操作随之取消，
+/// 并返回 `504 Gateway Timeout`，状态码和响应体可以通过 [`TimeoutMiddleware::status`]
+/// 和 [`TimeoutMiddleware::body`] 修改。状态码为 `503` 或 `429` 时附带 `Retry-After`，
+/// 按最近的请求完成速率估算正在处理的请求排空所需的时间（见 [`RetryAfter::load_shed`]）。
+///
+/// 应用级别的超时可以在路由上用另一个 `TimeoutMiddleware` 覆盖，
+/// 路由上的时间从路由中间件开始执行时重新计算；[`TimeoutMiddleware::exempt`]
+/// 则取消超时，适合 SSE 等长连接。之前的中间件也可以在请求中插入 [`NoTimeout`] 跳过超时。
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use summer_boot::utils::timeout::TimeoutMiddleware;
+///
+/// let mut app = summer_boot::new();
+/// app.with(TimeoutMiddleware::new(Duration::from_secs(5)));
+/// app.at("/report")
+///     .with(TimeoutMiddleware::new(Duration::from_secs(60)))
+///     .get(|_| async { Ok("done") });
+/// app.at("/events")
+///     .with(TimeoutMiddleware::exempt())
+///     .get(|_| async { Ok("streaming") });
+/// ```
+#[derive(Debug, Clone)]
+pub struct TimeoutMiddleware {
+    /// `None` 表示不限制时间
+    duration: Option<Duration>,
+    status: StatusCode,
+    body: Option<String>,
+    load: Arc<Load>,
+}
+
+/// 最外层超时中间件统计的负载，用于估算 `Retry-After`
+#[derive(Debug, Default)]
+struct Load {
+    in_flight: AtomicUsize,
+    estimator: DrainEstimator,
+}
+
+/// 请求结束时（包括超时被 drop）减少正在处理的请求数
+struct InFlight<'a>(&'a Load);
+
+impl<'a> InFlight<'a> {
+    fn new(load: &'a Load) -> Self {
+        load.in_flight.fetch_add(1, Ordering::SeqCst);
+        Self(load)
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// 请求中存在此扩展时 [`TimeoutMiddleware`] 不限制处理时间
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoTimeout;
+
+/// 最外层超时中间件的截止时间，内层中间件通过它覆盖或取消超时
+#[derive(Debug, Clone, Default)]
+struct Deadline(Arc<Mutex<Option<(Instant, TimeoutMiddleware)>>>);
+
+impl Deadline {
+    fn get(&self) -> Option<(Instant, TimeoutMiddleware)> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn set(&self, timeout: &TimeoutMiddleware) {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner()) = timeout
+            .duration
+            .map(|duration| (Instant::now() + duration, timeout.clone()));
+    }
+}
+
+impl TimeoutMiddleware {
+    /// 创建一个新的实例，超时返回 `504`
+    #[must_use]
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            duration: Some(duration),
+            status: StatusCode::GatewayTimeout,
+            body: None,
+            load: Arc::default(),
+        }
+    }
+
+    /// 不限制时间，用于在路由上取消应用级别的超时
+    #[must_use]
+    pub fn exempt() -> Self {
+        Self {
+            duration: None,
+            ..Self::new(Duration::ZERO)
+        }
+    }
+
+    /// 设置超时返回的状态码
+    #[must_use]
+    pub fn status(mut self, status: StatusCode) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// 设置超时返回的响应体，默认为空
+    #[must_use]
+    pub fn body(mut self, body: impl Into<String>) -> Self {
+        self.body = Some(body.into());
+        self
+    }
+
+    fn timed_out(&self, load: &Load) -> Response {
+        let mut res = Response::new(self.status);
+        if let Some(body) = &self.body {
+            res.set_body(body.as_str());
+        }
+        if matches!(
+            self.status,
+            StatusCode::ServiceUnavailable | StatusCode::TooManyRequests
+        ) {
+            let queued = load.in_flight.load(Ordering::SeqCst);
+            RetryAfter::load_shed(&load.estimator, queued).apply(&mut res);
+        }
+        res
+    }
+}
+
+#[async_trait]
+impl<State: Clone + Send + Sync + 'static> Middleware<State> for TimeoutMiddleware {
+    async fn handle(&self, mut req: Request<State>, next: Next<'_, State>) -> crate::Result {
+        if req.ext::<NoTimeout>().is_some() {
+            return Ok(next.run(req).await);
+        }
+        if let Some(deadline) = req.ext::<Deadline>() {
+            // 外层已经在计时，只替换截止时间
+            deadline.set(self);
+            if self.duration.is_none() {
+                req.set_ext(NoTimeout);
+            }
+            return Ok(next.run(req).await);
+        }
+        if self.duration.is_none() {
+            req.set_ext(NoTimeout);
+            return Ok(next.run(req).await);
+        }
+
+        let deadline = Deadline::default();
+        deadline.set(self);
+        req.set_ext(deadline.clone());
+        let _in_flight = InFlight::new(&self.load);
+        let mut res = Box::pin(next.run(req));
+        let res = loop {
+            let Some((at, _)) = deadline.get() else {
+                break res.await;
+            };
+            let remaining = at.saturating_duration_since(Instant::now());
+            if let Ok(res) = future::timeout(remaining, &mut res).await {
+                break res;
+            }
+            // 等待期间内层中间件可能延长或取消了超时
+            match deadline.get() {
+                Some((at, _)) if at > Instant::now() => continue,
+                None => continue,
+                Some((_, timeout)) => return Ok(timeout.timed_out(&self.load)),
+            }
+        };
+        self.load.estimator.record_completion();
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate as summer_boot;
+    use http_types::{Method, Request, Response, Url};
+
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    struct DropGuard(Arc<AtomicBool>);
+
+    impl Drop for DropGuard {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    fn get(path: &str) -> Request {
+        let url = Url::parse("http://localhost").unwrap().join(path).unwrap();
+        Request::new(Method::Get, url)
+    }
+
+    #[async_std::test]
+    async fn times_out_slow_handlers() {
+        let dropped = Arc::new(AtomicBool::new(false));
+        let guard = dropped.clone();
+
+        let mut app = summer_boot::new();
+        app.at("/fast")
+            .with(TimeoutMiddleware::new(Duration::from_secs(5)))
+            .get(|_| async { Ok("fast") });
+        app.at("/slow")
+            .with(
+                TimeoutMiddleware::new(Duration::from_millis(20))
+                    .status(StatusCode::GatewayTimeout),
+            )
+            .get(move |_| {
+                let guard = DropGuard(guard.clone());
+                async move {
+                    let _guard = guard;
+                    async_std::task::sleep(Duration::from_secs(10)).await;
+                    Ok("slow")
+                }
+            });
+
+        let mut res: Response = app.respond(get("/fast")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::Ok);
+        assert_eq!(res.body_string().await.unwrap(), "fast");
+
+        let res: Response = app.respond(get("/slow")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::GatewayTimeout);
+        assert!(dropped.load(Ordering::SeqCst));
+    }
+
+    #[async_std::test]
+    async fn cancels_handlers_past_the_deadline() {
+        let ticks = Arc::new(AtomicUsize::new(0));
+        let counter = ticks.clone();
+
+        let mut app = summer_boot::new();
+        app.with(TimeoutMiddleware::new(Duration::from_millis(100)).body("too slow"));
+        app.at("/inside").get(|_| async {
+            async_std::task::sleep(Duration::from_millis(10)).await;
+            Ok("inside")
+        });
+        app.at("/slow").get(move |_| {
+            let counter = counter.clone();
+            async move {
+                loop {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    async_std::task::sleep(Duration::from_millis(5)).await;
+                }
+                #[allow(unreachable_code)]
+                Ok("slow")
+            }
+        });
+
+        let mut res: Response = app.respond(get("/inside")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::Ok);
+        assert_eq!(res.body_string().await.unwrap(), "inside");
+
+        let mut res: Response = app.respond(get("/slow")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::GatewayTimeout);
+        assert_eq!(res.body_string().await.unwrap(), "too slow");
+        let after = ticks.load(Ordering::SeqCst);
+        assert!(after > 0);
+        async_std::task::sleep(Duration::from_millis(50)).await;
+        assert_eq!(ticks.load(Ordering::SeqCst), after);
+    }
+
+    #[async_std::test]
+    async fn routes_override_and_exempt() {
+        async fn sleepy(_: summer_boot::Request<()>) -> summer_boot::Result<&'static str> {
+            async_std::task::sleep(Duration::from_millis(80)).await;
+            Ok("done")
+        }
+
+        let mut app = summer_boot::new();
+        app.with(TimeoutMiddleware::new(Duration::from_millis(30)));
+        app.at("/default").get(sleepy);
+        app.at("/longer")
+            .with(TimeoutMiddleware::new(Duration::from_millis(500)))
+            .get(sleepy);
+        app.at("/shorter")
+            .with(
+                TimeoutMiddleware::new(Duration::from_millis(10))
+                    .status(StatusCode::ServiceUnavailable),
+            )
+            .get(sleepy);
+        app.at("/events")
+            .with(TimeoutMiddleware::exempt())
+            .get(sleepy);
+
+        let status = |path: &'static str| {
+            let app = app.clone();
+            async move {
+                let res: Response = app.respond(get(path)).await.unwrap();
+                res.status()
+            }
+        };
+        assert_eq!(status("/default").await, StatusCode::GatewayTimeout);
+        assert_eq!(status("/longer").await, StatusCode::Ok);
+        assert_eq!(status("/shorter").await, StatusCode::ServiceUnavailable);
+        assert_eq!(status("/events").await, StatusCode::Ok);
+
+        let mut req = get("/default");
+        req.ext_mut().insert(NoTimeout);
+        let res: Response = app.respond(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::Ok);
+    }
+
+    #[async_std::test]
+    async fn overloaded_timeouts_send_retry_after() {
+        use crate::http_types::headers::RETRY_AFTER;
+        use crate::utils::retry_after::RetryAfterCause;
+
+        let mut app = summer_boot::new();
+        app.at("/busy")
+            .with(
+                TimeoutMiddleware::new(Duration::from_millis(10))
+                    .status(StatusCode::ServiceUnavailable),
+            )
+            .get(|_| async {
+                async_std::task::sleep(Duration::from_secs(1)).await;
+                Ok("busy")
+            });
+        app.at("/slow")
+            .with(TimeoutMiddleware::new(Duration::from_millis(10)))
+            .get(|_| async {
+                async_std::task::sleep(Duration::from_secs(1)).await;
+                Ok("slow")
+            });
+
+        let res: Response = app.respond(get("/busy")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::ServiceUnavailable);
+        // 还没有完成的请求，无法估算速率时使用上限
+        assert_eq!(res[RETRY_AFTER], "300");
+        let retry = res.ext().get::<RetryAfter>().unwrap();
+        assert_eq!(retry.cause(), RetryAfterCause::LoadShed);
+
+        let res: Response = app.respond(get("/slow")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::GatewayTimeout);
+        assert!(res.header(RETRY_AFTER).is_none());
+    }
+}
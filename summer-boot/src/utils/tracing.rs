@@ -0,0 +1,292 @@
+//! OpenTelemetry 链路追踪
+
+use crate::http_types::headers::HeaderName;
+use crate::{Middleware, Next, Request, Response};
+
+use async_trait::async_trait;
+use opentelemetry::global::{self, BoxedTracer};
+use opentelemetry::propagation::{Extractor, Injector, TextMapPropagator};
+use opentelemetry::trace::{SpanKind, Status, TraceContextExt, Tracer, TracerProvider};
+use opentelemetry::{Context, KeyValue};
+use opentelemetry_otlp::{ExporterBuildError, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+
+use std::fmt;
+use std::sync::Arc;
+
+/// 为每个请求创建 OpenTelemetry span 的中间件
+///
+/// 从请求的 `traceparent` 和 `tracestate` 头中继承上游的链路，span 的上下文写入响应头。
+/// span 记录请求方法、路径、响应状态码和对端地址，响应生成后结束。
+/// 处理器可以通过请求扩展中的 [`RequestSpan`] 添加自定义属性。
+///
+/// # Examples
+///
+/// ```no_run
+/// use summer_boot::utils::tracing::{RequestSpan, TracingBuilder};
+/// use summer_boot::Request;
+///
+/// let tracing = TracingBuilder::new("orders")
+///     .otlp("http://localhost:4318/v1/traces")
+///     .build()
+///     .expect("failed to build otlp exporter");
+///
+/// let mut app = summer_boot::new();
+/// app.with(tracing);
+/// app.at("/orders/:id").get(|req: Request<()>| async move {
+///     if let Some(span) = req.ext::<RequestSpan>() {
+///         span.set_attribute(opentelemetry::KeyValue::new("order.id", req.param("id")?.to_string()));
+///     }
+///     Ok("ok")
+/// });
+/// ```
+#[derive(Clone)]
+pub struct TracingMiddleware {
+    tracer: Arc<BoxedTracer>,
+    propagator: Arc<TraceContextPropagator>,
+    provider: Option<SdkTracerProvider>,
+}
+
+impl TracingMiddleware {
+    /// 使用全局的 tracer provider 创建
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_tracer(global::tracer("summer-boot"), None)
+    }
+
+    /// 使用指定的 tracer provider 创建
+    #[must_use]
+    pub fn from_provider(provider: &SdkTracerProvider) -> Self {
+        let tracer = BoxedTracer::new(Box::new(provider.tracer("summer-boot")));
+        Self::with_tracer(tracer, Some(provider.clone()))
+    }
+
+    fn with_tracer(tracer: BoxedTracer, provider: Option<SdkTracerProvider>) -> Self {
+        Self {
+            tracer: Arc::new(tracer),
+            propagator: Arc::new(TraceContextPropagator::new()),
+            provider,
+        }
+    }
+
+    /// 创建时使用的 tracer provider，程序退出前调用 `shutdown` 导出剩余的 span
+    pub fn provider(&self) -> Option<&SdkTracerProvider> {
+        self.provider.as_ref()
+    }
+}
+
+impl Default for TracingMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for TracingMiddleware {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TracingMiddleware")
+            .field("provider", &self.provider)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl<State: Clone + Send + Sync + 'static> Middleware<State> for TracingMiddleware {
+    async fn handle(&self, mut req: Request<State>, next: Next<'_, State>) -> crate::Result {
+        let parent = self.propagator.extract(&RequestHeaders(&req));
+
+        let method = req.method().to_string();
+        let path = req.url().path().to_string();
+        let mut attributes = vec![
+            KeyValue::new("http.request.method", method.clone()),
+            KeyValue::new("url.path", path.clone()),
+        ];
+        if let Some(peer) = req.peer_addr() {
+            attributes.push(KeyValue::new("client.address", peer.to_string()));
+        }
+        let span = self
+            .tracer
+            .span_builder(format!("{} {}", method, path))
+            .with_kind(SpanKind::Server)
+            .with_attributes(attributes)
+            .start_with_context(self.tracer.as_ref(), &parent);
+        let cx = parent.with_span(span);
+
+        req.set_ext(RequestSpan { cx: cx.clone() });
+        let mut res = next.run(req).await;
+
+        let span = cx.span();
+        let status = res.status();
+        span.set_attribute(KeyValue::new(
+            "http.response.status_code",
+            i64::from(status as u16),
+        ));
+        if status.is_server_error() {
+            span.set_status(Status::error(status.canonical_reason()));
+        }
+        self.propagator
+            .inject_context(&cx, &mut ResponseHeaders(&mut res));
+        span.end();
+        Ok(res)
+    }
+}
+
+/// 当前请求的 span，由 [`TracingMiddleware`] 保存在请求扩展中
+#[derive(Debug, Clone)]
+pub struct RequestSpan {
+    cx: Context,
+}
+
+impl RequestSpan {
+    /// 包含当前 span 的上下文，可以用来创建子 span
+    pub fn context(&self) -> &Context {
+        &self.cx
+    }
+
+    pub fn set_attribute(&self, attribute: KeyValue) {
+        self.cx.span().set_attribute(attribute);
+    }
+
+    pub fn add_event(&self, name: impl Into<String>, attributes: Vec<KeyValue>) {
+        self.cx.span().add_event(name.into(), attributes);
+    }
+}
+
+/// 配置 span 导出器并创建 [`TracingMiddleware`]
+///
+/// span 通过 OTLP/HTTP 批量导出，导出在独立线程中进行，不阻塞请求。
+#[derive(Debug, Clone)]
+pub struct TracingBuilder {
+    service_name: String,
+    endpoint: String,
+}
+
+impl TracingBuilder {
+    /// 默认导出到 `http://localhost:4318/v1/traces`
+    #[must_use]
+    pub fn new(service_name: impl Into<String>) -> Self {
+        Self {
+            service_name: service_name.into(),
+            endpoint: DEFAULT_OTLP_ENDPOINT.to_string(),
+        }
+    }
+
+    /// 导出到 OTLP/HTTP collector
+    #[must_use]
+    pub fn otlp(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = endpoint.into();
+        self
+    }
+
+    /// 导出到 Jaeger
+    ///
+    /// Jaeger 1.35 之后原生支持 OTLP，`endpoint` 是 Jaeger collector 的 OTLP/HTTP 地址，
+    /// 例如 `http://jaeger:4318/v1/traces`。
+    #[must_use]
+    pub fn jaeger(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = endpoint.into();
+        self
+    }
+
+    pub fn build(self) -> Result<TracingMiddleware, ExporterBuildError> {
+        let exporter = SpanExporter::builder()
+            .with_http()
+            .with_endpoint(self.endpoint)
+            .build()?;
+        let provider = SdkTracerProvider::builder()
+            .with_resource(
+                Resource::builder()
+                    .with_service_name(self.service_name)
+                    .build(),
+            )
+            .with_batch_exporter(exporter)
+            .build();
+        Ok(TracingMiddleware::from_provider(&provider))
+    }
+}
+
+const DEFAULT_OTLP_ENDPOINT: &str = "http://localhost:4318/v1/traces";
+
+struct RequestHeaders<'a, State>(&'a Request<State>);
+
+impl<State> Extractor for RequestHeaders<'_, State> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.header(key).map(|values| values.as_str())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.header_names().map(HeaderName::as_str).collect()
+    }
+}
+
+struct ResponseHeaders<'a>(&'a mut Response);
+
+impl Injector for ResponseHeaders<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        self.0.insert_header(key, value);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http_types::{Method, Request as HttpRequest, Url};
+    use opentelemetry::trace::TraceId;
+    use opentelemetry::Value;
+    use opentelemetry_sdk::trace::InMemorySpanExporter;
+
+    const PARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    #[async_std::test]
+    async fn records_span_and_propagates_context() {
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+
+        let mut app = crate::new();
+        app.with(TracingMiddleware::from_provider(&provider));
+        app.at("/orders/:id").get(|req: Request<()>| async move {
+            let span = req.ext::<RequestSpan>().unwrap();
+            span.set_attribute(KeyValue::new("order.id", req.param("id")?.to_string()));
+            Ok("ok")
+        });
+
+        let mut req = HttpRequest::new(
+            Method::Get,
+            Url::parse("http://localhost/orders/7").unwrap(),
+        );
+        req.insert_header("traceparent", PARENT);
+        req.insert_header("tracestate", "vendor=1");
+        let res: crate::http_types::Response = app.respond(req).await.unwrap();
+
+        let spans = exporter.get_finished_spans().unwrap();
+        assert_eq!(spans.len(), 1);
+        let span = &spans[0];
+        assert_eq!(span.name, "GET /orders/7");
+        assert_eq!(span.span_kind, SpanKind::Server);
+        assert_eq!(
+            span.span_context.trace_id(),
+            TraceId::from_hex("4bf92f3577b34da6a3ce929d0e0e4736").unwrap()
+        );
+        let attribute = |key: &str| {
+            span.attributes
+                .iter()
+                .find(|kv| kv.key.as_str() == key)
+                .map(|kv| kv.value.clone())
+        };
+        assert_eq!(attribute("http.request.method"), Some(Value::from("GET")));
+        assert_eq!(attribute("url.path"), Some(Value::from("/orders/7")));
+        assert_eq!(
+            attribute("http.response.status_code"),
+            Some(Value::I64(200))
+        );
+        assert_eq!(attribute("order.id"), Some(Value::from("7")));
+
+        let traceparent = res["traceparent"].as_str();
+        assert!(traceparent.starts_with("00-4bf92f3577b34da6a3ce929d0e0e4736-"));
+        assert!(!traceparent.contains("00f067aa0ba902b7"));
+        assert_eq!(res["tracestate"].as_str(), "vendor=1");
+    }
+}
@@ -0,0 +1,190 @@
+//! 受信任的反向代理
+//!
+//! 只有连接来自受信任的代理时，[`Request::client_addr`](crate::Request::client_addr)
+//! 才会读取 `Forwarded` 或 `X-Forwarded-For`，避免客户端伪造自己的地址。
+
+use ipnet::IpNet;
+
+use std::net::{IpAddr, SocketAddr};
+
+/// 受信任的代理网段
+///
+/// # Examples
+///
+/// ```
+/// use summer_boot::utils::trusted_proxy::TrustedProxies;
+///
+/// let mut app = summer_boot::new();
+/// app.trusted_proxies(TrustedProxies::parse(["10.0.0.0/8", "::1"]).unwrap());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies {
+    nets: Vec<IpNet>,
+}
+
+impl TrustedProxies {
+    /// 不信任任何代理
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 添加受信任的网段
+    #[must_use]
+    pub fn trust(mut self, net: IpNet) -> Self {
+        self.nets.push(net);
+        self
+    }
+
+    /// 解析 CIDR 列表，单个地址视为只包含该地址的网段
+    pub fn parse<I, S>(cidrs: I) -> Result<Self, ipnet::AddrParseError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut proxies = Self::new();
+        for cidr in cidrs {
+            let cidr = cidr.as_ref().trim();
+            let net = match cidr.parse::<IpAddr>() {
+                Ok(ip) => IpNet::from(ip),
+                Err(_) => cidr.parse()?,
+            };
+            proxies = proxies.trust(net);
+        }
+        Ok(proxies)
+    }
+
+    /// 地址是否属于受信任的代理
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let ip = canonical(ip);
+        self.nets.iter().any(|net| net.contains(&ip))
+    }
+
+    /// 确定客户端地址
+    ///
+    /// 对端不受信任时直接返回对端地址，否则从右向左遍历 `chain`，
+    /// 返回第一个不受信任的地址。遇到无法解析的地址时停止，返回最后一个受信任的地址；
+    /// 所有地址都受信任时返回最左边的地址。
+    pub(crate) fn client_addr(&self, peer: IpAddr, chain: impl FnOnce() -> Vec<String>) -> IpAddr {
+        let mut addr = canonical(peer);
+        if !self.contains(addr) {
+            return addr;
+        }
+        for hop in chain().into_iter().rev() {
+            let Some(hop) = parse_addr(&hop) else {
+                break;
+            };
+            addr = hop;
+            if !self.contains(addr) {
+                break;
+            }
+        }
+        addr
+    }
+}
+
+/// IPv4 映射的 IPv6 地址按 IPv4 处理
+fn canonical(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+        ip => ip,
+    }
+}
+
+/// 解析 `1.2.3.4`、`1.2.3.4:80`、`[::1]`、`[::1]:80` 以及带引号的 `Forwarded` 节点
+pub(crate) fn parse_addr(addr: &str) -> Option<IpAddr> {
+    let addr = addr.trim().trim_matches('"');
+    let ip = addr
+        .parse::<IpAddr>()
+        .ok()
+        .or_else(|| addr.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+        .or_else(|| {
+            addr.strip_prefix('[')
+                .and_then(|addr| addr.strip_suffix(']'))
+                .and_then(|addr| addr.parse().ok())
+        })?;
+    Some(canonical(ip))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Request;
+    use http_types::{Method, Response, Url};
+
+    fn app(proxies: &[&str]) -> crate::Server<()> {
+        let mut app = crate::new();
+        app.trusted_proxies(TrustedProxies::parse(proxies).unwrap());
+        app.at("/").get(|req: Request<()>| async move {
+            Ok(req
+                .client_addr()
+                .map(|ip| ip.to_string())
+                .unwrap_or_default())
+        });
+        app
+    }
+
+    async fn client(app: &crate::Server<()>, peer: &str, headers: &[(&str, &str)]) -> String {
+        let mut req =
+            http_types::Request::new(Method::Get, Url::parse("http://localhost/").unwrap());
+        req.set_peer_addr(Some(peer));
+        for (name, value) in headers {
+            req.append_header(*name, *value);
+        }
+        let mut res: Response = app.respond(req).await.unwrap();
+        res.body_string().await.unwrap()
+    }
+
+    #[async_std::test]
+    async fn ignores_headers_from_untrusted_peers() {
+        let app = app(&["10.0.0.0/8"]);
+        let spoofed = [("X-Forwarded-For", "1.1.1.1")];
+        assert_eq!(
+            client(&app, "203.0.113.9:5000", &spoofed).await,
+            "203.0.113.9"
+        );
+        let spoofed = [("Forwarded", "for=1.1.1.1")];
+        assert_eq!(
+            client(&app, "203.0.113.9:5000", &spoofed).await,
+            "203.0.113.9"
+        );
+        assert_eq!(client(&app, "10.0.0.2:5000", &[]).await, "10.0.0.2");
+    }
+
+    #[async_std::test]
+    async fn walks_chain_from_the_right() {
+        let app = app(&["10.0.0.0/8"]);
+        // 客户端伪造了最左边的地址，两层代理分别追加了各自看到的地址
+        let chain = [("X-Forwarded-For", "1.1.1.1, 198.51.100.7, 10.0.0.5")];
+        assert_eq!(client(&app, "10.0.0.2:5000", &chain).await, "198.51.100.7");
+        let split = [
+            ("X-Forwarded-For", "1.1.1.1"),
+            ("X-Forwarded-For", "198.51.100.7, 10.0.0.5"),
+        ];
+        assert_eq!(client(&app, "10.0.0.2:5000", &split).await, "198.51.100.7");
+        let chain = [("X-Forwarded-For", "garbage, 10.0.0.5")];
+        assert_eq!(client(&app, "10.0.0.2:5000", &chain).await, "10.0.0.5");
+        let all_trusted = [("X-Forwarded-For", "10.0.0.9, 10.0.0.5")];
+        assert_eq!(
+            client(&app, "10.0.0.2:5000", &all_trusted).await,
+            "10.0.0.9"
+        );
+    }
+
+    #[async_std::test]
+    async fn matches_ipv6_networks() {
+        let app = app(&["fd00::/8", "::1"]);
+        let forwarded = [("Forwarded", r#"for="[2001:db8::7]:4711", for="[fd00::2]""#)];
+        assert_eq!(client(&app, "[::1]:5000", &forwarded).await, "2001:db8::7");
+        let chain = [("X-Forwarded-For", "2001:db8::7")];
+        assert_eq!(client(&app, "[fe80::1]:5000", &chain).await, "fe80::1");
+        assert_eq!(
+            client(&app, "[::ffff:10.0.0.1]:5000", &[]).await,
+            "10.0.0.1"
+        );
+
+        let proxies = TrustedProxies::parse(["fd00::/8"]).unwrap();
+        assert!(proxies.contains("fd12::1".parse().unwrap()));
+        assert!(TrustedProxies::parse(["fd00::/200"]).is_err());
+    }
+}
@@ -2,7 +2,9 @@
 
 use crate::{Middleware, Next, Request, Response};
 pub use async_trait::async_trait;
+use std::fmt;
 use std::future::Future;
+use std::pin::Pin;
 
 /// 定义对传入请求进行操作的中间件。
 ///
@@ -55,3 +57,144 @@ where
         (self.0)(response).await
     }
 }
+
+type BoxedBefore<State> = Box<
+    dyn Fn(Request<State>) -> Pin<Box<dyn Future<Output = Request<State>> + Send>> + Send + Sync,
+>;
+
+type BoxedAfter =
+    Box<dyn Fn(Response) -> Pin<Box<dyn Future<Output = crate::Result> + Send>> + Send + Sync>;
+
+/// 闭包装箱后的 [`Before`]，通过 [`before`] 创建
+///
+/// 闭包返回的 future 只需要 `Send`，可以在 `await` 期间持有非 `Sync` 的值。
+pub struct BeforeBoxed<State>(BoxedBefore<State>);
+
+impl<State> fmt::Debug for BeforeBoxed<State> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BeforeBoxed").finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl<State> Middleware<State> for BeforeBoxed<State>
+where
+    State: Clone + Send + Sync + 'static,
+{
+    async fn handle(&self, request: Request<State>, next: Next<'_, State>) -> crate::Result {
+        let request = (self.0)(request).await;
+        Ok(next.run(request).await)
+    }
+}
+
+/// 闭包装箱后的 [`After`]，通过 [`after`] 创建
+pub struct AfterBoxed(BoxedAfter);
+
+impl fmt::Debug for AfterBoxed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AfterBoxed").finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl<State> Middleware<State> for AfterBoxed
+where
+    State: Clone + Send + Sync + 'static,
+{
+    async fn handle(&self, request: Request<State>, next: Next<'_, State>) -> crate::Result {
+        let response = next.run(request).await;
+        (self.0)(response).await
+    }
+}
+
+/// 用异步闭包创建处理请求的中间件
+///
+/// ```rust
+/// use summer_boot::utils::util;
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use std::sync::Arc;
+///
+/// let hits = Arc::new(AtomicUsize::new(0));
+/// let mut app = summer_boot::new();
+/// app.with(util::before({
+///     let hits = hits.clone();
+///     move |request| {
+///         let hits = hits.clone();
+///         async move {
+///             hits.fetch_add(1, Ordering::SeqCst);
+///             request
+///         }
+///     }
+/// }));
+/// ```
+pub fn before<State, F, Fut>(f: F) -> BeforeBoxed<State>
+where
+    F: Fn(Request<State>) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Request<State>> + Send + 'static,
+{
+    BeforeBoxed(Box::new(move |request| Box::pin(f(request))))
+}
+
+/// 用异步闭包创建处理响应的中间件
+///
+/// ```rust
+/// use summer_boot::utils::util;
+///
+/// let mut app = summer_boot::new();
+/// app.with(util::after(|mut response: summer_boot::Response| async move {
+///     response.insert_header("X-Served-By", "summer");
+///     Ok(response)
+/// }));
+/// ```
+pub fn after<F, Fut>(f: F) -> AfterBoxed
+where
+    F: Fn(Response) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = crate::Result> + Send + 'static,
+{
+    AfterBoxed(Box::new(move |response| Box::pin(f(response))))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use http_types::{Method, Url};
+    use std::cell::Cell;
+    use std::sync::{Arc, Mutex};
+
+    #[async_std::test]
+    async fn boxed_closures_capture_outer_state() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let header = String::from("summer");
+
+        let mut app = crate::new();
+        app.with(before({
+            let seen = seen.clone();
+            move |request: Request<()>| {
+                let seen = seen.clone();
+                async move {
+                    // 非 `Sync` 的值跨越 `await`
+                    let step = Cell::new(1);
+                    async_std::task::yield_now().await;
+                    seen.lock()
+                        .unwrap()
+                        .push(format!("{} {}", step.get(), request.url().path()));
+                    request
+                }
+            }
+        }));
+        app.with(after(move |mut response: Response| {
+            let header = header.clone();
+            async move {
+                response.insert_header("X-Served-By", header);
+                Ok(response)
+            }
+        }));
+        app.at("/hello").get(|_| async { Ok("hello") });
+
+        let req =
+            http_types::Request::new(Method::Get, Url::parse("http://localhost/hello").unwrap());
+        let res: http_types::Response = app.respond(req).await.unwrap();
+        assert_eq!(res["X-Served-By"], "summer");
+        assert_eq!(*seen.lock().unwrap(), ["1 /hello"]);
+    }
+}
@@ -0,0 +1,184 @@
+//! RFC 6455 帧的编解码
+
+use async_std::io::{self, Read, ReadExt};
+
+use std::fmt;
+
+pub(crate) const OP_CONTINUATION: u8 = 0x0;
+pub(crate) const OP_TEXT: u8 = 0x1;
+pub(crate) const OP_BINARY: u8 = 0x2;
+pub(crate) const OP_CLOSE: u8 = 0x8;
+pub(crate) const OP_PING: u8 = 0x9;
+pub(crate) const OP_PONG: u8 = 0xA;
+
+/// 控制帧的最大负载长度
+const MAX_CONTROL_PAYLOAD: u64 = 125;
+
+/// 一个解码后的帧，负载已经去掉掩码
+#[derive(Debug)]
+pub(crate) struct Frame {
+    pub(crate) fin: bool,
+    pub(crate) opcode: u8,
+    pub(crate) payload: Vec<u8>,
+}
+
+/// 违反协议的帧，`code` 是关闭连接时发送的状态码
+#[derive(Debug)]
+pub(crate) struct ProtocolError {
+    pub(crate) code: u16,
+    message: &'static str,
+}
+
+/// 创建包含 [`ProtocolError`] 的 `io::Error`
+pub(crate) fn protocol_error(code: u16, message: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, ProtocolError { code, message })
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "websocket protocol error {}: {}",
+            self.code, self.message
+        )
+    }
+}
+
+impl std::error::Error for ProtocolError {}
+
+/// 读取客户端发送的帧，客户端的帧必须带掩码
+pub(crate) async fn read_frame<R>(reader: &mut R, max_payload: usize) -> io::Result<Frame>
+where
+    R: Read + Unpin + ?Sized,
+{
+    let mut head = [0; 2];
+    reader.read_exact(&mut head).await?;
+
+    let fin = head[0] & 0x80 != 0;
+    if head[0] & 0x70 != 0 {
+        return Err(protocol_error(1002, "reserved bits must be 0"));
+    }
+    let opcode = head[0] & 0x0F;
+    if head[1] & 0x80 == 0 {
+        return Err(protocol_error(1002, "client frames must be masked"));
+    }
+
+    let len = match head[1] & 0x7F {
+        126 => {
+            let mut len = [0; 2];
+            reader.read_exact(&mut len).await?;
+            u64::from(u16::from_be_bytes(len))
+        }
+        127 => {
+            let mut len = [0; 8];
+            reader.read_exact(&mut len).await?;
+            u64::from_be_bytes(len)
+        }
+        len => u64::from(len),
+    };
+    if opcode >= OP_CLOSE && (!fin || len > MAX_CONTROL_PAYLOAD) {
+        return Err(protocol_error(1002, "invalid control frame"));
+    }
+    if len > max_payload as u64 {
+        return Err(protocol_error(1009, "message too big"));
+    }
+
+    let mut mask = [0; 4];
+    reader.read_exact(&mut mask).await?;
+    let mut payload = vec![0; len as usize];
+    reader.read_exact(&mut payload).await?;
+    apply_mask(&mut payload, mask);
+
+    Ok(Frame {
+        fin,
+        opcode,
+        payload,
+    })
+}
+
+/// 编码一个帧，服务端发送的帧 `mask` 为 `None`
+pub(crate) fn encode_frame(
+    fin: bool,
+    opcode: u8,
+    payload: &[u8],
+    mask: Option<[u8; 4]>,
+) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(payload.len() + 14);
+    frame.push(if fin { 0x80 } else { 0 } | opcode);
+
+    let mask_bit = if mask.is_some() { 0x80 } else { 0 };
+    match payload.len() {
+        len if len < 126 => frame.push(mask_bit | len as u8),
+        len if len <= usize::from(u16::MAX) => {
+            frame.push(mask_bit | 126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(mask_bit | 127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+
+    match mask {
+        Some(mask) => {
+            frame.extend_from_slice(&mask);
+            let start = frame.len();
+            frame.extend_from_slice(payload);
+            apply_mask(&mut frame[start..], mask);
+        }
+        None => frame.extend_from_slice(payload),
+    }
+    frame
+}
+
+fn apply_mask(payload: &mut [u8], mask: [u8; 4]) {
+    for (i, byte) in payload.iter_mut().enumerate() {
+        *byte ^= mask[i % 4];
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[async_std::test]
+    async fn round_trips_masked_frames() {
+        for len in [0, 125, 126, 70_000] {
+            let payload: Vec<u8> = (0..len).map(|i| i as u8).collect();
+            let encoded = encode_frame(true, OP_BINARY, &payload, Some([1, 2, 3, 4]));
+            let frame = read_frame(&mut encoded.as_slice(), usize::MAX)
+                .await
+                .unwrap();
+            assert!(frame.fin);
+            assert_eq!(frame.opcode, OP_BINARY);
+            assert_eq!(frame.payload, payload);
+        }
+    }
+
+    #[async_std::test]
+    async fn rejects_invalid_frames() {
+        let code = |err: io::Error| {
+            err.into_inner()
+                .unwrap()
+                .downcast::<ProtocolError>()
+                .unwrap()
+                .code
+        };
+
+        let unmasked = encode_frame(true, OP_TEXT, b"hi", None);
+        let err = read_frame(&mut unmasked.as_slice(), 1024)
+            .await
+            .unwrap_err();
+        assert_eq!(code(err), 1002);
+
+        let fragmented_ping = encode_frame(false, OP_PING, b"", Some([0; 4]));
+        let err = read_frame(&mut fragmented_ping.as_slice(), 1024)
+            .await
+            .unwrap_err();
+        assert_eq!(code(err), 1002);
+
+        let big = encode_frame(true, OP_BINARY, &[0; 2048], Some([0; 4]));
+        let err = read_frame(&mut big.as_slice(), 1024).await.unwrap_err();
+        assert_eq!(code(err), 1009);
+    }
+}
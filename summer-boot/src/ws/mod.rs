@@ -0,0 +1,622 @@
+//! WebSocket
+//!
+//! 通过 [`upgrade`] 创建接受 WebSocket 握手的 endpoint，握手成功后在新任务中
+//! 以 [`WebSocketConnection`] 运行处理函数。
+//!
+//! # Examples
+//!
+//! ```
+//! use summer_boot::ws::{self, Message};
+//!
+//! let mut app = summer_boot::new();
+//! app.at("/ws").get(ws::upgrade(|mut conn| async move {
+//!     while let Some(message) = conn.recv().await {
+//!         if let Message::Text(text) = message? {
+//!             conn.send_text(text).await?;
+//!         }
+//!     }
+//!     Ok(())
+//! }));
+//! ```
+
+mod frame;
+
+use frame::{encode_frame, protocol_error, read_frame, Frame, ProtocolError};
+use frame::{OP_BINARY, OP_CLOSE, OP_CONTINUATION, OP_PING, OP_PONG, OP_TEXT};
+
+use crate::http_types::headers::{CONNECTION, UPGRADE};
+use crate::http_types::upgrade::Connection;
+use crate::{log, Endpoint, Request, Response, StatusCode};
+
+use async_std::io;
+use async_std::sync::Mutex;
+use async_std::task;
+use async_trait::async_trait;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use futures_util::io::{AsyncReadExt, AsyncWriteExt, ReadHalf, WriteHalf};
+use sha1::{Digest, Sha1};
+
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+
+/// 计算 `Sec-WebSocket-Accept` 时拼接的 GUID
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// 支持的协议版本
+const WEBSOCKET_VERSION: &str = "13";
+
+/// 默认的消息大小上限
+const DEFAULT_MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+
+/// 创建接受 WebSocket 握手的 endpoint
+///
+/// 请求缺少 `Upgrade: websocket`、`Connection: Upgrade` 或 `Sec-WebSocket-Key` 时返回 `400`，
+/// `Sec-WebSocket-Version` 不是 13 时返回 `426`。`handler` 返回错误时记录日志。
+pub fn upgrade<F, Fut>(handler: F) -> WebSocket<F>
+where
+    F: Fn(WebSocketConnection) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = crate::Result<()>> + Send + 'static,
+{
+    WebSocket {
+        handler: Arc::new(handler),
+        max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+    }
+}
+
+/// WebSocket endpoint，通过 [`upgrade`] 创建
+pub struct WebSocket<F> {
+    handler: Arc<F>,
+    max_message_size: usize,
+}
+
+impl<F> WebSocket<F> {
+    /// 设置单条消息的大小上限，超过时以 `1009` 关闭连接，默认 16 MiB
+    #[must_use]
+    pub fn max_message_size(mut self, size: usize) -> Self {
+        self.max_message_size = size;
+        self
+    }
+}
+
+impl<F> fmt::Debug for WebSocket<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WebSocket")
+            .field("max_message_size", &self.max_message_size)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl<State, F, Fut> Endpoint<State> for WebSocket<F>
+where
+    State: Clone + Send + Sync + 'static,
+    F: Fn(WebSocketConnection) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = crate::Result<()>> + Send + 'static,
+{
+    async fn call(&self, req: Request<State>) -> crate::Result {
+        let accept = match handshake(&req) {
+            Ok(accept) => accept,
+            Err(status) => {
+                let mut res = Response::new(status);
+                if status == StatusCode::UpgradeRequired {
+                    res.insert_header("Sec-WebSocket-Version", WEBSOCKET_VERSION);
+                }
+                return Ok(res);
+            }
+        };
+
+        let mut res = http_types::Response::new(StatusCode::SwitchingProtocols);
+        res.insert_header(UPGRADE, "websocket");
+        res.insert_header(CONNECTION, "Upgrade");
+        res.insert_header("Sec-WebSocket-Accept", accept);
+
+        let upgrade = res.recv_upgrade().await;
+        let handler = self.handler.clone();
+        let max_message_size = self.max_message_size;
+        task::spawn(async move {
+            if let Some(conn) = upgrade.await {
+                let conn = WebSocketConnection::new(conn, max_message_size);
+                if let Err(error) = handler(conn).await {
+                    log::error!("websocket handler error", { error: error.to_string() });
+                }
+            }
+        });
+        Ok(res.into())
+    }
+}
+
+/// 校验握手请求，返回 `Sec-WebSocket-Accept` 的值或错误状态码
+fn handshake<State>(req: &Request<State>) -> Result<String, StatusCode> {
+    let has_token = |name, token: &str| {
+        req.header(name).is_some_and(|values| {
+            values
+                .iter()
+                .flat_map(|value| value.as_str().split(','))
+                .any(|value| value.trim().eq_ignore_ascii_case(token))
+        })
+    };
+    if !has_token(UPGRADE, "websocket") || !has_token(CONNECTION, "upgrade") {
+        return Err(StatusCode::BadRequest);
+    }
+
+    let version = req.header("Sec-WebSocket-Version").map(|v| v.as_str());
+    if version != Some(WEBSOCKET_VERSION) {
+        return Err(StatusCode::UpgradeRequired);
+    }
+
+    // 客户端的 key 是 16 字节随机数的 base64
+    match req.header("Sec-WebSocket-Key").map(|v| v.as_str().trim()) {
+        Some(key) if STANDARD.decode(key).is_ok_and(|nonce| nonce.len() == 16) => {
+            Ok(accept_key(key))
+        }
+        _ => Err(StatusCode::BadRequest),
+    }
+}
+
+fn accept_key(key: &str) -> String {
+    let mut sha1 = Sha1::new();
+    sha1.update(key.as_bytes());
+    sha1.update(WEBSOCKET_GUID.as_bytes());
+    STANDARD.encode(sha1.finalize())
+}
+
+/// WebSocket 消息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+    /// 收到时已经自动回复 `Pong`
+    Ping(Vec<u8>),
+    Pong(Vec<u8>),
+    Close(Option<CloseFrame>),
+}
+
+/// 关闭帧中的状态码和原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CloseFrame {
+    pub code: u16,
+    pub reason: String,
+}
+
+/// 握手完成后的 WebSocket 连接
+///
+/// 可以通过 [`split`](WebSocketConnection::split) 拆分为发送端和接收端，在不同任务中同时读写。
+/// 写入等待连接可写，发送得快的一方会被慢客户端阻塞，不会无限缓冲。
+#[derive(Debug)]
+pub struct WebSocketConnection {
+    sender: WebSocketSender,
+    receiver: WebSocketReceiver,
+}
+
+impl WebSocketConnection {
+    fn new(conn: Connection, max_message_size: usize) -> Self {
+        let (reader, writer) = conn.split();
+        let sender = WebSocketSender {
+            inner: Arc::new(Mutex::new(Writer {
+                writer,
+                close_sent: false,
+            })),
+        };
+        let receiver = WebSocketReceiver {
+            reader,
+            sender: sender.clone(),
+            max_message_size,
+            fragments: None,
+            done: false,
+        };
+        Self { sender, receiver }
+    }
+
+    /// 接收下一条消息，连接关闭后返回 `None`
+    pub async fn recv(&mut self) -> Option<io::Result<Message>> {
+        self.receiver.recv().await
+    }
+
+    pub async fn send(&self, message: Message) -> io::Result<()> {
+        self.sender.send(message).await
+    }
+
+    pub async fn send_text(&self, text: impl Into<String>) -> io::Result<()> {
+        self.sender.send(Message::Text(text.into())).await
+    }
+
+    pub async fn send_binary(&self, bytes: impl Into<Vec<u8>>) -> io::Result<()> {
+        self.sender.send(Message::Binary(bytes.into())).await
+    }
+
+    pub fn split(self) -> (WebSocketSender, WebSocketReceiver) {
+        (self.sender, self.receiver)
+    }
+}
+
+struct Writer {
+    writer: WriteHalf<Connection>,
+    close_sent: bool,
+}
+
+/// 连接的发送端，可以 clone 后在多个任务中使用
+#[derive(Clone)]
+pub struct WebSocketSender {
+    inner: Arc<Mutex<Writer>>,
+}
+
+impl WebSocketSender {
+    /// 发送消息，发送 `Close` 之后不能再发送其他消息
+    pub async fn send(&self, message: Message) -> io::Result<()> {
+        let (opcode, payload) = match message {
+            Message::Text(text) => (OP_TEXT, text.into_bytes()),
+            Message::Binary(bytes) => (OP_BINARY, bytes),
+            Message::Ping(bytes) => (OP_PING, bytes),
+            Message::Pong(bytes) => (OP_PONG, bytes),
+            Message::Close(frame) => (OP_CLOSE, close_payload(frame)),
+        };
+        if opcode >= OP_CLOSE && payload.len() > 125 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "control frame payload must not exceed 125 bytes",
+            ));
+        }
+
+        let mut inner = self.inner.lock().await;
+        if inner.close_sent {
+            return Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                "websocket close frame already sent",
+            ));
+        }
+        inner.close_sent = opcode == OP_CLOSE;
+        inner
+            .writer
+            .write_all(&encode_frame(true, opcode, &payload, None))
+            .await?;
+        inner.writer.flush().await
+    }
+
+    /// 回复关闭帧（如果还没有发送过）并关闭连接的写入端
+    async fn finish(&self, frame: Option<CloseFrame>) -> io::Result<()> {
+        let mut inner = self.inner.lock().await;
+        if !inner.close_sent {
+            inner.close_sent = true;
+            let payload = close_payload(frame);
+            inner
+                .writer
+                .write_all(&encode_frame(true, OP_CLOSE, &payload, None))
+                .await?;
+        }
+        inner.writer.close().await
+    }
+}
+
+impl fmt::Debug for WebSocketSender {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WebSocketSender").finish()
+    }
+}
+
+fn close_payload(frame: Option<CloseFrame>) -> Vec<u8> {
+    match frame {
+        Some(frame) => {
+            let mut payload = frame.code.to_be_bytes().to_vec();
+            payload.extend_from_slice(frame.reason.as_bytes());
+            payload
+        }
+        None => Vec::new(),
+    }
+}
+
+/// 连接的接收端
+pub struct WebSocketReceiver {
+    reader: ReadHalf<Connection>,
+    sender: WebSocketSender,
+    max_message_size: usize,
+    /// 分片消息的类型和已经收到的负载
+    fragments: Option<(u8, Vec<u8>)>,
+    done: bool,
+}
+
+impl WebSocketReceiver {
+    /// 接收下一条消息，连接关闭后返回 `None`
+    ///
+    /// 收到 `Ping` 时自动回复 `Pong`，收到 `Close` 时回复关闭帧并关闭连接。
+    /// 客户端违反协议时以对应的状态码关闭连接并返回错误。
+    pub async fn recv(&mut self) -> Option<io::Result<Message>> {
+        if self.done {
+            return None;
+        }
+        match self.next_message().await {
+            Ok(message) => Some(Ok(message)),
+            Err(err) => {
+                self.done = true;
+                if err.kind() == io::ErrorKind::UnexpectedEof {
+                    // 客户端没有发送关闭帧就断开了连接
+                    return Some(Err(err));
+                }
+                let code = err
+                    .get_ref()
+                    .and_then(|inner| inner.downcast_ref::<ProtocolError>())
+                    .map(|err| err.code);
+                if let Some(code) = code {
+                    let frame = CloseFrame {
+                        code,
+                        reason: String::new(),
+                    };
+                    self.sender.finish(Some(frame)).await.ok();
+                }
+                Some(Err(err))
+            }
+        }
+    }
+
+    async fn next_message(&mut self) -> io::Result<Message> {
+        loop {
+            let Frame {
+                fin,
+                opcode,
+                payload,
+            } = read_frame(&mut self.reader, self.max_message_size).await?;
+
+            let (opcode, payload) = match opcode {
+                OP_PING => {
+                    self.sender.send(Message::Pong(payload.clone())).await?;
+                    return Ok(Message::Ping(payload));
+                }
+                OP_PONG => return Ok(Message::Pong(payload)),
+                OP_CLOSE => {
+                    let frame = parse_close(&payload)?;
+                    self.done = true;
+                    self.sender.finish(frame.clone()).await?;
+                    return Ok(Message::Close(frame));
+                }
+                OP_TEXT | OP_BINARY if self.fragments.is_none() => {
+                    if !fin {
+                        self.fragments = Some((opcode, payload));
+                        continue;
+                    }
+                    (opcode, payload)
+                }
+                OP_CONTINUATION => {
+                    let (opcode, mut buffered) = self
+                        .fragments
+                        .take()
+                        .ok_or_else(|| protocol_error(1002, "unexpected continuation frame"))?;
+                    if buffered.len() + payload.len() > self.max_message_size {
+                        return Err(protocol_error(1009, "message too big"));
+                    }
+                    buffered.extend_from_slice(&payload);
+                    if !fin {
+                        self.fragments = Some((opcode, buffered));
+                        continue;
+                    }
+                    (opcode, buffered)
+                }
+                OP_TEXT | OP_BINARY => {
+                    return Err(protocol_error(1002, "expected continuation frame"))
+                }
+                _ => return Err(protocol_error(1002, "unknown opcode")),
+            };
+
+            return if opcode == OP_TEXT {
+                String::from_utf8(payload)
+                    .map(Message::Text)
+                    .map_err(|_| protocol_error(1007, "text message is not valid UTF-8"))
+            } else {
+                Ok(Message::Binary(payload))
+            };
+        }
+    }
+}
+
+fn parse_close(payload: &[u8]) -> io::Result<Option<CloseFrame>> {
+    match payload {
+        [] => Ok(None),
+        [_] => Err(protocol_error(1002, "invalid close frame")),
+        [high, low, reason @ ..] => {
+            let reason = std::str::from_utf8(reason)
+                .map_err(|_| protocol_error(1007, "close reason is not valid UTF-8"))?;
+            Ok(Some(CloseFrame {
+                code: u16::from_be_bytes([*high, *low]),
+                reason: reason.to_string(),
+            }))
+        }
+    }
+}
+
+impl fmt::Debug for WebSocketReceiver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WebSocketReceiver")
+            .field("max_message_size", &self.max_message_size)
+            .field("done", &self.done)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http_types::{Method, Request as HttpRequest, Url};
+
+    use async_std::io::{Read, Write};
+    use std::collections::VecDeque;
+    use std::pin::Pin;
+    use std::task::{Context, Poll, Waker};
+
+    /// 内存中的单向管道
+    #[derive(Clone, Default)]
+    struct Pipe(Arc<std::sync::Mutex<PipeState>>);
+
+    #[derive(Default)]
+    struct PipeState {
+        buf: VecDeque<u8>,
+        closed: bool,
+        waker: Option<Waker>,
+    }
+
+    /// 内存中的双向连接
+    #[derive(Clone)]
+    struct Duplex {
+        read: Pipe,
+        write: Pipe,
+    }
+
+    fn duplex() -> (Duplex, Duplex) {
+        let (a, b) = (Pipe::default(), Pipe::default());
+        (
+            Duplex {
+                read: a.clone(),
+                write: b.clone(),
+            },
+            Duplex { read: b, write: a },
+        )
+    }
+
+    impl Read for Duplex {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            let mut state = self.read.0.lock().unwrap();
+            if state.buf.is_empty() {
+                if state.closed {
+                    return Poll::Ready(Ok(0));
+                }
+                state.waker = Some(cx.waker().clone());
+                return Poll::Pending;
+            }
+            let n = buf.len().min(state.buf.len());
+            for (dst, src) in buf.iter_mut().zip(state.buf.drain(..n)) {
+                *dst = src;
+            }
+            Poll::Ready(Ok(n))
+        }
+    }
+
+    impl Write for Duplex {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            let mut state = self.write.0.lock().unwrap();
+            state.buf.extend(buf);
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            let mut state = self.write.0.lock().unwrap();
+            state.closed = true;
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    async fn read_exactly(client: &mut Duplex, len: usize) -> Vec<u8> {
+        let mut buf = vec![0; len];
+        client.read_exact(&mut buf).await.unwrap();
+        buf
+    }
+
+    #[test]
+    fn computes_accept_key() {
+        // RFC 6455 1.3 中的示例
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    #[async_std::test]
+    async fn handshake_and_echo() {
+        let mut app = crate::new();
+        app.at("/ws").get(upgrade(|mut conn| async move {
+            while let Some(message) = conn.recv().await {
+                if let Message::Text(text) = message? {
+                    conn.send_text(text).await?;
+                }
+            }
+            Ok(())
+        }));
+
+        let (server, mut client) = duplex();
+        task::spawn(crate::http::accept(server, move |req| {
+            let app = app.clone();
+            async move { app.respond(req).await }
+        }));
+
+        client
+            .write_all(
+                b"GET /ws HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\n\
+                  Connection: keep-alive, Upgrade\r\nSec-WebSocket-Version: 13\r\n\
+                  Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n",
+            )
+            .await
+            .unwrap();
+
+        let mut head = Vec::new();
+        while !head.ends_with(b"\r\n\r\n") {
+            head.extend(read_exactly(&mut client, 1).await);
+        }
+        let head = String::from_utf8(head).unwrap().to_ascii_lowercase();
+        assert!(head.starts_with("http/1.1 101 switching protocols\r\n"));
+        assert!(head.contains("upgrade: websocket\r\n"));
+        assert!(head.contains("sec-websocket-accept: s3pplmbitxaq9kygzzhzrbk+xoo=\r\n"));
+
+        let hello = encode_frame(true, OP_TEXT, b"hello", Some([0x37, 0xfa, 0x21, 0x3d]));
+        client.write_all(&hello).await.unwrap();
+        // 服务端发送的帧不带掩码
+        assert_eq!(read_exactly(&mut client, 7).await, b"\x81\x05hello");
+
+        let ping = encode_frame(true, OP_PING, b"p", Some([1, 2, 3, 4]));
+        client.write_all(&ping).await.unwrap();
+        assert_eq!(read_exactly(&mut client, 3).await, b"\x8a\x01p");
+
+        let close = encode_frame(true, OP_CLOSE, &1000u16.to_be_bytes(), Some([9, 8, 7, 6]));
+        client.write_all(&close).await.unwrap();
+        assert_eq!(read_exactly(&mut client, 4).await, b"\x88\x02\x03\xe8");
+
+        // 关闭握手完成后服务端关闭连接
+        let mut rest = Vec::new();
+        client.read_to_end(&mut rest).await.unwrap();
+        assert!(rest.is_empty());
+    }
+
+    #[async_std::test]
+    async fn rejects_invalid_handshakes() {
+        let mut app = crate::new();
+        app.at("/ws").get(upgrade(|_conn| async { Ok(()) }));
+
+        let request = |version: &str, key: &str| {
+            let mut req = HttpRequest::new(Method::Get, Url::parse("http://localhost/ws").unwrap());
+            req.insert_header("Upgrade", "websocket");
+            req.insert_header("Connection", "Upgrade");
+            req.insert_header("Sec-WebSocket-Version", version);
+            req.insert_header("Sec-WebSocket-Key", key);
+            req
+        };
+
+        let res: http_types::Response = app
+            .respond(request("8", "dGhlIHNhbXBsZSBub25jZQ=="))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::UpgradeRequired);
+        assert_eq!(res["Sec-WebSocket-Version"], "13");
+
+        let res: http_types::Response = app.respond(request("13", "c2hvcnQ=")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BadRequest);
+
+        let req = HttpRequest::new(Method::Get, Url::parse("http://localhost/ws").unwrap());
+        let res: http_types::Response = app.respond(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BadRequest);
+    }
+}
@@ -0,0 +1,43 @@
+use async_std::io::{ReadExt, WriteExt};
+use async_std::net::{TcpListener, TcpStream};
+use async_std::task;
+use http_types::headers::CONTENT_LENGTH;
+use http_types::{Method, Request, Response, StatusCode, Url};
+
+fn app() -> summer_boot::Server<()> {
+    let mut app = summer_boot::new();
+    app.at("/hello").get(|_| async { Ok("hello world") });
+    app
+}
+
+#[async_std::test]
+async fn head_via_respond_has_no_body() {
+    let url = Url::parse("http://localhost/hello").unwrap();
+    let mut res: Response = app()
+        .respond(Request::new(Method::Head, url))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::Ok);
+    assert_eq!(res[CONTENT_LENGTH], "11");
+    assert_eq!(res.body_string().await.unwrap(), "");
+}
+
+#[async_std::test]
+async fn head_over_tcp_has_no_body() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    task::spawn(app().listen(listener));
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(b"HEAD /hello HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .await
+        .unwrap();
+    let mut raw = String::new();
+    stream.read_to_string(&mut raw).await.unwrap();
+
+    let (head, body) = raw.split_once("\r\n\r\n").unwrap();
+    assert!(head.starts_with("HTTP/1.1 200 OK"));
+    assert!(head.to_ascii_lowercase().contains("content-length: 11"));
+    assert_eq!(body, "");
+}
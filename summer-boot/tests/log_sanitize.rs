@@ -0,0 +1,126 @@
+use http_types::{Method, Request, Response, Url};
+use summer_boot::utils::request_id::RequestIdMiddleware;
+
+use std::env;
+use std::process::Command;
+
+/// 设置后 `logging_server` 开启日志并处理一个恶意请求
+const CHILD_ENV: &str = "LOG_SANITIZE_CHILD";
+
+const ERROR_MESSAGE: &str = "bad input\r\nINFO admin logged in \x1b[2J\x1b[31m";
+
+const REQUEST_ID: &str = "abc\"\x1b[2Jfake";
+
+#[async_std::test]
+#[ignore = "由 real_logger_output_escapes_request_values 等测试在子进程中运行"]
+async fn logging_server() {
+    if env::var(CHILD_ENV).is_err() {
+        return;
+    }
+    summer_boot::log::start();
+    let mut app = summer_boot::new();
+    app.with(RequestIdMiddleware::new());
+    app.at("/login")
+        .get(|_| async { Err::<String, _>(summer_boot::Error::from_str(400, ERROR_MESSAGE)) });
+
+    let mut req = Request::new(Method::Get, Url::parse("http://localhost/login").unwrap());
+    req.insert_header("X-Request-Id", REQUEST_ID);
+    let _: Response = app.respond(req).await.unwrap();
+}
+
+/// femme 输出的一条记录：消息所在的行和之后缩进的键值对
+fn record<'a>(stdout: &'a str, message: &str) -> Vec<(&'a str, &'a str)> {
+    stdout
+        .lines()
+        .skip_while(|line| !line.ends_with(message))
+        .skip(1)
+        .take_while(|line| line.starts_with("    "))
+        .map(|line| {
+            let pair = line.trim_start().trim_start_matches("\x1b[1m");
+            pair.split_once("\x1b[0m ").unwrap_or((pair, ""))
+        })
+        .collect()
+}
+
+/// 在子进程中运行 `logging_server`，返回日志输出
+fn logging_server_output() -> String {
+    let output = Command::new(env::current_exe().unwrap())
+        .args([
+            "logging_server",
+            "--exact",
+            "--ignored",
+            "--test-threads=1",
+            "--nocapture",
+        ])
+        .env(CHILD_ENV, "1")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// femme 在 debug 构建中输出带颜色的多行格式
+#[test]
+#[cfg_attr(
+    not(debug_assertions),
+    ignore = "femme 只在 debug 构建中输出 pretty 格式"
+)]
+fn real_logger_output_escapes_request_values() {
+    let stdout = logging_server_output();
+
+    let pairs = record(&stdout, "Client error --> Response sent");
+    let keys: Vec<_> = pairs.iter().map(|(key, _)| *key).collect();
+    assert_eq!(
+        keys,
+        [
+            "request_id",
+            "message",
+            "error_type",
+            "method",
+            "path",
+            "status",
+            "duration",
+            "duration_ms",
+            "bucket"
+        ],
+        "{}",
+        stdout
+    );
+    for (key, value) in &pairs {
+        assert!(!value.chars().any(char::is_control), "{}: {:?}", key, value);
+    }
+    let value = |name| pairs.iter().find(|(key, _)| *key == name).unwrap().1;
+    assert_eq!(value("request_id"), "abc\"\\u{1b}[2Jfake");
+    assert!(value("message").contains("bad input\\r\\nINFO admin logged in \\u{1b}[2J"));
+
+    // 伪造的记录没有出现在输出中
+    assert!(!stdout.lines().any(|line| line.starts_with("INFO admin")));
+    assert!(!stdout.contains("\x1b[2J"));
+}
+
+/// femme 在 release 构建中输出 ndjson，每条记录是一行 JSON
+#[test]
+#[cfg_attr(debug_assertions, ignore = "femme 只在 release 构建中输出 ndjson")]
+fn ndjson_output_escapes_request_values() {
+    let stdout = logging_server_output();
+
+    let records: Vec<serde_json::Value> = stdout
+        .lines()
+        // 其余的行是测试框架的输出
+        .filter(|line| line.starts_with('{'))
+        .map(|line| serde_json::from_str(line).unwrap_or_else(|e| panic!("{}: {:?}", e, line)))
+        .collect();
+    let record = records
+        .iter()
+        .find(|record| record["msg"] == "Client error --> Response sent")
+        .unwrap_or_else(|| panic!("{}", stdout));
+
+    assert_eq!(record["request_id"], "abc\"\\u{1b}[2Jfake");
+    let message = record["message"].as_str().unwrap();
+    assert!(message.contains("bad input\\r\\nINFO admin logged in \\u{1b}[2J"));
+    assert_eq!(record["path"], "/login");
+
+    // 伪造的记录没有出现在输出中
+    assert!(!stdout.lines().any(|line| line.starts_with("INFO admin")));
+    assert!(!stdout.contains('\x1b'));
+}
@@ -0,0 +1,88 @@
+use async_std::io::{ReadExt, WriteExt};
+use async_std::net::{TcpListener, TcpStream};
+use async_std::task;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// future 被丢弃时设置标记
+struct DropFlag(Arc<AtomicBool>);
+
+impl Drop for DropFlag {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+/// 读取直到出现 `needle`
+async fn read_until(stream: &mut TcpStream, raw: &mut String, needle: &str) {
+    let mut buf = [0; 1024];
+    while !raw.contains(needle) {
+        let n = stream.read(&mut buf).await.unwrap();
+        assert!(n > 0, "connection closed before {:?}: {:?}", needle, raw);
+        raw.push_str(std::str::from_utf8(&buf[..n]).unwrap());
+    }
+}
+
+#[async_std::test]
+async fn streams_events_and_cancels_on_disconnect() {
+    let dropped = Arc::new(AtomicBool::new(false));
+    let flag = dropped.clone();
+
+    let mut app = summer_boot::new();
+    app.at("/events")
+        .get(summer_boot::sse::endpoint(move |_req, sender| {
+            let flag = DropFlag(flag.clone());
+            async move {
+                let _flag = flag;
+                sender.send("greeting", "hello\nworld", Some("1")).await?;
+                sender.send("message", "second", Some("2")).await?;
+                loop {
+                    task::sleep(Duration::from_millis(10)).await;
+                    sender.send("tick", "", None).await?;
+                }
+            }
+        }));
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    task::spawn(app.listen(listener));
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(b"GET /events HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        .await
+        .unwrap();
+
+    let mut raw = String::new();
+    read_until(&mut stream, &mut raw, "data: second\n\n").await;
+
+    let (head, body) = raw.split_once("\r\n\r\n").unwrap();
+    let head = head.to_ascii_lowercase();
+    assert!(head.starts_with("http/1.1 200 ok"));
+    assert!(head.contains("content-type: text/event-stream"));
+    assert!(head.contains("transfer-encoding: chunked"));
+    assert!(!head.contains("content-length"));
+
+    // 每个事件是一个 chunk
+    let first = "event: greeting\nid: 1\ndata: hello\ndata: world\n\n";
+    let second = "event: message\nid: 2\ndata: second\n\n";
+    let expected = format!(
+        "{:X}\r\n{}\r\n{:X}\r\n{}",
+        first.len(),
+        first,
+        second.len(),
+        second
+    );
+    assert!(body.starts_with(&expected), "unexpected body: {:?}", body);
+
+    drop(stream);
+    for _ in 0..200 {
+        if dropped.load(Ordering::SeqCst) {
+            return;
+        }
+        task::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("sse handler was not cancelled after the client disconnected");
+}
@@ -0,0 +1,213 @@
+#![cfg(all(unix, feature = "upgrade"))]
+
+use summer_boot::upgrade::UpgradeConfig;
+
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::{env, thread};
+
+/// 服务进程监听的地址，未设置时 `upgrade_server` 直接返回
+const ADDR_ENV: &str = "UPGRADE_TEST_ADDR";
+/// 升级时执行的程序，默认为测试程序自身
+const PROGRAM_ENV: &str = "UPGRADE_TEST_PROGRAM";
+
+#[async_std::test]
+#[ignore = "由 handoff_keeps_serving 在子进程中运行"]
+async fn upgrade_server() {
+    let Ok(addr) = env::var(ADDR_ENV) else {
+        return;
+    };
+    let mut app = summer_boot::new();
+    app.at("/")
+        .get(|_| async { Ok(std::process::id().to_string()) });
+    // 比测试的等待时间长，空闲连接没有关闭时旧进程不会及时退出
+    let mut config = UpgradeConfig::new().drain_timeout(Duration::from_secs(30));
+    if let Ok(program) = env::var(PROGRAM_ENV) {
+        config = config.program(program).args(Vec::<String>::new());
+    }
+    app.listen_with_upgrade(addr, config).await.unwrap();
+}
+
+/// 请求一次，返回处理请求的进程 id
+fn get(addr: SocketAddr) -> io::Result<u32> {
+    let mut stream = TcpStream::connect_timeout(&addr, Duration::from_secs(1))?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    stream.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")?;
+    let mut raw = String::new();
+    stream.read_to_string(&mut raw)?;
+    raw.split_once("\r\n\r\n")
+        .filter(|(head, _)| head.starts_with("HTTP/1.1 200"))
+        .and_then(|(_, body)| body.parse().ok())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, raw.clone()))
+}
+
+fn kill(pid: u32, signal: &str) {
+    Command::new("kill")
+        .args([signal, &pid.to_string()])
+        .status()
+        .unwrap();
+}
+
+/// 在子进程中启动服务，返回子进程和第一个请求的响应进程 id
+fn start_server(addr: SocketAddr) -> (Child, u32) {
+    start_server_with(addr, &[])
+}
+
+fn start_server_with(addr: SocketAddr, envs: &[(&str, &str)]) -> (Child, u32) {
+    let mut server = Command::new(env::current_exe().unwrap())
+        .args(["upgrade_server", "--exact", "--ignored", "--test-threads=1"])
+        .env(ADDR_ENV, addr.to_string())
+        .envs(envs.iter().copied())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let started = Instant::now();
+    loop {
+        match get(addr) {
+            Ok(pid) => return (server, pid),
+            Err(_) if started.elapsed() < Duration::from_secs(10) => {
+                thread::sleep(Duration::from_millis(20))
+            }
+            Err(err) => {
+                server.kill().unwrap();
+                panic!("server did not start: {}", err);
+            }
+        }
+    }
+}
+
+/// 等待进程退出，最多等待 `limit`
+fn wait_exit(child: &mut Child, limit: Duration) -> Option<ExitStatus> {
+    let started = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait().unwrap() {
+            return Some(status);
+        }
+        if started.elapsed() > limit {
+            return None;
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+fn unused_addr() -> SocketAddr {
+    std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+}
+
+#[test]
+fn handoff_keeps_serving() {
+    let addr = unused_addr();
+    let (mut old, old_pid) = start_server(addr);
+    assert_eq!(old_pid, old.id());
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let client = thread::spawn({
+        let stop = stop.clone();
+        move || {
+            let mut pids = Vec::new();
+            let mut errors = Vec::new();
+            while !stop.load(Ordering::SeqCst) {
+                match get(addr) {
+                    Ok(pid) => pids.push(pid),
+                    Err(err) => errors.push(err.to_string()),
+                }
+            }
+            (pids, errors)
+        }
+    });
+
+    thread::sleep(Duration::from_millis(100));
+    kill(old_pid, "-USR2");
+
+    // 旧进程排空后退出
+    let status = wait_exit(&mut old, Duration::from_secs(10));
+    thread::sleep(Duration::from_millis(200));
+    stop.store(true, Ordering::SeqCst);
+    let (pids, errors) = client.join().unwrap();
+
+    let new_pid = *pids.last().unwrap();
+    kill(new_pid, "-TERM");
+    if status.is_none() {
+        old.kill().unwrap();
+    }
+
+    assert!(errors.is_empty(), "requests failed: {:?}", errors);
+    assert!(status.is_some_and(|status| status.success()));
+    assert_ne!(new_pid, old_pid);
+    assert!(pids.contains(&old_pid));
+    // 新进程就绪前后两个进程可能短暂地同时接受连接，旧进程退出后只由新进程处理
+    assert!(pids.iter().all(|pid| *pid == old_pid || *pid == new_pid));
+}
+
+#[test]
+fn failed_upgrade_keeps_old_process() {
+    let addr = unused_addr();
+    let (mut old, old_pid) = start_server_with(addr, &[(PROGRAM_ENV, "false")]);
+
+    kill(old_pid, "-USR2");
+    thread::sleep(Duration::from_millis(500));
+
+    // 新进程没有就绪就退出，旧进程继续接受连接
+    let status = old.try_wait().unwrap();
+    let pid = get(addr);
+    old.kill().unwrap();
+    old.wait().unwrap();
+
+    assert!(status.is_none(), "{:?}", status);
+    assert_eq!(pid.unwrap(), old_pid);
+}
+
+#[test]
+fn drain_closes_idle_keep_alive_connections() {
+    let addr = unused_addr();
+    let (mut old, old_pid) = start_server(addr);
+
+    // 处理完一个请求后保持空闲的 keep-alive 连接
+    let mut idle = TcpStream::connect(addr).unwrap();
+    idle.set_read_timeout(Some(Duration::from_secs(10)))
+        .unwrap();
+    idle.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        .unwrap();
+    let mut head = Vec::new();
+    let mut byte = [0];
+    while !head.ends_with(b"\r\n\r\n") {
+        idle.read_exact(&mut byte).unwrap();
+        head.push(byte[0]);
+    }
+    let head = String::from_utf8(head).unwrap();
+    assert!(head.starts_with("HTTP/1.1 200"), "{}", head);
+    assert!(!head.to_ascii_lowercase().contains("connection: close"));
+    let mut body = vec![0; old_pid.to_string().len()];
+    idle.read_exact(&mut body).unwrap();
+
+    kill(old_pid, "-USR2");
+    let started = Instant::now();
+
+    // 空闲连接被关闭，旧进程不等待排空超时就退出
+    let mut rest = Vec::new();
+    let closed = idle.read_to_end(&mut rest);
+    let status = wait_exit(&mut old, Duration::from_secs(10));
+    let elapsed = started.elapsed();
+
+    let new_pid = get(addr);
+    if let Ok(new_pid) = &new_pid {
+        kill(*new_pid, "-TERM");
+    }
+    if status.is_none() {
+        old.kill().unwrap();
+    }
+
+    assert!(closed.is_ok() && rest.is_empty(), "{:?} {:?}", closed, rest);
+    assert!(status.is_some_and(|status| status.success()));
+    assert!(elapsed < Duration::from_secs(10), "{:?}", elapsed);
+    assert_ne!(new_pid.unwrap(), old_pid);
+}